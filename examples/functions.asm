@@ -0,0 +1,24 @@
+; calculates the 10th fibonacci number recursively, the result is the exit code (55)
+main:
+    mov x2, 10
+    call fib
+    mov l1, x3
+    exit
+
+; returns the fibonacci number of x2 in x3, the stack is used to keep values between calls
+fib:
+    cmpl x2, 2
+    jmp fib_small
+    push x2
+    dec x2
+    call fib            ; x3 = fib(x2 - 1)
+    pop x2
+    push x3
+    sub x2, 2
+    call fib            ; x3 = fib(x2 - 2)
+    pop x4
+    add x3, x4
+    ret
+    .fib_small
+        mov x3, x2
+        ret
@@ -0,0 +1,20 @@
+; prints the bytes of "Hello" using the print syscall
+main:
+    mov x1, 0           ; the allocate syscall
+    mov x2, 5           ; allocate 5 bytes, the pointer is returned in x2
+    syscall
+    mov x5, x2
+    mov byte[x5], 72    ; H
+    inc x5
+    mov byte[x5], 101   ; e
+    inc x5
+    mov byte[x5], 108   ; l
+    inc x5
+    mov byte[x5], 108   ; l
+    inc x5
+    mov byte[x5], 111   ; o
+    mov x1, 2           ; the print syscall, x2 still points to the bytes
+    mov x3, 5           ; the amount of bytes to print
+    syscall
+    mov l1, 0
+    exit
@@ -0,0 +1,11 @@
+; adds the numbers 1 to 10 together, the result is the exit code (55)
+main:
+    mov x2, 0           ; the sum
+    mov x3, 1           ; the counter
+    .loop
+        add x2, x3
+        inc x3
+        cmple x3, 10    ; jmp only jumps when the comparison was true
+        jmp loop
+    mov l1, x2
+    exit
@@ -0,0 +1,36 @@
+; draws a red diagonal line in a 16x16 window, closing the window exits the program
+main:
+    mov x1, 3           ; the create window syscall
+    mov x2, 0           ; the window has no name
+    mov x3, 16          ; the width
+    mov x4, 16          ; the height
+    syscall             ; x2 = the pointer to the canvas
+    mov x6, x2          ; x2 is overwritten by the window events
+    mov x5, 0           ; the pixel on the diagonal
+    .draw
+        ; pixels are 4 bytes (red, green, blue, alpha), address = canvas + (pixel * 16 + pixel) * 4
+        mov x7, x5
+        mul x7, 17
+        mul x7, 4
+        add x7, x6
+        mov word[x7], 0x00FF    ; red = 0xFF, green = 0
+        add x7, 2
+        mov word[x7], 0xFF00    ; blue = 0, alpha = 0xFF
+        inc x5
+        cmpl x5, 16
+        jmp draw
+    mov x1, 5           ; the redraw syscall
+    syscall
+    .events
+        mov x1, 4       ; take the next window event, the event is returned in x2
+        syscall
+        cmpe x2, 0      ; 0 is the close event
+        jmp close
+        cmpne x2, 5     ; 5 means no event is queued
+        jmp events
+        hlt 0           ; wait until the next event is queued
+        clf
+        jmp events
+    .close
+    mov l1, 0
+    exit
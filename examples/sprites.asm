@@ -0,0 +1,75 @@
+; draws a green 4x4 sprite at the position of the mouse in a 32x32 window
+main:
+    mov x1, 3           ; the create window syscall
+    mov x2, 0           ; the window has no name
+    mov x3, 32          ; the width
+    mov x4, 32          ; the height
+    syscall             ; x2 = the pointer to the canvas
+    mov x10, x2         ; x2 is overwritten by the window events
+    .events
+        mov x1, 4       ; take the next window event, the event is returned in x2
+        syscall
+        cmpe x2, 0      ; 0 is the close event
+        jmp close
+        cmpe x2, 5      ; 5 means no event is queued
+        jmp wait
+        cmpne x2, 1     ; 1 is the cursor event, x3 and x4 contain the position
+        jmp events
+        call clear
+        call sprite
+        mov x1, 5       ; the redraw syscall
+        syscall
+        clf             ; the loops of the functions leave the compare flag unset
+        jmp events
+    .wait
+        hlt 0           ; wait until the next event is queued
+        clf
+        jmp events
+    .close
+        mov l1, 0
+        exit
+
+; makes every pixel of the canvas transparent
+clear:
+    mov x5, x10
+    mov x6, x10
+    add x6, 4096        ; 32 * 32 pixels of 4 bytes
+    .clear_pixel
+        mov word[x5], 0
+        add x5, 2
+        cmpl x5, x6
+        jmp clear_pixel
+    ret
+
+; draws the sprite with its top left corner at x3, x4 keeping it within the canvas
+sprite:
+    cmple x3, 28
+    jmp sprite_x
+    mov x3, 28
+    .sprite_x
+    cmple x4, 28
+    jmp sprite_y
+    mov x4, 28
+    .sprite_y
+    mov x7, 0           ; the row of the sprite
+    .sprite_row
+        mov x8, 0       ; the column of the sprite
+        .sprite_column
+            ; address = canvas + ((y + row) * 32 + x + column) * 4
+            mov x9, x4
+            add x9, x7
+            mul x9, 32
+            add x9, x3
+            add x9, x8
+            mul x9, 4
+            add x9, x10
+            mov word[x9], 0xFF00    ; red = 0, green = 0xFF
+            add x9, 2
+            mov word[x9], 0xFF00    ; blue = 0, alpha = 0xFF
+            inc x8
+            cmpl x8, 4
+            jmp sprite_column
+        inc x7
+        cmpl x7, 4
+        jmp sprite_row
+    ret
@@ -0,0 +1,308 @@
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, ErrorKind, Seek, SeekFrom, Write};
+use std::process::{Child, Command, Stdio};
+use std::str::FromStr;
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// the samples per second of the mixed sound and of the pcm samples played by the program
+pub const SAMPLE_RATE: u32 = 22050;
+/// the mixer renders this many samples at a time, a 50th of a second
+const CHUNK_SIZE: usize = SAMPLE_RATE as usize / 50;
+/// a single channel at full volume uses a quarter of the range so the three channels can be mixed without clipping much
+const CHANNEL_AMPLITUDE: f32 = i16::MAX as f32 / 4.0;
+const WAV_HEADER_SIZE: u32 = 44;
+/// the programs that play raw 16 bit mono samples at the SAMPLE_RATE from stdin, tried in this order until one of them
+/// is installed
+const PLAYERS: [(&str, &[&str]); 3] = [
+    ("pw-play", &["--format", "s16", "--rate", "22050", "--channels", "1", "-"]),
+    ("paplay", &["--raw", "--format=s16le", "--rate=22050", "--channels=1"]),
+    ("aplay", &["-q", "-t", "raw", "-f", "S16_LE", "-r", "22050", "-c", "1", "-"]),
+];
+
+/// where the sound is sent to, given as play or the path of a wav file
+#[derive(Clone, Debug)]
+pub enum AudioOutput {
+    /// plays the sound on the host using pw-play, paplay or aplay
+    Play,
+    Wav(String),
+}
+
+impl FromStr for AudioOutput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("play") {
+            Ok(AudioOutput::Play)
+        } else {
+            Ok(AudioOutput::Wav(s.to_string()))
+        }
+    }
+}
+
+/// the tone channels of the sound device
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoundChannel {
+    Square,
+    Noise,
+}
+
+impl SoundChannel {
+    pub fn from_number(number: u16) -> Option<Self> {
+        match number {
+            0 => Some(SoundChannel::Square),
+            1 => Some(SoundChannel::Noise),
+            _ => None,
+        }
+    }
+}
+
+enum AudioCommand {
+    Tone { channel: SoundChannel, frequency: u16, volume: u8, samples: usize },
+    Samples(Vec<u8>),
+}
+
+#[derive(Default)]
+struct Tone {
+    frequency: u16,
+    volume: u8,
+    remaining: usize,
+    /// how far the square wave is within its period, or when the noise is clocked next, from 0 to 1
+    phase: f32,
+}
+
+impl Tone {
+    fn is_playing(&self) -> bool {
+        self.remaining > 0 && self.volume > 0 && self.frequency > 0
+    }
+
+    /// advances the phase by a sample and returns true when a period has passed
+    fn advance(&mut self) -> bool {
+        self.remaining -= 1;
+        self.phase += self.frequency as f32 / SAMPLE_RATE as f32;
+        let wrapped = self.phase >= 1.0;
+        self.phase = self.phase.fract();
+        wrapped
+    }
+
+    fn amplitude(&self) -> f32 {
+        self.volume as f32 / u8::MAX as f32 * CHANNEL_AMPLITUDE
+    }
+}
+
+/// mixes the square channel, the noise channel and the queued pcm samples
+struct Mixer {
+    square: Tone,
+    noise: Tone,
+    /// the 15 bit linear feedback shift register of the noise channel
+    lfsr: u16,
+    samples: VecDeque<u8>,
+}
+
+impl Mixer {
+    fn new() -> Self {
+        Self {
+            square: Tone::default(),
+            noise: Tone::default(),
+            lfsr: 1,
+            samples: VecDeque::new(),
+        }
+    }
+
+    fn handle(&mut self, command: AudioCommand) {
+        match command {
+            AudioCommand::Tone { channel, frequency, volume, samples } => {
+                let tone = match channel {
+                    SoundChannel::Square => &mut self.square,
+                    SoundChannel::Noise => &mut self.noise,
+                };
+                tone.frequency = frequency;
+                tone.volume = volume;
+                tone.remaining = samples;
+            },
+            AudioCommand::Samples(samples) => self.samples.extend(samples),
+        }
+    }
+
+    fn is_playing(&self) -> bool {
+        self.square.is_playing() || self.noise.is_playing() || !self.samples.is_empty()
+    }
+
+    fn next_sample(&mut self) -> i16 {
+        let mut sample = 0.0;
+
+        if self.square.is_playing() {
+            let high = self.square.phase < 0.5;
+            sample += if high { self.square.amplitude() } else { -self.square.amplitude() };
+            self.square.advance();
+        }
+
+        if self.noise.is_playing() {
+            let high = self.lfsr & 1 == 0;
+            sample += if high { self.noise.amplitude() } else { -self.noise.amplitude() };
+            if self.noise.advance() {
+                let bit = (self.lfsr ^ (self.lfsr >> 1)) & 1;
+                self.lfsr = (self.lfsr >> 1) | (bit << 14);
+            }
+        }
+
+        if let Some(pcm) = self.samples.pop_front() {
+            sample += (pcm as f32 - 128.0) / 128.0 * CHANNEL_AMPLITUDE;
+        }
+
+        sample.clamp(i16::MIN as f32, i16::MAX as f32) as i16
+    }
+}
+
+/// the mixed samples are written to the stdin of the player or to the wav file
+enum AudioSink {
+    Player(Child),
+    Wav(BufWriter<File>),
+}
+
+impl AudioSink {
+    fn open(output: &AudioOutput) -> std::io::Result<Self> {
+        match output {
+            AudioOutput::Play => spawn_player().map(AudioSink::Player),
+            AudioOutput::Wav(path) => {
+                let file = OpenOptions::new().write(true).create(true).truncate(true).open(path)?;
+                let mut writer = BufWriter::new(file);
+                write_wav_header(&mut writer, 0)?;
+                Ok(AudioSink::Wav(writer))
+            },
+        }
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        match self {
+            AudioSink::Player(player) => player.stdin.as_mut().expect("the stdin of the player is piped").write_all(bytes),
+            AudioSink::Wav(writer) => writer.write_all(bytes),
+        }
+    }
+
+    /// waits until the player has played the remaining sound or completes the wav file with the written samples
+    fn finish(self, written: u32) -> std::io::Result<()> {
+        match self {
+            AudioSink::Player(mut player) => {
+                drop(player.stdin.take());
+                player.wait()?;
+                Ok(())
+            },
+            AudioSink::Wav(mut writer) => {
+                writer.seek(SeekFrom::Start(0))?;
+                write_wav_header(&mut writer, written * size_of::<i16>() as u32)?;
+                writer.flush()
+            },
+        }
+    }
+}
+
+/// a sound device with a square channel, a noise channel and a queue of pcm samples, mixed by a host thread at the
+/// speed it would be played at and played on the host or written to a 16 bit mono wav file
+pub struct AudioDevice {
+    sender: Sender<AudioCommand>,
+    thread: JoinHandle<std::io::Result<()>>,
+}
+
+impl AudioDevice {
+    /// starts the player or creates the wav file and starts the mixer thread
+    pub fn open(output: &AudioOutput) -> std::io::Result<Self> {
+        let sink = AudioSink::open(output)?;
+
+        let (sender, receiver) = channel();
+        let thread = thread::spawn(move || { mix(receiver, sink) });
+        Ok(Self {
+            sender,
+            thread,
+        })
+    }
+
+    /// plays the tone on the channel for the duration, replacing the tone that was playing on it, a volume of 0 stops it
+    pub fn play_tone(&self, channel: SoundChannel, frequency: u16, volume: u8, duration: Duration) {
+        let samples = (duration.as_secs_f64() * SAMPLE_RATE as f64) as usize;
+        _ = self.sender.send(AudioCommand::Tone { channel, frequency, volume, samples }); // the thread only stops once the device is finished
+    }
+
+    /// queues the unsigned 8 bit pcm samples after the samples that haven't been played yet
+    pub fn play_samples(&self, samples: Vec<u8>) {
+        _ = self.sender.send(AudioCommand::Samples(samples)); // same as above
+    }
+
+    /// lets the sounds that are still playing finish and completes the wav file or waits for the player
+    pub fn finish(self) -> std::io::Result<()> {
+        drop(self.sender);
+        self.thread.join().unwrap_or_else(| _ | { Err(std::io::Error::other("the mixer thread panicked")) })
+    }
+}
+
+/// renders a chunk every 50th of a second until the device is finished, after which the remaining sound is rendered at once
+fn mix(receiver: Receiver<AudioCommand>, mut sink: AudioSink) -> std::io::Result<()> {
+    let mut mixer = Mixer::new();
+    let mut finished = false;
+    let mut written = 0u32;
+    let start = Instant::now();
+
+    loop {
+        loop {
+            match receiver.try_recv() {
+                Ok(command) => mixer.handle(command),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => { finished = true; break; },
+            }
+        }
+        if finished && !mixer.is_playing() {
+            break;
+        }
+
+        let chunk: Vec<u8> = (0..CHUNK_SIZE).flat_map(| _ | { mixer.next_sample().to_le_bytes() }).collect();
+        sink.write_all(&chunk)?;
+        written += CHUNK_SIZE as u32;
+
+        if !finished {
+            let played = Duration::from_secs_f64(written as f64 / SAMPLE_RATE as f64);
+            if let Some(wait) = played.checked_sub(start.elapsed()) {
+                thread::sleep(wait);
+            }
+        }
+    }
+
+    sink.finish(written)
+}
+
+/// starts the first player of PLAYERS that is installed
+fn spawn_player() -> std::io::Result<Child> {
+    for (program, arguments) in PLAYERS {
+        let result = Command::new(program).args(arguments)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn();
+        match result {
+            Err(err) if err.kind() == ErrorKind::NotFound => continue,
+            result => return result,
+        }
+    }
+    Err(std::io::Error::new(ErrorKind::NotFound, "no audio player is installed, install pw-play, paplay or aplay"))
+}
+
+fn write_wav_header(stream: &mut impl Write, data_size: u32) -> std::io::Result<()> {
+    let bytes_per_sample = size_of::<i16>() as u32;
+
+    stream.write_all(b"RIFF")?;
+    stream.write_all(&(WAV_HEADER_SIZE - 8 + data_size).to_le_bytes())?;
+    stream.write_all(b"WAVE")?;
+
+    stream.write_all(b"fmt ")?;
+    stream.write_all(&16u32.to_le_bytes())?; // size of the format chunk
+    stream.write_all(&1u16.to_le_bytes())?; // pcm
+    stream.write_all(&1u16.to_le_bytes())?; // mono
+    stream.write_all(&SAMPLE_RATE.to_le_bytes())?;
+    stream.write_all(&(SAMPLE_RATE * bytes_per_sample).to_le_bytes())?; // bytes per second
+    stream.write_all(&(bytes_per_sample as u16).to_le_bytes())?; // block align
+    stream.write_all(&(bytes_per_sample as u16 * 8).to_le_bytes())?; // bits per sample
+
+    stream.write_all(b"data")?;
+    stream.write_all(&data_size.to_le_bytes())?;
+    Ok(())
+}
@@ -4,12 +4,13 @@
 //     register {register}
 //     memory {address}, {size}";
 
-use crate::computer::{Computer, ComputerError, ComputerErrorKind};
+use crate::computer::{Computer, ComputerError, ComputerErrorKind, REGISTER_COUNT};
 use crate::pattern_ignore_case::IgnoreCase;
 use std::io::stdin;
 use std::ops::Deref;
 use std::str::FromStr;
-use crate::cpu::CpuArchitecture;
+use itertools::Itertools;
+use crate::cpu::{CpuArchitecture, CpuError, CpuErrorKind};
 use crate::operand::Operand;
 
 enum StaticString {
@@ -41,6 +42,9 @@ impl Deref for StaticString {
 }
 
 macro_rules! join {
+    ($separator: literal,) => {
+        ""
+    };
     ($separator: literal, $value: tt) => {
         concat!("{", stringify!($value), "}")
     };
@@ -51,7 +55,7 @@ macro_rules! join {
 
 macro_rules! break_commands {
     (
-        $( $name:ident => | $computer:ident, $( $values:ident ),*  | $expr: expr ),*
+        $( $name:ident => | $computer:ident $(, $values:ident )*  | $expr: expr ),*
     ) => {
         const BREAKPOINT_MESSAGE: &str = 
             concat!("breakpoint reached, please type \"continue\" to continue.
@@ -76,9 +80,11 @@ commands:\n", $( "    ", stringify!($name), " ", join!(", ", $($values)*), "\n"
                     let trimmed_str = str_buffer.trim();
                     $(
                         if let Some(stripped) = trimmed_str.strip_prefix(IgnoreCase::new(stringify!($name))) {
+                            #[allow(unused)]
                             let mut split = stripped.split(',')
                             .map(| val | { val.trim() });
                             
+                            #[allow(unused)]
                             let mut count = 0;
                             $(
                                 count += 1;
@@ -112,6 +118,10 @@ commands:\n", $( "    ", stringify!($name), " ", join!(", ", $($values)*), "\n"
 }
 
 break_commands!(register => | computer, register | {
+    if str::eq_ignore_ascii_case(register, "flags") {
+        return Some(computer.cpu().get_flags().to_string().into());
+    }
+    
     let result = Operand::from_str(register);
     let operand = match result {
         Ok(op) => op,
@@ -126,6 +136,7 @@ break_commands!(register => | computer, register | {
             };
             println!("{}", value);
         },
+        Operand::FloatRegister(float_register) => println!("{}", computer.cpu().get_float_register(float_register)),
         _ => return Some("the value given is not a valid register".into()),
     };
     
@@ -143,6 +154,10 @@ break_commands!(register => | computer, register | {
         Err(err) => return Some(err.to_string().into()),
     };
     
+    if let Err(register) = address_operand.validate_registers(REGISTER_COUNT as u8) {
+        return Some(CpuError::with_message(CpuErrorKind::RegisterDoesNotExist, register.to_string()).to_string().into());
+    }
+    
     let result = address_operand.read_from_computer(computer);
     let address = match result {
         Ok(address) => address,
@@ -154,7 +169,12 @@ break_commands!(register => | computer, register | {
     }
     
     let mut buffer = [0u8;1024];
-    let result = computer.ram().read_buffer_at_unchecked(address, &mut buffer[..size as usize]);
+    let bank = computer.cpu().get_bank();
+    let result = if bank == 0 {
+        computer.ram().read_buffer_at_unchecked(address, &mut buffer[..size as usize])
+    } else {
+        computer.ram().read_bank_buffer_at(bank, address, &mut buffer[..size as usize])
+    };
     
     if let Err(err) = result {
         return Some(err.to_string().into());
@@ -166,5 +186,30 @@ break_commands!(register => | computer, register | {
     } else {
         None
     }
-});
-
+}, bank => | computer, bank | {
+    let result = u8::from_str(bank);
+    let bank = match result {
+        Ok(val) => val,
+        Err(err) => return Some(err.to_string().into()),
+    };
+    
+    let bank_count = computer.ram().bank_count();
+    if bank as usize >= bank_count {
+        return Some(format!("bank {} does not exist, there are {} banks", bank, bank_count).into());
+    }
+    
+    let previous_bank = computer.cpu().get_bank();
+    computer.cpu_mut().set_bank(bank);
+    Some(format!("selected bank {}, previously selected bank {}", bank, previous_bank).into())
+}, backtrace => | computer | {
+    match computer.cpu().backtrace() {
+        Some(backtrace) => Some(backtrace.into()),
+        None => Some("the calls are only tracked when running with --debug".into()),
+    }
+}, mappings => | computer | {
+    let mappings = computer.ram().mappings().iter()
+        .sorted_by_key(| mapping | { mapping.range.start })
+        .map(| mapping | { format!("0x{:X}..0x{:X} {}", mapping.range.start, mapping.range.end, mapping.permissions) })
+        .join("\n");
+    Some(mappings.into())
+});
\ No newline at end of file
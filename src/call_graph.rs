@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use crate::cpu::CpuArchitecture;
+
+#[derive(Default, Clone, Copy)]
+struct EdgeCount {
+    calls: u64,
+    tail_calls: u64,
+}
+
+/// the calls between the functions observed while the program ran, only used when running with --emit-callgraph
+pub struct CallGraph {
+    functions: HashMap<CpuArchitecture, String>,
+    /// the calls by the caller and the callee
+    edges: HashMap<(CpuArchitecture, CpuArchitecture), EdgeCount>,
+}
+
+impl CallGraph {
+    pub fn new(functions: Vec<(String, CpuArchitecture)>) -> Self {
+        Self {
+            functions: functions.into_iter().map(| (name, address) | { (address, name) }).collect(),
+            edges: HashMap::new(),
+        }
+    }
+
+    pub fn on_call(&mut self, caller: CpuArchitecture, callee: CpuArchitecture) {
+        self.edges.entry((caller, callee)).or_default().calls += 1;
+    }
+
+    pub fn on_tail_call(&mut self, caller: CpuArchitecture, callee: CpuArchitecture) {
+        self.edges.entry((caller, callee)).or_default().tail_calls += 1;
+    }
+
+    fn function_name(&self, address: CpuArchitecture) -> String {
+        match self.functions.get(&address) {
+            Some(name) => format!("{} (0x{:X})", name, address),
+            None => format!("0x{:X}", address),
+        }
+    }
+
+    /// writes the graph in the graphviz dot format, the edges are labeled with the amount of calls and tail calls are dashed
+    pub fn write_dot(&self, stream: &mut impl Write) -> std::io::Result<()> {
+        let mut edges: Vec<_> = self.edges.iter().collect();
+        edges.sort_unstable_by_key(| (edge, _) | { **edge });
+
+        let mut nodes: Vec<_> = edges.iter().flat_map(| ((caller, callee), _) | { [*caller, *callee] }).collect();
+        nodes.sort_unstable();
+        nodes.dedup();
+
+        writeln!(stream, "digraph calls {{")?;
+        writeln!(stream, "    node [shape=box];")?;
+        for node in nodes {
+            writeln!(stream, "    f{} [label=\"{}\"];", node, escape(&self.function_name(node)))?;
+        }
+        for ((caller, callee), count) in edges {
+            if count.calls > 0 {
+                writeln!(stream, "    f{} -> f{} [label=\"{}\"];", caller, callee, count.calls)?;
+            }
+            if count.tail_calls > 0 {
+                writeln!(stream, "    f{} -> f{} [label=\"{}\", style=dashed];", caller, callee, count.tail_calls)?;
+            }
+        }
+        writeln!(stream, "}}")
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let file = OpenOptions::new().write(true).create(true).truncate(true).open(path)?;
+        let mut writer = BufWriter::new(file);
+        self.write_dot(&mut writer)?;
+        writer.flush()
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
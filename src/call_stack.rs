@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use crate::cpu::CpuArchitecture;
+use crate::memory::AllocatedRam;
+
+/// the program starts at the first instruction, which is counted as its own function when it isn't called
+pub const ENTRY_ADDRESS: CpuArchitecture = 0;
+
+#[derive(Clone)]
+struct CallFrame {
+    function: CpuArchitecture,
+    return_address: CpuArchitecture,
+    /// the stack pointer before the return address was pushed, the arguments are directly below it
+    stack_pointer: CpuArchitecture,
+    /// false once the frame has been reused by a tail call
+    has_arguments: bool,
+}
+
+/// keeps track of the functions that are being called so the debugger can show a backtrace and the profiler can count
+/// the instructions of every function, only used when running with --debug, --profile or --emit-callgraph
+#[derive(Clone)]
+pub struct CallStack {
+    functions: HashMap<CpuArchitecture, String>,
+    /// the sizes of the arguments pushed by callargs by the return address of the call
+    arguments: HashMap<CpuArchitecture, Vec<CpuArchitecture>>,
+    frames: Vec<CallFrame>,
+}
+
+impl CallStack {
+    pub fn new(functions: Vec<(String, CpuArchitecture)>, arguments: HashMap<CpuArchitecture, Vec<CpuArchitecture>>) -> Self {
+        Self {
+            functions: functions.into_iter().map(| (name, address) | { (address, name) }).collect(),
+            arguments,
+            frames: Vec::new(),
+        }
+    }
+
+    /// frames above the stack pointer have been left without returning, eg: by popping the return address and jumping
+    fn remove_left_frames(&mut self, stack_pointer: CpuArchitecture) {
+        while self.frames.last().is_some_and(| frame | { frame.stack_pointer >= stack_pointer }) {
+            self.frames.pop();
+        }
+    }
+
+    /// forgets the frames when the program is restarted
+    pub fn reset(&mut self) {
+        self.frames.clear();
+    }
+
+    pub fn on_call(&mut self, function: CpuArchitecture, return_address: CpuArchitecture, stack_pointer: CpuArchitecture) {
+        self.remove_left_frames(stack_pointer);
+        self.frames.push(CallFrame { function, return_address, stack_pointer, has_arguments: true });
+    }
+
+    /// the current frame is reused by the function, the arguments of the call are no longer known
+    pub fn on_tail_call(&mut self, function: CpuArchitecture) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.function = function;
+            frame.has_arguments = false;
+        }
+    }
+
+    /// the function that is being called, the entry when not within a function
+    pub fn current_function(&self, stack_pointer: CpuArchitecture) -> CpuArchitecture {
+        self.functions(stack_pointer).last().unwrap_or(ENTRY_ADDRESS)
+    }
+
+    pub fn on_ret(&mut self) {
+        self.frames.pop();
+    }
+
+    /// the addresses of the functions that are being called from the outermost call inwards
+    pub fn functions(&self, stack_pointer: CpuArchitecture) -> impl Iterator<Item = CpuArchitecture> + '_ {
+        self.frames.iter()
+            .filter(move | frame | { frame.stack_pointer < stack_pointer })
+            .map(| frame | { frame.function })
+    }
+
+    fn function_name(&self, address: CpuArchitecture) -> String {
+        match self.functions.get(&address) {
+            Some(name) => format!("{} (0x{:X})", name, address),
+            None => format!("0x{:X}", address),
+        }
+    }
+
+    /// the frames from the innermost call outwards with the arguments read from the stack
+    pub fn backtrace(&self, stack: &AllocatedRam, stack_pointer: CpuArchitecture) -> String {
+        let frames: Vec<_> = self.frames.iter()
+            .filter(| frame | { frame.stack_pointer < stack_pointer })
+            .rev()
+            .collect();
+        if frames.is_empty() {
+            return "not within a function".to_string();
+        }
+
+        let mut lines = Vec::with_capacity(frames.len());
+        for (index, frame) in frames.iter().enumerate() {
+            let mut line = format!("#{} {}", index, self.function_name(frame.function));
+            if let Some(sizes) = self.arguments.get(&frame.return_address).filter(| _ | { frame.has_arguments }) {
+                let mut address = frame.stack_pointer;
+                let values: Vec<_> = sizes.iter().map(| size | {
+                    address = address.wrapping_sub(*size);
+                    let mut buffer = [0u8; size_of::<CpuArchitecture>()];
+                    match stack.read_buffer_at(address, &mut buffer[..*size as usize]) {
+                        Ok(()) => CpuArchitecture::from_le_bytes(buffer).to_string(),
+                        Err(_) => "?".to_string(),
+                    }
+                }).collect();
+                line += &format!(", arguments: {}", values.join(", "));
+            }
+            line += &format!(", returns to 0x{:X}", frame.return_address);
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+}
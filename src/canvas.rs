@@ -0,0 +1,147 @@
+use crate::cpu::CpuArchitecture;
+use crate::memory::{Ram, RamError, RamErrorKind};
+
+const PIXEL_SIZE: usize = size_of::<[u8;4]>();
+
+/// the pixels of the open window within the memory, drawn into by the drawing syscalls, the shapes are clipped to the
+/// canvas so their coordinates can be outside of it
+#[derive(Clone, Copy, Debug)]
+pub struct Canvas {
+    address: CpuArchitecture,
+    width: i32,
+    height: i32,
+}
+
+impl Canvas {
+    pub fn new(address: CpuArchitecture, size: (usize, usize)) -> Self {
+        Self {
+            address,
+            width: size.0 as i32,
+            height: size.1 as i32,
+        }
+    }
+
+    /// draws a line from the start to the end including both
+    pub fn draw_line(self, ram: &mut Ram, start: (i32, i32), end: (i32, i32), color: [u8;4]) -> Result<(), RamError> {
+        self.draw(ram, | pixels | {
+            // bresenham's line algorithm
+            let (mut x, mut y) = start;
+            let delta_x = (end.0 - x).abs();
+            let delta_y = -(end.1 - y).abs();
+            let step_x = if x < end.0 { 1 } else { -1 };
+            let step_y = if y < end.1 { 1 } else { -1 };
+            let mut error = delta_x + delta_y;
+            loop {
+                self.plot(pixels, x, y, color);
+                if x == end.0 && y == end.1 {
+                    break;
+                }
+                let doubled_error = error * 2;
+                if doubled_error >= delta_y {
+                    error += delta_y;
+                    x += step_x;
+                }
+                if doubled_error <= delta_x {
+                    error += delta_x;
+                    y += step_y;
+                }
+            }
+        })
+    }
+
+    /// draws the outline of the rectangle with its top left corner at the position
+    pub fn draw_rect(self, ram: &mut Ram, position: (i32, i32), size: (i32, i32), color: [u8;4]) -> Result<(), RamError> {
+        if size.0 == 0 || size.1 == 0 {
+            return Ok(());
+        }
+        let right = position.0 + size.0 - 1;
+        let bottom = position.1 + size.1 - 1;
+        self.draw(ram, | pixels | {
+            self.fill(pixels, position.0, position.1, right, position.1, color);
+            self.fill(pixels, position.0, bottom, right, bottom, color);
+            self.fill(pixels, position.0, position.1, position.0, bottom, color);
+            self.fill(pixels, right, position.1, right, bottom, color);
+        })
+    }
+
+    /// fills the rectangle with its top left corner at the position
+    pub fn fill_rect(self, ram: &mut Ram, position: (i32, i32), size: (i32, i32), color: [u8;4]) -> Result<(), RamError> {
+        if size.0 == 0 || size.1 == 0 {
+            return Ok(());
+        }
+        self.draw(ram, | pixels | {
+            self.fill(pixels, position.0, position.1, position.0 + size.0 - 1, position.1 + size.1 - 1, color);
+        })
+    }
+
+    /// draws the outline of the circle around the center
+    pub fn draw_circle(self, ram: &mut Ram, center: (i32, i32), radius: i32, color: [u8;4]) -> Result<(), RamError> {
+        self.draw(ram, | pixels | {
+            // the midpoint circle algorithm, every point is mirrored into the other 7 octants
+            let mut x = radius;
+            let mut y = 0;
+            let mut error = 1 - radius;
+            while x >= y {
+                for (offset_x, offset_y) in [(x, y), (y, x), (-y, x), (-x, y), (-x, -y), (-y, -x), (y, -x), (x, -y)] {
+                    self.plot(pixels, center.0 + offset_x, center.1 + offset_y, color);
+                }
+                y += 1;
+                if error < 0 {
+                    error += 2 * y + 1;
+                } else {
+                    x -= 1;
+                    error += 2 * (y - x) + 1;
+                }
+            }
+        })
+    }
+
+    /// errors when the pixels don't fit in the memory instead of drawing into a part of them
+    fn draw(self, ram: &mut Ram, callback: impl FnOnce(&mut [u8])) -> Result<(), RamError> {
+        let length = self.width as usize * self.height as usize * PIXEL_SIZE;
+        let Ok(length) = CpuArchitecture::try_from(length) else {
+            return Err(RamError::with_message(RamErrorKind::IndexOutOfBounds, format!("the canvas is {} bytes", length)));
+        };
+        ram.borrow_buffer_mut_checked(self.address, length, callback)
+    }
+
+    fn plot(self, pixels: &mut [u8], x: i32, y: i32, color: [u8;4]) {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return;
+        }
+        let index = (y * self.width + x) as usize * PIXEL_SIZE;
+        pixels[index..index + PIXEL_SIZE].copy_from_slice(&color);
+    }
+
+    /// fills the pixels from the top left to the bottom right corner including both, clipped to the canvas
+    fn fill(self, pixels: &mut [u8], left: i32, top: i32, right: i32, bottom: i32, color: [u8;4]) {
+        let (left, right) = (left.min(right).max(0), left.max(right).min(self.width - 1));
+        let (top, bottom) = (top.min(bottom).max(0), top.max(bottom).min(self.height - 1));
+        if left > right || top > bottom {
+            return;
+        }
+        for y in top..=bottom {
+            let start = (y * self.width + left) as usize * PIXEL_SIZE;
+            let end = (y * self.width + right + 1) as usize * PIXEL_SIZE;
+            for pixel in pixels[start..end].chunks_exact_mut(PIXEL_SIZE) {
+                pixel.copy_from_slice(&color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canvas_larger_than_the_memory_errors() {
+        let mut ram = Ram::new(u16::MAX).unwrap();
+        // 200 * 200 pixels are 160000 bytes, which wraps around to 28928 when truncated
+        let allocation = ram.alloc(28928).unwrap();
+        let canvas = Canvas::new(allocation.range().start, (200, 200));
+
+        let result = canvas.fill_rect(&mut ram, (0, 0), (200, 200), [255; 4]);
+        assert!(matches!(result, Err(error) if error.kind() == &RamErrorKind::IndexOutOfBounds));
+    }
+}
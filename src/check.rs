@@ -0,0 +1,444 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::BufReader;
+use std::path::Path;
+use crate::compile::{is_binary_path, load_program};
+use crate::computer::REGISTER_COUNT;
+use crate::cpu::{CpuArchitecture, SignedCpuArchitecture};
+use crate::instructions::{Instruction, InstructionSet};
+use crate::operand::{Operand, Register};
+use crate::program::{Program, INSTRUCTION_SIZE};
+
+/// the instructions that write their destination without reading it first
+const OVERWRITING_INSTRUCTIONS: &[&str] = &["Mov", "Movzx", "Movsx", "Pop", "Set", "Addr", "In", "Rand", "Lods", "Popcnt", "Clz", "Ctz", "Ftoi"];
+/// the instructions whose destination is only read, it holds an address or only the bit is tested
+const READ_DESTINATION_INSTRUCTIONS: &[&str] = &["Bt", "Memcpy", "Memset"];
+
+/// a problem found by the analysis at the address of an instruction
+struct Warning {
+    address: CpuArchitecture,
+    /// the address of the next instruction, used to find the line of the instruction
+    next_address: CpuArchitecture,
+    message: String,
+}
+
+struct Function<'a> {
+    name: &'a str,
+    address: CpuArchitecture,
+    /// the instructions from the start of the function until the next function with the address of the next instruction
+    instructions: Vec<(InstructionSet, CpuArchitecture, CpuArchitecture)>,
+}
+
+impl Function<'_> {
+    fn index_of(&self, address: CpuArchitecture) -> Option<usize> {
+        self.instructions.binary_search_by_key(&address, | (_, address, _) | { *address }).ok()
+    }
+
+    fn description(&self) -> String {
+        format!("{} (0x{:X})", self.name, self.address)
+    }
+}
+
+/// the bytes pushed since the start of the function and the depth at which bp was set to sp
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct StackState {
+    depth: i32,
+    frame: Option<i32>,
+    /// whether the compare flag is known to be set so jmp always jumps, eg: after clf
+    compare_flag: bool,
+}
+
+/// the registers a function uses by register number
+#[derive(Default)]
+struct RegisterUsage {
+    /// the registers read before the function writes them, these are the arguments of the function
+    arguments: BTreeSet<u8>,
+    /// the registers written by the function or the functions it calls that aren't restored
+    clobbered: BTreeSet<u8>,
+    /// the registers that are pushed and popped by the function
+    saved: BTreeSet<u8>,
+}
+
+fn register_number(register: Register) -> u8 {
+    register.register_number(REGISTER_COUNT as u8)
+}
+
+/// sp and bp are kept track of by the stack analysis instead
+fn is_general_register(number: u8) -> bool {
+    number < REGISTER_COUNT as u8 - 2
+}
+
+/// the name of the full size register, the first register can only be used as byte register
+pub(crate) fn register_name(number: u8) -> String {
+    match number.checked_sub(1) {
+        Some(index) => Register::new(index, size_of::<CpuArchitecture>() as u8).to_string(),
+        None => Register::new(0, 1).to_string(),
+    }
+}
+
+fn is_stack_pointer(operand: Operand) -> bool {
+    matches!(operand, Operand::Register(register) if register.is_stack_pointer())
+}
+
+fn is_base_pointer(operand: Operand) -> bool {
+    matches!(operand, Operand::Register(register) if register.is_base_pointer())
+}
+
+fn literal(operand: Operand) -> Option<i32> {
+    match operand {
+        Operand::Literal(literal) => Some(literal.literal() as SignedCpuArchitecture as i32),
+        _ => None,
+    }
+}
+
+/// the general registers read and written by the instruction by register number, the registers of a pointer are read
+pub(crate) fn register_accesses(instruction: InstructionSet) -> (Vec<u8>, Vec<u8>) {
+    let name: &str = instruction.as_ref();
+    let documentation = InstructionSet::DOCUMENTATION.iter().find(| documentation | { documentation.number == instruction.to_num() });
+    let operand_names = documentation.map_or(&[][..], | documentation | { documentation.operands });
+
+    let mut reads = Vec::new();
+    let mut writes = Vec::new();
+    for (operand_name, operand) in operand_names.iter().zip(instruction.operands()) {
+        let registers = operand.registers().map(register_number);
+        if !matches!(operand, Operand::Register(_) | Operand::RegisterPair(_)) {
+            reads.extend(registers);
+            continue;
+        }
+
+        let written = match *operand_name {
+            "destination" => !READ_DESTINATION_INSTRUCTIONS.contains(&name),
+            "a" | "b" => name == "Xchg",
+            "source" => name == "Xadd",
+            "expected" => name == "Cas",
+            _ => false,
+        };
+        let read = !written || !(*operand_name == "destination" && OVERWRITING_INSTRUCTIONS.contains(&name));
+        for register in registers {
+            if read {
+                reads.push(register);
+            }
+            if written {
+                writes.push(register);
+            }
+        }
+    }
+
+    // lods and stos advance the address within x9 and x10
+    let implicit = match instruction {
+        InstructionSet::Lods(_) => Some(Register::new(8, size_of::<CpuArchitecture>() as u8)),
+        InstructionSet::Stos(_) => Some(Register::new(9, size_of::<CpuArchitecture>() as u8)),
+        _ => None,
+    };
+    if let Some(register) = implicit {
+        reads.push(register_number(register));
+        writes.push(register_number(register));
+    }
+
+    reads.retain(| register | { is_general_register(*register) });
+    writes.retain(| register | { is_general_register(*register) });
+    (reads, writes)
+}
+
+/// the address that is called or jumped to, None if it is only known at runtime
+fn control_flow_target(instruction: InstructionSet, next_address: CpuArchitecture) -> Option<CpuArchitecture> {
+    match instruction {
+        InstructionSet::Rjmp(rjmp) => literal(rjmp.offset()).map(| offset | { next_address.wrapping_add(offset as CpuArchitecture) }),
+        InstructionSet::Rcall(rcall) => literal(rcall.offset()).map(| offset | { next_address.wrapping_add(offset as CpuArchitecture) }),
+        _ => match instruction.control_flow_address() {
+            Some(Operand::Literal(literal)) => Some(literal.literal()),
+            _ => None,
+        },
+    }
+}
+
+fn is_call(instruction: InstructionSet) -> bool {
+    matches!(instruction, InstructionSet::Call(_) | InstructionSet::Rcall(_))
+}
+
+fn is_jump(instruction: InstructionSet) -> bool {
+    !is_call(instruction) && !matches!(instruction, InstructionSet::Tailcall(_) | InstructionSet::Addr(_) | InstructionSet::Switch(_) | InstructionSet::Case(_)) &&
+        (instruction.control_flow_address().is_some() || matches!(instruction, InstructionSet::Rjmp(_)))
+}
+
+fn sets_compare_flag(instruction: InstructionSet) -> bool {
+    matches!(instruction, InstructionSet::Cmpe(_) | InstructionSet::Cmpne(_) | InstructionSet::Cmple(_) | InstructionSet::Cmpl(_) |
+        InstructionSet::Cmpge(_) | InstructionSet::Cmpg(_) | InstructionSet::Fcmp(_) | InstructionSet::Bt(_) | InstructionSet::Bts(_) |
+        InstructionSet::Btr(_))
+}
+
+/// the stack after the instruction, None if the stack pointer is changed in a way that can't be followed
+fn apply_stack_effect(instruction: InstructionSet, mut state: StackState) -> Option<StackState> {
+    let pointer_size = size_of::<CpuArchitecture>() as i32;
+    if sets_compare_flag(instruction) {
+        state.compare_flag = false;
+    }
+
+    match instruction {
+        InstructionSet::Push(push) => state.depth += push.source().size() as i32,
+        InstructionSet::Pop(pop) => {
+            state.depth -= pop.destination().size() as i32;
+            if is_stack_pointer(pop.destination()) {
+                return None;
+            }
+            if is_base_pointer(pop.destination()) {
+                state.frame = None;
+            }
+        },
+        InstructionSet::Enter(enter) => {
+            state.depth += pointer_size;
+            state.frame = Some(state.depth);
+            state.depth += literal(enter.size())?;
+        },
+        InstructionSet::Leave(_) => {
+            state.depth = state.frame? - pointer_size;
+            state.frame = None;
+        },
+        InstructionSet::Clf(_) => state.compare_flag = true,
+        InstructionSet::Add(add) if is_stack_pointer(add.destination()) => state.depth += literal(add.source())?,
+        InstructionSet::Sub(sub) if is_stack_pointer(sub.destination()) => state.depth -= literal(sub.source())?,
+        InstructionSet::Mov(mov) if is_stack_pointer(mov.destination()) && is_base_pointer(mov.source()) => state.depth = state.frame?,
+        InstructionSet::Mov(mov) if is_base_pointer(mov.destination()) && is_stack_pointer(mov.source()) => state.frame = Some(state.depth),
+        _ => {
+            let name: &str = instruction.as_ref();
+            let destinations = instruction.operands().into_iter().take(1).filter(| operand | {
+                matches!(operand, Operand::Register(_)) && !READ_DESTINATION_INSTRUCTIONS.contains(&name)
+            });
+            for destination in destinations {
+                if is_stack_pointer(destination) {
+                    return None;
+                }
+                if is_base_pointer(destination) {
+                    state.frame = None;
+                }
+            }
+        },
+    }
+    Some(state)
+}
+
+/// follows every path through the function and warns when it returns or tail calls with a different stack depth than
+/// it started with, or when paths reach the same instruction with a different depth
+fn check_stack(function: &Function, warnings: &mut Vec<Warning>) {
+    let mut visited: HashMap<CpuArchitecture, i32> = HashMap::new();
+    // every depth a function is left with is reported instead of only the first
+    let mut left = HashSet::new();
+    let mut pending = vec![(function.address, StackState { depth: 0, frame: None, compare_flag: false })];
+
+    while let Some((address, state)) = pending.pop() {
+        // jumps to other functions aren't followed
+        let Some(index) = function.index_of(address) else { continue; };
+        let (instruction, address, next_address) = function.instructions[index];
+        let leaving = match instruction {
+            InstructionSet::Ret(_) | InstructionSet::Iret(_) => Some("returns"),
+            InstructionSet::Tailcall(_) => Some("tail calls"),
+            _ => None,
+        };
+
+        if let Some(leaving) = leaving {
+            if state.depth != 0 && left.insert((address, state.depth)) {
+                let imbalance = if state.depth > 0 {
+                    format!("with {} bytes still pushed", state.depth)
+                } else {
+                    format!("after popping {} bytes more than it pushed", -state.depth)
+                };
+                warnings.push(Warning {
+                    address,
+                    next_address,
+                    message: format!("{} {} {}", function.description(), leaving, imbalance),
+                });
+            }
+            continue;
+        }
+        match visited.get(&address) {
+            Some(depth) if *depth == state.depth => continue,
+            Some(depth) => {
+                warnings.push(Warning {
+                    address,
+                    next_address,
+                    message: format!("{} has {} bytes pushed on one path to the instruction and {} bytes on another", function.description(), depth, state.depth),
+                });
+                continue;
+            },
+            None => { visited.insert(address, state.depth); },
+        }
+        if matches!(instruction, InstructionSet::Exit(_) | InstructionSet::Switch(_)) {
+            continue;
+        }
+
+        let Some(state) = apply_stack_effect(instruction, state) else { continue; };
+        if is_jump(instruction) {
+            if let Some(target) = control_flow_target(instruction, next_address) {
+                pending.push((target, state));
+            }
+            let unconditional = state.compare_flag && matches!(instruction, InstructionSet::Jmp(_) | InstructionSet::Rjmp(_));
+            if unconditional {
+                continue;
+            }
+        }
+        pending.push((next_address, state));
+    }
+}
+
+fn register_usage(function: &Function) -> RegisterUsage {
+    let mut usage = RegisterUsage::default();
+    let mut accessed = BTreeSet::new();
+    let mut pushed = BTreeSet::new();
+    let mut popped = BTreeSet::new();
+
+    for (instruction, _, _) in &function.instructions {
+        let (reads, writes) = register_accesses(*instruction);
+        for register in reads {
+            if accessed.insert(register) {
+                usage.arguments.insert(register);
+            }
+        }
+        accessed.extend(writes.iter().copied());
+        usage.clobbered.extend(writes);
+
+        match instruction {
+            InstructionSet::Push(push) => pushed.extend(push.source().registers().map(register_number)),
+            InstructionSet::Pop(pop) => popped.extend(pop.destination().registers().map(register_number)),
+            _ => {},
+        }
+    }
+
+    usage.saved = pushed.intersection(&popped).copied().collect();
+    usage.clobbered = usage.clobbered.difference(&usage.saved).copied().collect();
+    usage.clobbered = usage.clobbered.difference(&usage.arguments).copied().collect();
+    usage
+}
+
+/// the functions of the program called by the function with the index of the call
+fn calls(function: &Function, functions: &HashMap<CpuArchitecture, usize>, dependency_calls: &HashSet<CpuArchitecture>) -> Vec<(usize, usize)> {
+    function.instructions.iter().enumerate().filter_map(| (index, (instruction, address, next_address)) | {
+        if !is_call(*instruction) || dependency_calls.contains(address) {
+            return None;
+        }
+        let callee = functions.get(&control_flow_target(*instruction, *next_address)?)?;
+        Some((index, *callee))
+    }).collect()
+}
+
+/// warns when a function sets a register or receives it as argument, calls a function that overwrites it without
+/// restoring it and reads it again after the call, the instructions are scanned in order so jumps aren't followed
+fn check_clobbered_registers(functions: &[Function], dependency_calls: &HashSet<CpuArchitecture>, warnings: &mut Vec<Warning>) {
+    let indices: HashMap<_, _> = functions.iter().enumerate().map(| (index, function) | { (function.address, index) }).collect();
+    let calls: Vec<_> = functions.iter().map(| function | { calls(function, &indices, dependency_calls) }).collect();
+    let mut usages: Vec<_> = functions.iter().map(register_usage).collect();
+
+    // the registers clobbered by a callee are clobbered by the caller too unless the caller saves them
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (index, function_calls) in calls.iter().enumerate() {
+            let inherited: BTreeSet<_> = function_calls.iter()
+                .flat_map(| (_, callee) | { usages[*callee].clobbered.clone() })
+                .filter(| register | { !usages[index].saved.contains(register) && !usages[index].arguments.contains(register) })
+                .collect();
+            let clobbered = &mut usages[index].clobbered;
+            let count = clobbered.len();
+            clobbered.extend(inherited);
+            changed |= clobbered.len() != count;
+        }
+    }
+
+    for (index, function) in functions.iter().enumerate() {
+        for (call_index, callee) in &calls[index] {
+            for register in &usages[*callee].clobbered {
+                let set_before = usages[index].arguments.contains(register) ||
+                    function.instructions[..*call_index].iter().any(| (instruction, _, _) | { register_accesses(*instruction).1.contains(register) });
+                if !set_before || !is_read_after(function, *call_index + 1, *register, &calls[index], &usages) {
+                    continue;
+                }
+
+                let (_, address, next_address) = function.instructions[*call_index];
+                warnings.push(Warning {
+                    address,
+                    next_address,
+                    message: format!("{} uses {} after calling {}, which overwrites it without restoring it",
+                                     function.description(), register_name(*register), functions[*callee].description()),
+                });
+            }
+        }
+    }
+}
+
+/// whether the register is read before it is written again starting at the instruction, stops at the end of the function
+fn is_read_after(function: &Function, start: usize, register: u8, calls: &[(usize, usize)], usages: &[RegisterUsage]) -> bool {
+    for (index, (instruction, _, _)) in function.instructions.iter().enumerate().skip(start) {
+        let (reads, writes) = register_accesses(*instruction);
+        if reads.contains(&register) {
+            return true;
+        }
+        let overwritten_by_call = calls.iter().any(| (call_index, callee) | { *call_index == index && usages[*callee].clobbered.contains(&register) });
+        if writes.contains(&register) || overwritten_by_call ||
+            matches!(instruction, InstructionSet::Ret(_) | InstructionSet::Iret(_) | InstructionSet::Tailcall(_) | InstructionSet::Exit(_)) {
+            return false;
+        }
+    }
+    false
+}
+
+/// checks every function for stack imbalance and registers that are overwritten by the functions it calls
+fn analyze(program: &Program) -> Vec<Warning> {
+    let addresses: Vec<_> = program.instructions().iter()
+        .map(| (instruction, address) | { (*instruction, address, address + instruction.binary_size() + INSTRUCTION_SIZE) })
+        .collect();
+    // the functions of other programs aren't known so calls to them are ignored
+    let dependency_calls: HashSet<_> = program.dependency_calls().map(| position | { addresses[position].1 }).collect();
+    let sorted_functions = program.sorted_functions();
+    let functions: Vec<_> = sorted_functions.iter().enumerate()
+        .filter(| (index, (_, address)) | { sorted_functions.get(index + 1).is_none_or(| (_, next) | { next != address }) })
+        .map(| (index, (name, address)) | {
+            let end = sorted_functions.get(index + 1).map_or(CpuArchitecture::MAX, | (_, end) | { *end });
+            Function {
+                name,
+                address: *address,
+                instructions: addresses.iter().filter(| (_, start, _) | { (*address..end).contains(start) }).copied().collect(),
+            }
+        })
+        .collect();
+
+    let mut warnings = Vec::new();
+    for function in &functions {
+        check_stack(function, &mut warnings);
+    }
+    check_clobbered_registers(&functions, &dependency_calls, &mut warnings);
+    warnings.sort_by_key(| warning | { warning.address });
+    warnings
+}
+
+/// compiles the program and reports the errors, analyzing it as well when analyze is set
+pub fn check(path: String, analyze_program: bool) {
+    let path = Path::new(&path);
+    let result = load_program(path);
+    let program = match result {
+        Ok(program) => program,
+        Err(err) => { println!("could not compile program: {}, filename: {}", err, path.display()); return; }
+    };
+    if !analyze_program {
+        println!("the program compiled without errors");
+        return;
+    }
+    if is_binary_path(path) {
+        println!("a binary doesn't keep the functions of the program, analyze the assembly file it was built from instead");
+        return;
+    }
+
+    let warnings = analyze(&program);
+    for warning in &warnings {
+        match line(path, warning) {
+            Some((line_number, line)) => println!("warning: {}, at 0x{:X} on line {}: \"{}\"", warning.message, warning.address, line_number, line.trim()),
+            None => println!("warning: {}, at 0x{:X}", warning.message, warning.address),
+        }
+    }
+    println!("the analysis found {} warning{}", warnings.len(), if warnings.len() == 1 { "" } else { "s" });
+}
+
+/// the line of the instruction of the warning within the assembly file
+fn line(path: &Path, warning: &Warning) -> Option<(u32, String)> {
+    let file = OpenOptions::new().read(true).open(path).ok()?;
+    // the instructions of callargs don't end at a line so no line is found for them
+    Program::get_line(warning.next_address, &mut BufReader::new(file)).ok().filter(| (_, line) | { !line.trim().is_empty() })
+}
@@ -1,17 +1,41 @@
 use std::cell::Cell;
 use std::fs::OpenOptions;
-use std::io::{BufReader, BufWriter, Seek, SeekFrom};
+use std::io::{BufReader, BufWriter, Cursor, Read, Seek, SeekFrom};
 use std::path::Path;
 use crate::computer::{Computer, REGISTER_COUNT};
+use crate::hot_reload::HotReload;
+use crate::fs_image::FsImage;
+use crate::frame_capture::FrameCapture;
+use crate::audio::{AudioDevice, AudioOutput};
+use crate::input_script::InputScript;
+use crate::serial::{SerialMode, Uart, SERIAL_PORTS};
+use crate::rng::Rng;
+use crate::sanitizer::Sanitizer;
+use crate::nvram::Nvram;
+use crate::sandbox::{SandboxPolicy, ALLOWED_ENV_VARS, SANDBOX};
+use crate::instructions::SHOW_HUD;
 use crate::cpu::{Cpu, CpuArchitecture};
-use crate::memory::Ram;
+use crate::memory::{AllocStrategy, Ram};
 use crate::program::{DEPENDENCY_EXTENSION, Program};
 
 thread_local! {
     pub static DEBUG: Cell<bool> = const { Cell::new(false) };
+    pub static MMAP_DEPENDENCIES: Cell<bool> = const { Cell::new(false) };
+    pub static STRICT: Cell<bool> = const { Cell::new(false) };
+    pub static SANITIZE: Cell<bool> = const { Cell::new(false) };
+    pub static MEMVIZ: Cell<bool> = const { Cell::new(false) };
+    pub static TIME: Cell<bool> = const { Cell::new(false) };
+    pub static PROFILE: Cell<bool> = const { Cell::new(false) };
+    /// the amount of instructions executed per frame of the window, 0 runs the program until it waits for an event
+    pub static CYCLES_PER_FRAME: Cell<u32> = const { Cell::new(0) };
 }
 
-pub fn build(path: String, out: Option<String>){
+pub fn build(path: String, out: Option<String>, mmap_dependencies: bool, arch: u32){
+    if arch != CpuArchitecture::BITS {
+        println!("cannot build for a {} bit cpu, the emulator only supports its {} bit architecture", arch, CpuArchitecture::BITS);
+        return;
+    }
+    MMAP_DEPENDENCIES.set(mmap_dependencies);
     let path = Path::new(&path);
     let out = out.unwrap_or_else(| | {
         path.with_extension(&DEPENDENCY_EXTENSION[1..]).to_str().unwrap().to_string()
@@ -45,43 +69,203 @@ pub fn build(path: String, out: Option<String>){
     println!("file has been successfully build and is stored at {}", out.display());
 }
 
-pub fn run(path: String, memory_amount: CpuArchitecture, debug: bool) {
-    DEBUG.set(debug);
-    
-    let mem = Ram::new(memory_amount);
-    let cpu = Cpu::<REGISTER_COUNT>::new();
-
-    let mut computer = Computer::new(cpu, mem);
-
-    let path = Path::new(&path);
-    let result = OpenOptions::new().read(true).open(path);
-    let file = match result {
-        Ok(file) => file,
-        Err(err) => { println!("could not read from file: {}, filename: {}", err, path.display()); return; }
-    };
+pub fn is_binary_path(path: &Path) -> bool {
+    path.extension().unwrap_or("".as_ref()).eq(&DEPENDENCY_EXTENSION[1..])
+}
 
+/// loads an assembly or binary file as a program
+pub fn load_program(path: &Path) -> std::io::Result<Program> {
+    let file = OpenOptions::new().read(true).open(path)?;
     let mut buf_reader = BufReader::with_capacity(4096, file);
-    let result = if path.extension().unwrap_or("".as_ref()).eq(&DEPENDENCY_EXTENSION[1..]) {
+    if is_binary_path(path) {
         Program::from_binary(&mut buf_reader)
     } else {
         Program::from_stream(&mut buf_reader)
+    }
+}
+
+/// the settings of the emulator when running a program
+pub struct RunOptions {
+    pub memory_amount: CpuArchitecture,
+    pub memory_max: Option<CpuArchitecture>,
+    pub alloc_strategy: AllocStrategy,
+    pub aslr: bool,
+    pub banks: u8,
+    pub debug: bool,
+    pub watch: bool,
+    pub fuse: bool,
+    pub fs: Option<String>,
+    pub nvram: Option<String>,
+    pub mmap_dependencies: bool,
+    pub strict: bool,
+    pub sanitize: bool,
+    pub memviz: bool,
+    pub hud: bool,
+    pub time: bool,
+    pub profile: bool,
+    pub emit_callgraph: Option<String>,
+    pub cycles_per_frame: Option<u32>,
+    pub capture_frames: Option<String>,
+    pub capture_every: u32,
+    pub audio: Option<AudioOutput>,
+    pub input_script: Option<String>,
+    pub serial: SerialMode,
+    pub seed: Option<u64>,
+    pub sandbox: SandboxPolicy,
+    pub allowed_env_vars: Vec<String>,
+    pub arguments: Vec<String>,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            memory_amount: 1024,
+            memory_max: None,
+            alloc_strategy: AllocStrategy::FirstFit,
+            aslr: false,
+            banks: 0,
+            debug: false,
+            watch: false,
+            fuse: false,
+            fs: None,
+            nvram: None,
+            mmap_dependencies: false,
+            strict: false,
+            sanitize: false,
+            memviz: false,
+            hud: false,
+            time: false,
+            profile: false,
+            emit_callgraph: None,
+            cycles_per_frame: None,
+            capture_frames: None,
+            capture_every: 1,
+            audio: None,
+            input_script: None,
+            serial: SerialMode::Stdio,
+            seed: None,
+            sandbox: SandboxPolicy::deny_all(),
+            allowed_env_vars: Vec::new(),
+            arguments: Vec::new(),
+        }
+    }
+}
+
+/// applies the emulator settings and creates the computer that will run the program
+fn create_computer(options: &RunOptions) -> Option<Computer> {
+    DEBUG.set(options.debug);
+    MMAP_DEPENDENCIES.set(options.mmap_dependencies);
+    STRICT.set(options.strict);
+    SANITIZE.set(options.sanitize);
+    MEMVIZ.set(options.memviz);
+    TIME.set(options.time);
+    PROFILE.set(options.profile);
+    SHOW_HUD.set(options.hud);
+    CYCLES_PER_FRAME.set(options.cycles_per_frame.unwrap_or(0));
+    SANDBOX.set(options.sandbox);
+    ALLOWED_ENV_VARS.set(options.allowed_env_vars.clone());
+    
+    let result = Ram::with_maximum(options.memory_amount, options.memory_max.unwrap_or(options.memory_amount))
+        .and_then(| mut mem | { mem.add_banks(options.banks).map(| _ | mem) });
+    let mut mem = match result {
+        Ok(mem) => mem,
+        Err(err) => { println!("could not create the emulator memory: {}", err); return None; }
     };
-    let program = match result {
-        Ok(program) => program,
-        Err(err) => { println!("could not compile program: {}", err); return; }
-    };
+    mem.set_alloc_strategy(options.alloc_strategy);
+    let mut cpu = Cpu::<REGISTER_COUNT>::new();
+    // the sanitizer checks the instructions one by one so they aren't fused
+    cpu.set_use_decode_cache(options.fuse && !options.sanitize);
 
+    let mut computer = Computer::new(cpu, mem);
+    computer.set_arguments(options.arguments.clone());
+    if let Some(path) = &options.emit_callgraph {
+        computer.set_call_graph_path(path.clone());
+    }
+    
+    if let Some(fs) = &options.fs {
+        let result = FsImage::open_or_create(fs);
+        match result {
+            Ok(image) => computer.files_mut().set_image(image),
+            Err(err) => { println!("could not open filesystem image: {}, filename: {}", err, fs); return None; }
+        }
+    }
+    
+    if let Some(path) = &options.nvram {
+        let result = Nvram::open(path);
+        let nvram = match result {
+            Ok(nvram) => nvram,
+            Err(err) => { println!("could not open the nvram: {}, filename: {}", err, path); return None; }
+        };
+        if let Err(err) = computer.attach_nvram(nvram) {
+            println!("could not attach the nvram as a bank: {}", err);
+            return None;
+        }
+    }
+    
+    if let Some(directory) = &options.capture_frames {
+        let result = FrameCapture::new(directory, options.capture_every);
+        match result {
+            Ok(frame_capture) => computer.set_frame_capture(frame_capture),
+            Err(err) => { println!("could not create the frame capture directory: {}, directory: {}", err, directory); return None; }
+        }
+    }
+    
+    if let Some(output) = &options.audio {
+        let result = AudioDevice::open(output);
+        match result {
+            Ok(audio) => computer.set_audio(audio),
+            Err(err) => { println!("could not open the audio output: {}, audio: {:?}", err, output); return None; }
+        }
+    }
+    
+    if let Some(path) = &options.input_script {
+        let result = InputScript::from_file(path);
+        match result {
+            Ok(input_script) => computer.set_input_script(input_script),
+            Err(err) => { println!("could not read input script: {}, filename: {}", err, path); return None; }
+        }
+    }
+    
+    if let Some(seed) = options.seed {
+        computer.set_rng(Rng::new(seed));
+    } else if options.debug {
+        let seed = computer.rng_mut().seed();
+        println!("the seed of rand is {}, use --seed {} to get the same numbers", seed, seed);
+    }
+    
+    if options.aslr {
+        let seed = computer.rng_mut().seed();
+        computer.ram_mut().set_aslr(Rng::new(seed));
+    }
+    
+    if let SerialMode::Tcp(_) = options.serial {
+        let result = Uart::open(options.serial);
+        match result {
+            Ok(uart) => computer.device_bus_mut().register(SERIAL_PORTS, Box::new(uart)),
+            Err(err) => { println!("could not open the serial console: {}, serial: {:?}", err, options.serial); return None; }
+        }
+    }
+    
+    Some(computer)
+}
+
+/// runs the program and prints the error and the line where it occurred in debug mode or when sanitizing,
+/// followed by the violations found by the sanitizer
+fn start_program(mut computer: Computer, program: Program, reader: &mut (impl Read + Seek), show_lines: bool) {
+    if let Some(description) = program.metadata().description() {
+        println!("{}", description);
+    }
     let result = computer.start_program(program);
     match result {
         Ok(_) => {},
         Err(err) => {
             println!("an error occurred while running emulator: {}", err);
-            if debug {
-                let result = buf_reader.seek(SeekFrom::Start(0));
+            if show_lines {
+                let result = reader.seek(SeekFrom::Start(0));
                 if let Err(err) = result {
                     println!("could not find the line where the error occurred: {}", err);
                 } else {
-                    let result = Program::get_line(computer.cpu().get_program_counter(), &mut buf_reader);
+                    let result = Program::get_line(computer.cpu().get_program_counter(), reader);
                     match result {
                         Ok((line_number, line)) => println!("the error occurred on the line: {}, \"{}\"", line_number, line.trim()),
                         Err(err) => println!("could not find the line where the error occurred: {}", err),
@@ -90,4 +274,69 @@ pub fn run(path: String, memory_amount: CpuArchitecture, debug: bool) {
             }
         }
     };
-}
\ No newline at end of file
+    
+    if let Some(sanitizer) = computer.cpu_mut().take_sanitizer() {
+        report_violations(&sanitizer, reader);
+    }
+}
+
+fn report_violations(sanitizer: &Sanitizer, reader: &mut (impl Read + Seek)) {
+    for violation in sanitizer.violations() {
+        // the line is left out when it can't be found, eg: for binaries
+        let line = reader.seek(SeekFrom::Start(0)).ok()
+            .and_then(| _ | { Program::get_line(violation.next_address, reader).ok() })
+            .filter(| (_, line) | { !line.trim().is_empty() })
+            .map_or(String::new(), | (line_number, line) | { format!(", line: {}, \"{}\"", line_number, line.trim()) });
+        let count = if violation.count > 1 { format!(" ({} times)", violation.count) } else { String::new() };
+        println!("sanitizer: {}, pc: 0x{:X}{}{}", violation.message, violation.address, line, count);
+    }
+    let violations = sanitizer.violations().len();
+    println!("the sanitizer found {} violation{}", violations, if violations == 1 { "" } else { "s" });
+}
+
+pub fn run(path: String, options: RunOptions) {
+    let Some(mut computer) = create_computer(&options) else { return; };
+
+    let path = Path::new(&path);
+    let result = OpenOptions::new().read(true).open(path);
+    let file = match result {
+        Ok(file) => file,
+        Err(err) => { println!("could not read from file: {}, filename: {}", err, path.display()); return; }
+    };
+
+    let mut buf_reader = BufReader::with_capacity(4096, file);
+    let is_binary = is_binary_path(path);
+    let result = if is_binary {
+        Program::from_binary(&mut buf_reader)
+    } else {
+        Program::from_stream(&mut buf_reader)
+    };
+    let program = match result {
+        Ok(program) => program,
+        Err(err) => { println!("could not compile program: {}", err); return; }
+    };
+    
+    if options.watch {
+        if is_binary {
+            println!("cannot watch a binary file, hot reloading is disabled");
+        } else {
+            computer.set_hot_reload(HotReload::new(path, &program));
+        }
+    }
+
+    start_program(computer, program, &mut buf_reader, options.debug || options.sanitize);
+}
+
+/// runs an assembly program that is already in memory, eg. the embedded examples
+pub fn run_source(source: &str, options: RunOptions) {
+    let Some(computer) = create_computer(&options) else { return; };
+
+    let mut reader = Cursor::new(source.as_bytes());
+    let result = Program::from_stream(&mut reader);
+    let program = match result {
+        Ok(program) => program,
+        Err(err) => { println!("could not compile program: {}", err); return; }
+    };
+    
+    start_program(computer, program, &mut reader, options.debug || options.sanitize);
+}
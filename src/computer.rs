@@ -1,12 +1,31 @@
 use std::io::{stdout, Write};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use crate::break_point::BreakPoint;
-use crate::compile::DEBUG;
-use crate::cpu::{Cpu, CpuError, CpuErrorKind};
-use crate::memory::Ram;
+use crate::compile::{DEBUG, MEMVIZ, PROFILE, SANITIZE, STRICT, TIME};
+use crate::memviz::MemoryVisualizer;
+use crate::hot_reload::HotReload;
+use crate::file_table::FileTable;
+use crate::device_bus::{DeviceBus, DeviceSignal};
+use crate::rng::Rng;
+use crate::nvram::Nvram;
+use crate::perf_counters::PerfCounters;
+use crate::profiler::Profiler;
+use crate::call_graph::CallGraph;
+use crate::socket_table::SocketTable;
+use crate::stack_guard::StackGuard;
+use crate::sanitizer::Sanitizer;
+use crate::call_stack::CallStack;
+use crate::cpu::{Cpu, CpuArchitecture, CpuError, CpuErrorKind, CpuState, Flag};
+use crate::memory::{AllocatedRam, Ram, RamError, RamErrorKind, RamState};
+use crate::frame_capture::FrameCapture;
+use crate::audio::AudioDevice;
+use crate::input_script::InputScript;
 use crate::error_creator;
-use crate::program::{Program, ProgramError};
-use crate::instructions::{Instruction, InstructionError};
+use crate::program::{Program, ProgramError, KEEP_CMP_FLAG_VERSION};
+use crate::instructions::{Instruction, InstructionError, InstructionErrorKind, InstructionSet};
+use crate::decode_cache::DecodedInstruction;
+use crate::interrupt::{Interrupt, Interrupts};
+use crate::operand::Register;
 
 error_creator!(
     ComputerError,
@@ -17,11 +36,39 @@ error_creator!(
     Other => ""
 );
 
-pub const REGISTER_COUNT: usize = 12;
+/// the general registers, the base pointer and the stack pointer
+pub const REGISTER_COUNT: usize = 13;
+
+/// a copy of the cpu, memory and interrupts that the computer can go back to, the devices and open files aren't copied
+pub struct Snapshot {
+    cpu: CpuState<REGISTER_COUNT>,
+    ram: RamState,
+    interrupts: Interrupts,
+}
 
 pub struct Computer {
     cpu: Cpu<REGISTER_COUNT>,
     ram: Ram,
+    hot_reload: Option<HotReload>,
+    frame_capture: Option<FrameCapture>,
+    audio: Option<AudioDevice>,
+    input_script: Option<InputScript>,
+    files: FileTable,
+    sockets: SocketTable,
+    interrupts: Interrupts,
+    device_bus: DeviceBus,
+    rng: Rng,
+    nvram: Option<Nvram>,
+    /// the %title of the running program
+    title: Option<String>,
+    /// when the program was loaded, used by the GetTimeMs syscall
+    start_time: Instant,
+    perf_counters: PerfCounters,
+    profiler: Option<Profiler>,
+    /// where the call graph is written to after the program exits
+    call_graph_path: Option<String>,
+    /// the arguments given after -- which are passed to the program
+    arguments: Vec<String>,
 }
 
 impl Computer {
@@ -29,10 +76,143 @@ impl Computer {
         Self {
             cpu, 
             ram,
+            hot_reload: None,
+            frame_capture: None,
+            audio: None,
+            input_script: None,
+            files: FileTable::new(),
+            sockets: SocketTable::new(),
+            interrupts: Interrupts::new(),
+            device_bus: DeviceBus::new(),
+            rng: Rng::from_time(),
+            nvram: None,
+            title: None,
+            start_time: Instant::now(),
+            perf_counters: PerfCounters::new(),
+            profiler: None,
+            call_graph_path: None,
+            arguments: Vec::new(),
         }
     }
     
+    pub fn set_hot_reload(&mut self, hot_reload: HotReload) {
+        self.hot_reload = Some(hot_reload);
+    }
+    
+    /// patches the running program if hot reloading is enabled and the source has changed
+    pub fn check_hot_reload(&mut self) {
+        if let Some(mut hot_reload) = self.hot_reload.take() {
+            hot_reload.check(self);
+            self.hot_reload = Some(hot_reload);
+        }
+    }
+    
+    pub fn set_frame_capture(&mut self, frame_capture: FrameCapture) {
+        self.frame_capture = Some(frame_capture);
+    }
+    
+    /// saves the presented frame if frames are being captured, capturing stops when a frame cannot be saved
+    pub fn capture_frame(&mut self, canvas: &AllocatedRam, size: (usize, usize)) {
+        if let Some(frame_capture) = &mut self.frame_capture {
+            if let Err(err) = frame_capture.on_frame(canvas, size) {
+                println!("could not capture frame: {}, capturing has stopped", err);
+                self.frame_capture = None;
+            }
+        }
+    }
+    
+    pub fn set_audio(&mut self, audio: AudioDevice) {
+        self.audio = Some(audio);
+    }
+    
+    /// the sound device, which only exists when the sound is written to a file
+    pub fn audio(&self) -> Option<&AudioDevice> {
+        self.audio.as_ref()
+    }
+    
+    pub fn set_call_graph_path(&mut self, path: String) {
+        self.call_graph_path = Some(path);
+    }
+    
+    pub fn set_arguments(&mut self, arguments: Vec<String>) {
+        self.arguments = arguments;
+    }
+    
+    pub fn set_input_script(&mut self, input_script: InputScript) {
+        self.input_script = Some(input_script);
+    }
+    
+    /// the window events that are played back when a window is created
+    pub fn take_input_script(&mut self) -> Option<InputScript> {
+        self.input_script.take()
+    }
+    
     pub fn start_program(&mut self, program: Program) -> Result<()> {
+        let instant = Instant::now();
+        
+        let exit_code = if MEMVIZ.get() {
+            MemoryVisualizer::run(self, program)?
+        } else {
+            self.execute_program(program)?
+        };
+        
+        println!("program exited with exit code: {}, time to run: {} ms", exit_code, instant.elapsed().as_nanos() as f64 / 1e6);
+        if TIME.get() {
+            println!("{}", self.perf_counters.report(self.ram.size()));
+        }
+        if let Some(profiler) = &self.profiler {
+            println!("{}", profiler.report());
+        }
+        if let (Some(path), Some(call_graph)) = (&self.call_graph_path, self.cpu.take_call_graph()) {
+            if let Err(err) = call_graph.save(path) {
+                println!("could not write the call graph: {}, filename: {}", err, path);
+            }
+        }
+        if let Some(audio) = self.audio.take() {
+            if let Err(err) = audio.finish() {
+                println!("could not write the audio file: {}", err);
+            }
+        }
+        self.ram.deallocate_all();
+        
+        Ok(())
+    }
+    
+    /// runs the program until it exits and returns the exit code, the memory allocated by the program itself is not freed
+    pub fn execute_program(&mut self, program: Program) -> Result<CpuArchitecture> {
+        self.load_program(program)?;
+        
+        loop {
+            let exited = self.step()?;
+            if exited {
+                break;
+            }
+        }
+        
+        Ok(self.cpu.exit_code())
+    }
+    
+    /// allocates the program and prepares the cpu to run it
+    pub fn load_program(&mut self, program: Program) -> Result<()> {
+        if STRICT.get() || SANITIZE.get() {
+            self.cpu.set_stack_guard(StackGuard::new(program.sorted_functions()));
+        }
+        if SANITIZE.get() {
+            self.cpu.set_sanitizer(Sanitizer::new());
+        }
+        if DEBUG.get() || PROFILE.get() || self.call_graph_path.is_some() {
+            self.cpu.set_call_stack(CallStack::new(program.sorted_functions(), program.call_arguments().clone()));
+        }
+        if self.call_graph_path.is_some() {
+            self.cpu.set_call_graph(CallGraph::new(program.sorted_functions()));
+        }
+        if PROFILE.get() {
+            self.profiler = Some(Profiler::new(program.sorted_functions()));
+        }
+        
+        self.cpu.set_reset_cmp_flag_on_read(program.version() < KEEP_CMP_FLAG_VERSION);
+        self.title = program.metadata().title().map(str::to_string);
+        self.start_time = Instant::now();
         let result = program.allocate(&mut self.ram);
         
         let instructions = match result {
@@ -44,55 +224,184 @@ impl Computer {
         if let Err(err) = result {
             return Err(ComputerError::new(ComputerErrorKind::CpuError(err)));
         }
+        if !self.arguments.is_empty() {
+            self.pass_arguments().map_err(| err | { ComputerError::new(ComputerErrorKind::CpuError(CpuError::new(CpuErrorKind::RamError(err)))) })?;
+        }
+        self.perf_counters.measure_heap(self.ram.size() - self.ram.size_left());
         
-        let instant = Instant::now();
+        Ok(())
+    }
+    
+    /// copies the arguments into memory like argc and argv, x1 = the amount of arguments and x2 = a pointer to the
+    /// pointers of the arguments, which are null terminated
+    fn pass_arguments(&mut self) -> std::result::Result<(), RamError> {
+        let pointers_length = (self.arguments.len() * size_of::<CpuArchitecture>()) as CpuArchitecture;
+        let length = self.arguments.iter()
+            .try_fold(pointers_length, | length, argument | {
+                CpuArchitecture::try_from(argument.len() + 1).ok().and_then(| argument_length | { length.checked_add(argument_length) })
+            })
+            .ok_or(RamError::new(RamErrorKind::OutOfMemory))?;
         
-        loop {
-            let result = self.execute_next_instruction();
-            let exited = match result {
-                Ok(exited) => exited,
-                Err(err) => {
-                    if DEBUG.get() {
-                        println!("An error occurred whilst running program: {}. Starting a breakpoint", err.to_string());
-                        self.breakpoint()?;
-                    }
-                    
-                    return Err(err);
-                },
-            };
-            
-            if exited {
-                break;
-            }
+        // SAFETY: deallocated with the rest of the program memory when it exits
+        let pointers = unsafe { self.ram.alloc_unsafe(length)? };
+        let mut pointer = pointers + pointers_length;
+        for (index, argument) in self.arguments.iter().enumerate() {
+            self.ram.write_at_checked(pointers + (index * size_of::<CpuArchitecture>()) as CpuArchitecture, &pointer)?;
+            self.ram.write_buffer_at_checked(pointer, argument.as_bytes())?;
+            self.ram.write_buffer_at_checked(pointer + argument.len() as CpuArchitecture, &[0])?;
+            pointer += argument.len() as CpuArchitecture + 1;
         }
         
-        println!("program exited with exit code: {}, time to run: {} ms", self.cpu.exit_code(), instant.elapsed().as_nanos() as f64 / 1e6);
-        self.ram.deallocate_all();
-        
+        self.cpu.set_register(Register::new(0, size_of::<CpuArchitecture>() as u8), self.arguments.len() as CpuArchitecture).unwrap(); // the cpu has more than 2 registers
+        self.cpu.set_register(Register::new(1, size_of::<CpuArchitecture>() as u8), pointers).unwrap();
         Ok(())
     }
     
+    /// executes next instruction and starts a breakpoint when it fails in debug mode, if true the program has exited
+    pub fn step(&mut self) -> Result<bool> {
+        let result = self.execute_next_instruction();
+        match result {
+            Ok(exited) => Ok(exited),
+            Err(err) => {
+                if DEBUG.get() {
+                    println!("An error occurred whilst running program: {}. Starting a breakpoint", err.to_string());
+                    self.breakpoint()?;
+                }
+                
+                Err(err)
+            },
+        }
+    }
+    
     /// executes next instruction if true the program has exited
     pub fn execute_next_instruction(&mut self) -> Result<bool> {
-        let result = self.cpu.fetch_instruction();
+        // the device interrupts wait while a handler runs so the handlers don't nest, the timers due meanwhile are dropped
+        let interrupts_masked = self.cpu.get_flag(Flag::Interrupt);
+        if self.cpu.is_running_program() && self.interrupts.timer_due() && !interrupts_masked {
+            // the timer is ignored when it has no handler
+            if let Some(handler) = self.interrupts.handler(&self.ram, Interrupt::Timer as CpuArchitecture) {
+                self.cpu.interrupt(handler)?;
+            }
+        }
+        if self.cpu.is_running_program() {
+            if let Some(signal) = self.device_bus.tick(&mut self.ram, interrupts_masked) {
+                self.handle_device_signal(signal)?;
+            }
+        }
+        
+        let instruction_address = self.cpu.get_program_counter();
+        let result = self.cpu.fetch_decoded_instruction();
         let instruction = match result {
             Ok(instruction) => instruction,
             Err(err) => {
                 if err.kind() == &CpuErrorKind::EndOfProgram {
                     return Ok(true);
                 }
+                // the instruction couldn't be read so the handler returns to the same instruction
+                if let Some(fault) = Interrupt::from_cpu_fault(&err, instruction_address) {
+                    if self.handle_fault(fault)? {
+                        return Ok(false);
+                    }
+                }
                 return Err(ComputerError::new(ComputerErrorKind::CpuError(err)));
             }
         };
 
-        instruction.execute(self)?;
+        if let (Some(profiler), Some(functions)) = (&mut self.profiler, self.cpu.called_functions()) {
+            profiler.sample(functions, instruction.instruction_count());
+        }
+        if let DecodedInstruction::Single(single) = &instruction {
+            self.sanitize(*single, instruction_address);
+        }
+        
+        let result = if TIME.get() {
+            let syscall = instruction.is_syscall();
+            let instruction_count = instruction.instruction_count();
+            let start = self.perf_counters.begin_instruction();
+            let result = instruction.execute(self);
+            self.perf_counters.end_instruction(start, instruction_count, syscall);
+            if syscall {
+                self.perf_counters.measure_heap(self.ram.size() - self.ram.size_left());
+            }
+            result
+        } else {
+            instruction.execute(self)
+        };
+        if let Err(err) = result {
+            // a fault with a handler returns to the instruction after the one that caused it
+            let fault = Interrupt::from_fault(&err, instruction_address);
+            if !fault.map_or(Ok(false), | fault | { self.handle_fault(fault) })? {
+                return Err(err.into());
+            }
+        }
         Ok(false)
     }
     
+    /// checks the instruction for undefined behavior before it is executed when running with --sanitize
+    fn sanitize(&mut self, instruction: InstructionSet, address: CpuArchitecture) {
+        if !SANITIZE.get() {
+            return;
+        }
+        // addr only takes the address of its pointer
+        let pointers: Vec<_> = match instruction {
+            InstructionSet::Addr(_) => Vec::new(),
+            _ => instruction.operands().into_iter().filter_map(| operand | { operand.pointed_to(self) }).collect(),
+        };
+        let next_address = self.cpu.get_program_counter();
+        if let Some(sanitizer) = self.cpu.sanitizer_mut() {
+            sanitizer.check(instruction, &pointers, address, next_address);
+        }
+    }
+    
+    /// like the timer the interrupt of a device is ignored when it has no handler, except for the watchdog which stops the program
+    fn handle_device_signal(&mut self, signal: DeviceSignal) -> Result<()> {
+        match signal {
+            DeviceSignal::Interrupt(interrupt) => match self.interrupts.handler(&self.ram, interrupt as CpuArchitecture) {
+                Some(handler) => self.cpu.interrupt(handler)?,
+                None if matches!(interrupt, Interrupt::Watchdog) => {
+                    return Err(InstructionError::new(InstructionErrorKind::WatchdogExpired).into());
+                },
+                None => {},
+            },
+            DeviceSignal::Reset => {
+                self.interrupts = Interrupts::new();
+                self.cpu.reset_program();
+            },
+        }
+        Ok(())
+    }
+    
+    /// calls the handler of the fault with the address that caused it in x2, false if the fault has no handler
+    fn handle_fault(&mut self, (interrupt, address): (Interrupt, CpuArchitecture)) -> Result<bool> {
+        let Some(handler) = self.interrupts.handler(&self.ram, interrupt as CpuArchitecture) else {
+            return Ok(false);
+        };
+        
+        self.cpu.interrupt(handler)?;
+        let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+        self.cpu.set_register(register, address)?; // cpu is expected to have 4 registers
+        Ok(true)
+    }
+    
+    /// calls the handler of the interrupt
+    pub fn interrupt(&mut self, interrupt: CpuArchitecture) -> std::result::Result<(), InstructionError> {
+        match self.interrupts.handler(&self.ram, interrupt) {
+            Some(handler) => Ok(self.cpu.interrupt(handler)?),
+            None => Err(InstructionError::with_message(InstructionErrorKind::InterruptNotHandled, format!("interrupt: {}", interrupt))),
+        }
+    }
+    
     pub fn breakpoint(&mut self) -> Result<()> {
         BreakPoint::create_breakpoint(self)
     }
 
+    /// writes the bytes to stdout as they are, they are flushed as the text doesn't have to end with a newline
+    pub fn print_text(buffer: &[u8]) -> std::io::Result<()> {
+        let mut stdout = stdout();
+        stdout.write_all(buffer)?;
+        stdout.flush()
+    }
+
     pub fn print_bytes(buffer: &[u8]) -> std::io::Result<()> {
         let mut stdout = stdout();
         stdout.write_all("{ ".as_bytes())?;
@@ -124,4 +433,64 @@ impl Computer {
     pub fn ram_mut(&mut self) -> &mut Ram {
         &mut self.ram
     }
+    
+    pub fn files_mut(&mut self) -> &mut FileTable {
+        &mut self.files
+    }
+    
+    pub fn sockets_mut(&mut self) -> &mut SocketTable {
+        &mut self.sockets
+    }
+    
+    pub fn interrupts_mut(&mut self) -> &mut Interrupts {
+        &mut self.interrupts
+    }
+    
+    /// the time since the program was loaded
+    pub fn elapsed(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+    
+    /// the %title of the running program, used as the name of an unnamed window
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+    
+    pub fn perf_counters_mut(&mut self) -> &mut PerfCounters {
+        &mut self.perf_counters
+    }
+    
+    pub fn device_bus_mut(&mut self) -> &mut DeviceBus {
+        &mut self.device_bus
+    }
+    
+    /// replaces the random number generator used by rand, eg: to use the seed given by --seed
+    pub fn set_rng(&mut self, rng: Rng) {
+        self.rng = rng;
+    }
+    
+    pub fn rng_mut(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+    
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            cpu: self.cpu.state(),
+            ram: self.ram.state(),
+            interrupts: self.interrupts.clone(),
+        }
+    }
+    
+    pub fn restore_snapshot(&mut self, snapshot: &Snapshot) {
+        self.cpu.restore_state(&snapshot.cpu);
+        self.ram.restore_state(&snapshot.ram);
+        self.interrupts = snapshot.interrupts.clone();
+    }
+    
+    /// attaches the nvram as the next bank and returns its number, it is saved when the computer is dropped
+    pub fn attach_nvram(&mut self, nvram: Nvram) -> std::result::Result<u8, RamError> {
+        let bank = self.ram.attach_bank(nvram.memory())?;
+        self.nvram = Some(nvram);
+        Ok(bank)
+    }
 }
\ No newline at end of file
@@ -1,11 +1,22 @@
+use std::fmt::{Display, Formatter};
 use std::io::Read;
+use strum::{AsRefStr, EnumIter, IntoEnumIterator};
+use num_derive::{FromPrimitive, ToPrimitive};
 use crate::memory::{AllocatedRam, Ram, RamError};
 use crate::error_creator;
 use crate::instructions::{InstructionSet, Instruction};
-use crate::operand::Register;
+use crate::operand::{FloatRegister, Register};
 use crate::program::INSTRUCTION_SIZE;
+use crate::decode_cache::{DecodeCache, DecodedInstruction};
+use crate::stack_guard::StackGuard;
+use crate::call_stack::CallStack;
+use crate::call_graph::CallGraph;
+use crate::sanitizer::Sanitizer;
 
 pub type CpuArchitecture = u16;
+pub type SignedCpuArchitecture = i16;
+/// the amount of f32 registers used by the float instructions
+pub const FLOAT_REGISTER_COUNT: usize = 8;
 
 pub trait FromBytes : Sized {
     fn from(value: [u8; size_of::<Self>()]) -> Self;
@@ -39,23 +50,115 @@ error_creator!(
     CpuError,
     CpuErrorKind,
     ProgramAlreadyRunning => "A program is already running cannot run another",
+    /// the program ran past its last instruction, commonly caused by a missing exit or ret
     ExpectedAnInstruction => "Program was not exited but no more instructions were found",
+    /// the program counter points at data instead of an instruction, commonly caused by a jump or call to a wrong address
     InvalidInstruction => "An invalid instruction was given which the cpu does not recognise",
     EndOfProgram => "End of program has been reached",
+    /// an instruction uses a register number larger than the amount of registers of the cpu
     RegisterDoesNotExist => "The cpu doesn't have the register",
+    /// too many values have been pushed, commonly caused by infinite recursion or pushes without pops within a loop
     StackOverflow => "A stackoverflow has occurred",
+    /// more values have been popped than pushed, commonly caused by a ret without a call or an extra pop
     StackUnderflow => "A stack underflow has occurred",
+    /// a function popped or overwrote a value it didn't push, only detected when running with --strict or --sanitize
+    StackCorrupted => "The return address of a function has been overwritten",
     RamError(RamError) => "",
     Other => ""
 );
 
+/// a flag within the flags register
+#[derive(Clone, Copy, Debug, EnumIter, AsRefStr)]
+pub enum Flag {
+    /// set by the compare instructions and read by jmp and set, it is kept until the next compare or clf
+    Compare,
+    /// the result of add, sub, inc, dec or compare was 0
+    Zero,
+    /// the unsigned result of add, sub or compare didn't fit in the destination
+    Carry,
+    /// the highest bit of the result of add, sub, inc, dec or compare is set
+    Sign,
+    /// the signed result of add, sub, inc, dec or compare didn't fit in the destination
+    Overflow,
+    /// set by std and cleared by cld, lods and stos move their index register backwards when it is set
+    Direction,
+    /// set while an interrupt handler runs, the device interrupts wait and the timer is skipped until iret restores the flags
+    Interrupt,
+}
+
+/// which results of add, sub and mul raise the ArithmeticOverflow error instead of wrapping
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum OverflowTrap {
+    Off = 0,
+    /// the unsigned result didn't fit in the destination, the carry flag
+    Unsigned = 1,
+    /// the signed result didn't fit in the destination, the overflow flag
+    Signed = 2,
+}
+
+/// the flags register, every flag is a bit at the position of the flag
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Flags(u8);
+
+impl Flags {
+    pub fn get(self, flag: Flag) -> bool {
+        self.0 & (1 << flag as u8) != 0
+    }
+
+    pub fn set(&mut self, flag: Flag, value: bool) {
+        if value {
+            self.0 |= 1 << flag as u8;
+        } else {
+            self.0 &= !(1 << flag as u8);
+        }
+    }
+}
+
+impl Display for Flags {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let flags: Vec<_> = Flag::iter().filter(| flag | { self.get(*flag) }).map(| flag | { flag.as_ref().to_lowercase() }).collect();
+        write!(f, "[{}]", flags.join(", "))
+    }
+}
+
+impl Default for Flags {
+    fn default() -> Self {
+        // the compare flag is set by default so jmp without a compare always jumps
+        Self(1 << Flag::Compare as u8)
+    }
+}
+
 pub struct Cpu<const S : usize> {
     program_pointer: AllocatedRam,
     program_counter: CpuArchitecture,
     stack_base: AllocatedRam,
     exit_code: CpuArchitecture,
     registers: [CpuArchitecture; S],
-    cmp_flag : bool,
+    float_registers: [f32; FLOAT_REGISTER_COUNT],
+    flags: Flags,
+    bank: u8,
+    use_decode_cache: bool,
+    decode_cache: Option<DecodeCache>,
+    stack_guard: Option<StackGuard>,
+    call_stack: Option<CallStack>,
+    call_graph: Option<CallGraph>,
+    sanitizer: Option<Sanitizer>,
+    reset_cmp_flag_on_read: bool,
+    overflow_trap: OverflowTrap,
+}
+
+/// the registers, flags and position of the running program, used to rewind it
+#[derive(Clone)]
+pub struct CpuState<const S : usize> {
+    program_counter: CpuArchitecture,
+    exit_code: CpuArchitecture,
+    registers: [CpuArchitecture; S],
+    float_registers: [f32; FLOAT_REGISTER_COUNT],
+    flags: Flags,
+    bank: u8,
+    stack_guard: Option<StackGuard>,
+    call_stack: Option<CallStack>,
+    overflow_trap: OverflowTrap,
 }
 
 /// converts a value into a new byte size
@@ -108,7 +211,17 @@ impl<const S : usize> Cpu<S> {
             stack_base: Default::default(),
             exit_code: 0,
             registers: [0; S],
-            cmp_flag: true,
+            float_registers: [0.0; FLOAT_REGISTER_COUNT],
+            flags: Flags::default(),
+            bank: 0,
+            use_decode_cache: false,
+            decode_cache: None,
+            stack_guard: None,
+            call_stack: None,
+            call_graph: None,
+            sanitizer: None,
+            reset_cmp_flag_on_read: false,
+            overflow_trap: OverflowTrap::Off,
         }
     }
     
@@ -122,8 +235,10 @@ impl<const S : usize> Cpu<S> {
         } else {
             self.program_pointer = program_pointer;
             self.program_counter = 0;
+            self.rebuild_decode_cache();
 
             self.exit_code = 0;
+            self.bank = 0;
             
             let size = ram.size();
             let result = if size > 8192 {
@@ -139,51 +254,212 @@ impl<const S : usize> Cpu<S> {
             
             self.stack_base = stack;
             self.registers[S - 1] = self.stack_base.range().start;
+            self.registers[S - 2] = self.stack_base.range().start;
+            if let Some(sanitizer) = &mut self.sanitizer {
+                sanitizer.reset_registers(&[S as u8 - 1, S as u8 - 2]);
+            }
             
             Ok(())
         }
     }
     
+    /// restarts the running program from the start with cleared registers and flags, the memory it allocated is kept
+    pub fn reset_program(&mut self) {
+        self.program_counter = 0;
+        self.registers = [0; S];
+        self.registers[S - 1] = self.stack_base.range().start;
+        self.registers[S - 2] = self.stack_base.range().start;
+        self.float_registers = [0.0; FLOAT_REGISTER_COUNT];
+        self.flags = Flags::default();
+        self.bank = 0;
+        self.overflow_trap = OverflowTrap::Off;
+        if let Some(stack_guard) = &mut self.stack_guard {
+            stack_guard.reset();
+        }
+        if let Some(call_stack) = &mut self.call_stack {
+            call_stack.reset();
+        }
+        if let Some(sanitizer) = &mut self.sanitizer {
+            sanitizer.reset_registers(&[S as u8 - 1, S as u8 - 2]);
+        }
+    }
+    
+    /// copies the state of the running program, the program and stack memory are kept in the ram
+    pub fn state(&self) -> CpuState<S> {
+        CpuState {
+            program_counter: self.program_counter,
+            exit_code: self.exit_code,
+            registers: self.registers,
+            float_registers: self.float_registers,
+            flags: self.flags,
+            bank: self.bank,
+            stack_guard: self.stack_guard.clone(),
+            call_stack: self.call_stack.clone(),
+            overflow_trap: self.overflow_trap,
+        }
+    }
+    
+    /// continues the running program from the copied state
+    pub fn restore_state(&mut self, state: &CpuState<S>) {
+        self.program_counter = state.program_counter;
+        self.exit_code = state.exit_code;
+        self.registers = state.registers;
+        self.float_registers = state.float_registers;
+        self.flags = state.flags;
+        self.bank = state.bank;
+        self.stack_guard = state.stack_guard.clone();
+        self.call_stack = state.call_stack.clone();
+        self.overflow_trap = state.overflow_trap;
+    }
+    
+    /// decodes the instruction at the address within the program without executing it, returns the instruction and its size
+    pub fn read_instruction_at(&mut self, address: CpuArchitecture) -> Result<(InstructionSet, CpuArchitecture)> {
+        read_instruction(&mut self.program_pointer.as_stream(address))
+    }
+    
     pub fn fetch_instruction(&mut self) -> Result<InstructionSet> {
         if !self.is_running_program() {
             return Err(CpuError::new(CpuErrorKind::EndOfProgram))
         }
         
+        // running past the last instruction is reported as the program not exiting instead
+        if self.program_counter as usize != self.program_pointer.range().len() {
+            self.program_pointer.check_execute(self.program_counter)?;
+        }
         let (instruction, size) = read_instruction(&mut self.program_pointer.as_stream(self.program_counter))?;
+        Self::validate_registers(instruction)?;
         self.program_counter += size;
         
         Ok(instruction)
     }
     
+    /// fetches the next instruction from the decode cache if enabled, which can fuse two instructions together
+    pub fn fetch_decoded_instruction(&mut self) -> Result<DecodedInstruction> {
+        if let Some(cache) = &self.decode_cache {
+            if let Some((instruction, size)) = cache.get(self.program_counter) {
+                if !self.is_running_program() {
+                    return Err(CpuError::new(CpuErrorKind::EndOfProgram))
+                }
+                self.program_counter += size;
+                return Ok(instruction);
+            }
+        }
+        
+        self.fetch_instruction().map(DecodedInstruction::Single)
+    }
+    
+    /// enables caching decoded instructions, programs that modify their own instructions cannot use the cache
+    pub fn set_use_decode_cache(&mut self, use_decode_cache: bool) {
+        self.use_decode_cache = use_decode_cache;
+    }
+    
+    /// verifies the return address of every call when returning, the guard is removed when the program exits
+    pub fn set_stack_guard(&mut self, stack_guard: StackGuard) {
+        self.stack_guard = Some(stack_guard);
+    }
+    
+    /// keeps track of the calls for the backtrace of the debugger, removed when the program exits
+    pub fn set_call_stack(&mut self, call_stack: CallStack) {
+        self.call_stack = Some(call_stack);
+    }
+    
+    /// the functions that are being called from the outermost call inwards, None if the calls aren't tracked
+    pub fn called_functions(&self) -> Option<impl Iterator<Item = CpuArchitecture> + '_> {
+        let call_stack = self.call_stack.as_ref()?;
+        Some(call_stack.functions(self.get_stack_pointer()))
+    }
+    
+    /// records the calls between the functions, needs the call stack and is kept after the program exits
+    pub fn set_call_graph(&mut self, call_graph: CallGraph) {
+        self.call_graph = Some(call_graph);
+    }
+    
+    pub fn take_call_graph(&mut self) -> Option<CallGraph> {
+        self.call_graph.take()
+    }
+    
+    /// the sanitizer is kept after the program exits so its violations can be reported
+    pub fn set_sanitizer(&mut self, sanitizer: Sanitizer) {
+        self.sanitizer = Some(sanitizer);
+    }
+    
+    pub fn sanitizer_mut(&mut self) -> Option<&mut Sanitizer> {
+        self.sanitizer.as_mut()
+    }
+    
+    pub fn take_sanitizer(&mut self) -> Option<Sanitizer> {
+        self.sanitizer.take()
+    }
+    
+    /// the functions that are being called with their arguments, None if the calls aren't tracked
+    pub fn backtrace(&self) -> Option<String> {
+        let call_stack = self.call_stack.as_ref()?;
+        Some(call_stack.backtrace(&self.stack_base, self.get_stack_pointer()))
+    }
+    
+    /// decodes the program memory again, needs to be called when the program memory has changed
+    pub fn rebuild_decode_cache(&mut self) {
+        self.decode_cache = if self.use_decode_cache && self.is_running_program() {
+            Some(DecodeCache::new::<S>(&mut self.program_pointer))
+        } else {
+            None
+        };
+    }
+    
     fn check_register_exists(&self, register: Register) -> Result<()> {
-        let register_index = register.register_number(S as u8);
-        if register_index >= S as u8 {
+        if !register.exists(S as u8) {
             Err(CpuError::with_message(CpuErrorKind::RegisterDoesNotExist, register.to_string()))
         } else { 
             Ok(())
         }
     }
     
+    /// checks that all registers used by the instruction exist, so they can be accessed unchecked when executing
+    pub fn validate_registers(instruction: InstructionSet) -> Result<()> {
+        match instruction.validate_registers(S as u8) {
+            Ok(_) => Ok(()),
+            Err(register) => Err(CpuError::with_message(CpuErrorKind::RegisterDoesNotExist, register.to_string())),
+        }
+    }
+    
     pub fn get_register(&self, register: Register) -> Result<CpuArchitecture> {
         self.check_register_exists(register)?;
         
+        Ok(self.get_register_unchecked(register))
+    }
+    
+    /// gets the register without checking if it exists, the register has to be validated when decoding
+    #[inline]
+    pub fn get_register_unchecked(&self, register: Register) -> CpuArchitecture {
         let register_index = register.register_number(S as u8);
         let register_value = self.registers[register_index as usize];
         let register_size = register.register_size();
         
         // convert into smaller type if needed
-        Ok(convert_to_byte_size(register_value, register_size))
+        convert_to_byte_size(register_value, register_size)
     }
     
     pub fn set_register(&mut self, register: Register, value: CpuArchitecture) -> Result<()> {
         self.check_register_exists(register)?;
         
+        self.set_register_unchecked(register, value);
+        Ok(())
+    }
+    
+    /// sets the register without checking if it exists, the register has to be validated when decoding
+    #[inline]
+    pub fn set_register_unchecked(&mut self, register: Register, value: CpuArchitecture) {
         let register_index = register.register_number(S as u8);
         let register_size = register.register_size();
         let value= convert_to_byte_size(value, register_size);
         self.registers[register_index as usize] = value;
-        
-        Ok(())
+        if let Some(sanitizer) = &mut self.sanitizer {
+            sanitizer.on_register_write(register_index);
+        }
+    }
+    
+    pub fn program_memory(&self) -> &AllocatedRam {
+        &self.program_pointer
     }
     
     pub fn get_program_counter(&self) -> CpuArchitecture {
@@ -213,6 +489,101 @@ impl<const S : usize> Cpu<S> {
         Ok(())
     }
 
+    /// pushes the return address and jumps to the address, the call is recorded when the stack is guarded
+    pub fn call(&mut self, address: CpuArchitecture) -> Result<()> {
+        let return_address = self.program_counter;
+        let stack_pointer = self.get_stack_pointer();
+        if let Some(call_stack) = &mut self.call_stack {
+            if let Some(call_graph) = &mut self.call_graph {
+                call_graph.on_call(call_stack.current_function(stack_pointer), address);
+            }
+            call_stack.on_call(address, return_address, stack_pointer);
+        }
+        self.program_counter = address;
+        self.push(&return_address)?;
+        
+        if let Some(stack_guard) = &mut self.stack_guard {
+            stack_guard.on_call(address, return_address, stack_pointer);
+        }
+        Ok(())
+    }
+    
+    /// pushes the flags and x2 and calls the interrupt handler with the interrupt flag set, iret returns from it,
+    /// x2 is kept as a fault handler gets the address of the fault in it
+    pub fn interrupt(&mut self, handler: CpuArchitecture) -> Result<()> {
+        let flags = self.flags.0 as CpuArchitecture;
+        self.push(&flags)?;
+        let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+        let value = self.get_register(register)?;
+        self.push(&value)?;
+        self.flags.set(Flag::Interrupt, true);
+        self.call(handler)
+    }
+    
+    /// returns from the interrupt handler and restores x2 and the flags pushed when it was called
+    pub fn iret(&mut self) -> Result<()> {
+        self.ret()?;
+        let value: CpuArchitecture = self.pop()?;
+        let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+        self.set_register(register, value)?;
+        let flags: CpuArchitecture = self.pop()?;
+        self.flags = Flags(flags as u8);
+        Ok(())
+    }
+    
+    /// pushes the base pointer, points it at the stack pointer and reserves size bytes above it for the locals
+    pub fn enter(&mut self, size: CpuArchitecture) -> Result<()> {
+        let stack_size = self.stack_base.range().end - self.stack_base.range().start;
+        let frame_size = size.checked_add(size_of::<CpuArchitecture>() as CpuArchitecture);
+        if frame_size.is_none_or(| frame_size | { frame_size > stack_size - self.get_stack_pointer() }) {
+            return Err(CpuError::new(CpuErrorKind::StackOverflow));
+        }
+        
+        let base_pointer = self.registers[S - 2];
+        self.push(&base_pointer)?;
+        self.registers[S - 2] = self.registers[S - 1];
+        self.registers[S - 1] += size;
+        Ok(())
+    }
+    
+    /// frees the locals of the frame and restores the base pointer pushed by enter
+    pub fn leave(&mut self) -> Result<()> {
+        let base_pointer = self.registers[S - 2];
+        if base_pointer < self.stack_base.range().start || base_pointer > self.registers[S - 1] {
+            return Err(CpuError::with_message(CpuErrorKind::StackUnderflow, format!("the base pointer 0x{:X} is outside of the stack", base_pointer)));
+        }
+        
+        self.registers[S - 1] = base_pointer;
+        self.registers[S - 2] = self.pop()?;
+        Ok(())
+    }
+    
+    /// jumps to the function while keeping the return address of the current function
+    pub fn tail_call(&mut self, address: CpuArchitecture) {
+        self.program_counter = address;
+        let stack_pointer = self.get_stack_pointer();
+        if let Some(call_stack) = &mut self.call_stack {
+            if let Some(call_graph) = &mut self.call_graph {
+                call_graph.on_tail_call(call_stack.current_function(stack_pointer), address);
+            }
+            call_stack.on_tail_call(address);
+        }
+    }
+    
+    /// pops the return address and jumps back to it, the address is verified first when the stack is guarded
+    pub fn ret(&mut self) -> Result<()> {
+        let address = self.pop()?;
+        if let Some(stack_guard) = &mut self.stack_guard {
+            stack_guard.on_ret(address)?;
+        }
+        
+        self.program_counter = address;
+        if let Some(call_stack) = &mut self.call_stack {
+            call_stack.on_ret();
+        }
+        Ok(())
+    }
+
     pub fn pop<T : Sized + FromBytes>(&mut self) -> Result<T>
         where [();size_of::<T>()]:
     {
@@ -236,6 +607,9 @@ impl<const S : usize> Cpu<S> {
         let value = self.registers[0];
         
         self.exit_code = value;
+        self.decode_cache = None;
+        self.stack_guard = None;
+        self.call_stack = None;
         self.program_pointer = Default::default();
         self.stack_base = Default::default();
     }
@@ -244,14 +618,59 @@ impl<const S : usize> Cpu<S> {
         self.exit_code
     }
     
+    /// only resets the flag for binaries built before the flag was kept after reading it
     pub fn get_cmp_flag(&mut self) -> bool {
-        let flag = self.cmp_flag;
-        self.cmp_flag = true;
+        let flag = self.flags.get(Flag::Compare);
+        if self.reset_cmp_flag_on_read {
+            self.flags.set(Flag::Compare, true);
+        }
         flag
     }
     
+    pub fn set_reset_cmp_flag_on_read(&mut self, reset_cmp_flag_on_read: bool) {
+        self.reset_cmp_flag_on_read = reset_cmp_flag_on_read;
+    }
+    
+    pub fn overflow_trap(&self) -> OverflowTrap {
+        self.overflow_trap
+    }
+    
+    pub fn set_overflow_trap(&mut self, overflow_trap: OverflowTrap) {
+        self.overflow_trap = overflow_trap;
+    }
+    
     pub fn set_cmp_flag(&mut self, expr:bool) {
-        self.cmp_flag = expr;
+        self.flags.set(Flag::Compare, expr);
+    }
+    
+    /// the float register, the register is validated when decoding
+    pub fn get_float_register(&self, register: FloatRegister) -> f32 {
+        self.float_registers[register.index() as usize]
+    }
+    
+    pub fn set_float_register(&mut self, register: FloatRegister, value: f32) {
+        self.float_registers[register.index() as usize] = value;
+    }
+    
+    pub fn get_flags(&self) -> Flags {
+        self.flags
+    }
+    
+    pub fn get_flag(&self, flag: Flag) -> bool {
+        self.flags.get(flag)
+    }
+    
+    pub fn set_flag(&mut self, flag: Flag, value: bool) {
+        self.flags.set(flag, value);
+    }
+    
+    /// the memory bank that pointer operands read from and write to
+    pub fn get_bank(&self) -> u8 {
+        self.bank
+    }
+    
+    pub fn set_bank(&mut self, bank: u8) {
+        self.bank = bank;
     }
 }
 
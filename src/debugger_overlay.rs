@@ -0,0 +1,38 @@
+use crate::computer::{Computer, REGISTER_COUNT};
+use crate::cpu::CpuArchitecture;
+use crate::operand::Register;
+
+/// the amount of instructions shown from the program counter
+const DISASSEMBLY_LENGTH: usize = 8;
+/// the amount of registers shown on a line
+const REGISTERS_PER_LINE: usize = 4;
+
+/// the registers, flags and the next instructions of the frozen program, drawn on the window by the debugger hotkey
+pub fn debugger_lines(computer: &mut Computer) -> Vec<String> {
+    let cpu = computer.cpu();
+    let program_counter = cpu.get_program_counter();
+    let mut lines = vec![
+        "PAUSED, F8 RESUMES, F6 STEPS A FRAME".to_string(),
+        format!("PC {:04X} BANK {} FLAGS {}", program_counter, cpu.get_bank(), cpu.get_flags()),
+    ];
+    let ram = computer.ram();
+    lines.push(format!("HEAP {}/{} LARGEST FREE {}", ram.allocated_memory(), ram.size(), ram.largest_free_block()));
+
+    // the last 2 cpu registers are the stack and base pointer
+    let general_registers = (0..REGISTER_COUNT - 3).map(| index | { Register::new(index as u8, size_of::<CpuArchitecture>() as u8) });
+    let registers: Vec<_> = general_registers.chain([Register::stack_pointer(), Register::base_pointer()]).map(| register | {
+        format!("{:>3} {:04X}", register.to_string(), cpu.get_register_unchecked(register))
+    }).collect();
+    lines.extend(registers.chunks(REGISTERS_PER_LINE).map(| line | { line.join(" ") }));
+
+    let mut address = program_counter;
+    for index in 0..DISASSEMBLY_LENGTH {
+        let Ok((instruction, size)) = computer.cpu_mut().read_instruction_at(address) else {
+            break;
+        };
+        let marker = if index == 0 { '>' } else { ' ' };
+        lines.push(format!("{} {:04X} {}", marker, address, instruction));
+        address = address.wrapping_add(size);
+    }
+    lines
+}
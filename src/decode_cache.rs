@@ -0,0 +1,309 @@
+use crate::computer::Computer;
+use crate::cpu::{read_instruction, Cpu, CpuArchitecture};
+use crate::instructions::{arithmetic_with_flags, check_overflow_trap, Add, Call, Instruction, InstructionError, InstructionSet, Is, Mov, Push};
+use crate::memory::AllocatedRam;
+use crate::operand::Operand;
+
+/// an instruction or a pair of instructions which are executed by a single handler,
+/// the size is the binary size of the second instruction of the pair
+#[derive(Clone, Copy, Debug)]
+pub enum DecodedInstruction {
+    Single(InstructionSet),
+    CmpJmp(InstructionSet, CpuArchitecture, InstructionSet),
+    MovAdd(Mov, CpuArchitecture, Add),
+    PushCall(Push, CpuArchitecture, Call),
+}
+
+/// the decoded instructions of a program indexed by their address,
+/// programs that modify their own instructions cannot use the cache
+pub struct DecodeCache {
+    entries: Vec<Option<(DecodedInstruction, CpuArchitecture)>>,
+}
+
+fn compare(instruction: InstructionSet, computer: &mut Computer) -> Option<Result<bool, InstructionError>> {
+    match instruction {
+        InstructionSet::Cmpe(cmp) => Some(cmp.compare(computer)),
+        InstructionSet::Cmpne(cmp) => Some(cmp.compare(computer)),
+        InstructionSet::Cmple(cmp) => Some(cmp.compare(computer)),
+        InstructionSet::Cmpl(cmp) => Some(cmp.compare(computer)),
+        InstructionSet::Cmpge(cmp) => Some(cmp.compare(computer)),
+        InstructionSet::Cmpg(cmp) => Some(cmp.compare(computer)),
+        _ => None,
+    }
+}
+
+/// the address a jmp or rjmp jumps to, the program counter has to point to the next instruction
+fn jump_target(instruction: InstructionSet, computer: &mut Computer) -> Option<Result<CpuArchitecture, InstructionError>> {
+    match instruction {
+        InstructionSet::Jmp(jmp) => Some(jmp.address().read_from_computer(computer)),
+        InstructionSet::Rjmp(rjmp) => Some(rjmp.target(computer)),
+        _ => None,
+    }
+}
+
+fn is_compare(instruction: &InstructionSet) -> bool {
+    matches!(instruction, InstructionSet::Cmpe(_) | InstructionSet::Cmpne(_) | InstructionSet::Cmple(_) |
+        InstructionSet::Cmpl(_) | InstructionSet::Cmpge(_) | InstructionSet::Cmpg(_))
+}
+
+fn fuse(first: InstructionSet, second: InstructionSet, second_size: CpuArchitecture) -> DecodedInstruction {
+    if matches!(second, InstructionSet::Jmp(_) | InstructionSet::Rjmp(_)) && is_compare(&first) {
+        return DecodedInstruction::CmpJmp(first, second_size, second);
+    }
+
+    if let (Some(mov), Some(add)) = (Mov::is(&first), Add::is(&second)) {
+        // only registers and literals are fused as pointers or different sized registers can overlap
+        if matches!(mov.destination(), Operand::Register(_)) &&
+            mov.destination() == add.destination() &&
+            matches!(add.source(), Operand::Literal(_)) {
+            return DecodedInstruction::MovAdd(mov, second_size, add);
+        }
+    }
+
+    if let (Some(push), Some(call)) = (Push::is(&first), Call::is(&second)) {
+        if matches!(push.source(), Operand::Literal(_)) {
+            return DecodedInstruction::PushCall(push, second_size, call);
+        }
+    }
+
+    DecodedInstruction::Single(first)
+}
+
+impl DecodeCache {
+    /// decodes all instructions within the program memory, decoding stops at the first invalid instruction
+    pub fn new<const S: usize>(program: &mut AllocatedRam) -> Self {
+        let length = program.range().len();
+        let mut decoded = Vec::new();
+
+        let mut position = 0;
+        while position < length {
+            let result = read_instruction(&mut program.as_stream(position as CpuArchitecture));
+            match result {
+                Ok((instruction, size)) => {
+                    // instructions with invalid registers are not cached so they fail when fetched
+                    if Cpu::<S>::validate_registers(instruction).is_ok() {
+                        decoded.push((position, instruction, size));
+                    }
+                    position += size as usize;
+                },
+                Err(_) => break,
+            }
+        }
+
+        let mut entries = vec![None; length];
+        for (index, (position, instruction, size)) in decoded.iter().enumerate() {
+            let instruction = match decoded.get(index + 1) {
+                Some((second_position, second, second_size)) if *second_position == position + *size as usize =>
+                    fuse(*instruction, *second, *second_size),
+                _ => DecodedInstruction::Single(*instruction),
+            };
+            entries[*position] = Some((instruction, *size));
+        }
+
+        Self {
+            entries
+        }
+    }
+
+    /// the decoded instruction at the address and the size of the (first) instruction
+    pub fn get(&self, address: CpuArchitecture) -> Option<(DecodedInstruction, CpuArchitecture)> {
+        self.entries.get(address as usize).copied().flatten()
+    }
+}
+
+impl DecodedInstruction {
+    /// the amount of instructions that have been fused together
+    pub fn instruction_count(&self) -> u64 {
+        match self {
+            DecodedInstruction::Single(_) => 1,
+            _ => 2,
+        }
+    }
+
+    pub fn is_syscall(&self) -> bool {
+        matches!(self, DecodedInstruction::Single(InstructionSet::Syscall(_)))
+    }
+
+    pub fn execute(self, computer: &mut Computer) -> Result<(), InstructionError> {
+        match self {
+            DecodedInstruction::Single(instruction) => instruction.execute(computer),
+            DecodedInstruction::CmpJmp(cmp, size, jmp) => {
+                let result = compare(cmp, computer).unwrap()?; // only compare instructions are fused with jmp
+                Self::advance(computer, size);
+
+                // the compare sets the flag like it does unfused, jmp reads it, which only resets it for older binaries
+                computer.cpu_mut().set_cmp_flag(result);
+                if computer.cpu_mut().get_cmp_flag() {
+                    let address = jump_target(jmp, computer).unwrap()?; // only jmp and rjmp are fused with compares
+                    computer.cpu_mut().set_program_counter(address);
+                }
+                Ok(())
+            },
+            DecodedInstruction::MovAdd(mov, size, add) => {
+                let value = mov.source().read_from_computer(computer)?;
+                let value2 = add.source().read_from_computer(computer)?;
+                Self::advance(computer, size);
+
+                let result = arithmetic_with_flags(computer, value, value2, false, add.destination().size(), false);
+                if let Err(err) = check_overflow_trap(computer) {
+                    // like unfused the mov is done before the add traps
+                    mov.destination().write_to_computer(computer, value)?;
+                    return Err(err);
+                }
+                mov.destination().write_to_computer(computer, result)
+            },
+            DecodedInstruction::PushCall(push, size, call) => {
+                push.execute(computer)?;
+                Self::advance(computer, size);
+
+                call.execute(computer)
+            },
+        }
+    }
+
+    fn advance(computer: &mut Computer, size: CpuArchitecture) {
+        let program_counter = computer.cpu().get_program_counter();
+        computer.cpu_mut().set_program_counter(program_counter + size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use crate::computer::{Computer, REGISTER_COUNT};
+    use crate::cpu::{Cpu, CpuArchitecture, Flags};
+    use crate::memory::Ram;
+    use crate::operand::Register;
+    use crate::program::Program;
+
+    /// the exit code, x1 to x10, sp, bp and the flags after the program exits, or the error it stopped with
+    fn run(source: &str, fuse: bool) -> Result<(CpuArchitecture, Vec<CpuArchitecture>, Flags), String> {
+        let mut cpu = Cpu::<REGISTER_COUNT>::new();
+        cpu.set_use_decode_cache(fuse);
+        let mut computer = Computer::new(cpu, Ram::new(4096).unwrap());
+
+        let program = Program::from_str(source).unwrap();
+        let exit_code = computer.execute_program(program).map_err(| err | { err.to_string() })?;
+
+        let mut registers: Vec<_> = (0..10).map(| index | {
+            computer.cpu().get_register(Register::new(index, size_of::<CpuArchitecture>() as u8)).unwrap()
+        }).collect();
+        registers.push(computer.cpu().get_register(Register::stack_pointer()).unwrap());
+        registers.push(computer.cpu().get_register(Register::base_pointer()).unwrap());
+        Ok((exit_code, registers, computer.cpu().get_flags()))
+    }
+
+    fn assert_same_fused(source: &str) {
+        assert_eq!(run(source, false), run(source, true), "source:\n{}", source);
+    }
+
+    #[test]
+    fn fused_compare_sets_the_compare_flag() {
+        let source = "main:
+            mov x2, 0
+            cmpe x2, 1
+            jmp first
+            jmp second
+            mov l1, 1
+            exit
+            .first
+            mov l1, 2
+            exit
+            .second
+            mov l1, 3
+            exit";
+        assert_eq!(run(source, true).unwrap().0, 1);
+        assert_same_fused(source);
+    }
+
+    #[test]
+    fn examples_run_the_same_fused() {
+        // the window examples can't run without a display
+        for source in [
+            include_str!("../examples/hello.asm"),
+            include_str!("../examples/loops.asm"),
+            include_str!("../examples/functions.asm"),
+        ] {
+            assert_same_fused(source);
+        }
+    }
+
+    #[test]
+    fn fused_pairs_run_the_same() {
+        let compares = ["cmpe", "cmpne", "cmpl", "cmple", "cmpg", "cmpge"];
+        for compare in compares {
+            for (a, b) in [(1, 2), (2, 2), (3, 2), (0xFFFF, 1)] {
+                assert_same_fused(&format!("main:
+                    mov x2, {a}
+                    {compare} x2, {b}
+                    jmp taken
+                    mov l1, 1
+                    exit
+                    .taken
+                    mov l1, 2
+                    exit"));
+            }
+        }
+
+        // the compare flag is kept over later compares that aren't followed by a jmp
+        assert_same_fused("main:
+            mov x2, 5
+            mov x3, 0
+            .loop
+            add x3, 3
+            dec x2
+            cmpg x2, 0
+            jmp loop
+            cmpe x3, 15
+            mov l1, 0
+            jmp done
+            mov l1, 1
+            .done
+            exit");
+
+        // mov and add to the same register, including an add that overflows and sets the carry flag
+        assert_same_fused("main:
+            mov x2, 10
+            add x2, 5
+            mov x3, 0xFFFF
+            add x3, 2
+            mov l3, 200
+            add l3, 100
+            mov l1, l4
+            exit");
+
+        // the overflow trap stops the fused add like it stops the unfused one, with the mov already done
+        for (mode, destination, value) in [(1, "x3", 0xFFFF), (2, "x3", 0x7FFF), (1, "l4", 0xFF), (2, "l4", 0x7F)] {
+            let source = format!("main:
+                mov x1, 28
+                mov x2, {mode}
+                syscall
+                mov {destination}, 2
+                add {destination}, 1
+                mov {destination}, {value}
+                add {destination}, 1
+                mov l1, 7
+                exit");
+            assert!(run(&source, true).is_err());
+            assert_same_fused(&source);
+        }
+
+        // a literal argument pushed right before the call, the stack grows up so it is below the return address
+        let source = "main:
+            push 7
+            call double
+            pop x3
+            push 100
+            call double
+            pop x4
+            mov l1, l3
+            exit
+
+            double:
+            mov x2, word[sp - 4]
+            add x2, x2
+            ret";
+        assert_eq!(run(source, true).unwrap().0, 200);
+        assert_same_fused(source);
+    }
+}
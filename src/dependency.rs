@@ -1,11 +1,12 @@
 use std::io::{Read, Seek, SeekFrom, Write};
-use crate::cpu::{read_instruction, CpuArchitecture, FromBytes};
-use crate::file_handler::ReadFileHandler;
-use crate::instructions::InstructionSet;
-use crate::operand::Operand;
-use crate::program::{ProgramError, ProgramErrorKind, DEPENDENCY_EXTENSION};
+use std::rc::Rc;
+use crate::cpu::{read_instruction, CpuArchitecture};
+use crate::compile::MMAP_DEPENDENCIES;
+use crate::file_handler::{FunctionTable, ReadFileHandler};
+use crate::instructions::{Instruction, InstructionSet};
+use crate::operand::{Literal, Operand};
+use crate::program::{Program, ProgramError, ProgramErrorKind, BINARY_VERSION, DEPENDENCY_EXTENSION, INSTRUCTION_SIZE};
 use crate::read_ext::ReadLine;
-use crate::write_ext::WriteExt;
 
 pub struct Dependency {
     function_name: String,
@@ -29,55 +30,71 @@ impl Dependency {
         file_name.push_str(dependency_name);
         file_name.push_str(DEPENDENCY_EXTENSION);
 
-        let file_ref = conv_io_error!(file_handler.open(file_name), dependency_name);
+        let file_ref = conv_io_error!(file_handler.open(file_name.as_str()), dependency_name);
         let mut file = file_ref.borrow_mut();
 
-        let instruction_offset = conv_io_error!(file.read_type::<u32>(), dependency_name);
+        // the function table is only read once per file
+        let function_table = match file_handler.function_table(&file_name) {
+            Some(function_table) => function_table,
+            None => {
+                let function_table = Rc::new(conv_io_error!(Self::read_function_table(&mut *file), dependency_name));
+                file_handler.set_function_table(&file_name, function_table.clone());
+                function_table
+            }
+        };
+
+        if !function_table.contains_key(&Program::version_entry_name(BINARY_VERSION)) {
+            return Err(ProgramError::with_message(ProgramErrorKind::DependencyOutdated, format!("dependency: {}", dependency_name)));
+        }
+        let arch = Program::binary_arch(function_table.keys().map(String::as_bytes));
+        if arch != CpuArchitecture::BITS {
+            return Err(ProgramError::with_message(ProgramErrorKind::DependencyWrongArchitecture, format!("dependency: {}, arch: {} bit", dependency_name, arch)));
+        }
+
+        let (instruction_offset, instruction_length) = match function_table.get(function_name) {
+            Some(val) => *val,
+            None => return Err(Self::create_function_not_found_error(dependency_function)),
+        };
+
+        let mut vec = vec![0u8;instruction_length as usize];
+        conv_io_error!(file.seek(SeekFrom::Start(instruction_offset as u64)), dependency_name);
+        let bytes_read = conv_io_error!(file.read(vec.as_mut_slice()), dependency_name);
+        if bytes_read != instruction_length as usize {
+            return Err(Self::create_function_not_found_error(dependency_function));
+        }
+
+        Ok(Self {
+            function_name: dependency_function.to_string(),
+            instructions: vec,
+        })
+    }
+
+    /// reads the names of the functions within the dependency together with the offset and length of their instructions
+    fn read_function_table(file: &mut impl Read) -> std::io::Result<FunctionTable> {
+        let instruction_offset = file.read_type::<u32>()?;
         let mut index = size_of_val(&instruction_offset);
-        let mut name_buffer = [0u8;u8::MAX as usize + size_of::<CpuArchitecture>()];
+        let mut name_buffer = [0u8;u8::MAX as usize];
         let mut current_instruction_offset = instruction_offset;
+        let mut function_table = FunctionTable::new();
 
         while index < instruction_offset as usize {
-            let name_length = conv_io_error!(file.read_type::<u8>(), dependency_name);
-            let bytes_read = size_of_val(&name_length);
-            if bytes_read == 0 {
-                return Err(Self::create_function_not_found_error(dependency_function));
-            }
-            index += bytes_read;
+            let name_length = file.read_type::<u8>()?;
+            file.read_exact(&mut name_buffer[..name_length as usize])?;
+            let instruction_length = file.read_type::<CpuArchitecture>()?;
+            index += size_of_val(&name_length) + name_length as usize + size_of_val(&instruction_length);
 
-            let read_length = (name_length as usize) + size_of::<CpuArchitecture>();
-            let bytes_read = conv_io_error!(file.read(&mut name_buffer[..read_length]), dependency_name);
-            if bytes_read != read_length {
-                return Err(Self::create_function_not_found_error(dependency_function));
-            }
-            index += bytes_read;
-
-            let (current_name, instruction_length_bytes) = name_buffer.split_at(name_length as usize);
-            let instruction_length:CpuArchitecture = FromBytes::from(instruction_length_bytes[..size_of::<CpuArchitecture>()].try_into().unwrap());
-
-            if current_name.eq(function_name.as_bytes()) {
-                let mut vec = vec![0u8;instruction_length as usize];
-                conv_io_error!(file.seek(SeekFrom::Start(current_instruction_offset as u64)), dependency_name);
-                let bytes_read = conv_io_error!(file.read(vec.as_mut_slice()), dependency_name);
-                if bytes_read != instruction_length as usize {
-                    return Err(Self::create_function_not_found_error(dependency_function));
-                }
-                return Ok(
-                    Self{
-                        function_name: dependency_function.to_string(),
-                        instructions: vec,
-                    }
-                )
-            }
+            let name = String::from_utf8_lossy(&name_buffer[..name_length as usize]).into_owned();
+            function_table.insert(name, (current_instruction_offset, instruction_length));
             current_instruction_offset += instruction_length as u32;
         }
 
-        Err(Self::create_function_not_found_error(dependency_function))
+        Ok(function_table)
     }
     
     pub fn get_dependencies<'a>(dependency_functions: impl Iterator<Item = &'a str>) -> Result<Vec<Self>, ProgramError> {
         let mut dependencies = Vec::with_capacity(4);
         let mut file_handler = ReadFileHandler::new();
+        file_handler.set_memory_mapped(MMAP_DEPENDENCIES.get());
 
         for function in dependency_functions {
             let dependency = Dependency::new(function, &mut file_handler)?;
@@ -115,15 +132,32 @@ impl Dependency {
     pub fn instructions(&mut self, index: CpuArchitecture) -> Result<&[u8], ProgramError> {
         let mut stream = BufferStream::new(self.instructions.as_mut_slice());
 
-        fn set_new_control_flow_position(stream: &mut BufferStream, index: CpuArchitecture, address: Operand) {
-            if let Operand::Literal(lit) = address {
-                stream.set_position(stream.position() - size_of::<CpuArchitecture>() as CpuArchitecture);
-                stream.write_type(&(lit.literal() + index)).unwrap(); // should never panic
+        // the instruction is encoded again as the address isn't always the last operand
+        fn set_new_control_flow_position(stream: &mut BufferStream, position: CpuArchitecture, index: CpuArchitecture, instruction: InstructionSet) {
+            if let Some(Operand::Literal(lit)) = instruction.control_flow_address() {
+                let moved = instruction.with_control_flow_address(Operand::Literal(Literal::new(lit.literal() + index))).unwrap(); // is a control flow instruction
+                stream.set_position(position + INSTRUCTION_SIZE);
+                moved.to_binary(stream).unwrap(); // should never panic as the size hasn't changed
             }
         }
 
         // moves all call/jmp instruction to the new position where these functions/labels are
         while stream.length_left() > 0 {
+            // only call/jmp instructions are decoded, all others are skipped using their encoded size
+            let position = stream.position();
+            let option = InstructionSet::encoded_size(&stream.memory[position as usize..]);
+            match option {
+                Some((number, _)) if InstructionSet::CONTROL_FLOW_NUMBERS.contains(&number) => {},
+                Some((_, size)) => {
+                    if position as usize + size as usize >= stream.memory.len() {
+                        break;
+                    }
+                    stream.set_position(position + size);
+                    continue;
+                },
+                None => {}, // decoded to get the error
+            }
+            
             let result = read_instruction(&mut stream);
             let (instruction, _) = match result {
                 Ok(val) => val,
@@ -131,11 +165,7 @@ impl Dependency {
                                                                   format!("error: {}, function: {}", err, self.function_name()))),
             };
 
-            match instruction {
-                InstructionSet::Call(call) => set_new_control_flow_position(&mut stream, index, call.address()),
-                InstructionSet::Jmp(jmp) => set_new_control_flow_position(&mut stream, index, jmp.address()),
-                _ => {}
-            }
+            set_new_control_flow_position(&mut stream, position, index, instruction);
         }
 
         Ok(self.instructions.as_slice())
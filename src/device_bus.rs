@@ -0,0 +1,142 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+use crate::cpu::CpuArchitecture;
+use crate::dma::{Dma, DMA_PORTS};
+use crate::interrupt::Interrupt;
+use crate::memory::Ram;
+use crate::rtc::{Rtc, RTC_PORTS};
+use crate::serial::{Uart, SERIAL_PORTS};
+use crate::watchdog::{Watchdog, WATCHDOG_PORTS};
+
+/// what a device asks of the computer before the next instruction is executed
+pub enum DeviceSignal {
+    /// calls the handler of the interrupt
+    Interrupt(Interrupt),
+    /// restarts the program from the start, the memory it allocated is kept
+    Reset,
+}
+
+/// an emulated device that handles the in and out instructions for the ports it is registered at
+pub trait Device {
+    /// the value read by in from the port
+    fn read(&mut self, port: CpuArchitecture) -> CpuArchitecture;
+    /// the value written by out to the port
+    fn write(&mut self, port: CpuArchitecture, value: CpuArchitecture);
+    /// called before every instruction while a program is running
+    fn tick(&mut self, _ram: &mut Ram) -> Option<DeviceSignal> {
+        None
+    }
+}
+
+/// dispatches the in and out instructions to the device registered at the port
+pub struct DeviceBus {
+    devices: Vec<(Range<CpuArchitecture>, Box<dyn Device>)>,
+    /// the signals that haven't been handled yet, the devices clear their state when they signal so none can be dropped
+    signals: VecDeque<DeviceSignal>,
+}
+
+impl DeviceBus {
+    /// a bus with the uart on stdio, the real time clock, the watchdog and the dma controller connected
+    pub fn new() -> Self {
+        let mut bus = Self {
+            devices: Vec::new(),
+            signals: VecDeque::new(),
+        };
+        bus.register(SERIAL_PORTS, Box::new(Uart::stdio()));
+        bus.register(RTC_PORTS, Box::new(Rtc::new()));
+        bus.register(WATCHDOG_PORTS, Box::new(Watchdog::new()));
+        bus.register(DMA_PORTS, Box::new(Dma::new()));
+        bus
+    }
+
+    /// connects the device to the ports, devices registered later take precedence over earlier ones
+    pub fn register(&mut self, ports: Range<CpuArchitecture>, device: Box<dyn Device>) {
+        self.devices.push((ports, device));
+    }
+
+    fn device(&mut self, port: CpuArchitecture) -> Option<&mut Box<dyn Device>> {
+        self.devices.iter_mut().rev()
+            .find(| (ports, _) | { ports.contains(&port) })
+            .map(| (_, device) | { device })
+    }
+
+    /// reads from the device at the port, None if no device is connected to it
+    pub fn read(&mut self, port: CpuArchitecture) -> Option<CpuArchitecture> {
+        self.device(port).map(| device | { device.read(port) })
+    }
+
+    /// writes to the device at the port, None if no device is connected to it
+    pub fn write(&mut self, port: CpuArchitecture, value: CpuArchitecture) -> Option<()> {
+        self.device(port).map(| device | { device.write(port, value) })
+    }
+
+    /// ticks every device and gives the oldest signal, the signals of the devices are queued so one is handled before
+    /// every instruction, while the interrupts are masked the interrupts stay queued and only a reset is given,
+    /// a reset drops the signals that were queued before it
+    pub fn tick(&mut self, ram: &mut Ram, interrupts_masked: bool) -> Option<DeviceSignal> {
+        for (_, device) in self.devices.iter_mut() {
+            self.signals.extend(device.tick(ram));
+        }
+
+        let signal = if interrupts_masked {
+            let index = self.signals.iter().position(| signal | { matches!(signal, DeviceSignal::Reset) })?;
+            self.signals.remove(index)
+        } else {
+            self.signals.pop_front()
+        };
+        if matches!(signal, Some(DeviceSignal::Reset)) {
+            self.signals.clear();
+        }
+        signal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// signals the interrupt on the first tick only, like the clock and the dma controller
+    struct OneShot(Option<Interrupt>);
+
+    impl Device for OneShot {
+        fn read(&mut self, _port: CpuArchitecture) -> CpuArchitecture {
+            0
+        }
+
+        fn write(&mut self, _port: CpuArchitecture, _value: CpuArchitecture) {}
+
+        fn tick(&mut self, _ram: &mut Ram) -> Option<DeviceSignal> {
+            self.0.take().map(DeviceSignal::Interrupt)
+        }
+    }
+
+    fn bus(devices: impl IntoIterator<Item = Box<dyn Device>>) -> DeviceBus {
+        let mut bus = DeviceBus { devices: Vec::new(), signals: VecDeque::new() };
+        for (port, device) in devices.into_iter().enumerate() {
+            bus.register(port as CpuArchitecture..port as CpuArchitecture + 1, device);
+        }
+        bus
+    }
+
+    #[test]
+    fn signals_of_the_same_tick_are_all_delivered() {
+        let mut ram = Ram::new(16).unwrap();
+        let mut bus = bus([
+            Box::new(OneShot(Some(Interrupt::Alarm))) as Box<dyn Device>,
+            Box::new(OneShot(Some(Interrupt::Dma))),
+        ]);
+
+        assert!(matches!(bus.tick(&mut ram, false), Some(DeviceSignal::Interrupt(Interrupt::Alarm))));
+        assert!(matches!(bus.tick(&mut ram, false), Some(DeviceSignal::Interrupt(Interrupt::Dma))));
+        assert!(bus.tick(&mut ram, false).is_none());
+    }
+
+    #[test]
+    fn masked_interrupts_wait() {
+        let mut ram = Ram::new(16).unwrap();
+        let mut bus = bus([Box::new(OneShot(Some(Interrupt::Dma))) as Box<dyn Device>]);
+
+        assert!(bus.tick(&mut ram, true).is_none());
+        assert!(matches!(bus.tick(&mut ram, false), Some(DeviceSignal::Interrupt(Interrupt::Dma))));
+    }
+}
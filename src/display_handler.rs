@@ -1,20 +1,54 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
 use glium::{Display, implement_vertex, IndexBuffer, Program, Surface};
 use glium::glutin::surface::WindowSurface;
 use glium::uniforms::EmptyUniforms;
 use glium::winit::application::ApplicationHandler;
 use glium::winit::event::{KeyEvent, MouseButton, WindowEvent};
-use glium::winit::event_loop::ActiveEventLoop;
+use glium::winit::event_loop::{ActiveEventLoop, ControlFlow};
 use glium::winit::keyboard::{Key, NamedKey};
 use glium::winit::window::WindowId;
-use crate::computer::Computer;
+use crate::computer::{Computer, Snapshot};
+use crate::compile::CYCLES_PER_FRAME;
 use crate::cpu::CpuArchitecture;
-use crate::instructions::{AWAITING_EVENT, REDRAW};
+use crate::instructions::{AWAITING_EVENT, REDRAW, SHOW_HUD, WAKE_TIME, WINDOW_EVENTS};
+use crate::hud::{draw_text, Hud};
+use crate::debugger_overlay::debugger_lines;
+use crate::input_script::{InputEvent, InputScript};
 use crate::memory::AllocatedRam;
-use crate::operand::Register;
 use crate::error_creator;
 use crate::computer::ComputerError;
 use crate::window::vertex_buffer_from_memory;
 
+/// pauses and resumes the program
+const PAUSE_KEY: NamedKey = NamedKey::F5;
+/// pauses the program and runs a single frame
+const STEP_KEY: NamedKey = NamedKey::F6;
+/// toggles running the program as fast as possible
+const TURBO_KEY: NamedKey = NamedKey::F7;
+/// freezes the program and shows the debugger on the window, or resumes it
+const DEBUGGER_KEY: NamedKey = NamedKey::F8;
+/// goes back at least REWIND_TIME in the program
+const REWIND_KEY: NamedKey = NamedKey::F9;
+/// the time between the snapshots taken to rewind the program
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(1);
+/// the amount of snapshots that are kept
+const SNAPSHOT_COUNT: usize = 10;
+const REWIND_TIME: Duration = Duration::from_secs(3);
+/// the time between the frames when running with a cycle budget per frame
+const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+/// the oldest event is dropped once this many events are queued, so a program that never takes them doesn't grow the queue
+const MAX_QUEUED_EVENTS: usize = 256;
+
+/// a snapshot of the program with the state of the window needed to continue it
+struct RewindPoint {
+    time: Instant,
+    snapshot: Snapshot,
+    awaiting_event: bool,
+    /// the time left until the timer event when the program waits using hlt
+    wake_after: Option<Duration>,
+}
+
 error_creator!(
     AppError,
     AppErrorKind,
@@ -30,34 +64,205 @@ pub(crate) struct AppHandler<'a> {
     program: Program,
     index_buffer: IndexBuffer<u32>,
     size: (usize, usize),
+    hud: Hud,
+    input_script: Option<InputScript>,
+    next_frame: Instant,
+    /// the program isn't run while paused, the events stay queued until it is resumed
+    paused: bool,
+    /// runs a single frame, or wakes the program once without a cycle budget, while paused
+    step: bool,
+    /// runs the frames back to back and wakes the program from hlt immediately
+    turbo: bool,
+    /// shows the registers and the next instructions on top of the frames while paused
+    debugger: bool,
+    rewind_points: VecDeque<RewindPoint>,
 }
 
 impl<'a> AppHandler<'a> {
     pub(crate) fn new(computer: &'a mut Computer, memory: AllocatedRam, display: Display<WindowSurface>,
                         program: Program, index_buffer: IndexBuffer<u32>, size: (usize, usize)) -> Self {
         Self {
+            input_script: computer.take_input_script().map(| mut input_script | { input_script.start(); input_script }),
             computer,
             error: Ok(()),
             memory,
             display,
             program,
             index_buffer,
-            size
+            size,
+            hud: Hud::new(),
+            next_frame: Instant::now(),
+            paused: false,
+            step: false,
+            turbo: false,
+            debugger: false,
+            rewind_points: VecDeque::new(),
         }
     }
     
     pub(crate) fn result(self) -> Result<()> {
         self.error
     }
+    
+    /// queues the event for GetWindowEvent, the cursor is kept within the canvas
+    fn queue_event(&self, event: InputEvent) {
+        let event = match event {
+            InputEvent::Cursor { x, y } => InputEvent::Cursor {
+                x: x.min((self.size.0 - 1) as CpuArchitecture),
+                y: y.min((self.size.1 - 1) as CpuArchitecture),
+            },
+            event => event,
+        };
+        WINDOW_EVENTS.with_borrow_mut(| events | {
+            if events.len() == MAX_QUEUED_EVENTS {
+                events.pop_front();
+            }
+            events.push_back(event);
+        });
+    }
+    
+    /// wakes the program when it waits and an event is queued, or when the timeout of hlt has passed which queues the
+    /// timer event, returns whether the program was woken
+    fn wake(&mut self) -> bool {
+        if !AWAITING_EVENT.get() {
+            return false;
+        }
+        let timer_due = WAKE_TIME.get().is_some_and(| time | { self.turbo || time <= Instant::now() });
+        if timer_due {
+            self.queue_event(InputEvent::Timer);
+        }
+        if WINDOW_EVENTS.with_borrow(VecDeque::is_empty) {
+            return false;
+        }
+        
+        self.computer.check_hot_reload();
+        WAKE_TIME.set(None);
+        AWAITING_EVENT.set(false);
+        true
+    }
+    
+    /// runs the program until it waits for the next event or the amount of instructions is executed,
+    /// returns the amount of executed instructions
+    fn execute(&mut self, event_loop: &ActiveEventLoop, instructions: u64) -> u64 {
+        let mut executed = 0;
+        while !AWAITING_EVENT.get() && executed < instructions {
+            executed += 1;
+            self.hud.on_instruction();
+            let result = self.computer.execute_next_instruction();
+            let exited = match result {
+                Ok(val) => val,
+                Err(err) => {
+                    self.error = Err(AppError::new(AppErrorKind::ComputerError(err)));
+                    event_loop.exit();
+                    break;
+                }
+            };
+            if exited {
+                event_loop.exit();
+                break;
+            }
+            // with a cycle budget the frame is only presented at the end of the frame
+            if REDRAW.get() {
+                if CYCLES_PER_FRAME.get() == 0 {
+                    self.redraw();
+                }
+                REDRAW.set(false);
+            }
+        }
+        executed
+    }
+    
+    /// handles the hotkeys of the emulator, which aren't passed to the program, returns whether the key is a hotkey
+    fn handle_hotkey(&mut self, key: NamedKey, pressed: bool) -> bool {
+        match key {
+            PAUSE_KEY => if pressed {
+                self.paused = !self.paused;
+                self.debugger &= self.paused;
+            },
+            STEP_KEY => if pressed {
+                self.paused = true;
+                self.step = true;
+            },
+            TURBO_KEY => if pressed { self.turbo = !self.turbo },
+            DEBUGGER_KEY => if pressed {
+                self.debugger = !self.debugger;
+                self.paused = self.debugger;
+            },
+            REWIND_KEY => if pressed { self.rewind() },
+            _ => return false,
+        }
+        true
+    }
+    
+    /// takes a snapshot when SNAPSHOT_INTERVAL has passed since the last one, the oldest is dropped when there are too many
+    fn take_snapshot(&mut self) {
+        let now = Instant::now();
+        if self.rewind_points.back().is_some_and(| point | { now - point.time < SNAPSHOT_INTERVAL }) {
+            return;
+        }
+        
+        if self.rewind_points.len() == SNAPSHOT_COUNT {
+            self.rewind_points.pop_front();
+        }
+        self.rewind_points.push_back(RewindPoint {
+            time: now,
+            snapshot: self.computer.snapshot(),
+            awaiting_event: AWAITING_EVENT.get(),
+            wake_after: WAKE_TIME.get().map(| time | { time.saturating_duration_since(now) }),
+        });
+    }
+    
+    /// restores the newest snapshot that is at least REWIND_TIME old, or the oldest snapshot, the newer snapshots are dropped
+    fn rewind(&mut self) {
+        let now = Instant::now();
+        while self.rewind_points.len() > 1 && self.rewind_points.back().is_some_and(| point | { now - point.time < REWIND_TIME }) {
+            self.rewind_points.pop_back();
+        }
+        let Some(point) = self.rewind_points.pop_back() else {
+            return;
+        };
+        
+        self.computer.restore_snapshot(&point.snapshot);
+        AWAITING_EVENT.set(point.awaiting_event);
+        WAKE_TIME.set(point.wake_after.map(| wake_after | { now + wake_after }));
+        WINDOW_EVENTS.with_borrow_mut(VecDeque::clear);
+        self.redraw();
+    }
+    
+    /// runs the cycle budget of a frame, the program is woken whenever it waits and an event is queued,
+    /// the frame is presented afterwards even when the program didn't ask for it
+    fn run_frame(&mut self, event_loop: &ActiveEventLoop) {
+        let mut cycles_left = CYCLES_PER_FRAME.get() as u64;
+        while cycles_left != 0 && !event_loop.exiting() {
+            if AWAITING_EVENT.get() && !self.wake() {
+                break;
+            }
+            cycles_left -= self.execute(event_loop, cycles_left);
+        }
+        
+        if !event_loop.exiting() {
+            self.redraw();
+        }
+    }
 
-    fn redraw(&self) {
+    fn redraw(&mut self) {
         let vertex_buffer = vertex_buffer_from_memory(&self.display, &self.memory, self.size).unwrap();
 
         let mut frame = self.display.draw();
         frame.clear_color(1.0, 1.0, 1.0, 1.0);
         frame.draw(&vertex_buffer, &self.index_buffer, &self.program,
                    &EmptyUniforms, &Default::default()).unwrap();
+        self.hud.on_frame();
+        if SHOW_HUD.get() {
+            self.hud.draw(&mut frame, &self.display, &self.program, self.computer);
+        }
+        if self.debugger {
+            // below the hud so both can be shown
+            draw_text(&mut frame, &self.display, &self.program, &debugger_lines(self.computer), 1);
+        }
         frame.finish().unwrap();
+        self.computer.perf_counters_mut().on_redraw();
+        self.computer.capture_frame(&self.memory, self.size);
     }
 }
 
@@ -66,28 +271,27 @@ impl<'a> ApplicationHandler for AppHandler<'a> {
     }
 
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
-        let event_num = match event {
-            WindowEvent::CloseRequested => 0,
+        if let WindowEvent::KeyboardInput { event: KeyEvent { logical_key: Key::Named(key), state, .. }, .. } = &event {
+            let debugger = self.debugger;
+            if self.handle_hotkey(*key, state.is_pressed()) {
+                // shows or removes the debugger right away as the program doesn't present frames while paused
+                if debugger || self.debugger {
+                    self.redraw();
+                }
+                return;
+            }
+        }
+        
+        let event = match event {
+            WindowEvent::CloseRequested => InputEvent::Close,
             WindowEvent::CursorMoved { position, .. } => {
-                let x_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
-                let y_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
-                
                 let dimensions = self.display.get_framebuffer_dimensions();
                 let x = ((position.x / dimensions.0 as f64) * self.size.0 as f64) as CpuArchitecture;
                 let y = ((position.y / dimensions.1 as f64) * self.size.1 as f64) as CpuArchitecture;
-                let x = x.min((self.size.0 - 1) as CpuArchitecture);
-                let y = y.min((self.size.1 - 1) as CpuArchitecture);
-                
-                self.computer.cpu_mut().set_register(x_register, x).unwrap(); // cpu should have 4 or more registers
-                self.computer.cpu_mut().set_register(y_register, y).unwrap();
-                1
+                InputEvent::Cursor { x, y }
             },
             WindowEvent::MouseInput { state, button, .. } => {
-                let is_press_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
-                let button_num_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
-                
-                let pressed = state.is_pressed() as CpuArchitecture;
-                let button_num = match button {
+                let button = match button {
                     MouseButton::Left => 0,
                     MouseButton::Right => 1,
                     MouseButton::Middle => 2,
@@ -95,18 +299,11 @@ impl<'a> ApplicationHandler for AppHandler<'a> {
                     MouseButton::Back => 4,
                     MouseButton::Other(val) => val as CpuArchitecture + 4,
                 };
-                
-                self.computer.cpu_mut().set_register(is_press_register, pressed).unwrap(); // cpu should have 4 or more registers
-                self.computer.cpu_mut().set_register(button_num_register, button_num).unwrap();
-                
-                2
+                InputEvent::Mouse { pressed: state.is_pressed(), button }
             },
             WindowEvent::KeyboardInput { event, .. } => {
-                let button_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
-                let down_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
-
                 let KeyEvent { logical_key, .. } = event;
-                let button = match logical_key {
+                let key = match logical_key {
                     Key::Character(c) => {
                         c.chars().next().unwrap()
                     },
@@ -118,40 +315,56 @@ impl<'a> ApplicationHandler for AppHandler<'a> {
                     },
                     _ => '\0',
                 };
-                
-                let down = event.state.is_pressed() as CpuArchitecture;
-                
-                self.computer.cpu_mut().set_register(button_register, button as CpuArchitecture).unwrap(); // cpu should have more than 4 registers
-                self.computer.cpu_mut().set_register(down_register, down).unwrap();
-                
-                3
+                InputEvent::Key { key, down: event.state.is_pressed() }
             },
-            _ => CpuArchitecture::MAX,
+            _ => InputEvent::Other,
         };
         
-        let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
-        self.computer.cpu_mut().set_register(register, event_num).unwrap(); // cpu should have 4 or more registers
+        self.queue_event(event);
+        if CYCLES_PER_FRAME.get() == 0 && !self.paused && self.wake() {
+            self.execute(event_loop, u64::MAX);
+        }
+    }
+    
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        while let Some(event) = self.input_script.as_mut().and_then(InputScript::next_due) {
+            self.queue_event(event);
+        }
         
-        while !AWAITING_EVENT.get() {
-            let result = self.computer.execute_next_instruction();
-            let exited = match result {
-                Ok(val) => val,
-                Err(err) => {
-                    self.error = Err(AppError::new(AppErrorKind::ComputerError(err)));
-                    event_loop.exit();
-                    break;
-                }
-            };
-            if exited {
-                event_loop.exit();
-                break;
-            }
-            if REDRAW.get() {
-                self.redraw();
-                REDRAW.set(false);
+        let step = std::mem::take(&mut self.step);
+        if CYCLES_PER_FRAME.get() != 0 {
+            if step || (!self.paused && (self.turbo || Instant::now() >= self.next_frame)) {
+                self.run_frame(event_loop);
+                // a slow frame slows the program down instead of skipping frames
+                self.next_frame = (self.next_frame + FRAME_TIME).max(Instant::now());
             }
+        } else if (step || !self.paused) && !event_loop.exiting() && self.wake() {
+            // the program is woken at most once so turbo doesn't keep waking a program that waits using hlt
+            self.execute(event_loop, u64::MAX);
+        }
+        
+        if step && self.debugger && !event_loop.exiting() {
+            self.redraw();
+        }
+        if !self.paused && !event_loop.exiting() {
+            self.take_snapshot();
+        }
+        
+        if self.turbo && !self.paused {
+            event_loop.set_control_flow(ControlFlow::Poll);
+            return;
+        }
+        let script_time = self.input_script.as_ref().and_then(InputScript::next_time);
+        let program_time = match CYCLES_PER_FRAME.get() {
+            _ if self.paused => None,
+            0 => WAKE_TIME.get(),
+            _ => Some(self.next_frame),
+        };
+        let next_time = script_time.into_iter().chain(program_time).min();
+        match next_time {
+            Some(time) => event_loop.set_control_flow(ControlFlow::WaitUntil(time)),
+            None => event_loop.set_control_flow(ControlFlow::Wait),
         }
-        AWAITING_EVENT.set(false);
     }
 }
 
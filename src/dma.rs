@@ -0,0 +1,104 @@
+use std::ops::Range;
+use crate::cpu::CpuArchitecture;
+use crate::device_bus::{Device, DeviceSignal};
+use crate::interrupt::Interrupt;
+use crate::memory::Ram;
+
+/// the ports of the dma controller: the source address and bank, the destination address and bank, the length and the control
+pub const DMA_PORTS: Range<CpuArchitecture> = 0x28..0x2E;
+/// writing 1 starts the transfer, which is done before the next instruction and raises the dma interrupt, the interrupt
+/// is queued by the device bus so it is handled after the signals other devices raised before it,
+/// reading gives the status of the last transfer
+pub const DMA_CONTROL_PORT: CpuArchitecture = DMA_PORTS.end - 1;
+/// the status when the last transfer failed, eg: because the memory isn't allocated or the bank doesn't exist
+pub const DMA_STATUS_FAILED: CpuArchitecture = 1;
+
+/// copies memory between addresses of any bank on the host instead of using a loop of movs
+pub struct Dma {
+    /// the source address, source bank, destination address, destination bank and length
+    registers: [CpuArchitecture; 5],
+    pending: bool,
+    status: CpuArchitecture,
+}
+
+impl Dma {
+    pub fn new() -> Self {
+        Self {
+            registers: [0; 5],
+            pending: false,
+            status: 0,
+        }
+    }
+
+    fn transfer(&self, ram: &mut Ram) -> bool {
+        let [source, source_bank, destination, destination_bank, length] = self.registers;
+        let mut buffer = vec![0u8; length as usize];
+        ram.read_bank_buffer_at(source_bank as u8, source, &mut buffer).is_ok() &&
+            ram.write_bank_buffer_at(destination_bank as u8, destination, &buffer).is_ok()
+    }
+}
+
+impl Device for Dma {
+    fn read(&mut self, port: CpuArchitecture) -> CpuArchitecture {
+        match self.registers.get((port - DMA_PORTS.start) as usize) {
+            Some(value) => *value,
+            None => self.status,
+        }
+    }
+
+    fn write(&mut self, port: CpuArchitecture, value: CpuArchitecture) {
+        match self.registers.get_mut((port - DMA_PORTS.start) as usize) {
+            Some(register) => *register = value,
+            None => self.pending = value == 1,
+        }
+    }
+
+    fn tick(&mut self, ram: &mut Ram) -> Option<DeviceSignal> {
+        if !self.pending {
+            return None;
+        }
+
+        self.pending = false;
+        self.status = if self.transfer(ram) { 0 } else { DMA_STATUS_FAILED };
+        Some(DeviceSignal::Interrupt(Interrupt::Dma))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start_transfer(dma: &mut Dma, registers: [CpuArchitecture; 5]) {
+        for (port, value) in DMA_PORTS.zip(registers) {
+            dma.write(port, value);
+        }
+        dma.write(DMA_CONTROL_PORT, 1);
+    }
+
+    #[test]
+    fn transfer_raises_the_interrupt_once() {
+        let mut ram = Ram::new(16).unwrap();
+        ram.add_banks(1).unwrap();
+        ram.write_bank_buffer_at(1, 0, &[1, 2, 3, 4]).unwrap();
+        let mut dma = Dma::new();
+
+        start_transfer(&mut dma, [0, 1, 8, 1, 4]);
+        assert!(matches!(dma.tick(&mut ram), Some(DeviceSignal::Interrupt(Interrupt::Dma))));
+        assert!(dma.tick(&mut ram).is_none());
+
+        let mut buffer = [0; 4];
+        ram.read_bank_buffer_at(1, 8, &mut buffer).unwrap();
+        assert_eq!(buffer, [1, 2, 3, 4]);
+        assert_eq!(dma.read(DMA_CONTROL_PORT), 0);
+    }
+
+    #[test]
+    fn failed_transfer_still_raises_the_interrupt() {
+        let mut ram = Ram::new(16).unwrap();
+        let mut dma = Dma::new();
+
+        start_transfer(&mut dma, [0, 5, 8, 5, 4]);
+        assert!(matches!(dma.tick(&mut ram), Some(DeviceSignal::Interrupt(Interrupt::Dma))));
+        assert_eq!(dma.read(DMA_CONTROL_PORT), DMA_STATUS_FAILED);
+    }
+}
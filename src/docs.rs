@@ -0,0 +1,205 @@
+use std::fs::OpenOptions;
+use std::io::{stdout, BufWriter, Write};
+use clap_derive::ValueEnum;
+use crate::computer::REGISTER_COUNT;
+use crate::cpu::{CpuArchitecture, FLOAT_REGISTER_COUNT};
+use crate::rtc::{RTC_ALARM_PORT, RTC_PORTS};
+use crate::serial::{SERIAL_INPUT_ENDED, SERIAL_INPUT_READY, SERIAL_PORTS};
+use crate::dma::{DMA_CONTROL_PORT, DMA_PORTS, DMA_STATUS_FAILED};
+use crate::watchdog::{WATCHDOG_ACTION_PORT, WATCHDOG_PET_PORT, WATCHDOG_TIMEOUT_PORT};
+use crate::instructions::{syscall_documentation, InstructionSet, INSTRUCTION_ALIASES};
+use crate::operand::{BankedPointer, DisplacementPointer, FloatRegister, IndexPointer, RegisterPair, FLOAT_REGISTER_CHARACTER, REGISTER_PAIR_SEPARATOR, Literal, Pointer, Register, POINTER_STRINGS, REGISTER_CHARACTERS, STACK_POINTER_NAME, BASE_POINTER_NAME};
+use crate::program::INSTRUCTION_SIZE;
+
+pub struct InstructionDocumentation {
+    pub name: &'static str,
+    pub number: CpuArchitecture,
+    pub operands: &'static [&'static str],
+    pub description: &'static str,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum DocsFormat {
+    Markdown,
+    Html,
+}
+
+impl DocsFormat {
+    fn heading(self, stream: &mut impl Write, level: usize, text: &str) -> std::io::Result<()> {
+        match self {
+            DocsFormat::Markdown => writeln!(stream, "{} {}\n", "#".repeat(level), text),
+            DocsFormat::Html => writeln!(stream, "<h{level}>{}</h{level}>", escape_html(text)),
+        }
+    }
+
+    fn paragraph(self, stream: &mut impl Write, text: &str) -> std::io::Result<()> {
+        match self {
+            DocsFormat::Markdown => writeln!(stream, "{}\n", text),
+            DocsFormat::Html => writeln!(stream, "<p>{}</p>", escape_html(text)),
+        }
+    }
+
+    fn table(self, stream: &mut impl Write, header: &[&str], rows: &[Vec<String>]) -> std::io::Result<()> {
+        match self {
+            DocsFormat::Markdown => {
+                writeln!(stream, "| {} |", header.join(" | "))?;
+                writeln!(stream, "|{}", "---|".repeat(header.len()))?;
+                for row in rows {
+                    let cells: Vec<_> = row.iter().map(| cell | { cell.replace('|', "\\|") }).collect();
+                    writeln!(stream, "| {} |", cells.join(" | "))?;
+                }
+                writeln!(stream)
+            },
+            DocsFormat::Html => {
+                writeln!(stream, "<table>")?;
+                let header: Vec<_> = header.iter().map(| cell | { format!("<th>{}</th>", escape_html(cell)) }).collect();
+                writeln!(stream, "<tr>{}</tr>", header.join(""))?;
+                for row in rows {
+                    let cells: Vec<_> = row.iter().map(| cell | { format!("<td>{}</td>", escape_html(cell)) }).collect();
+                    writeln!(stream, "<tr>{}</tr>", cells.join(""))?;
+                }
+                writeln!(stream, "</table>")
+            },
+        }
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// writes the instruction set reference generated from the instruction and syscall definitions
+fn write_docs(stream: &mut impl Write, format: DocsFormat) -> std::io::Result<()> {
+    if let DocsFormat::Html = format {
+        writeln!(stream, "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Instruction set reference</title></head>\n<body>")?;
+    }
+
+    format.heading(stream, 1, "Instruction set reference")?;
+
+    format.heading(stream, 2, "Operands")?;
+    let register_sizes = size_of::<CpuArchitecture>().ilog2() as usize + 1;
+    let mut rows = Vec::new();
+    for (index, character) in REGISTER_CHARACTERS.iter().take(register_sizes).enumerate() {
+        // the smaller registers are numbered by their size so more of them exist
+        let count = (0..).take_while(| register | { Register::new(*register, 1 << index).exists(REGISTER_COUNT as u8) }).count();
+        rows.push(vec![
+            format!("{}1 - {}{}", character, character, count),
+            format!("{} byte register", 1 << index),
+            Register::binary_size().to_string(),
+        ]);
+    }
+    rows.push(vec![STACK_POINTER_NAME.to_string(), "the stack pointer".to_string(), Register::binary_size().to_string()]);
+    rows.push(vec![BASE_POINTER_NAME.to_string(), "the base pointer, set by enter and restored by leave".to_string(), Register::binary_size().to_string()]);
+    rows.push(vec![
+        format!("{}1 - {}{}", FLOAT_REGISTER_CHARACTER, FLOAT_REGISTER_CHARACTER, FLOAT_REGISTER_COUNT),
+        "4 byte float register, only used by the float instructions".to_string(),
+        FloatRegister::binary_size().to_string(),
+    ]);
+    rows.push(vec![
+        format!("x1{}x2", REGISTER_PAIR_SEPARATOR),
+        format!("{} byte value of two registers, the first holds the upper half, only used by the long instructions", size_of::<CpuArchitecture>() * 2),
+        RegisterPair::binary_size().to_string(),
+    ]);
+    rows.push(vec!["10, -10, 0x1F, 0b101, 0o17".to_string(), "literal".to_string(), Literal::binary_size().to_string()]);
+    for (index, pointer) in POINTER_STRINGS.iter().take(register_sizes).enumerate() {
+        rows.push(vec![
+            format!("{}[x1], {}[0x100]", pointer, pointer),
+            format!("{} byte value at the address of the register or literal", 1 << index),
+            format!("{} + register or literal", Pointer::binary_size()),
+        ]);
+    }
+    rows.push(vec![
+        "word[x1 + 4], word[sp - 2], word[bp + 2]".to_string(),
+        "value at the address of the register plus or minus the literal".to_string(),
+        DisplacementPointer::binary_size().to_string(),
+    ]);
+    rows.push(vec![
+        "word[x1 + x2], word[x1 + x2 * 2]".to_string(),
+        "value at the address of the register plus the index register times 1, 2, 4 or 8".to_string(),
+        IndexPointer::binary_size().to_string(),
+    ]);
+    rows.push(vec![
+        format!("word[x1{}x2]", REGISTER_PAIR_SEPARATOR),
+        "value at the address of the second register in the bank of the first register, the selected bank is ignored".to_string(),
+        BankedPointer::binary_size().to_string(),
+    ]);
+    format.table(stream, &["Form", "Description", "Encoded bytes"], &rows)?;
+
+    format.heading(stream, 2, "Instructions")?;
+    format.paragraph(stream, &format!("instructions are encoded as the instruction number of {} byte(s) followed by the operands", INSTRUCTION_SIZE))?;
+    let rows: Vec<_> = InstructionSet::DOCUMENTATION.iter().map(| instruction | {
+        vec![
+            instruction.number.to_string(),
+            instruction.name.to_lowercase(),
+            instruction.operands.join(", "),
+            instruction.description.trim().to_string(),
+        ]
+    }).collect();
+    format.table(stream, &["Number", "Mnemonic", "Operands", "Description"], &rows)?;
+
+    format.paragraph(stream, "the aliases are replaced by the mnemonic they stand for so code from other assemblers can be used")?;
+    let rows: Vec<_> = INSTRUCTION_ALIASES.iter().map(| (alias, mnemonic) | {
+        vec![alias.to_string(), mnemonic.to_string()]
+    }).collect();
+    format.table(stream, &["Alias", "Mnemonic"], &rows)?;
+
+    format.heading(stream, 2, "Directives")?;
+    format.paragraph(stream, "%title, %author and %version describe the program, eg: %title \"Snake\", they are stored in built binaries, shown when the program is run and the title names a window that is opened without a name")?;
+
+    format.heading(stream, 2, "Calling convention")?;
+    format.paragraph(stream, "callargs function, argument1, argument2, ... pushes the arguments from the last to the first, calls the function and removes the arguments from the stack once it returns")?;
+    format.paragraph(stream, &format!("the first argument is directly below the return address of {} bytes, the return value is passed in x2", size_of::<CpuArchitecture>()))?;
+    format.paragraph(stream, &format!("after enter size the locals are at bp up to bp + size and the arguments are below the {} byte bp pushed by enter, leave frees the locals before returning", size_of::<CpuArchitecture>()))?;
+    format.paragraph(stream, &format!("the stack grows upwards so the last pushed value is at sp minus its size, eg: word[{} - {}] is the last pushed word", STACK_POINTER_NAME, size_of::<CpuArchitecture>()))?;
+
+    format.heading(stream, 2, "Interrupts")?;
+    format.paragraph(stream, &format!("the vector table set using SetInterruptTable contains a {} byte handler address per interrupt, addr loads the address of a function to fill it", size_of::<CpuArchitecture>()))?;
+    format.paragraph(stream, &format!("a handler is called with a frame of {} byte values, from the bottom of the stack: the flags, x2 and the return address, so x2 is at word[{} - {}] in the handler", size_of::<CpuArchitecture>(), STACK_POINTER_NAME, size_of::<CpuArchitecture>() * 2))?;
+    format.paragraph(stream, "a handler returns using iret, which pops the frame and restores x2 and the flags, a fault returns to the instruction after the one that caused it and the handler has to keep the other registers it changes")?;
+    format.paragraph(stream, "the interrupt flag is set while a handler runs, the device interrupts wait until the handler returns and the timer is skipped while faults and int still call their handler")?;
+    format.paragraph(stream, "a fault handler gets the address that caused the fault in x2, the memory address of a segmentation fault or otherwise the address of the instruction, an invalid instruction returns to the same address")?;
+
+    format.heading(stream, 2, "Ports")?;
+    format.paragraph(stream, "in and out access the devices connected to the ports, a port without a device gives the PortNotConnected error")?;
+    let rows = vec![
+        vec![SERIAL_PORTS.start.to_string(), "serial console data, out sends the lower byte and in waits for the next byte or gives 65535 when the input has ended, connected to stdio or a tcp port using --serial".to_string()],
+        vec![(SERIAL_PORTS.start + 1).to_string(), format!("serial console status, in gives {} when a byte can be read without waiting and {} when the input has ended", SERIAL_INPUT_READY, SERIAL_INPUT_ENDED)],
+        vec![format!("{} - {}", RTC_PORTS.start, RTC_ALARM_PORT - 1), "clock, in reads the seconds, minutes and hours, the days since 1970, the day of the month, the month, the year and the day of the week (0 is sunday) in UTC".to_string()],
+        vec![RTC_ALARM_PORT.to_string(), "clock alarm, out raises the alarm interrupt once after the amount of seconds and 0 cancels it, in reads the seconds left".to_string()],
+        vec![WATCHDOG_TIMEOUT_PORT.to_string(), "watchdog timeout, out starts the watchdog with a timeout of the value times 1000 executed instructions and 0 stops it, in reads the thousands of instructions left".to_string()],
+        vec![WATCHDOG_PET_PORT.to_string(), "watchdog pet, out restarts the countdown".to_string()],
+        vec![WATCHDOG_ACTION_PORT.to_string(), "watchdog action, out 0 raises the watchdog interrupt when it expires, which stops the program without a handler, and 1 restarts the program".to_string()],
+        vec![format!("{} - {}", DMA_PORTS.start, DMA_CONTROL_PORT - 1), "dma controller, the source address, source bank, destination address, destination bank and length of the transfer".to_string()],
+        vec![DMA_CONTROL_PORT.to_string(), format!("dma control, out 1 copies the memory before the next instruction and raises the dma interrupt, in reads 0 if the last transfer succeeded or {} if it failed", DMA_STATUS_FAILED)],
+    ];
+    format.table(stream, &["Port", "Device"], &rows)?;
+
+    format.heading(stream, 2, "Syscalls")?;
+    format.paragraph(stream, "the syscall number is passed in x1, arguments are passed in x2 and onwards and results are returned in x2")?;
+    let rows: Vec<_> = syscall_documentation().map(| (name, number, documentation) | {
+        vec![number.to_string(), name.to_string(), documentation.trim().to_string()]
+    }).collect();
+    format.table(stream, &["Number", "Name", "Description"], &rows)?;
+
+    if let DocsFormat::Html = format {
+        writeln!(stream, "</body>\n</html>")?;
+    }
+    Ok(())
+}
+
+pub fn docs(format: DocsFormat, out: Option<String>) {
+    let result = match out {
+        Some(out) => {
+            let result = OpenOptions::new().write(true).create(true).truncate(true).open(&out);
+            match result {
+                Ok(file) => write_docs(&mut BufWriter::new(file), format),
+                Err(err) => { println!("could not write to file: {}, filename: {}", err, out); return; }
+            }
+        },
+        None => write_docs(&mut stdout().lock(), format),
+    };
+
+    if let Err(err) = result {
+        println!("unable to write the documentation: {}", err);
+    }
+}
@@ -1,3 +1,40 @@
+use std::fmt::{Display, Formatter, UpperHex};
+use std::rc::Rc;
+
+/// the extra message of an error, static strings and addresses are not copied or formatted until displayed
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub enum ErrorMessage {
+    Static(&'static str),
+    Shared(Rc<str>),
+    Address(u64),
+}
+
+impl From<&'static str> for ErrorMessage {
+    fn from(value: &'static str) -> Self {
+        ErrorMessage::Static(value)
+    }
+}
+
+impl From<String> for ErrorMessage {
+    fn from(value: String) -> Self {
+        ErrorMessage::Shared(value.into())
+    }
+}
+
+impl Display for ErrorMessage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorMessage::Static(message) => f.write_str(message),
+            ErrorMessage::Shared(message) => f.write_str(message),
+            ErrorMessage::Address(address) => {
+                f.write_str("(0x")?;
+                UpperHex::fmt(address, f)?;
+                f.write_str(")")
+            },
+        }
+    }
+}
+
 #[macro_export] macro_rules! conv_ident {
     ($ident:ident, $new:ident) => { $new };
 }
@@ -6,10 +43,9 @@
     (
         $error_name:ident,
         $error_kind_name:ident,
-        $( $kind:ident$(($val:ident))? => $string:expr ),*
+        $( $(#[doc = $doc:literal])* $kind:ident$(($val:ident))? => $string:expr ),*
     ) => {
         use $crate::conv_ident;
-        use std::rc::Rc;
         #[derive(Clone, Eq, PartialEq, Hash)]
         pub enum $error_kind_name {
             $($kind $(($val))? ),*
@@ -18,7 +54,7 @@
         #[derive(Clone, Eq, PartialEq, Hash)]
         pub struct $error_name {
             kind: $error_kind_name,
-            message: Rc<String>,
+            message: Option<std::rc::Rc<$crate::error::ErrorMessage>>,
         }
         
         impl std::fmt::Debug for $error_name {
@@ -31,11 +67,11 @@
                     $( $error_kind_name::$kind $((conv_ident!($val, val)))? => { 
                         $( if true { std::fmt::Display::fmt(&conv_ident!($val, val), f)?; } else )? 
                         { f.write_str($string)?; } 
-                        if !self.message.is_empty() {
+                        if let Some(message) = &self.message {
                             if !$string.trim().is_empty() $(|| conv_ident!($val, true))? {
                                 f.write_str(", ")?;
                             }
-                            f.write_str(self.message.as_str())
+                            std::fmt::Display::fmt(message, f)
                         } else { Ok(()) } } ),*
                 }
             }
@@ -46,19 +82,25 @@
         type Result<T> = std::result::Result<T, $error_name>;
         
         impl $error_name {
+            /// the name, message and doc comment of every kind, the doc comment describes common causes
+            #[allow(unused)]
+            pub const KINDS: &'static [(&'static str, &'static str, &'static str)] = &[
+                $((stringify!($kind), $string, concat!($($doc),*))),*
+            ];
+            
             #[allow(unused)]
             pub fn new(kind: $error_kind_name) -> Self {
                 Self {
                     kind,
-                    message: Rc::default(),
+                    message: None,
                 }
             }
             
             #[allow(unused)]
-            pub fn with_message(kind: $error_kind_name, message: impl Into<String>) -> Self {
+            pub fn with_message(kind: $error_kind_name, message: impl Into<$crate::error::ErrorMessage>) -> Self {
                 Self {
                     kind,
-                    message: Rc::new(message.into()),
+                    message: Some(std::rc::Rc::new(message.into())),
                 }
             }
         
@@ -66,6 +108,11 @@
             pub fn kind(&self) -> &$error_kind_name {
                 &self.kind
             }
+            
+            #[allow(unused)]
+            pub fn message(&self) -> Option<&$crate::error::ErrorMessage> {
+                self.message.as_deref()
+            }
         }
         
         $( $(
@@ -0,0 +1,78 @@
+use crate::compile::{run_source, RunOptions};
+use crate::cpu::CpuArchitecture;
+
+struct Example {
+    name: &'static str,
+    description: &'static str,
+    source: &'static str,
+    memory_amount: CpuArchitecture,
+}
+
+/// the embedded examples, ordered from easiest to hardest
+const EXAMPLES: [Example; 5] = [
+    Example {
+        name: "hello",
+        description: "allocates memory and prints the bytes of \"Hello\"",
+        source: include_str!("../examples/hello.asm"),
+        memory_amount: 1024,
+    },
+    Example {
+        name: "loops",
+        description: "adds the numbers 1 to 10 together using a label and conditional jmp",
+        source: include_str!("../examples/loops.asm"),
+        memory_amount: 1024,
+    },
+    Example {
+        name: "functions",
+        description: "calculates a fibonacci number using recursive calls and the stack",
+        source: include_str!("../examples/functions.asm"),
+        memory_amount: 1024,
+    },
+    Example {
+        name: "pixels",
+        description: "opens a window and draws a line by writing pixels to the canvas",
+        source: include_str!("../examples/pixels.asm"),
+        memory_amount: 2048,
+    },
+    Example {
+        name: "sprites",
+        description: "draws a sprite that follows the mouse using window events",
+        source: include_str!("../examples/sprites.asm"),
+        memory_amount: 8192,
+    },
+];
+
+fn list_examples() {
+    println!("examples:");
+    let width = EXAMPLES.iter().map(| example | { example.name.len() }).max().unwrap_or_default();
+    for example in &EXAMPLES {
+        println!("    {:width$}  {}", example.name, example.description);
+    }
+    println!("use `examples <name>` to show the source of an example and `examples <name> --run` to run it");
+}
+
+/// lists the examples, or shows or runs the example with the name
+pub fn examples(name: Option<String>, run: bool, debug: bool) {
+    let Some(name) = name else {
+        list_examples();
+        return;
+    };
+
+    let option = EXAMPLES.iter().find(| example | { example.name.eq_ignore_ascii_case(&name) });
+    let Some(example) = option else {
+        println!("unknown example: {}", name);
+        list_examples();
+        return;
+    };
+
+    if run {
+        run_source(example.source, RunOptions {
+            memory_amount: example.memory_amount,
+            debug,
+            ..RunOptions::default()
+        });
+    } else {
+        println!("; {} - {}", example.name, example.description);
+        print!("{}", example.source);
+    }
+}
@@ -0,0 +1,78 @@
+use std::str::FromStr;
+use crate::cpu::{CpuError, IntoBytes};
+use crate::instructions::{Instruction, InstructionError, InstructionSet};
+use crate::memory::RamError;
+use crate::memory_buffer::MemoryBufferError;
+use crate::program::{ProgramError, INSTRUCTION_SIZE};
+
+const ERROR_PREFIX: &str = "E:";
+
+type ErrorKinds = &'static [(&'static str, &'static str, &'static str)];
+
+const ERRORS: [(&str, ErrorKinds); 5] = [
+    ("ProgramError", ProgramError::KINDS),
+    ("InstructionError", InstructionError::KINDS),
+    ("CpuError", CpuError::KINDS),
+    ("RamError", RamError::KINDS),
+    ("MemoryBufferError", MemoryBufferError::KINDS),
+];
+
+fn format_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(| byte | { format!("0x{:02X}", byte) }).collect::<Vec<_>>().join(" ")
+}
+
+fn explain_instruction(input: &str) {
+    let result = InstructionSet::from_str(input);
+    let instruction = match result {
+        Ok(instruction) => instruction,
+        Err(err) => { println!("could not parse the instruction: {}", err); return; }
+    };
+    let option = InstructionSet::DOCUMENTATION.iter().find(| documentation | {
+        documentation.number == instruction.to_num()
+    });
+    let documentation = option.unwrap(); // every instruction is documented
+
+    let mut bytes = IntoBytes::into(&instruction.to_num())[..INSTRUCTION_SIZE as usize].to_vec();
+    instruction.to_binary(&mut bytes).unwrap(); // writing to a vec doesn't fail
+
+    println!("instruction: {}", instruction);
+    println!("encoding: {} ({} bytes)", format_bytes(&bytes), bytes.len());
+    println!("    instruction number: {} ({})", format_bytes(&bytes[..INSTRUCTION_SIZE as usize]), documentation.number);
+    for (name, operand) in documentation.operands.iter().zip(instruction.operands()) {
+        let mut operand_bytes = Vec::new();
+        operand.write_to_stream(&mut operand_bytes).unwrap(); // same as above
+        println!("    {}: {} ({}), {}", name, format_bytes(&operand_bytes), operand, operand.description());
+    }
+    println!("semantics: {}", documentation.description.trim());
+}
+
+fn explain_error(name: &str) {
+    for (error_name, kinds) in ERRORS {
+        let option = kinds.iter().find(| (kind, message, _) | {
+            !message.is_empty() && kind.eq_ignore_ascii_case(name)
+        });
+        if let Some((kind, message, causes)) = option {
+            println!("{}::{}: {}", error_name, kind, message);
+            if !causes.is_empty() {
+                println!("common causes: {}", causes.trim());
+            }
+            return;
+        }
+    }
+
+    println!("unknown error: {}, known errors are:", name);
+    for (error_name, kinds) in ERRORS {
+        for (kind, message, _) in kinds.iter().filter(| (_, message, _) | { !message.is_empty() }) {
+            println!("    {}{} ({}): {}", ERROR_PREFIX, kind, error_name, message);
+        }
+    }
+}
+
+/// explains the encoding and semantics of an instruction or the causes of an error when prefixed by E:
+pub fn explain(input: String) {
+    let input = input.trim();
+    match input.get(..ERROR_PREFIX.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(ERROR_PREFIX) => explain_error(input[ERROR_PREFIX.len()..].trim()),
+        _ => explain_instruction(input),
+    }
+}
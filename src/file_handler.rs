@@ -1,41 +1,123 @@
 use std::cell::RefCell;
-use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::fs::{File, OpenOptions};
-use std::io::{Seek, SeekFrom};
+use std::io::{Cursor, Read, Seek, SeekFrom};
 use std::ops::Deref;
 use std::rc::Rc;
+use memmap2::Mmap;
+use crate::cpu::CpuArchitecture;
 
+const DEFAULT_MAX_OPEN_FILES: usize = 32;
+
+/// the offset and length of the instructions of every function within a dependency file
+pub(crate) type FunctionTable = HashMap<String, (u32, CpuArchitecture)>;
+
+pub(crate) enum HandledFile {
+    File(File),
+    Mapped(Cursor<Mmap>),
+}
+
+impl Read for HandledFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            HandledFile::File(file) => file.read(buf),
+            HandledFile::Mapped(mapped) => mapped.read(buf),
+        }
+    }
+}
+
+impl Seek for HandledFile {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            HandledFile::File(file) => file.seek(pos),
+            HandledFile::Mapped(mapped) => mapped.seek(pos),
+        }
+    }
+}
+
+struct OpenedFile {
+    file: Rc<RefCell<HandledFile>>,
+    function_table: Option<Rc<FunctionTable>>,
+    last_use: u64,
+}
+
+/// keeps the most recently used files open, the least recently used file is closed when too many files are open
 pub(crate) struct ReadFileHandler {
-    files: HashMap<Rc<String>, Rc<RefCell<File>>>
+    files: HashMap<Rc<str>, OpenedFile>,
+    max_open_files: usize,
+    memory_mapped: bool,
+    uses: u64,
 }
 
 impl ReadFileHandler {
     pub(crate) fn new() -> Self {
-        Self {
-            files: HashMap::new(),
-        }
+        Self::with_capacity(DEFAULT_MAX_OPEN_FILES)
     }
 
     pub(crate) fn with_capacity(capacity: usize) -> Self {
         Self {
             files: HashMap::with_capacity(capacity),
+            max_open_files: capacity.max(1),
+            memory_mapped: false,
+            uses: 0,
+        }
+    }
+
+    /// memory maps the opened files instead of reading them from disk
+    pub(crate) fn set_memory_mapped(&mut self, memory_mapped: bool) {
+        self.memory_mapped = memory_mapped;
+    }
+
+    fn open_file(&self, path: &str) -> std::io::Result<HandledFile> {
+        let file = OpenOptions::new().read(true).open(path)?;
+        if self.memory_mapped {
+            // SAFETY: dependencies are only read and are not expected to be modified while running
+            let mapped = unsafe { Mmap::map(&file)? };
+            Ok(HandledFile::Mapped(Cursor::new(mapped)))
+        } else {
+            Ok(HandledFile::File(file))
+        }
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let option = self.files.iter()
+            .min_by_key(| (_, opened_file) | { opened_file.last_use })
+            .map(| (path, _) | { path.clone() });
+        if let Some(path) = option {
+            self.files.remove(&path);
+        }
+    }
+
+    pub(crate) fn open(&mut self, path: impl Into<String>) -> std::io::Result<Rc<RefCell<HandledFile>>> {
+        let path: Rc<str> = Rc::from(path.into());
+        self.uses += 1;
+
+        if let Some(opened_file) = self.files.get_mut(&path) {
+            opened_file.last_use = self.uses;
+            opened_file.file.borrow_mut().seek(SeekFrom::Start(0))?;
+            return Ok(opened_file.file.clone());
+        }
+
+        let file = Rc::new(RefCell::new(self.open_file(path.deref())?));
+        if self.files.len() >= self.max_open_files {
+            self.evict_least_recently_used();
         }
+        self.files.insert(path, OpenedFile {
+            file: file.clone(),
+            function_table: None,
+            last_use: self.uses,
+        });
+        Ok(file)
+    }
+
+    /// the function table of an opened file if it has been read before
+    pub(crate) fn function_table(&self, path: &str) -> Option<Rc<FunctionTable>> {
+        self.files.get(path).and_then(| opened_file | { opened_file.function_table.clone() })
     }
-    
-    pub(crate) fn open(&mut self, path: impl Into<String>) -> std::io::Result<Rc<RefCell<File>>> {
-        let path = Rc::new(path.into());
-        
-        Ok(match self.files.entry(path.clone()) {
-            Entry::Occupied(o) => {
-                let file = o.get().clone();
-                file.borrow_mut().seek(SeekFrom::Start(0))?;
-                file
-            },
-            Entry::Vacant(v) => {
-                let file = OpenOptions::new().read(true).open(path.deref())?;
-                v.insert(Rc::new(RefCell::new(file))).clone()
-            }
-        })
-    }
-}
\ No newline at end of file
+
+    pub(crate) fn set_function_table(&mut self, path: &str, function_table: Rc<FunctionTable>) {
+        if let Some(opened_file) = self.files.get_mut(path) {
+            opened_file.function_table = Some(function_table);
+        }
+    }
+}
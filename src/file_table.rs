@@ -0,0 +1,140 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use crate::cpu::CpuArchitecture;
+use crate::fs_image::FsImage;
+
+enum FileHandle {
+    Image {
+        entry: usize,
+        position: u32,
+    },
+    Host(File),
+}
+
+/// the files opened by the guest program, handles are indices into the table,
+/// the files are in the filesystem image when one is given and on the host otherwise
+pub struct FileTable {
+    image: Option<FsImage>,
+    handles: Vec<Option<FileHandle>>,
+}
+
+impl FileTable {
+    pub fn new() -> Self {
+        Self {
+            image: None,
+            handles: Vec::new(),
+        }
+    }
+
+    pub fn set_image(&mut self, image: FsImage) {
+        self.image = Some(image);
+    }
+
+    /// whether the files are opened on the host instead of in a filesystem image
+    pub fn uses_host(&self) -> bool {
+        self.image.is_none()
+    }
+
+    fn image(&mut self) -> Result<&mut FsImage> {
+        match &mut self.image {
+            Some(image) => Ok(image),
+            None => Err(Error::new(ErrorKind::Unsupported, "no filesystem image has been given, use --fs to add one")),
+        }
+    }
+
+    fn handle(&mut self, handle: CpuArchitecture) -> Result<&mut FileHandle> {
+        match self.handles.get_mut(handle as usize) {
+            Some(Some(file)) => Ok(file),
+            _ => Err(Error::new(ErrorKind::NotFound, format!("file handle {} is not open", handle))),
+        }
+    }
+
+    fn insert(&mut self, file: FileHandle) -> CpuArchitecture {
+        match self.handles.iter().position(| handle | { handle.is_none() }) {
+            Some(index) => {
+                self.handles[index] = Some(file);
+                index as CpuArchitecture
+            },
+            None => {
+                self.handles.push(Some(file));
+                (self.handles.len() - 1) as CpuArchitecture
+            }
+        }
+    }
+
+    /// opens the file with the name and returns its handle, if create is true the file is created or truncated
+    pub fn open(&mut self, name: &str, create: bool) -> Result<CpuArchitecture> {
+        if self.uses_host() {
+            let file = if create {
+                OpenOptions::new().read(true).write(true).create(true).truncate(true).open(name)?
+            } else {
+                // read only files can still be opened for reading
+                OpenOptions::new().read(true).write(true).open(name).or_else(| _ | { File::open(name) })?
+            };
+            return Ok(self.insert(FileHandle::Host(file)));
+        }
+        
+        let image = self.image()?;
+        let entry = if create {
+            image.create_file(name)?
+        } else {
+            match image.find(name) {
+                Some(entry) => entry,
+                None => return Err(Error::new(ErrorKind::NotFound, format!("file not found: {}", name))),
+            }
+        };
+        image.flush()?;
+
+        Ok(self.insert(FileHandle::Image { entry, position: 0 }))
+    }
+
+    pub fn read(&mut self, handle: CpuArchitecture, buffer: &mut [u8]) -> Result<usize> {
+        let (entry, position) = match self.handle(handle)? {
+            FileHandle::Image { entry, position } => (*entry, *position),
+            FileHandle::Host(file) => return file.read(buffer),
+        };
+        let read = self.image()?.read(entry, position, buffer)?;
+        if let FileHandle::Image { position, .. } = self.handle(handle)? {
+            *position += read as u32;
+        }
+        Ok(read)
+    }
+
+    pub fn write(&mut self, handle: CpuArchitecture, buffer: &[u8]) -> Result<usize> {
+        let (entry, position) = match self.handle(handle)? {
+            FileHandle::Image { entry, position } => (*entry, *position),
+            FileHandle::Host(file) => return file.write(buffer),
+        };
+        let image = self.image()?;
+        let written = image.write(entry, position, buffer)?;
+        image.flush()?;
+        if let FileHandle::Image { position, .. } = self.handle(handle)? {
+            *position += written as u32;
+        }
+        Ok(written)
+    }
+
+    /// sets the position of the file and returns the new position, which cannot be past the end of the file
+    pub fn seek(&mut self, handle: CpuArchitecture, new_position: u32) -> Result<u32> {
+        let entry = match self.handle(handle)? {
+            FileHandle::Image { entry, .. } => *entry,
+            FileHandle::Host(file) => {
+                let size = file.metadata()?.len();
+                let position = file.seek(SeekFrom::Start((new_position as u64).min(size)))?;
+                return Ok(position as u32);
+            },
+        };
+        let size = self.image()?.file_size(entry);
+        if let FileHandle::Image { position, .. } = self.handle(handle)? {
+            *position = new_position.min(size);
+        }
+        Ok(new_position.min(size))
+    }
+
+    /// closes the file, host files are closed when their handle is dropped
+    pub fn close(&mut self, handle: CpuArchitecture) -> Result<()> {
+        self.handle(handle)?;
+        self.handles[handle as usize] = None;
+        Ok(())
+    }
+}
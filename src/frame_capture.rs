@@ -0,0 +1,87 @@
+use std::fs::{create_dir_all, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use crate::memory::AllocatedRam;
+
+const BMP_HEADER_SIZE: u32 = 14;
+const DIB_HEADER_SIZE: u32 = 40;
+/// 72 dpi in pixels per meter
+const PIXELS_PER_METER: i32 = 2835;
+
+/// saves every nth presented frame of the window as a bmp image within a directory
+pub struct FrameCapture {
+    directory: PathBuf,
+    every: u32,
+    frame: u32,
+    saved: u32,
+}
+
+impl FrameCapture {
+    /// creates the directory if it doesn't exist
+    pub fn new(directory: impl Into<PathBuf>, every: u32) -> std::io::Result<Self> {
+        let directory = directory.into();
+        create_dir_all(&directory)?;
+
+        Ok(Self {
+            directory,
+            every: every.max(1),
+            frame: 0,
+            saved: 0,
+        })
+    }
+
+    /// saves the canvas if it is the nth frame, the canvas contains size.0 * size.1 rgba pixels
+    pub fn on_frame(&mut self, canvas: &AllocatedRam, size: (usize, usize)) -> std::io::Result<()> {
+        let frame = self.frame;
+        self.frame += 1;
+        if !frame.is_multiple_of(self.every) {
+            return Ok(());
+        }
+
+        let path = self.directory.join(format!("frame_{:05}.bmp", self.saved));
+        let file = OpenOptions::new().write(true).create(true).truncate(true).open(path)?;
+        let mut writer = BufWriter::new(file);
+        canvas.borrow_buffer(| buffer | { write_bmp(&mut writer, buffer, size) })?;
+        writer.flush()?;
+
+        self.saved += 1;
+        Ok(())
+    }
+}
+
+/// writes the rgba pixels as an uncompressed 24 bit bmp, the alpha channel is ignored like it is when drawing
+fn write_bmp(stream: &mut impl Write, pixels: &[u8], size: (usize, usize)) -> std::io::Result<()> {
+    let (width, height) = size;
+    let row_size = (width * 3).next_multiple_of(4);
+    let image_size = (row_size * height) as u32;
+    let offset = BMP_HEADER_SIZE + DIB_HEADER_SIZE;
+
+    stream.write_all(b"BM")?;
+    stream.write_all(&(offset + image_size).to_le_bytes())?;
+    stream.write_all(&0u32.to_le_bytes())?;
+    stream.write_all(&offset.to_le_bytes())?;
+
+    stream.write_all(&DIB_HEADER_SIZE.to_le_bytes())?;
+    stream.write_all(&(width as i32).to_le_bytes())?;
+    stream.write_all(&(height as i32).to_le_bytes())?; // a positive height stores the rows bottom up
+    stream.write_all(&1u16.to_le_bytes())?; // planes
+    stream.write_all(&24u16.to_le_bytes())?; // bits per pixel
+    stream.write_all(&0u32.to_le_bytes())?; // no compression
+    stream.write_all(&image_size.to_le_bytes())?;
+    stream.write_all(&PIXELS_PER_METER.to_le_bytes())?;
+    stream.write_all(&PIXELS_PER_METER.to_le_bytes())?;
+    stream.write_all(&0u32.to_le_bytes())?; // colors in the palette
+    stream.write_all(&0u32.to_le_bytes())?; // important colors
+
+    let mut row = vec![0u8; row_size];
+    for y in (0..height).rev() {
+        for x in 0..width {
+            let index = (y * width + x) * 4;
+            let pixel = pixels.get(index..index + 4).unwrap_or(&[0; 4]);
+            row[x * 3..x * 3 + 3].copy_from_slice(&[pixel[2], pixel[1], pixel[0]]);
+        }
+        stream.write_all(&row)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,449 @@
+use std::collections::BTreeSet;
+use std::fs::OpenOptions;
+use std::io::{Error, ErrorKind, Result, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+// layout of the image, all numbers are stored as little endian:
+//     header:      magic, block size (u32), block count (u32), max files (u32)
+//     file table:  max files entries of name length (u8), name, first block (u32), size (u32)
+//     fat:         block count entries (u32) with the next block of the file
+//     data:        block count blocks of block size bytes, block 0 is never used
+const MAGIC: &[u8; 4] = b"EMFS";
+const HEADER_SIZE: usize = MAGIC.len() + size_of::<u32>() * 3;
+const ENTRY_SIZE: usize = 64;
+const MAX_NAME_LENGTH: usize = ENTRY_SIZE - size_of::<u8>() - size_of::<u32>() * 2;
+const FREE_BLOCK: u32 = 0;
+const END_OF_CHAIN: u32 = u32::MAX;
+
+const DEFAULT_BLOCK_SIZE: u32 = 512;
+const DEFAULT_BLOCK_COUNT: u32 = 2048;
+const DEFAULT_MAX_FILES: u32 = 64;
+
+/// a sandboxed filesystem stored within a single host file using a simple FAT-like layout
+pub struct FsImage {
+    path: PathBuf,
+    data: Vec<u8>,
+    block_size: u32,
+    block_count: u32,
+    max_files: u32,
+    /// the chunks of block size bytes of the image that changed since the last flush
+    dirty: BTreeSet<usize>,
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("invalid filesystem image: {}", message))
+}
+
+impl FsImage {
+    /// opens the image at the path or creates a new empty image if it doesn't exist
+    pub fn open_or_create(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if path.exists() {
+            let data = std::fs::read(&path)?;
+            Self::from_data(path, data)
+        } else {
+            let mut image = Self::create(path, DEFAULT_BLOCK_SIZE, DEFAULT_BLOCK_COUNT, DEFAULT_MAX_FILES);
+            image.flush()?;
+            Ok(image)
+        }
+    }
+
+    fn create(path: PathBuf, block_size: u32, block_count: u32, max_files: u32) -> Self {
+        let mut image = Self {
+            path,
+            data: Vec::new(),
+            block_size,
+            block_count,
+            max_files,
+            dirty: BTreeSet::new(),
+        };
+        image.data = vec![0u8; image.data_offset() + block_size as usize * block_count as usize];
+        image.mark_dirty(0, image.data.len());
+        image.data[..MAGIC.len()].copy_from_slice(MAGIC);
+        image.write_u32(MAGIC.len(), block_size);
+        image.write_u32(MAGIC.len() + 4, block_count);
+        image.write_u32(MAGIC.len() + 8, max_files);
+        image
+    }
+
+    fn from_data(path: PathBuf, data: Vec<u8>) -> Result<Self> {
+        if data.len() < HEADER_SIZE || &data[..MAGIC.len()] != MAGIC {
+            return Err(invalid_data("missing header"));
+        }
+
+        let mut image = Self {
+            path,
+            data,
+            block_size: 0,
+            block_count: 0,
+            max_files: 0,
+            dirty: BTreeSet::new(),
+        };
+        image.block_size = image.read_u32(MAGIC.len());
+        image.block_count = image.read_u32(MAGIC.len() + 4);
+        image.max_files = image.read_u32(MAGIC.len() + 8);
+
+        // the header comes from the file so the sizes are checked before anything is indexed using them
+        let size = (image.max_files as usize).checked_mul(ENTRY_SIZE)
+            .and_then(| table | { (image.block_count as usize).checked_mul(size_of::<u32>())?.checked_add(table) })
+            .and_then(| tables | { (image.block_size as usize).checked_mul(image.block_count as usize)?.checked_add(tables) })
+            .and_then(| size | { size.checked_add(HEADER_SIZE) });
+        if image.block_size == 0 || size != Some(image.data.len()) {
+            return Err(invalid_data("the size of the image doesn't match its header"));
+        }
+        image.validate()?;
+        Ok(image)
+    }
+
+    /// checks that the fat and the file table only point at blocks within the image and that no chain is cyclic
+    /// or shared by files, so the chains can be followed without checking every block
+    fn validate(&self) -> Result<()> {
+        let is_block = | block: u32 | { block != FREE_BLOCK && block < self.block_count };
+        if self.block_count > 0 && self.next_block(0) != FREE_BLOCK {
+            return Err(invalid_data("block 0 is used"));
+        }
+        for block in 1..self.block_count {
+            let next = self.next_block(block);
+            if next != FREE_BLOCK && next != END_OF_CHAIN && !is_block(next) {
+                return Err(invalid_data(&format!("block {} points at block {} which is outside of the image", block, next)));
+            }
+        }
+
+        let mut used = vec![false; self.block_count as usize];
+        for entry in 0..self.max_files as usize {
+            let offset = self.entry_offset(entry);
+            if self.data[offset] == 0 {
+                continue;
+            }
+            if self.data[offset] as usize > MAX_NAME_LENGTH {
+                return Err(invalid_data(&format!("the name of file {} is too long", entry)));
+            }
+
+            let mut block = self.first_block(entry);
+            while block != FREE_BLOCK && block != END_OF_CHAIN {
+                if !is_block(block) || used[block as usize] {
+                    return Err(invalid_data(&format!("the chain of file {} is cyclic, shared or points outside of the image", entry)));
+                }
+                used[block as usize] = true;
+                block = self.next_block(block);
+            }
+        }
+        Ok(())
+    }
+
+    /// writes the chunks of the image that changed since the last flush back to the host file
+    pub fn flush(&mut self) -> Result<()> {
+        if self.dirty.is_empty() {
+            return Ok(());
+        }
+
+        let mut file = OpenOptions::new().write(true).create(true).truncate(false).open(&self.path)?;
+        let chunk_size = self.block_size as usize;
+        let mut chunks = self.dirty.iter().copied().peekable();
+        while let Some(first) = chunks.next() {
+            let mut last = first;
+            while chunks.next_if_eq(&(last + 1)).is_some() {
+                last += 1;
+            }
+
+            let start = first * chunk_size;
+            let end = ((last + 1) * chunk_size).min(self.data.len());
+            file.seek(SeekFrom::Start(start as u64))?;
+            file.write_all(&self.data[start..end])?;
+        }
+        self.dirty.clear();
+        Ok(())
+    }
+
+    fn mark_dirty(&mut self, offset: usize, length: usize) {
+        let chunk_size = self.block_size as usize;
+        self.dirty.extend(offset / chunk_size..(offset + length).div_ceil(chunk_size));
+    }
+
+    fn write_bytes(&mut self, offset: usize, bytes: &[u8]) {
+        self.data[offset..offset + bytes.len()].copy_from_slice(bytes);
+        self.mark_dirty(offset, bytes.len());
+    }
+
+    fn read_u32(&self, offset: usize) -> u32 {
+        u32::from_le_bytes(self.data[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn write_u32(&mut self, offset: usize, value: u32) {
+        self.write_bytes(offset, &value.to_le_bytes());
+    }
+
+    fn entry_offset(&self, entry: usize) -> usize {
+        HEADER_SIZE + entry * ENTRY_SIZE
+    }
+
+    fn fat_offset(&self) -> usize {
+        HEADER_SIZE + self.max_files as usize * ENTRY_SIZE
+    }
+
+    fn data_offset(&self) -> usize {
+        self.fat_offset() + self.block_count as usize * size_of::<u32>()
+    }
+
+    fn block_offset(&self, block: u32) -> usize {
+        self.data_offset() + block as usize * self.block_size as usize
+    }
+
+    fn next_block(&self, block: u32) -> u32 {
+        self.read_u32(self.fat_offset() + block as usize * size_of::<u32>())
+    }
+
+    fn set_next_block(&mut self, block: u32, next: u32) {
+        let offset = self.fat_offset() + block as usize * size_of::<u32>();
+        self.write_u32(offset, next);
+    }
+
+    fn entry_name(&self, entry: usize) -> &[u8] {
+        let offset = self.entry_offset(entry);
+        let length = self.data[offset] as usize;
+        &self.data[offset + 1..offset + 1 + length]
+    }
+
+    fn first_block(&self, entry: usize) -> u32 {
+        self.read_u32(self.entry_offset(entry) + 1 + MAX_NAME_LENGTH)
+    }
+
+    fn set_first_block(&mut self, entry: usize, block: u32) {
+        let offset = self.entry_offset(entry) + 1 + MAX_NAME_LENGTH;
+        self.write_u32(offset, block);
+    }
+
+    pub fn file_size(&self, entry: usize) -> u32 {
+        self.read_u32(self.entry_offset(entry) + 1 + MAX_NAME_LENGTH + 4)
+    }
+
+    fn set_file_size(&mut self, entry: usize, size: u32) {
+        let offset = self.entry_offset(entry) + 1 + MAX_NAME_LENGTH + 4;
+        self.write_u32(offset, size);
+    }
+
+    /// returns the entry of the file with the name
+    pub fn find(&self, name: &str) -> Option<usize> {
+        (0..self.max_files as usize).find(| entry | {
+            self.data[self.entry_offset(*entry)] != 0 && self.entry_name(*entry) == name.as_bytes()
+        })
+    }
+
+    /// creates an empty file, an existing file with the same name is truncated
+    pub fn create_file(&mut self, name: &str) -> Result<usize> {
+        if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("file names have to be between 1 and {} bytes long", MAX_NAME_LENGTH)));
+        }
+
+        let entry = match self.find(name) {
+            Some(entry) => entry,
+            None => {
+                let option = (0..self.max_files as usize).find(| entry | { self.data[self.entry_offset(*entry)] == 0 });
+                let entry = match option {
+                    Some(entry) => entry,
+                    None => return Err(Error::new(ErrorKind::StorageFull, "the file table of the filesystem image is full")),
+                };
+
+                let offset = self.entry_offset(entry);
+                self.write_bytes(offset, &[name.len() as u8]);
+                self.write_bytes(offset + 1, name.as_bytes());
+                entry
+            }
+        };
+
+        let mut block = self.first_block(entry);
+        let mut length = 0;
+        while block != FREE_BLOCK && block != END_OF_CHAIN {
+            // a chain can't be longer than the image
+            length += 1;
+            if length > self.block_count {
+                return Err(invalid_data("the chain of a file is cyclic"));
+            }
+            let next = self.next_block(block);
+            self.set_next_block(block, FREE_BLOCK);
+            block = next;
+        }
+        self.set_first_block(entry, FREE_BLOCK);
+        self.set_file_size(entry, 0);
+
+        Ok(entry)
+    }
+
+    fn allocate_block(&mut self) -> Result<u32> {
+        let option = (1..self.block_count).find(| block | { self.next_block(*block) == FREE_BLOCK });
+        let block = match option {
+            Some(block) => block,
+            None => return Err(Error::new(ErrorKind::StorageFull, "the filesystem image is full")),
+        };
+
+        self.set_next_block(block, END_OF_CHAIN);
+        let offset = self.block_offset(block);
+        self.data[offset..offset + self.block_size as usize].fill(0);
+        self.mark_dirty(offset, self.block_size as usize);
+        Ok(block)
+    }
+
+    /// returns the block at block_index within the file, allocating the blocks up to it if allocate is true
+    fn file_block(&mut self, entry: usize, block_index: u32, allocate: bool) -> Result<Option<u32>> {
+        // a file can't have more blocks than the image
+        if block_index >= self.block_count {
+            if allocate {
+                return Err(Error::new(ErrorKind::StorageFull, "the filesystem image is full"));
+            }
+            return Ok(None);
+        }
+
+        let mut block = self.first_block(entry);
+        if block == FREE_BLOCK {
+            if !allocate {
+                return Ok(None);
+            }
+            block = self.allocate_block()?;
+            self.set_first_block(entry, block);
+        }
+
+        for _ in 0..block_index {
+            let mut next = self.next_block(block);
+            if next == END_OF_CHAIN {
+                if !allocate {
+                    return Ok(None);
+                }
+                next = self.allocate_block()?;
+                self.set_next_block(block, next);
+            }
+            block = next;
+        }
+
+        Ok(Some(block))
+    }
+
+    /// reads from the file at the offset, returns the amount of bytes read
+    pub fn read(&mut self, entry: usize, offset: u32, buffer: &mut [u8]) -> Result<usize> {
+        let size = self.file_size(entry);
+        if offset >= size {
+            return Ok(0);
+        }
+
+        let length = buffer.len().min((size - offset) as usize);
+        let mut read = 0;
+        while read < length {
+            let position = offset + read as u32;
+            let block = match self.file_block(entry, position / self.block_size, false)? {
+                Some(block) => block,
+                None => return Err(invalid_data("file is smaller than its size")),
+            };
+
+            let block_position = (position % self.block_size) as usize;
+            let amount = (self.block_size as usize - block_position).min(length - read);
+            let start = self.block_offset(block) + block_position;
+            buffer[read..read + amount].copy_from_slice(&self.data[start..start + amount]);
+            read += amount;
+        }
+
+        Ok(length)
+    }
+
+    /// writes to the file at the offset growing the file if needed, when the image fills up the size of the file
+    /// includes the bytes written before the error
+    pub fn write(&mut self, entry: usize, offset: u32, buffer: &[u8]) -> Result<usize> {
+        if buffer.len() > u32::MAX as usize || offset.checked_add(buffer.len() as u32).is_none() {
+            return Err(Error::new(ErrorKind::FileTooLarge, "the file would be larger than 4 GiB"));
+        }
+
+        let mut written = 0;
+        let mut result = Ok(());
+        while written < buffer.len() {
+            let position = offset + written as u32;
+            let block = match self.file_block(entry, position / self.block_size, true) {
+                Ok(block) => block.unwrap(), // blocks are allocated
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                },
+            };
+
+            let block_position = (position % self.block_size) as usize;
+            let amount = (self.block_size as usize - block_position).min(buffer.len() - written);
+            let start = self.block_offset(block) + block_position;
+            self.write_bytes(start, &buffer[written..written + amount]);
+            written += amount;
+        }
+
+        let end = offset + written as u32;
+        if end > self.file_size(entry) {
+            self.set_file_size(entry, end);
+        }
+        result.map(| _ | { written })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(block_count: u32) -> FsImage {
+        FsImage::create(PathBuf::from("test.img"), 16, block_count, 2)
+    }
+
+    #[test]
+    fn overflowing_header_is_rejected() {
+        let mut data = image(4).data;
+        data[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        data[MAGIC.len() + 4..MAGIC.len() + 8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(FsImage::from_data(PathBuf::from("test.img"), data).is_err());
+    }
+
+    #[test]
+    fn invalid_fat_is_rejected() {
+        let mut image = image(4);
+        image.set_next_block(1, 9);
+        assert!(FsImage::from_data(PathBuf::from("test.img"), image.data).is_err());
+    }
+
+    #[test]
+    fn cyclic_chain_is_rejected() {
+        let mut image = image(4);
+        let entry = image.create_file("a").unwrap();
+        image.write(entry, 0, &[1; 32]).unwrap();
+        let first = image.first_block(entry);
+        let last = image.next_block(first);
+        image.set_next_block(last, first);
+        assert!(FsImage::from_data(PathBuf::from("test.img"), image.data).is_err());
+    }
+
+    #[test]
+    fn full_image_keeps_the_written_size() {
+        let mut image = image(4);
+        let entry = image.create_file("a").unwrap();
+        let error = image.write(entry, 0, &[1; 64]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::StorageFull);
+        // block 0 is never used so 3 blocks were written
+        assert_eq!(image.file_size(entry), 48);
+    }
+
+    #[test]
+    fn flushed_chunks_match_the_image() {
+        let path = std::env::temp_dir().join(format!("emulator-fs-image-{}.img", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut image = FsImage::open_or_create(&path).unwrap();
+        let entry = image.create_file("a").unwrap();
+        image.write(entry, 700, &[7; 600]).unwrap();
+        image.flush().unwrap();
+
+        let reopened = FsImage::open_or_create(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(reopened.data == image.data);
+    }
+
+    #[test]
+    fn write_only_marks_the_touched_chunks() {
+        let mut image = image(4);
+        image.dirty.clear();
+        let entry = image.create_file("a").unwrap();
+        image.dirty.clear();
+        image.write(entry, 0, &[1; 4]).unwrap();
+        let block = image.block_offset(image.first_block(entry)) / 16;
+        assert!(image.dirty.contains(&block));
+        assert!(!image.dirty.contains(&(block + 1)));
+    }
+}
@@ -0,0 +1,97 @@
+use std::fs::OpenOptions;
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+use crate::computer::Computer;
+use crate::cpu::CpuArchitecture;
+use crate::memory::Ram;
+use crate::program::Program;
+
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// watches the source of the running program and patches changed functions into program memory,
+/// the patch is only applied when every function keeps the same address so ram and registers stay valid
+pub struct HotReload {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+    functions: Vec<(String, CpuArchitecture)>,
+}
+
+impl HotReload {
+    pub fn new(path: impl Into<PathBuf>, program: &Program) -> Self {
+        let path = path.into();
+        let modified = Self::modified_time(&path);
+        Self {
+            path,
+            modified,
+            last_check: Instant::now(),
+            functions: program.sorted_functions(),
+        }
+    }
+
+    fn modified_time(path: &PathBuf) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(| metadata | { metadata.modified() }).ok()
+    }
+
+    /// reloads the program if the source has changed since the last check
+    pub fn check(&mut self, computer: &mut Computer) {
+        if self.last_check.elapsed() < CHECK_INTERVAL {
+            return;
+        }
+        self.last_check = Instant::now();
+
+        let modified = Self::modified_time(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+
+        match self.reload(computer) {
+            Ok(patched) if patched.is_empty() => println!("hot reload: no functions have changed"),
+            Ok(patched) => println!("hot reload: patched functions [{}]", patched.join(", ")),
+            Err(err) => println!("hot reload skipped: {}", err),
+        }
+    }
+
+    fn reload(&mut self, computer: &mut Computer) -> Result<Vec<String>, String> {
+        let file = OpenOptions::new().read(true).open(&self.path).map_err(| err | err.to_string())?;
+        let program = Program::from_stream(&mut BufReader::with_capacity(4096, file)).map_err(| err | err.to_string())?;
+
+        let functions = program.sorted_functions();
+        if functions != self.functions {
+            return Err("the functions or their addresses have changed, restart the program to apply the changes".to_string());
+        }
+
+        let mut scratch_ram = Ram::with_maximum(1, CpuArchitecture::MAX).map_err(| err | err.to_string())?;
+        let allocated = program.allocate(&mut scratch_ram).map_err(| err | err.to_string())?;
+        let new_binary = allocated.borrow_buffer(| buffer | { buffer.to_vec() });
+
+        let program_memory = computer.cpu().program_memory();
+        let old_binary = program_memory.borrow_buffer(| buffer | { buffer.to_vec() });
+        if old_binary.len() != new_binary.len() {
+            return Err("the size of the program has changed, restart the program to apply the changes".to_string());
+        }
+
+        // the code before the first function and the dependencies after the last function are patched as well
+        let mut regions = Vec::with_capacity(functions.len() + 1);
+        let mut previous = ("<entry>".to_string(), 0);
+        for (name, address) in functions {
+            regions.push((previous.0, previous.1 as usize..address as usize));
+            previous = (name, address);
+        }
+        regions.push((previous.0, previous.1 as usize..new_binary.len()));
+
+        let mut patched = Vec::new();
+        for (name, range) in regions {
+            if old_binary[range.clone()] != new_binary[range.clone()] {
+                program_memory.write_buffer_at(range.start as CpuArchitecture, &new_binary[range])
+                    .map_err(| err | err.to_string())?;
+                patched.push(name);
+            }
+        }
+        computer.cpu_mut().rebuild_decode_cache();
+
+        Ok(patched)
+    }
+}
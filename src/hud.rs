@@ -0,0 +1,157 @@
+use std::time::{Duration, Instant};
+use glium::glutin::surface::WindowSurface;
+use glium::{Display, Frame, Program, Surface, VertexBuffer};
+use glium::uniforms::EmptyUniforms;
+use crate::computer::Computer;
+use crate::display_handler::Vertex;
+use crate::window::index_buffer_from_size;
+
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+/// the size of a glyph pixel in screen pixels
+const PIXEL_SCALE: f32 = 3f32;
+const TEXT_COLOR: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+const BACKGROUND_COLOR: [u8; 4] = [0x00, 0x00, 0x00, 0xFF];
+
+/// the rows of a 3x5 glyph, the highest of the 3 bits is the left pixel, lowercase letters use the uppercase glyph
+fn glyph(character: char) -> [u8; GLYPH_HEIGHT] {
+    match character.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' | 'S' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b111, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b111, 0b100, 0b100, 0b100, 0b111],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b111, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b111, 0b100, 0b100],
+        'G' => [0b111, 0b100, 0b101, 0b101, 0b111],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b111],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b111, 0b101, 0b111, 0b100, 0b100],
+        'Q' => [0b111, 0b101, 0b101, 0b111, 0b001],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '[' => [0b110, 0b100, 0b100, 0b100, 0b110],
+        ']' => [0b011, 0b001, 0b001, 0b001, 0b011],
+        '(' => [0b010, 0b100, 0b100, 0b100, 0b010],
+        ')' => [0b010, 0b001, 0b001, 0b001, 0b010],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        _ => [0; GLYPH_HEIGHT],
+    }
+}
+
+/// draws the lines of text on a background in the top left corner of the frame, starting at the text line first_line
+pub fn draw_text(frame: &mut Frame, display: &Display<WindowSurface>, program: &Program, lines: &[String], first_line: usize) {
+    let dimensions = display.get_framebuffer_dimensions();
+    let pixel_width = 2f32 * PIXEL_SCALE / dimensions.0 as f32;
+    let pixel_height = 2f32 * PIXEL_SCALE / dimensions.1 as f32;
+
+    let mut vertices = Vec::new();
+    let mut add_square = | x: f32, y: f32, width: f32, height: f32, color: [u8; 4] | {
+        vertices.push(Vertex::new([x, y], color));
+        vertices.push(Vertex::new([x + width, y], color));
+        vertices.push(Vertex::new([x, y - height], color));
+        vertices.push(Vertex::new([x + width, y - height], color));
+    };
+
+    // 1 pixel of padding around the text and 1 pixel between the glyphs and the lines
+    let top = 1f32 - (first_line * (GLYPH_HEIGHT + 1)) as f32 * pixel_height;
+    let longest = lines.iter().map(| line | { line.chars().count() }).max().unwrap_or(0);
+    let columns = longest * (GLYPH_WIDTH + 1) + 1;
+    let rows = lines.len() * (GLYPH_HEIGHT + 1) + 1;
+    add_square(-1f32, top, columns as f32 * pixel_width, rows as f32 * pixel_height, BACKGROUND_COLOR);
+    for (line_index, line) in lines.iter().enumerate() {
+        let line_y = top - (line_index * (GLYPH_HEIGHT + 1)) as f32 * pixel_height;
+        for (index, character) in line.chars().enumerate() {
+            let glyph_x = -1f32 + (index * (GLYPH_WIDTH + 1) + 1) as f32 * pixel_width;
+            for (row, bits) in glyph(character).iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                        let x = glyph_x + column as f32 * pixel_width;
+                        let y = line_y - (row + 1) as f32 * pixel_height;
+                        add_square(x, y, pixel_width, pixel_height, TEXT_COLOR);
+                    }
+                }
+            }
+        }
+    }
+
+    let vertex_buffer = VertexBuffer::new(display, &vertices).unwrap();
+    let index_buffer = index_buffer_from_size(display, (vertices.len() / 4, 1));
+    frame.draw(&vertex_buffer, &index_buffer, program, &EmptyUniforms, &Default::default()).unwrap();
+}
+
+/// the performance statistics drawn on top of the canvas
+pub struct Hud {
+    second_start: Instant,
+    frames: u32,
+    fps: u32,
+    instructions: u64,
+    instructions_per_frame: u64,
+}
+
+impl Hud {
+    pub fn new() -> Self {
+        Self {
+            second_start: Instant::now(),
+            frames: 0,
+            fps: 0,
+            instructions: 0,
+            instructions_per_frame: 0,
+        }
+    }
+
+    pub fn on_instruction(&mut self) {
+        self.instructions += 1;
+    }
+
+    /// updates the statistics when a frame is presented
+    pub fn on_frame(&mut self) {
+        self.frames += 1;
+        self.instructions_per_frame = self.instructions;
+        self.instructions = 0;
+
+        if self.second_start.elapsed() >= Duration::from_secs(1) {
+            self.fps = self.frames;
+            self.frames = 0;
+            self.second_start = Instant::now();
+        }
+    }
+
+    fn text(&self, computer: &Computer) -> String {
+        let ram = computer.ram();
+        format!("FPS {} IPF {} HEAP {}/{}", self.fps, self.instructions_per_frame, ram.size() - ram.size_left(), ram.size())
+    }
+
+    /// draws the statistics in the top left corner of the frame
+    pub fn draw(&self, frame: &mut Frame, display: &Display<WindowSurface>, program: &Program, computer: &Computer) {
+        draw_text(frame, display, program, &[self.text(computer)], 0);
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::VecDeque;
+use std::fs::read_to_string;
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use crate::cpu::CpuArchitecture;
+
+/// a window event as it is passed to the program
+#[derive(Clone, Copy, Debug)]
+pub enum InputEvent {
+    Close,
+    /// the position of the cursor on the canvas
+    Cursor { x: CpuArchitecture, y: CpuArchitecture },
+    Mouse { pressed: bool, button: CpuArchitecture },
+    Key { key: char, down: bool },
+    /// the timeout of hlt has passed
+    Timer,
+    Other,
+}
+
+/// the event number returned by GetWindowEvent when no events are queued
+pub const NO_EVENT: CpuArchitecture = 5;
+
+impl InputEvent {
+    /// the event number and the data of the event as they are returned by GetWindowEvent in x2, x3 and x4
+    pub fn to_registers(self) -> (CpuArchitecture, Option<[CpuArchitecture;2]>) {
+        match self {
+            InputEvent::Close => (0, None),
+            InputEvent::Cursor { x, y } => (1, Some([x, y])),
+            InputEvent::Mouse { pressed, button } => (2, Some([pressed as CpuArchitecture, button])),
+            InputEvent::Key { key, down } => (3, Some([key as CpuArchitecture, down as CpuArchitecture])),
+            InputEvent::Timer => (4, None),
+            InputEvent::Other => (CpuArchitecture::MAX, None),
+        }
+    }
+}
+
+/// window events that are played back at a time after the window has been created, read from a file where every line is
+/// ```text
+/// t=500 key a down
+/// t=600 key enter up
+/// t=750 mouse 10,20 left press
+/// t=800 move 12,20
+/// t=900 close
+/// ```
+/// the time is in milliseconds and the positions are canvas pixels, empty lines and lines starting with # are ignored
+pub struct InputScript {
+    events: VecDeque<(Duration, InputEvent)>,
+    start: Instant,
+}
+
+fn create_script_error(line_number: usize, message: impl AsRef<str>) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("line {}: {}", line_number, message.as_ref()))
+}
+
+fn parse_position(str: &str) -> Option<(CpuArchitecture, CpuArchitecture)> {
+    let (x, y) = str.split_once(',')?;
+    Some((CpuArchitecture::from_str(x.trim()).ok()?, CpuArchitecture::from_str(y.trim()).ok()?))
+}
+
+fn parse_button(str: &str) -> Option<CpuArchitecture> {
+    // the same numbers as the window events
+    match str {
+        "left" => Some(0),
+        "right" => Some(1),
+        "middle" => Some(2),
+        "forward" => Some(3),
+        "back" => Some(4),
+        _ => None,
+    }
+}
+
+fn parse_key(str: &str) -> Option<char> {
+    match str {
+        "enter" => Some('\n'),
+        "space" => Some(' '),
+        _ => {
+            let mut chars = str.chars();
+            let key = chars.next()?;
+            chars.next().is_none().then_some(key)
+        },
+    }
+}
+
+/// parses a line into its time and the events that are sent at that time
+fn parse_line(line: &str) -> Option<(Duration, Vec<InputEvent>)> {
+    let mut words = line.split_whitespace();
+    let time = u64::from_str(words.next()?.strip_prefix("t=")?).ok()?;
+    let events = match (words.next()?, words.next(), words.next()) {
+        ("key", Some(key), Some(state)) => {
+            let down = match state { "down" => true, "up" => false, _ => return None };
+            vec![InputEvent::Key { key: parse_key(key)?, down }]
+        },
+        ("mouse", Some(position), Some(button)) => {
+            let (x, y) = parse_position(position)?;
+            let pressed = match words.next()? { "press" => true, "release" => false, _ => return None };
+            vec![InputEvent::Cursor { x, y }, InputEvent::Mouse { pressed, button: parse_button(button)? }]
+        },
+        ("move", Some(position), None) => {
+            let (x, y) = parse_position(position)?;
+            vec![InputEvent::Cursor { x, y }]
+        },
+        ("close", None, None) => vec![InputEvent::Close],
+        _ => return None,
+    };
+
+    if words.next().is_some() {
+        return None;
+    }
+    Some((Duration::from_millis(time), events))
+}
+
+impl InputScript {
+    pub fn from_file(path: &str) -> std::io::Result<Self> {
+        read_to_string(path)?.parse()
+    }
+
+    /// starts counting the times of the events from now
+    pub fn start(&mut self) {
+        self.start = Instant::now();
+    }
+
+    /// removes and returns the next event if its time has passed
+    pub fn next_due(&mut self) -> Option<InputEvent> {
+        let (time, _) = self.events.front()?;
+        if self.start.elapsed() < *time {
+            return None;
+        }
+        self.events.pop_front().map(| (_, event) | { event })
+    }
+
+    /// the moment the next event is due
+    pub fn next_time(&self) -> Option<Instant> {
+        self.events.front().map(| (time, _) | { self.start + *time })
+    }
+}
+
+impl FromStr for InputScript {
+    type Err = Error;
+
+    fn from_str(str: &str) -> std::result::Result<Self, Self::Err> {
+        let mut events = Vec::new();
+        for (index, line) in str.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let option = parse_line(line);
+            match option {
+                Some((time, line_events)) => events.extend(line_events.into_iter().map(| event | { (time, event) })),
+                None => return Err(create_script_error(index + 1, format!("invalid event: \"{}\"", line))),
+            }
+        }
+        // the sort is stable so events at the same time keep the order of the file
+        events.sort_by_key(| (time, _) | { *time });
+
+        Ok(Self {
+            events: events.into(),
+            start: Instant::now(),
+        })
+    }
+}
@@ -1,32 +1,78 @@
-use std::cell::Cell;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::cmp::Ordering;
 use std::fmt::{Display, Formatter, Write};
 use std::io::{Write as IOWrite, Read as IORead};
+use std::path::Path;
 use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use enum_dispatch::enum_dispatch;
-use strum::AsRefStr;
+use strum::{AsRefStr, EnumIter, EnumMessage, IntoEnumIterator, IntoStaticStr};
 use crate::computer::Computer;
-use crate::cpu::{CpuArchitecture, CpuError, IntoBytes, FromBytes};
-use crate::memory::RamError;
-use crate::operand::{Literal, Operand, Register};
+use crate::cpu::{Cpu, CpuArchitecture, CpuError, IntoBytes, FromBytes, SignedCpuArchitecture, Flag, Flags, OverflowTrap};
+use crate::memory::{Ram, RamError, RamErrorKind};
+use crate::operand::{Literal, Operand, Pointer, Register};
 use crate::error_creator;
 use num_derive::{ToPrimitive, FromPrimitive};
 use num_traits::FromPrimitive;
-use crate::compile::DEBUG;
+use crate::compile::{load_program, DEBUG, SANITIZE};
 use crate::window::Window;
+use crate::canvas::Canvas;
+use crate::input_script::{InputEvent, NO_EVENT};
+use crate::program::INSTRUCTION_SIZE;
+use crate::sandbox::{is_env_var_allowed, Permission, SANDBOX};
+use crate::audio::SoundChannel;
+use crate::docs::InstructionDocumentation;
+use crate::ipc::{create_shared_memory, get_shared_memory, receive_message, send_message};
+use miniz_oxide::deflate::compress_to_vec;
+use miniz_oxide::inflate::decompress_to_vec_with_limit;
 
 error_creator!(
     InstructionError,
     InstructionErrorKind,
     OperandNop => "Operand cannot be Nop",
+    /// a literal was used as destination, only registers and pointers can be written to
     DestinationInvalid => "Destination either has to be a register, register pointer or literal pointer",
+    /// a float register was used by an integer instruction, use fmov and ftoi to convert between them
+    FloatRegisterNotAllowed => "A float register can only be used by the float instructions",
+    /// a register pair was used by an instruction that isn't a long instruction like addl
+    RegisterPairNotAllowed => "A register pair can only be used by the long instructions",
     RamError(RamError) => "",
     CpuError(CpuError) => "",
+    /// a misspelled mnemonic, see emulator docs for all instructions
     StringInstructionNotFound => "The instruction given was not found",
+    /// a register that doesn't exist or a literal that doesn't fit in the cpu architecture
     InvalidOperandString => "The operand is invalid",
+    /// too many or too few operands separated by commas
     InvalidOperandCount => "The string provided doesn't have the valid operand count for the instruction",
+    /// x1 doesn't contain a syscall number when calling syscall
     SyscallFunctionNotFound => "The syscall function number is not found",
     PrintError => "an error occurred while printing",
     WindowAlreadyCreated => "cannot create multiple windows, a window already exists",
+    /// a drawing syscall was used before the window was created using the CreateWindow syscall
+    WindowNotCreated => "cannot draw without a window, create one first",
+    ExecError => "an error occurred while executing a program",
+    SharedMemoryNotFound => "The shared memory with the given key does not exist",
+    FileError => "an error occurred while accessing a file",
+    /// the address couldn't be reached, the port is already in use or the connection was reset by the other side
+    NetworkError => "an error occurred while using a socket",
+    /// the channel of the tone syscall isn't 0 or 1
+    InvalidSoundChannel => "The sound channel does not exist, use 0 for the square channel and 1 for the noise channel",
+    /// the syscall accesses the host, which has to be allowed using --allow-fs, --allow-net or --allow-env
+    PermissionDenied => "The syscall requires host access that has not been allowed",
+    /// the divisor of div, idiv or imod is 0
+    DivideByZero => "Cannot divide by zero",
+    /// the table of a switch doesn't contain a case with a literal address at the index
+    InvalidSwitchTable => "The switch table does not contain a case at the index",
+    /// int was used without setting a vector table using the SetInterruptTable syscall, or the table has no handler for the interrupt
+    InterruptNotHandled => "The interrupt does not have a handler",
+    /// the result of add, sub or mul didn't fit in the destination while the overflow trap is enabled using SetOverflowTrap
+    ArithmeticOverflow => "The result of the arithmetic does not fit in the destination",
+    /// the watchdog started by writing to port 32 wasn't petted by writing to port 33 in time and has no interrupt handler
+    WatchdogExpired => "The watchdog has expired",
+    /// in or out was used with a port that no device is registered at, see emulator docs for the ports
+    PortNotConnected => "No device is connected to the port",
     Other => ""
 );
 
@@ -45,6 +91,12 @@ pub trait Instruction : Clone + Copy + FromStr + Display + Is {
     fn to_binary(self, stream: &mut impl IOWrite) -> std::io::Result<CpuArchitecture>;
 
     fn initialize(&mut self, stream: &mut impl IORead) -> std::io::Result<CpuArchitecture>;
+
+    /// returns the first register that doesn't exist on a cpu with register_count registers
+    fn validate_registers(self, register_count: u8) -> std::result::Result<(), Register>;
+    
+    /// the operands in the order in which they are written
+    fn operands(self) -> Vec<Operand>;
 }
 
 macro_rules! replace_expr {
@@ -68,8 +120,42 @@ macro_rules! compute_recursive {
     }};
 }
 
+/// other mnemonics for instructions so code ported from other assemblers parses, the alias is replaced by the mnemonic
+/// before parsing, eg: je label becomes jz label
+pub const INSTRUCTION_ALIASES: &[(&str, &str)] = &[
+    ("jump", "jmp"),
+    ("return", "ret"),
+    ("cmp", "cmpe"),
+    ("je", "jz"),
+    ("jne", "jnz"),
+    ("jb", "jc"),
+    ("jnae", "jc"),
+    ("jae", "jnc"),
+    ("jnb", "jnc"),
+    ("jna", "jbe"),
+    ("jnbe", "ja"),
+    ("jnge", "jl"),
+    ("jnl", "jge"),
+    ("jng", "jle"),
+    ("jnle", "jg"),
+    ("sal", "shl"),
+    ("halt", "hlt"),
+];
+
+/// replaces the alias at the start of the line by its mnemonic, the alias has to be followed by whitespace like a mnemonic
+pub fn resolve_alias(line: &str) -> Cow<'_, str> {
+    let alias = INSTRUCTION_ALIASES.iter().find(| (alias, _) | {
+        line.get(..alias.len()).is_some_and(| start | { start.eq_ignore_ascii_case(alias) }) &&
+            line[alias.len()..].chars().next().is_none_or(char::is_whitespace)
+    });
+    match alias {
+        Some((alias, mnemonic)) => Cow::Owned(format!("{}{}", mnemonic, &line[alias.len()..])),
+        None => Cow::Borrowed(line),
+    }
+}
+
 macro_rules! create_instructions {
-    ($($val:ident => $literal:literal),*) => {
+    ($($(#[doc = $doc:literal])* $val:ident => $literal:literal),*) => {
         #[enum_dispatch(Instruction)]
         #[derive(AsRefStr, Clone, Copy, Debug)]
         pub enum InstructionSet {
@@ -97,15 +183,61 @@ macro_rules! create_instructions {
                 let max = compute_recursive!(max, $($literal)*); 
                 max as CpuArchitecture
             }
+            
+            /// the documentation of every instruction taken from the doc comments within create_instructions
+            pub const DOCUMENTATION: &'static [InstructionDocumentation] = &[
+                $(InstructionDocumentation {
+                    name: stringify!($val),
+                    number: $literal,
+                    operands: $val::OPERAND_NAMES,
+                    description: concat!($($doc),*),
+                }),*
+            ];
+            
+            /// the operand count of every instruction indexed by the instruction number
+            pub const OPERAND_COUNTS: [Option<u8>; Self::max_instruction_number() as usize + 1] = {
+                let mut table = [None; Self::max_instruction_number() as usize + 1];
+                $(table[$literal] = Some($val::OPERAND_COUNT);)*
+                table
+            };
+            
+            /// returns the instruction number and binary size of the encoded instruction at the start of the buffer
+            /// without decoding the instruction, None is returned if the instruction is invalid or incomplete
+            pub fn encoded_size(buffer: &[u8]) -> Option<(CpuArchitecture, CpuArchitecture)> {
+                let mut bytes = [0u8;size_of::<CpuArchitecture>()];
+                bytes[..INSTRUCTION_SIZE as usize].copy_from_slice(buffer.get(..INSTRUCTION_SIZE as usize)?);
+                let instruction_number: CpuArchitecture = FromBytes::from(bytes);
+                
+                let operand_count = (*Self::OPERAND_COUNTS.get(instruction_number as usize)?)?;
+                let mut size = INSTRUCTION_SIZE;
+                for _ in 0..operand_count {
+                    size += Operand::encoded_size(buffer.get(size as usize..)?)?;
+                }
+                
+                Some((instruction_number, size))
+            }
         }
         
+        $(
+            impl $val {
+                #[allow(unused)]
+                pub const NUMBER: CpuArchitecture = $literal;
+            }
+        )*
+        
         impl std::str::FromStr for InstructionSet {
             type Err = InstructionError;
         
             fn from_str(str: &str) -> std::result::Result<Self, Self::Err> {
+                let str = resolve_alias(str);
+                let str = str.as_ref();
                 $(
-                    if str.len() >= stringify!($val).len() && str[..stringify!($val).len()].eq_ignore_ascii_case(stringify!($val)) {
-                        return Ok($val::from_str(&str[stringify!($val).len()..])?.into());
+                    // the mnemonic has to be followed by whitespace so mov doesn't match movzx
+                    if let Some(operands) = str.get(stringify!($val).len()..) {
+                        if str[..stringify!($val).len()].eq_ignore_ascii_case(stringify!($val)) &&
+                            (operands.is_empty() || operands.starts_with(char::is_whitespace)) {
+                            return Ok($val::from_str(operands)?.into());
+                        }
                     }
                 )*
                 return Err(InstructionError::with_message(InstructionErrorKind::StringInstructionNotFound, format!("line: \"{}\"", str)));
@@ -157,6 +289,9 @@ macro_rules! operand_instruction {
         }
         
         impl $instruction {
+            pub const OPERAND_COUNT: u8 = count_tts!($($name)*) as u8;
+            pub const OPERAND_NAMES: &'static [&'static str] = &[$(stringify!($name)),*];
+            
             #[allow(unused)]
             pub fn new($($name: Operand),*) -> Self {
                 Self {
@@ -198,6 +333,17 @@ macro_rules! operand_instruction {
                 )*
                 Ok(total_size)
             }
+            
+            fn validate_registers(self, #[allow(unused)] register_count: u8) -> std::result::Result<(), Register> {
+                $(
+                    self.$name.validate_registers(register_count)?;
+                )*
+                Ok(())
+            }
+            
+            fn operands(self) -> Vec<Operand> {
+                vec![$(self.$name),*]
+            }
         }
         
         impl FromStr for $instruction {
@@ -255,31 +401,247 @@ macro_rules! fmt_helper {
 }
 
 create_instructions!(
+    /// exits the program with the value of l1 as exit code
     Exit => 0,
+    /// copies source into destination
     Mov => 1,
+    /// adds source to destination, wrapping on overflow, sets the zero, carry, sign and overflow flags
     Add => 2,
+    /// subtracts source from destination, wrapping on overflow, sets the zero, carry, sign and overflow flags
     Sub => 3,
+    /// multiplies destination by source, wrapping on overflow
     Mul => 4,
+    /// divides destination by source as unsigned numbers
     Div => 5,
+    /// pushes the return address and jumps to the function or address
     Call => 6,
+    /// pops the return address and jumps back to it
     Ret => 7,
+    /// calls the host function with the number in x1, see the syscalls
     Syscall => 8,
+    /// pushes source onto the stack using the size of source
     Push => 9,
+    /// pops the size of destination from the stack into destination
     Pop => 10,
+    /// jumps to the label or address if the compare flag is set, the flag is kept until the next compare or clf
     Jmp => 11,
+    /// sets the compare flag if a is equal to b, the other flags are set as if b was subtracted from a
     Cmpe => 12,
+    /// sets the compare flag if a is not equal to b, the other flags are set as if b was subtracted from a
     Cmpne => 13,
+    /// sets the compare flag if a is less than or equal to b, the other flags are set as if b was subtracted from a
     Cmple => 14,
+    /// sets the compare flag if a is less than b, the other flags are set as if b was subtracted from a
     Cmpl => 15,
+    /// sets the compare flag if a is greater than or equal to b, the other flags are set as if b was subtracted from a
     Cmpge => 16,
+    /// sets the compare flag if a is greater than b, the other flags are set as if b was subtracted from a
     Cmpg => 17,
+    /// writes the compare flag (0 or 1) into destination
     Set => 18,
+    /// starts a breakpoint when running in debug mode, removed otherwise
     Break => 19,
+    /// shifts destination left by source bits
     Shl => 20,
+    /// shifts destination right by source bits
     Shr => 21,
+    /// bitwise exclusive or of destination and source
     Xor => 22,
+    /// bitwise and of destination and source
     And => 23,
-    Or => 24
+    /// bitwise or of destination and source
+    Or => 24,
+    /// selects the memory bank that pointers read from and write to
+    Bank => 25,
+    /// multiplies destination by source as signed numbers
+    Imul => 26,
+    /// divides destination by source as signed numbers
+    Idiv => 27,
+    /// the signed remainder of dividing destination by source
+    Imod => 28,
+    /// adds 1 to destination, wrapping on overflow, sets the zero, sign and overflow flags and keeps the carry flag
+    Inc => 29,
+    /// subtracts 1 from destination, wrapping on overflow, sets the zero, sign and overflow flags and keeps the carry flag
+    Dec => 30,
+    /// jumps to the label or address if the zero flag is set
+    Jz => 31,
+    /// jumps to the label or address if the zero flag is not set
+    Jnz => 32,
+    /// jumps to the label or address if the carry flag is set
+    Jc => 33,
+    /// jumps to the label or address if the carry flag is not set
+    Jnc => 34,
+    /// jumps to the label or address if the sign flag is set
+    Js => 35,
+    /// jumps to the label or address if the sign flag is not set
+    Jns => 36,
+    /// jumps to the label or address if the overflow flag is set
+    Jo => 37,
+    /// jumps to the label or address if the overflow flag is not set
+    Jno => 38,
+    /// adds source and the carry flag to destination, sets the zero, carry, sign and overflow flags
+    Adc => 39,
+    /// subtracts source and the carry flag from destination, sets the zero, carry, sign and overflow flags
+    Sbb => 40,
+    /// swaps the values of a and b, both have to be writable
+    Xchg => 41,
+    /// copies source into destination filling the bits above the size of source with zeros
+    Movzx => 42,
+    /// copies source into destination filling the bits above the size of source with the sign bit of source
+    Movsx => 43,
+    /// jumps to the function or address without pushing a return address, the function returns to the caller of the
+    /// current function so recursion at the end of a function doesn't grow the stack
+    Tailcall => 44,
+    /// pushes bp, sets bp to sp and reserves size bytes on the stack for the locals of the function
+    Enter => 45,
+    /// frees the locals by setting sp to bp and pops bp of the caller
+    Leave => 46,
+    /// jumps to the address of the case at index within the table of cases at address, continues with the next
+    /// instruction when index is not less than count
+    Switch => 47,
+    /// an entry of a switch table containing the address of a label, does nothing when executed
+    Case => 48,
+    /// copies the bit of destination at the bit index into the compare and carry flags, the index wraps around the size
+    /// of destination
+    Bt => 49,
+    /// copies the bit of destination at the bit index into the compare and carry flags and sets the bit
+    Bts => 50,
+    /// copies the bit of destination at the bit index into the compare and carry flags and clears the bit
+    Btr => 51,
+    /// adds source to destination as unsigned numbers, clamping to the largest value that fits in destination
+    Adds => 52,
+    /// subtracts source from destination as unsigned numbers, clamping to 0
+    Subs => 53,
+    /// writes the amount of set bits of source into destination
+    Popcnt => 54,
+    /// writes the amount of zero bits above the highest set bit of source into destination, the size of source in bits
+    /// when source is 0
+    Clz => 55,
+    /// writes the amount of zero bits below the lowest set bit of source into destination, the size of source in bits
+    /// when source is 0
+    Ctz => 56,
+    /// adds every byte of source to the same byte of destination, the bytes wrap on overflow without carrying into the
+    /// next byte
+    Padd8 => 57,
+    /// subtracts every byte of source from the same byte of destination, the bytes wrap on overflow without borrowing
+    /// from the next byte
+    Psub8 => 58,
+    /// keeps the smallest of every byte of destination and the same byte of source as unsigned numbers
+    Pmin8 => 59,
+    /// does nothing, used for padding and patching binaries
+    Nop => 60,
+    /// waits until a window event is queued or until timeout milliseconds have passed, which queues the timer event (4),
+    /// returns right away when events are queued, a timeout of 0 waits without a timer, sleeps for the timeout when no
+    /// window is open
+    Hlt => 61,
+    /// resets the compare flag so jmp jumps unconditionally again
+    Clf => 62,
+    /// calls the handler of the interrupt within the vector table, see the SetInterruptTable syscall
+    Int => 63,
+    /// writes the address of the function into destination, eg: to fill the vector table of SetInterruptTable
+    Addr => 64,
+    /// copies length bytes from the address source to the address destination within the selected bank, the bytes can overlap
+    Memcpy => 65,
+    /// sets length bytes at the address destination within the selected bank to the lowest byte of value
+    Memset => 66,
+    /// jumps like jmp to the address of the next instruction plus offset as a signed number, jmp to a label is
+    /// assembled into rjmp so the code can be placed at any address
+    Rjmp => 67,
+    /// calls like call the address of the next instruction plus offset as a signed number, only functions of the
+    /// same program can be called by name
+    Rcall => 68,
+    /// adds source to destination as packed bcd numbers (4 bits per decimal digit), sets the carry flag when the
+    /// result doesn't fit in destination and the zero flag when it is 0
+    Bcdadd => 69,
+    /// subtracts source from destination as packed bcd numbers (4 bits per decimal digit), sets the carry flag when
+    /// source is larger than destination and the zero flag when the result is 0
+    Bcdsub => 70,
+    /// jumps to the label or address if a was less than b as signed numbers, after a compare or sub of a and b
+    Jl => 71,
+    /// jumps to the label or address if a was less than or equal to b as signed numbers, after a compare or sub of a and b
+    Jle => 72,
+    /// jumps to the label or address if a was greater than b as signed numbers, after a compare or sub of a and b
+    Jg => 73,
+    /// jumps to the label or address if a was greater than or equal to b as signed numbers, after a compare or sub of a and b
+    Jge => 74,
+    /// jumps to the label or address if a was greater than b as unsigned numbers, after a compare or sub of a and b,
+    /// jc jumps if a was less
+    Ja => 75,
+    /// jumps to the label or address if a was less than or equal to b as unsigned numbers, after a compare or sub of a
+    /// and b, jnc jumps if a was greater or equal
+    Jbe => 76,
+    /// multiplies destination by source as signed fixed point numbers with fraction bits after the point, rounded
+    /// down and wrapping on overflow, the fraction wraps around the size of destination
+    Fxmul => 77,
+    /// divides destination by source as signed fixed point numbers with fraction bits after the point, rounded
+    /// towards 0 and wrapping on overflow, the fraction wraps around the size of destination
+    Fxdiv => 78,
+    /// adds source to the float register destination, source is converted from a signed number when it isn't a float register
+    Fadd => 79,
+    /// subtracts source from the float register destination, source is converted from a signed number when it isn't a
+    /// float register
+    Fsub => 80,
+    /// multiplies the float register destination by source, source is converted from a signed number when it isn't a
+    /// float register
+    Fmul => 81,
+    /// divides the float register destination by source, source is converted from a signed number when it isn't a
+    /// float register, dividing by 0 gives infinity
+    Fdiv => 82,
+    /// sets the compare flag if a is equal to b, the zero flag if equal, the carry and sign flags if a is less than b
+    /// and the overflow flag if either is NaN, a and b are converted from signed numbers when these aren't float registers
+    Fcmp => 83,
+    /// copies source into the float register destination, source is converted from a signed number when it isn't a
+    /// float register
+    Fmov => 84,
+    /// converts the float register source into a signed number rounded towards 0, clamped to the size of destination
+    Ftoi => 85,
+    /// reads the 4 byte float at the address within the selected bank into the float register destination
+    Fld => 86,
+    /// writes the float register source as 4 byte float to the address within the selected bank
+    Fst => 87,
+    /// adds source to the register pair destination as 4 byte numbers, source is zero extended when it isn't a register
+    /// pair, sets the zero, carry, sign and overflow flags
+    Addl => 88,
+    /// subtracts source from the register pair destination as 4 byte numbers, source is zero extended when it isn't a
+    /// register pair, sets the zero, carry, sign and overflow flags
+    Subl => 89,
+    /// multiplies the register pair destination by source as 4 byte numbers, wrapping on overflow, source is zero
+    /// extended when it isn't a register pair
+    Mull => 90,
+    /// multiplies destination by source as unsigned numbers and writes the upper half of the product of twice the size
+    /// of destination into destination, mul writes the lower half
+    Mulh => 91,
+    /// multiplies destination by source as unsigned numbers, clamping to the largest value that fits in destination
+    Muls => 92,
+    /// reads the value of the device at port into destination, the devices are listed in the ports of emulator docs,
+    /// eg: port 16 gives the seconds of the clock
+    In => 93,
+    /// writes source to the device at port, the devices are listed in the ports of emulator docs, eg: port 0 sends the
+    /// lower byte to the serial console
+    Out => 94,
+    /// writes source into destination if destination equals expected and sets the zero flag, otherwise loads
+    /// destination into expected and clears the zero flag, the compare and write happen as a single step
+    Cas => 95,
+    /// adds source to destination and writes the previous value of destination into source as a single step, both have
+    /// to be writable, sets the zero, carry, sign and overflow flags like add
+    Xadd => 96,
+    /// writes a random number of the size of destination into destination, the numbers are reproducible using --seed
+    Rand => 97,
+    /// loads the value of the size of destination at the address in x9 into destination and advances x9 past it,
+    /// backwards when the direction flag is set
+    Lods => 98,
+    /// stores source at the address in x10 using the size of source and advances x10 past it, backwards when the
+    /// direction flag is set
+    Stos => 99,
+    /// sets the direction flag so lods and stos move backwards
+    Std => 100,
+    /// clears the direction flag so lods and stos move forwards
+    Cld => 101,
+    /// starts a breakpoint like break and prints the id to tell breakpoints apart when running in debug mode, removed
+    /// otherwise
+    Brk => 102,
+    /// returns from an interrupt handler, pops the return address, x2 and the flags pushed when the handler was called
+    Iret => 103
 );
 
 fn create_invalid_op_count_error(str:&str, got:impl Display, expected:CpuArchitecture) -> InstructionError {
@@ -297,6 +659,18 @@ operand_instruction!(Mov, | mov: Mov, computer: &mut Computer | {
     mov.destination.write_to_computer(computer, value)
 }, destination, source);
 
+operand_instruction!(Movzx, | movzx: Movzx, computer: &mut Computer | {
+    let value = movzx.source.read_from_computer(computer)?;
+    
+    movzx.destination.write_to_computer(computer, value)
+}, destination, source);
+
+operand_instruction!(Movsx, | movsx: Movsx, computer: &mut Computer | {
+    let value = movsx.source.read_signed_from_computer(computer)?;
+    
+    movsx.destination.write_to_computer(computer, value as CpuArchitecture)
+}, destination, source);
+
 macro_rules! operation_instruction {
     (
         $operation_name:ident,
@@ -306,235 +680,1961 @@ macro_rules! operation_instruction {
             let value = operation.destination.read_from_computer(computer)?;
             let value2 = operation.source.read_from_computer(computer)?;
     
-            let final_value = ($operation)(value, value2);
+            let final_value = ($operation)(value, value2)?;
             
             operation.destination.write_to_computer(computer, final_value)
         }, destination, source);
     };
 }
 
-operation_instruction!(Add, | a:CpuArchitecture, b | { a.wrapping_add(b)});
-operation_instruction!(Sub, | a:CpuArchitecture, b | { a.wrapping_sub(b) });
-operation_instruction!(Mul, | a:CpuArchitecture, b | { a.wrapping_mul(b) });
-operation_instruction!(Div, | a:CpuArchitecture, b | { a / b });
-operation_instruction!(Shl, | a:CpuArchitecture, b | { a.wrapping_shl(b as u32) });
-operation_instruction!(Shr, | a:CpuArchitecture, b | { a.wrapping_shr(b as u32) });
-operation_instruction!(Xor, | a:CpuArchitecture, b | { a ^ b });
-operation_instruction!(And, | a:CpuArchitecture, b | { a & b });
-operation_instruction!(Or, | a:CpuArchitecture, b | { a | b });
-
-operand_instruction!(Call, | call:Call, computer:&mut Computer | {
-    let current_addr = computer.cpu().get_program_counter();
-    let address = call.address.read_from_computer(computer)?;
-    computer.cpu_mut().set_program_counter(address);
-    computer.cpu_mut().push(&current_addr)?;
-    Ok(())
-}, address);
-
-impl Call {
-    pub fn const_function_binary_size() -> CpuArchitecture {
-        Literal::binary_size()
-    }
-}
-
-impl From<Operand> for Call {
-    fn from(value: Operand) -> Self {
-        Self { address: value }
+fn check_divisor<T : Default + PartialEq>(divisor: T) -> Result<T> {
+    if divisor == T::default() {
+        Err(InstructionError::new(InstructionErrorKind::DivideByZero))
+    } else {
+        Ok(divisor)
     }
 }
 
-empty_instruction!(Ret, | computer: &mut Computer | {
-    let address = computer.cpu_mut().pop()?;
-    computer.cpu_mut().set_program_counter(address);
-    Ok(())
-});
-
-thread_local! {
-    pub static AWAITING_EVENT: Cell<bool> = const { Cell::new(false) };
-    pub static REDRAW: Cell<bool> = const { Cell::new(false) };
+/// adds or subtracts b and the carry from a within size bytes and sets the zero, carry, sign and overflow flags of the result
+pub fn arithmetic_with_flags(computer: &mut Computer, a: CpuArchitecture, b: CpuArchitecture, carry: bool, size: CpuArchitecture, subtract: bool) -> CpuArchitecture {
+    let bits = (size.clamp(1, size_of::<CpuArchitecture>() as CpuArchitecture) * 8) as u32;
+    let mask = u32::MAX >> (u32::BITS - bits);
+    let sign_bit = 1 << (bits - 1);
+    let (a, b, carry) = (a as u32 & mask, b as u32 & mask, carry as u32);
+    
+    let (result, carry, overflow) = if subtract {
+        let result = a.wrapping_sub(b).wrapping_sub(carry) & mask;
+        (result, a < b + carry, (a ^ b) & (a ^ result) & sign_bit != 0)
+    } else {
+        let result = a + b + carry;
+        (result & mask, result > mask, !(a ^ b) & (a ^ result) & sign_bit != 0)
+    };
+    
+    let cpu = computer.cpu_mut();
+    cpu.set_flag(Flag::Zero, result == 0);
+    cpu.set_flag(Flag::Carry, carry);
+    cpu.set_flag(Flag::Sign, result & sign_bit != 0);
+    cpu.set_flag(Flag::Overflow, overflow);
+    result as CpuArchitecture
 }
 
-empty_instruction!(Syscall, | computer: &mut Computer | {
-    let register = Register::new(0, size_of::<CpuArchitecture>() as u8);
-    let function_number = computer.cpu().get_register(register).unwrap(); // cpu is expected to have 4 registers
-    let option = FromPrimitive::from_usize(function_number as usize);
+/// errors when the overflow trap is enabled and the flags of the last add or sub show that the result didn't fit
+pub(crate) fn check_overflow_trap(computer: &Computer) -> Result<()> {
+    let flag = match computer.cpu().overflow_trap() {
+        OverflowTrap::Off => return Ok(()),
+        OverflowTrap::Unsigned => Flag::Carry,
+        OverflowTrap::Signed => Flag::Overflow,
+    };
     
-    match option {
-        Some(function) => match function {
-            SyscallFunction::Allocate => {
-                let alloc_amount_register = Register::new(1, size_of::<CpuArchitecture>() as u8); 
-                let alloc_amount = computer.cpu().get_register(alloc_amount_register).unwrap(); // same as above
-                
-                // SAFETY: no safety :( allocation happens within the emulator by the user
-                // or it will be deallocated when the program finishes
-                let pointer = unsafe { computer.ram_mut().alloc_unsafe(alloc_amount)? };
-                computer.cpu_mut().set_register(alloc_amount_register, pointer).unwrap(); // same as above
-                Ok(())
-            },
-            SyscallFunction::Deallocate => {
-                let pointer_register = Register::new(1, size_of::<CpuArchitecture>() as u8); 
-                let pointer = computer.cpu().get_register(pointer_register).unwrap(); // same as above
-                
-                let option = computer.ram_mut().dealloc(pointer);
-                computer.cpu_mut().set_register(pointer_register, option.unwrap_or(0)).unwrap(); // same as above
-                
-                Ok(())
-            },
-            SyscallFunction::Print => {
-                let register = Register::new(1, size_of::<CpuArchitecture>() as u8); // same as above
-                let pointer = computer.cpu().get_register(register)?;
-                let register =  Register::new(2, size_of::<CpuArchitecture>() as u8);
-                let length = computer.cpu().get_register(register)?;
-                
-                let error = computer.ram().borrow_buffer_checked(pointer, length, Computer::print_bytes)?;
-                
-                match error {
-                    Ok(_) => Ok(()),
-                    Err(err) => Err(InstructionError::with_message(InstructionErrorKind::PrintError, err.to_string()))
-                }
-            },
-            SyscallFunction::CreateWindow => {
-                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
-                let pointer = computer.cpu().get_register(register).unwrap(); // same as above
-                
-                let (size, window_name) = if pointer != 0 {
-                    let register = Register::new(2, size_of::<CpuArchitecture>() as u8);
-                    let length = computer.cpu().get_register(register).unwrap(); // same as above
-                    
-                    let window_name = computer.ram().borrow_buffer_checked(pointer, length, | buffer | {
-                        let mut str = String::with_capacity(buffer.len());
-                        for b in buffer {
-                            str.push(*b as char);
-                        }
-                        
-                        str
-                    })?;
-                    
-                    let width_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
-                    let width = computer.cpu().get_register(width_register).unwrap(); // same as above
-                    
-                    let height_register = Register::new(4, size_of::<CpuArchitecture>() as u8);
-                    let height = computer.cpu().get_register(height_register)?;
-                    
-                    ((width, height), window_name)
-                } else {
-                    let width_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
-                    let width = computer.cpu().get_register(width_register).unwrap(); // same as above
-                    
-                    let height_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
-                    let height = computer.cpu().get_register(height_register).unwrap(); // same as above
-                    
-                    ((width, height), String::new())
-                };
-                let canvas_size = (size.0 as usize, size.1 as usize);
-                
-                let window_name_option = if window_name.is_empty() {
-                    None
-                } else {
-                    Some(window_name.as_str())
-                };
-                
-                Window::run(canvas_size, window_name_option, computer, register)
-            },
-            SyscallFunction::GetWindowEvent => {
-                AWAITING_EVENT.set(true);
-                Ok(())
-            },
-            SyscallFunction::Redraw => {
-                REDRAW.set(true);
-                Ok(())
-            }
-        },
-        None => Err(InstructionError::with_message(InstructionErrorKind::SyscallFunctionNotFound, format!("got: {}", function_number)))
+    if computer.cpu().get_flag(flag) {
+        Err(InstructionError::new(InstructionErrorKind::ArithmeticOverflow))
+    } else {
+        Ok(())
     }
-});
+}
 
-#[derive(FromPrimitive, ToPrimitive)]
-enum SyscallFunction {
-    Allocate = 0,
-    Deallocate = 1,
-    Print = 2,
-    CreateWindow = 3,
-    GetWindowEvent = 4,
-    Redraw = 5,
+macro_rules! flag_operation_instruction {
+    (
+        $operation_name:ident,
+        $subtract:literal,
+        $use_carry:literal
+    ) => {
+        operand_instruction!($operation_name, | operation: $operation_name, computer: &mut Computer | {
+            let value = operation.destination.read_from_computer(computer)?;
+            let value2 = operation.source.read_from_computer(computer)?;
+            let carry = $use_carry && computer.cpu().get_flag(Flag::Carry);
+            
+            let final_value = arithmetic_with_flags(computer, value, value2, carry, operation.destination.size(), $subtract);
+            check_overflow_trap(computer)?;
+            
+            operation.destination.write_to_computer(computer, final_value)
+        }, destination, source);
+    };
 }
 
-operand_instruction!(Push, | push:Push, computer: &mut Computer | -> Result<()> {
-    let value = push.source.read_from_computer(computer)?;
+flag_operation_instruction!(Add, false, false);
+flag_operation_instruction!(Sub, true, false);
+flag_operation_instruction!(Adc, false, true);
+flag_operation_instruction!(Sbb, true, true);
+operand_instruction!(Mul, | mul: Mul, computer: &mut Computer | {
+    let value = mul.destination.read_from_computer(computer)?;
+    let value2 = mul.source.read_from_computer(computer)?;
+    let bits = mul.destination.size() as u32 * 8;
     
-    let buffer = IntoBytes::into(&value);
-    computer.cpu_mut().push_buffer(&buffer[..push.source.size() as usize])?;
+    let overflow = match computer.cpu().overflow_trap() {
+        OverflowTrap::Off => false,
+        OverflowTrap::Unsigned => value as u32 * value2 as u32 > u32::MAX >> (u32::BITS - bits),
+        OverflowTrap::Signed => {
+            let sign_extend = | value: CpuArchitecture, size: CpuArchitecture | {
+                let unused_bits = u32::BITS - size as u32 * 8;
+                (((value as u32) << unused_bits) as i32 >> unused_bits) as i64
+            };
+            let product = sign_extend(value, mul.destination.size()) * sign_extend(value2, mul.source.size());
+            !(-(1 << (bits - 1))..1 << (bits - 1)).contains(&product)
+        },
+    };
+    if overflow {
+        return Err(InstructionError::new(InstructionErrorKind::ArithmeticOverflow));
+    }
     
-    Ok(())
-}, source);
+    mul.destination.write_to_computer(computer, value.wrapping_mul(value2))
+}, destination, source);
 
-operand_instruction!(Pop, | pop:Pop, computer: &mut Computer | -> Result<()> {
-    let mut buffer = [0u8;size_of::<CpuArchitecture>()];
-    computer.cpu_mut().pop_buffer(&mut buffer[..pop.destination.size() as usize])?;
-    
-    let value:CpuArchitecture = FromBytes::from(buffer);
-    pop.destination.write_to_computer(computer, value)?;
+operand_instruction!(Mulh, | mulh: Mulh, computer: &mut Computer | {
+    let value = mulh.destination.read_from_computer(computer)? as u32;
+    let value2 = mulh.source.read_from_computer(computer)? as u32;
+    let bits = mulh.destination.size() as u32 * 8;
     
-    Ok(())
-}, destination);
+    mulh.destination.write_to_computer(computer, ((value * value2) >> bits) as CpuArchitecture)
+}, destination, source);
+operation_instruction!(Div, | a:CpuArchitecture, b | -> Result<CpuArchitecture> { Ok(a / check_divisor(b)?) });
+operation_instruction!(Shl, | a:CpuArchitecture, b | -> Result<CpuArchitecture> { Ok(a.wrapping_shl(b as u32)) });
+operation_instruction!(Shr, | a:CpuArchitecture, b | -> Result<CpuArchitecture> { Ok(a.wrapping_shr(b as u32)) });
+operation_instruction!(Xor, | a:CpuArchitecture, b | -> Result<CpuArchitecture> { Ok(a ^ b) });
+operation_instruction!(And, | a:CpuArchitecture, b | -> Result<CpuArchitecture> { Ok(a & b) });
+operation_instruction!(Or, | a:CpuArchitecture, b | -> Result<CpuArchitecture> { Ok(a | b) });
 
+/// the operation is applied to every byte of destination and the same byte of source
+macro_rules! packed_operation_instruction {
+    (
+        $operation_name:ident,
+        $operation: expr
+    ) => {
+        operation_instruction!($operation_name, | a:CpuArchitecture, b:CpuArchitecture | -> Result<CpuArchitecture> {
+            let mut bytes = a.to_le_bytes();
+            for (byte, byte2) in bytes.iter_mut().zip(b.to_le_bytes()) {
+                *byte = ($operation)(*byte, byte2);
+            }
+            Ok(CpuArchitecture::from_le_bytes(bytes))
+        });
+    };
+}
 
-operand_instruction!(Jmp, | jmp:Jmp, computer:&mut Computer | -> Result<()> {
-    let cmp_flag = computer.cpu_mut().get_cmp_flag();
-    if cmp_flag {
-        let address = jmp.address.read_from_computer(computer)?;
-        computer.cpu_mut().set_program_counter(address);
-    }
-    Ok(())
-}, address);
+packed_operation_instruction!(Padd8, | a:u8, b:u8 | { a.wrapping_add(b) });
+packed_operation_instruction!(Psub8, | a:u8, b:u8 | { a.wrapping_sub(b) });
+packed_operation_instruction!(Pmin8, | a:u8, b:u8 | { a.min(b) });
 
-impl Jmp {
-    pub fn const_function_binary_size() -> CpuArchitecture {
-        Literal::binary_size()
-    }
+/// the operation gets source and the amount of bits of source
+macro_rules! count_instruction {
+    (
+        $operation_name:ident,
+        $operation: expr
+    ) => {
+        operand_instruction!($operation_name, | operation: $operation_name, computer: &mut Computer | {
+            let value = operation.source.read_from_computer(computer)?;
+            let bits = operation.source.size() as u32 * 8;
+            
+            let count: u32 = ($operation)(value, bits);
+            
+            operation.destination.write_to_computer(computer, count as CpuArchitecture)
+        }, destination, source);
+    };
 }
 
-impl From<Operand> for Jmp {
-    fn from(value: Operand) -> Self {
-        Self { address: value }
-    }
-}
+count_instruction!(Popcnt, | a:CpuArchitecture, _:u32 | { a.count_ones() });
+count_instruction!(Clz, | a:CpuArchitecture, bits:u32 | { a.leading_zeros() - (CpuArchitecture::BITS - bits) });
+count_instruction!(Ctz, | a:CpuArchitecture, bits:u32 | { a.trailing_zeros().min(bits) });
 
-macro_rules! cmp_instruction {
-    ($name:ident, $comparison:expr) => {
-        operand_instruction!($name, | compare: $name, computer: &mut Computer | -> Result<()> {
-            let value1 = compare.a.read_from_computer(computer)?;
-            let value2 = compare.b.read_from_computer(computer)?;
+/// the operation gets destination, source and the largest value that fits in destination
+macro_rules! saturating_operation_instruction {
+    (
+        $operation_name:ident,
+        $operation: expr
+    ) => {
+        operand_instruction!($operation_name, | operation: $operation_name, computer: &mut Computer | {
+            let value = operation.destination.read_from_computer(computer)?;
+            let value2 = operation.source.read_from_computer(computer)?;
+            let unused_bits = (size_of::<CpuArchitecture>() as u32 - operation.destination.size() as u32) * 8;
             
-            let cmp = ($comparison)(value1, value2);
-            computer.cpu_mut().set_cmp_flag(cmp);
+            let final_value = ($operation)(value, value2, CpuArchitecture::MAX >> unused_bits);
             
-            Ok(())
-        }, a, b);
+            operation.destination.write_to_computer(computer, final_value)
+        }, destination, source);
     };
 }
 
-cmp_instruction!(Cmpe, | a, b | { a == b });
-cmp_instruction!(Cmpne, | a, b | { a != b });
-cmp_instruction!(Cmple, | a, b | { a <= b });
-cmp_instruction!(Cmpl, | a, b | { a < b });
-cmp_instruction!(Cmpge, | a, b | { a >= b });
-cmp_instruction!(Cmpg, | a, b | { a > b });
-
-operand_instruction!(Set, | set:Set, computer: &mut Computer | {
-    let flag = computer.cpu_mut().get_cmp_flag();
-    set.destination.write_to_computer(computer, flag as CpuArchitecture)
-}, destination);
+saturating_operation_instruction!(Adds, | a:CpuArchitecture, b:CpuArchitecture, max:CpuArchitecture | { a.saturating_add(b).min(max) });
+saturating_operation_instruction!(Subs, | a:CpuArchitecture, b:CpuArchitecture, _:CpuArchitecture | { a.saturating_sub(b) });
+saturating_operation_instruction!(Muls, | a:CpuArchitecture, b:CpuArchitecture, max:CpuArchitecture | { a.saturating_mul(b).min(max) });
 
-empty_instruction!(Break, | computer: &mut Computer | -> Result<()> {
-    if DEBUG.get() {
-        let result = computer.breakpoint();
-        if let Err(err) = result {
-            Err(InstructionError::with_message(InstructionErrorKind::Other, err.to_string()))
-        } else {
-            Ok(())
-        }
-    } else {
+/// the operation gets a decimal digit of destination, the same digit of source and the carry of the previous digit
+/// and returns the digit and the carry into the next digit
+macro_rules! bcd_operation_instruction {
+    (
+        $operation_name:ident,
+        $operation: expr
+    ) => {
+        operand_instruction!($operation_name, | operation: $operation_name, computer: &mut Computer | {
+            let value = operation.destination.read_from_computer(computer)?;
+            let value2 = operation.source.read_from_computer(computer)?;
+            
+            let mut final_value = 0;
+            let mut carry = false;
+            for digit_index in 0..(operation.destination.size() as u32 * 2) {
+                let shift = digit_index * 4;
+                let (digit, next_carry) = ($operation)((value >> shift) & 0xF, (value2 >> shift) & 0xF, carry);
+                final_value |= digit << shift;
+                carry = next_carry;
+            }
+            computer.cpu_mut().set_flag(Flag::Carry, carry);
+            computer.cpu_mut().set_flag(Flag::Zero, final_value == 0);
+            
+            operation.destination.write_to_computer(computer, final_value)
+        }, destination, source);
+    };
+}
+
+bcd_operation_instruction!(Bcdadd, | a:CpuArchitecture, b:CpuArchitecture, carry:bool | {
+    let sum = a + b + carry as CpuArchitecture;
+    (sum % 10, sum >= 10)
+});
+bcd_operation_instruction!(Bcdsub, | a:CpuArchitecture, b:CpuArchitecture, borrow:bool | {
+    let subtrahend = b + borrow as CpuArchitecture;
+    // digits above 9 aren't valid bcd, 20 is added so these don't underflow
+    if a >= subtrahend { (a - subtrahend, false) } else { ((a + 20 - subtrahend) % 10, true) }
+});
+
+/// the operation returns the new value of destination from its value and the mask of the bit, None keeps destination
+macro_rules! bit_instruction {
+    (
+        $name:ident,
+        $operation: expr
+    ) => {
+        operand_instruction!($name, | instruction: $name, computer: &mut Computer | {
+            let value = instruction.destination.read_from_computer(computer)?;
+            let bit = instruction.bit.read_from_computer(computer)? % (instruction.destination.size() * 8);
+            let mask = 1 << bit;
+            
+            let is_set = value & mask != 0;
+            computer.cpu_mut().set_cmp_flag(is_set);
+            computer.cpu_mut().set_flag(Flag::Carry, is_set);
+            
+            match ($operation)(value, mask) {
+                Some(final_value) => instruction.destination.write_to_computer(computer, final_value),
+                None => Ok(()),
+            }
+        }, destination, bit);
+    };
+}
+
+bit_instruction!(Bt, | _:CpuArchitecture, _:CpuArchitecture | -> Option<CpuArchitecture> { None });
+bit_instruction!(Bts, | a:CpuArchitecture, mask:CpuArchitecture | -> Option<CpuArchitecture> { Some(a | mask) });
+bit_instruction!(Btr, | a:CpuArchitecture, mask:CpuArchitecture | -> Option<CpuArchitecture> { Some(a & !mask) });
+
+macro_rules! signed_operation_instruction {
+    (
+        $operation_name:ident,
+        $operation: expr
+    ) => {
+        operand_instruction!($operation_name, | operation: $operation_name, computer: &mut Computer | {
+            let value = operation.destination.read_signed_from_computer(computer)?;
+            let value2 = operation.source.read_signed_from_computer(computer)?;
+    
+            let final_value = ($operation)(value, value2)?;
+            
+            operation.destination.write_to_computer(computer, final_value as CpuArchitecture)
+        }, destination, source);
+    };
+}
+
+signed_operation_instruction!(Imul, | a:SignedCpuArchitecture, b | -> Result<SignedCpuArchitecture> { Ok(a.wrapping_mul(b)) });
+signed_operation_instruction!(Idiv, | a:SignedCpuArchitecture, b | -> Result<SignedCpuArchitecture> { Ok(a.wrapping_div(check_divisor(b)?)) });
+signed_operation_instruction!(Imod, | a:SignedCpuArchitecture, b | -> Result<SignedCpuArchitecture> { Ok(a.wrapping_rem(check_divisor(b)?)) });
+
+/// the operation gets destination and source sign extended to twice their size and the fraction bits
+macro_rules! fixed_point_instruction {
+    (
+        $operation_name:ident,
+        $operation: expr
+    ) => {
+        operand_instruction!($operation_name, | operation: $operation_name, computer: &mut Computer | {
+            let value = operation.destination.read_signed_from_computer(computer)?;
+            let value2 = operation.source.read_signed_from_computer(computer)?;
+            let fraction = operation.fraction.read_from_computer(computer)? % (operation.destination.size() * 8);
+            
+            let final_value = ($operation)(value as i32, value2 as i32, fraction as u32)?;
+            
+            operation.destination.write_to_computer(computer, final_value as CpuArchitecture)
+        }, destination, source, fraction);
+    };
+}
+
+fixed_point_instruction!(Fxmul, | a:i32, b:i32, fraction:u32 | -> Result<i32> { Ok(a.wrapping_mul(b) >> fraction) });
+fixed_point_instruction!(Fxdiv, | a:i32, b:i32, fraction:u32 | -> Result<i32> { Ok((a << fraction).wrapping_div(check_divisor(b)?)) });
+
+/// the operation gets the register pair destination and source as 4 byte numbers and returns the result and whether
+/// the flags are set as if it was an add or a subtract, None doesn't change the flags
+macro_rules! long_operation_instruction {
+    (
+        $operation_name:ident,
+        $operation: expr
+    ) => {
+        operand_instruction!($operation_name, | operation: $operation_name, computer: &mut Computer | {
+            let value = operation.destination.read_long_from_computer(computer)?;
+            let value2 = operation.source.read_long_from_computer(computer)?;
+            
+            let (final_value, subtract): (u32, Option<bool>) = ($operation)(value, value2);
+            if let Some(subtract) = subtract {
+                let sign_bit = 1 << (u32::BITS - 1);
+                let (carry, overflow) = if subtract {
+                    (value < value2, (value ^ value2) & (value ^ final_value) & sign_bit != 0)
+                } else {
+                    (final_value < value, !(value ^ value2) & (value ^ final_value) & sign_bit != 0)
+                };
+                let cpu = computer.cpu_mut();
+                cpu.set_flag(Flag::Zero, final_value == 0);
+                cpu.set_flag(Flag::Carry, carry);
+                cpu.set_flag(Flag::Sign, final_value & sign_bit != 0);
+                cpu.set_flag(Flag::Overflow, overflow);
+            }
+            
+            operation.destination.write_long_to_computer(computer, final_value)
+        }, destination, source);
+    };
+}
+
+long_operation_instruction!(Addl, | a:u32, b:u32 | { (a.wrapping_add(b), Some(false)) });
+long_operation_instruction!(Subl, | a:u32, b:u32 | { (a.wrapping_sub(b), Some(true)) });
+long_operation_instruction!(Mull, | a:u32, b:u32 | { (a.wrapping_mul(b), None) });
+
+/// the operation gets the float register destination and source as float
+macro_rules! float_operation_instruction {
+    (
+        $operation_name:ident,
+        $operation: expr
+    ) => {
+        operand_instruction!($operation_name, | operation: $operation_name, computer: &mut Computer | {
+            let value = operation.destination.read_float_from_computer(computer)?;
+            let value2 = operation.source.read_float_from_computer(computer)?;
+            
+            operation.destination.write_float_to_computer(computer, ($operation)(value, value2))
+        }, destination, source);
+    };
+}
+
+float_operation_instruction!(Fadd, | a:f32, b:f32 | { a + b });
+float_operation_instruction!(Fsub, | a:f32, b:f32 | { a - b });
+float_operation_instruction!(Fmul, | a:f32, b:f32 | { a * b });
+float_operation_instruction!(Fdiv, | a:f32, b:f32 | { a / b });
+float_operation_instruction!(Fmov, | _:f32, b:f32 | { b });
+
+operand_instruction!(Fcmp, | fcmp: Fcmp, computer: &mut Computer | {
+    let value = fcmp.a.read_float_from_computer(computer)?;
+    let value2 = fcmp.b.read_float_from_computer(computer)?;
+    
+    let ordering = value.partial_cmp(&value2);
+    let cpu = computer.cpu_mut();
+    cpu.set_cmp_flag(ordering == Some(Ordering::Equal));
+    cpu.set_flag(Flag::Zero, ordering == Some(Ordering::Equal));
+    cpu.set_flag(Flag::Carry, ordering == Some(Ordering::Less));
+    cpu.set_flag(Flag::Sign, ordering == Some(Ordering::Less));
+    cpu.set_flag(Flag::Overflow, ordering.is_none());
+    Ok(())
+}, a, b);
+
+operand_instruction!(Ftoi, | ftoi: Ftoi, computer: &mut Computer | {
+    let value = ftoi.source.read_float_from_computer(computer)?;
+    let bits = (ftoi.destination.size() as u32 * 8).min(CpuArchitecture::BITS);
+    let max = (1i32 << (bits - 1)) - 1;
+    
+    // NaN converts to 0
+    let final_value = (value as i32).clamp(-max - 1, max);
+    ftoi.destination.write_to_computer(computer, final_value as CpuArchitecture)
+}, destination, source);
+
+operand_instruction!(Fld, | fld: Fld, computer: &mut Computer | {
+    let address = fld.address.read_from_computer(computer)?;
+    let mut buffer = [0u8;size_of::<f32>()];
+    let bank = computer.cpu().get_bank();
+    computer.ram().read_bank_buffer_at(bank, address, &mut buffer)?;
+    
+    fld.destination.write_float_to_computer(computer, f32::from_ne_bytes(buffer))
+}, destination, address);
+
+operand_instruction!(Fst, | fst: Fst, computer: &mut Computer | {
+    let address = fst.address.read_from_computer(computer)?;
+    let value = fst.source.read_float_from_computer(computer)?;
+    let bank = computer.cpu().get_bank();
+    computer.ram_mut().write_bank_buffer_at(bank, address, &value.to_ne_bytes())?;
+    Ok(())
+}, address, source);
+
+operand_instruction!(In, | instruction: In, computer: &mut Computer | {
+    let port = instruction.port.read_from_computer(computer)?;
+    match computer.device_bus_mut().read(port) {
+        Some(value) => instruction.destination.write_to_computer(computer, value),
+        None => Err(InstructionError::with_message(InstructionErrorKind::PortNotConnected, format!("port: {}", port))),
+    }
+}, destination, port);
+
+operand_instruction!(Out, | instruction: Out, computer: &mut Computer | {
+    let port = instruction.port.read_from_computer(computer)?;
+    let value = instruction.source.read_from_computer(computer)?;
+    match computer.device_bus_mut().write(port, value) {
+        Some(_) => Ok(()),
+        None => Err(InstructionError::with_message(InstructionErrorKind::PortNotConnected, format!("port: {}", port))),
+    }
+}, port, source);
+
+macro_rules! step_instruction {
+    (
+        $operation_name:ident,
+        $subtract:literal
+    ) => {
+        operand_instruction!($operation_name, | operation: $operation_name, computer: &mut Computer | {
+            let value = operation.destination.read_from_computer(computer)?;
+            // like x86 the carry flag is kept so inc and dec can be used to count within adc and sbb loops
+            let carry = computer.cpu().get_flag(Flag::Carry);
+            let final_value = arithmetic_with_flags(computer, value, 1, false, operation.destination.size(), $subtract);
+            computer.cpu_mut().set_flag(Flag::Carry, carry);
+            
+            operation.destination.write_to_computer(computer, final_value)
+        }, destination);
+    };
+}
+
+step_instruction!(Inc, false);
+step_instruction!(Dec, true);
+
+operand_instruction!(Xchg, | xchg: Xchg, computer: &mut Computer | {
+    if !xchg.a.is_writable() || !xchg.b.is_writable() {
+        return Err(InstructionError::new(InstructionErrorKind::DestinationInvalid));
+    }
+    
+    let value = xchg.a.read_from_computer(computer)?;
+    let value2 = xchg.b.read_from_computer(computer)?;
+    
+    xchg.a.write_to_computer(computer, value2)?;
+    let result = xchg.b.write_to_computer(computer, value);
+    if result.is_err() {
+        // restores a so either both or neither of the values are swapped
+        xchg.a.write_to_computer(computer, value)?;
+    }
+    result
+}, a, b);
+
+operand_instruction!(Cas, | cas: Cas, computer: &mut Computer | {
+    if !cas.destination.is_writable() || !cas.expected.is_writable() {
+        return Err(InstructionError::new(InstructionErrorKind::DestinationInvalid));
+    }
+    
+    let value = cas.destination.read_from_computer(computer)?;
+    let expected = cas.expected.read_from_computer(computer)?;
+    let equal = value == expected;
+    if equal {
+        let source = cas.source.read_from_computer(computer)?;
+        cas.destination.write_to_computer(computer, source)?;
+    } else {
+        cas.expected.write_to_computer(computer, value)?;
+    }
+    computer.cpu_mut().set_flag(Flag::Zero, equal);
+    Ok(())
+}, destination, expected, source);
+
+operand_instruction!(Xadd, | xadd: Xadd, computer: &mut Computer | {
+    if !xadd.destination.is_writable() || !xadd.source.is_writable() {
+        return Err(InstructionError::new(InstructionErrorKind::DestinationInvalid));
+    }
+    
+    let value = xadd.destination.read_from_computer(computer)?;
+    let value2 = xadd.source.read_from_computer(computer)?;
+    let sum = arithmetic_with_flags(computer, value, value2, false, xadd.destination.size(), false);
+    
+    xadd.destination.write_to_computer(computer, sum)?;
+    let result = xadd.source.write_to_computer(computer, value);
+    if result.is_err() {
+        // restores destination so the exchange either fully happens or not at all
+        xadd.destination.write_to_computer(computer, value)?;
+    }
+    result
+}, destination, source);
+
+operand_instruction!(Rand, | rand: Rand, computer: &mut Computer | {
+    let value = computer.rng_mut().next() as CpuArchitecture;
+    rand.destination.write_to_computer(computer, value)
+}, destination);
+
+operand_instruction!(Call, | call:Call, computer:&mut Computer | {
+    let address = call.address.read_from_computer(computer)?;
+    computer.cpu_mut().call(address)?;
+    Ok(())
+}, address);
+
+impl Call {
+    pub fn const_function_binary_size() -> CpuArchitecture {
+        Literal::binary_size()
+    }
+}
+
+impl From<Operand> for Call {
+    fn from(value: Operand) -> Self {
+        Self { address: value }
+    }
+}
+
+operand_instruction!(Rcall, | rcall:Rcall, computer:&mut Computer | {
+    let address = rcall.target(computer)?;
+    computer.cpu_mut().call(address)?;
+    Ok(())
+}, offset);
+
+impl Rcall {
+    pub fn const_function_binary_size() -> CpuArchitecture {
+        Literal::binary_size()
+    }
+
+    /// the address of the function, the program counter has to point to the next instruction
+    pub fn target(self, computer: &mut Computer) -> Result<CpuArchitecture> {
+        let offset = self.offset.read_from_computer(computer)?;
+        Ok(computer.cpu().get_program_counter().wrapping_add(offset))
+    }
+}
+
+operand_instruction!(Tailcall, | tailcall:Tailcall, computer:&mut Computer | {
+    let address = tailcall.address.read_from_computer(computer)?;
+    computer.cpu_mut().tail_call(address);
+    Ok(())
+}, address);
+
+impl Tailcall {
+    pub fn const_function_binary_size() -> CpuArchitecture {
+        Literal::binary_size()
+    }
+}
+
+impl From<Operand> for Tailcall {
+    fn from(value: Operand) -> Self {
+        Self { address: value }
+    }
+}
+
+empty_instruction!(Nop, | _: &mut Computer | {
+    Ok(())
+});
+
+operand_instruction!(Memcpy, | memcpy:Memcpy, computer:&mut Computer | -> Result<()> {
+    let destination = memcpy.destination.read_from_computer(computer)?;
+    let source = memcpy.source.read_from_computer(computer)?;
+    let length = memcpy.length.read_from_computer(computer)?;
+    
+    let bank = computer.cpu().get_bank();
+    computer.ram_mut().copy_bank_within(bank, destination, source, length)?;
+    Ok(())
+}, destination, source, length);
+
+operand_instruction!(Memset, | memset:Memset, computer:&mut Computer | -> Result<()> {
+    let destination = memset.destination.read_from_computer(computer)?;
+    let value = memset.value.read_from_computer(computer)?;
+    let length = memset.length.read_from_computer(computer)?;
+    
+    let bank = computer.cpu().get_bank();
+    computer.ram_mut().fill_bank(bank, destination, value as u8, length)?;
+    Ok(())
+}, destination, value, length);
+
+operand_instruction!(Addr, | addr:Addr, computer:&mut Computer | -> Result<()> {
+    let address = addr.address.read_from_computer(computer)?;
+    addr.destination.write_to_computer(computer, address)
+}, destination, address);
+
+operand_instruction!(Int, | int:Int, computer:&mut Computer | -> Result<()> {
+    let interrupt = int.interrupt.read_from_computer(computer)?;
+    computer.interrupt(interrupt)
+}, interrupt);
+
+/// the index registers of lods (x9) and stos (x10)
+const SOURCE_INDEX_REGISTER: u8 = 8;
+const DESTINATION_INDEX_REGISTER: u8 = 9;
+
+/// moves the address in the index register past size bytes in the direction of the direction flag
+fn advance_index_register(computer: &mut Computer, register: Register, address: CpuArchitecture, size: CpuArchitecture) -> Result<()> {
+    let next = if computer.cpu().get_flag(Flag::Direction) {
+        address.wrapping_sub(size)
+    } else {
+        address.wrapping_add(size)
+    };
+    computer.cpu_mut().set_register(register, next)?;
+    Ok(())
+}
+
+operand_instruction!(Lods, | lods: Lods, computer: &mut Computer | {
+    // float registers and register pairs are rejected when writing to them
+    let size = lods.destination.size().clamp(1, size_of::<CpuArchitecture>() as CpuArchitecture);
+    let register = Register::new(SOURCE_INDEX_REGISTER, size_of::<CpuArchitecture>() as u8);
+    let address = computer.cpu().get_register(register)?;
+    
+    let value = Pointer::new(size.ilog2() as u8 + 1).get_pointed_to_value(address, computer)?;
+    lods.destination.write_to_computer(computer, value)?;
+    advance_index_register(computer, register, address, size)
+}, destination);
+
+operand_instruction!(Stos, | stos: Stos, computer: &mut Computer | {
+    let value = stos.source.read_from_computer(computer)?;
+    let size = stos.source.size();
+    let register = Register::new(DESTINATION_INDEX_REGISTER, size_of::<CpuArchitecture>() as u8);
+    let address = computer.cpu().get_register(register)?;
+    
+    Pointer::new(size.ilog2() as u8 + 1).set_pointed_to_value(address, computer, value)?;
+    advance_index_register(computer, register, address, size)
+}, source);
+
+empty_instruction!(Std, | computer: &mut Computer | {
+    computer.cpu_mut().set_flag(Flag::Direction, true);
+    Ok(())
+});
+
+empty_instruction!(Cld, | computer: &mut Computer | {
+    computer.cpu_mut().set_flag(Flag::Direction, false);
+    Ok(())
+});
+
+empty_instruction!(Clf, | computer: &mut Computer | {
+    computer.cpu_mut().set_cmp_flag(true);
+    Ok(())
+});
+
+empty_instruction!(Ret, | computer: &mut Computer | {
+    computer.cpu_mut().ret()?;
+    Ok(())
+});
+
+empty_instruction!(Iret, | computer: &mut Computer | {
+    computer.cpu_mut().iret()?;
+    Ok(())
+});
+
+operand_instruction!(Enter, | enter:Enter, computer:&mut Computer | {
+    let size = enter.size.read_from_computer(computer)?;
+    computer.cpu_mut().enter(size)?;
+    Ok(())
+}, size);
+
+empty_instruction!(Leave, | computer: &mut Computer | {
+    computer.cpu_mut().leave()?;
+    Ok(())
+});
+
+thread_local! {
+    pub static AWAITING_EVENT: Cell<bool> = const { Cell::new(false) };
+    pub static REDRAW: Cell<bool> = const { Cell::new(false) };
+    pub static SHOW_HUD: Cell<bool> = const { Cell::new(false) };
+    pub static WINDOW_OPEN: Cell<bool> = const { Cell::new(false) };
+    /// the pixels of the open window that the drawing syscalls draw into
+    pub static CANVAS: Cell<Option<Canvas>> = const { Cell::new(None) };
+    /// the moment the timer event is passed to the program when it is waiting using hlt
+    pub static WAKE_TIME: Cell<Option<Instant>> = const { Cell::new(None) };
+    /// the window events that haven't been taken by the program using GetWindowEvent yet
+    pub static WINDOW_EVENTS: RefCell<VecDeque<InputEvent>> = const { RefCell::new(VecDeque::new()) };
+}
+
+operand_instruction!(Hlt, | hlt:Hlt, computer:&mut Computer | -> Result<()> {
+    let timeout = Duration::from_millis(hlt.timeout.read_from_computer(computer)? as u64);
+    if !WINDOW_OPEN.get() {
+        std::thread::sleep(timeout);
+        return Ok(());
+    }
+    
+    // the program only waits when it has taken every event
+    if WINDOW_EVENTS.with_borrow(VecDeque::is_empty) {
+        AWAITING_EVENT.set(true);
+        WAKE_TIME.set((!timeout.is_zero()).then(| | { Instant::now() + timeout }));
+    }
+    Ok(())
+}, timeout);
+
+empty_instruction!(Syscall, | computer: &mut Computer | {
+    let register = Register::new(0, size_of::<CpuArchitecture>() as u8);
+    let function_number = computer.cpu().get_register(register).unwrap(); // cpu is expected to have 4 registers
+    let option: Option<SyscallFunction> = FromPrimitive::from_usize(function_number as usize);
+    
+    if let Some(permission) = option.as_ref().and_then(SyscallFunction::permission) {
+        if !SANDBOX.get().is_allowed(permission) {
+            return Err(InstructionError::with_message(InstructionErrorKind::PermissionDenied, format!("syscall: {}, allow it using {}", function_number, permission.flag())));
+        }
+    }
+    
+    match option {
+        Some(function) => match function {
+            SyscallFunction::Allocate => {
+                let alloc_amount_register = Register::new(1, size_of::<CpuArchitecture>() as u8); 
+                let alloc_amount = computer.cpu().get_register(alloc_amount_register).unwrap(); // same as above
+                
+                // SAFETY: no safety :( allocation happens within the emulator by the user
+                // or it will be deallocated when the program finishes
+                let pointer = unsafe { computer.ram_mut().alloc_unsafe(alloc_amount)? };
+                computer.cpu_mut().set_register(alloc_amount_register, pointer).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::Deallocate => {
+                let pointer_register = Register::new(1, size_of::<CpuArchitecture>() as u8); 
+                let pointer = computer.cpu().get_register(pointer_register).unwrap(); // same as above
+                
+                let option = if SANITIZE.get() {
+                    quarantine_allocation(computer, pointer)
+                } else {
+                    computer.ram_mut().dealloc(pointer)
+                };
+                computer.cpu_mut().set_register(pointer_register, option.unwrap_or(0)).unwrap(); // same as above
+                
+                Ok(())
+            },
+            SyscallFunction::Realloc => {
+                let pointer_register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let pointer = computer.cpu().get_register(pointer_register).unwrap(); // same as above
+                let length_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let length = computer.cpu().get_register(length_register).unwrap(); // same as above
+                
+                let pointer = computer.ram_mut().realloc(pointer, length)?;
+                computer.cpu_mut().set_register(pointer_register, pointer).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::MemInfo => {
+                let size = computer.ram().size();
+                let allocated = computer.ram().allocated_memory();
+                let largest_free_block = computer.ram().largest_free_block();
+                for (index, value) in [size, allocated, largest_free_block].into_iter().enumerate() {
+                    let register = Register::new(index as u8 + 1, size_of::<CpuArchitecture>() as u8);
+                    computer.cpu_mut().set_register(register, value).unwrap(); // same as above
+                }
+                Ok(())
+            },
+            SyscallFunction::Connect => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let pointer = computer.cpu().get_register(register).unwrap(); // same as above
+                let length_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let length = computer.cpu().get_register(length_register).unwrap(); // same as above
+                
+                let address = computer.ram().borrow_buffer_checked(pointer, length, | buffer | {
+                    String::from_utf8_lossy(buffer).into_owned()
+                })?;
+                let handle = computer.sockets_mut().connect(&address)
+                    .map_err(| err | { InstructionError::with_message(InstructionErrorKind::NetworkError, format!("address: {}, {}", address, err)) })?;
+                computer.cpu_mut().set_register(register, handle).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::Listen => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let port = computer.cpu().get_register(register).unwrap(); // same as above
+                
+                let handle = computer.sockets_mut().listen(port)
+                    .map_err(| err | { InstructionError::with_message(InstructionErrorKind::NetworkError, format!("port: {}, {}", port, err)) })?;
+                computer.cpu_mut().set_register(register, handle).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::Accept => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let handle = computer.cpu().get_register(register).unwrap(); // same as above
+                
+                let connection = computer.sockets_mut().accept(handle).map_err(create_network_error)?;
+                computer.cpu_mut().set_register(register, connection).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::Send => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let handle = computer.cpu().get_register(register).unwrap(); // same as above
+                let pointer_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let pointer = computer.cpu().get_register(pointer_register).unwrap(); // same as above
+                let length_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
+                let length = computer.cpu().get_register(length_register).unwrap(); // same as above
+                
+                let buffer = computer.ram().borrow_buffer_checked(pointer, length, | buffer | { buffer.to_vec() })?;
+                let sent = computer.sockets_mut().send(handle, &buffer).map_err(create_network_error)?;
+                computer.cpu_mut().set_register(register, sent as CpuArchitecture).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::Recv => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let handle = computer.cpu().get_register(register).unwrap(); // same as above
+                let pointer_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let pointer = computer.cpu().get_register(pointer_register).unwrap(); // same as above
+                let length_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
+                let length = computer.cpu().get_register(length_register).unwrap(); // same as above
+                
+                let mut buffer = vec![0u8; length as usize];
+                let received = computer.sockets_mut().receive(handle, &mut buffer).map_err(create_network_error)?;
+                computer.ram().write_buffer_at_checked(pointer, &buffer[..received])?;
+                computer.cpu_mut().set_register(register, received as CpuArchitecture).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::Close => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let handle = computer.cpu().get_register(register).unwrap(); // same as above
+                
+                computer.sockets_mut().close(handle).map_err(create_network_error)
+            },
+            SyscallFunction::Tone => {
+                let mut arguments = [0; 4];
+                for (index, argument) in arguments.iter_mut().enumerate() {
+                    let register = Register::new(index as u8 + 1, size_of::<CpuArchitecture>() as u8);
+                    *argument = computer.cpu().get_register(register).unwrap(); // same as above
+                }
+                let [channel, frequency, volume, duration] = arguments;
+                
+                let channel = SoundChannel::from_number(channel)
+                    .ok_or_else(| | { InstructionError::with_message(InstructionErrorKind::InvalidSoundChannel, format!("channel: {}", channel)) })?;
+                if let Some(audio) = computer.audio() {
+                    audio.play_tone(channel, frequency, volume.min(u8::MAX as CpuArchitecture) as u8, Duration::from_millis(duration as u64));
+                }
+                Ok(())
+            },
+            SyscallFunction::PlaySamples => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let pointer = computer.cpu().get_register(register).unwrap(); // same as above
+                let length_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let length = computer.cpu().get_register(length_register).unwrap(); // same as above
+                
+                let samples = computer.ram().borrow_buffer_checked(pointer, length, | buffer | { buffer.to_vec() })?;
+                if let Some(audio) = computer.audio() {
+                    audio.play_samples(samples);
+                }
+                Ok(())
+            },
+            SyscallFunction::DrawLine | SyscallFunction::DrawRect | SyscallFunction::FillRect | SyscallFunction::DrawCircle => {
+                let mut arguments = [0; 6];
+                for (index, argument) in arguments.iter_mut().enumerate() {
+                    let register = Register::new(index as u8 + 1, size_of::<CpuArchitecture>() as u8);
+                    *argument = computer.cpu().get_register(register).unwrap(); // same as above
+                }
+                let canvas = CANVAS.get().ok_or_else(| | { InstructionError::new(InstructionErrorKind::WindowNotCreated) })?;
+                
+                // the positions are signed so the shapes can start outside of the canvas
+                let signed = | value: CpuArchitecture | { value as SignedCpuArchitecture as i32 };
+                let color = | low: CpuArchitecture, high: CpuArchitecture | {
+                    let [red, green] = low.to_le_bytes();
+                    let [blue, alpha] = high.to_le_bytes();
+                    [red, green, blue, alpha]
+                };
+                let [x, y, a, b, c, d] = arguments;
+                let ram = computer.ram_mut();
+                match function {
+                    SyscallFunction::DrawLine => canvas.draw_line(ram, (signed(x), signed(y)), (signed(a), signed(b)), color(c, d))?,
+                    SyscallFunction::DrawRect => canvas.draw_rect(ram, (signed(x), signed(y)), (a as i32, b as i32), color(c, d))?,
+                    SyscallFunction::FillRect => canvas.fill_rect(ram, (signed(x), signed(y)), (a as i32, b as i32), color(c, d))?,
+                    _ => canvas.draw_circle(ram, (signed(x), signed(y)), a as i32, color(b, c))?,
+                }
+                Ok(())
+            },
+            SyscallFunction::Print => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8); // same as above
+                let pointer = computer.cpu().get_register(register)?;
+                let register =  Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let length = computer.cpu().get_register(register)?;
+                
+                let error = computer.ram().borrow_buffer_checked(pointer, length, Computer::print_bytes)?;
+                
+                match error {
+                    Ok(_) => Ok(()),
+                    Err(err) => Err(InstructionError::with_message(InstructionErrorKind::PrintError, err.to_string()))
+                }
+            },
+            SyscallFunction::GetTimeMs | SyscallFunction::GetUnixTime => {
+                let low_register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let high_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                
+                let time = match function {
+                    SyscallFunction::GetTimeMs => computer.elapsed().as_millis() as u32,
+                    _ => SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, | duration | { duration.as_secs() as u32 }),
+                };
+                computer.cpu_mut().set_register(low_register, time as CpuArchitecture).unwrap(); // same as above
+                computer.cpu_mut().set_register(high_register, (time >> CpuArchitecture::BITS) as CpuArchitecture).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::PrintString => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let pointer = computer.cpu().get_register(register).unwrap(); // same as above
+                let register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let length = computer.cpu().get_register(register).unwrap(); // same as above
+                
+                let error = computer.ram().borrow_buffer_checked(pointer, length, Computer::print_text)?;
+                error.map_err(| err | { InstructionError::with_message(InstructionErrorKind::PrintError, err.to_string()) })
+            },
+            SyscallFunction::PrintInt => {
+                let value_register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let value = computer.cpu().get_register(value_register).unwrap(); // same as above
+                let base_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let base = computer.cpu().get_register(base_register).unwrap(); // same as above
+                let signed_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
+                let signed = computer.cpu().get_register(signed_register).unwrap() != 0; // same as above
+                
+                let negative = signed && (value as SignedCpuArchitecture) < 0;
+                let magnitude = if negative { (value as SignedCpuArchitecture).unsigned_abs() } else { value };
+                let Some(digits) = format_number(magnitude, base) else {
+                    computer.cpu_mut().set_register(value_register, CpuArchitecture::MAX).unwrap(); // same as above
+                    return Ok(());
+                };
+                
+                let text = if negative { format!("-{}", digits) } else { digits };
+                Computer::print_text(text.as_bytes())
+                    .map_err(| err | { InstructionError::with_message(InstructionErrorKind::PrintError, err.to_string()) })
+            },
+            SyscallFunction::GetEnv => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let pointer = computer.cpu().get_register(register).unwrap(); // same as above
+                let length_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let length = computer.cpu().get_register(length_register).unwrap(); // same as above
+                let out_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
+                let out_pointer = computer.cpu().get_register(out_register).unwrap(); // same as above
+                let capacity_register = Register::new(4, size_of::<CpuArchitecture>() as u8);
+                let capacity = computer.cpu().get_register(capacity_register).unwrap(); // same as above
+                
+                let name = computer.ram().borrow_buffer_checked(pointer, length, | buffer | {
+                    String::from_utf8_lossy(buffer).into_owned()
+                })?;
+                if !is_env_var_allowed(&name) {
+                    return Err(InstructionError::with_message(InstructionErrorKind::PermissionDenied, format!("variable: {}, allow it using {} or --allow-env-var {}", name, Permission::Env.flag(), name)));
+                }
+                
+                // a value longer than the memory cannot be copied and is handled like a missing variable
+                let value = std::env::var(&name).ok().filter(| value | { value.len() < CpuArchitecture::MAX as usize });
+                let Some(value) = value else {
+                    computer.cpu_mut().set_register(register, CpuArchitecture::MAX).unwrap(); // same as above
+                    return Ok(());
+                };
+                
+                let copied = value.len().min(capacity as usize);
+                computer.ram().write_buffer_at_checked(out_pointer, &value.as_bytes()[..copied])?;
+                computer.cpu_mut().set_register(register, value.len() as CpuArchitecture).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::CreateWindow => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let pointer = computer.cpu().get_register(register).unwrap(); // same as above
+                
+                let (size, window_name) = if pointer != 0 {
+                    let register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                    let length = computer.cpu().get_register(register).unwrap(); // same as above
+                    
+                    let window_name = computer.ram().borrow_buffer_checked(pointer, length, | buffer | {
+                        let mut str = String::with_capacity(buffer.len());
+                        for b in buffer {
+                            str.push(*b as char);
+                        }
+                        
+                        str
+                    })?;
+                    
+                    let width_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
+                    let width = computer.cpu().get_register(width_register).unwrap(); // same as above
+                    
+                    let height_register = Register::new(4, size_of::<CpuArchitecture>() as u8);
+                    let height = computer.cpu().get_register(height_register)?;
+                    
+                    ((width, height), window_name)
+                } else {
+                    let width_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                    let width = computer.cpu().get_register(width_register).unwrap(); // same as above
+                    
+                    let height_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
+                    let height = computer.cpu().get_register(height_register).unwrap(); // same as above
+                    
+                    ((width, height), String::new())
+                };
+                let canvas_size = (size.0 as usize, size.1 as usize);
+                
+                // an unnamed window uses the %title of the program
+                let window_name = if window_name.is_empty() {
+                    computer.title().unwrap_or_default().to_string()
+                } else {
+                    window_name
+                };
+                let window_name_option = if window_name.is_empty() {
+                    None
+                } else {
+                    Some(window_name.as_str())
+                };
+                
+                Window::run(canvas_size, window_name_option, computer, register)
+            },
+            SyscallFunction::GetWindowEvent => {
+                let event = WINDOW_EVENTS.with_borrow_mut(VecDeque::pop_front);
+                let (event_number, data) = event.map_or((NO_EVENT, None), InputEvent::to_registers);
+                if let Some([first, second]) = data {
+                    computer.cpu_mut().set_register(Register::new(2, size_of::<CpuArchitecture>() as u8), first)?;
+                    computer.cpu_mut().set_register(Register::new(3, size_of::<CpuArchitecture>() as u8), second)?;
+                }
+                computer.cpu_mut().set_register(Register::new(1, size_of::<CpuArchitecture>() as u8), event_number)?;
+                Ok(())
+            },
+            SyscallFunction::Redraw => {
+                REDRAW.set(true);
+                Ok(())
+            },
+            SyscallFunction::ToggleHud => {
+                SHOW_HUD.set(!SHOW_HUD.get());
+                Ok(())
+            },
+            SyscallFunction::SetInterruptTable => {
+                let table_register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let count_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let table = computer.cpu().get_register(table_register).unwrap(); // same as above
+                let count = computer.cpu().get_register(count_register).unwrap(); // same as above
+                
+                computer.interrupts_mut().set_table(table, count);
+                Ok(())
+            },
+            SyscallFunction::SetTimer => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let interval = computer.cpu().get_register(register).unwrap(); // same as above
+                
+                computer.interrupts_mut().set_timer(Duration::from_millis(interval as u64));
+                Ok(())
+            },
+            SyscallFunction::SetOverflowTrap => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let mode = computer.cpu().get_register(register).unwrap(); // same as above
+                
+                let previous = match FromPrimitive::from_u16(mode) {
+                    Some(overflow_trap) => {
+                        let previous = computer.cpu().overflow_trap();
+                        computer.cpu_mut().set_overflow_trap(overflow_trap);
+                        previous as CpuArchitecture
+                    },
+                    None => CpuArchitecture::MAX,
+                };
+                computer.cpu_mut().set_register(register, previous).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::Itoa => {
+                let value_register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let value = computer.cpu().get_register(value_register).unwrap(); // same as above
+                let base_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let base = computer.cpu().get_register(base_register).unwrap(); // same as above
+                let pointer_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
+                let pointer = computer.cpu().get_register(pointer_register).unwrap(); // same as above
+                
+                let length = match format_number(value, base) {
+                    Some(digits) => {
+                        computer.ram().write_buffer_at_checked(pointer, digits.as_bytes())?;
+                        digits.len() as CpuArchitecture
+                    },
+                    None => CpuArchitecture::MAX,
+                };
+                computer.cpu_mut().set_register(value_register, length).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::Sin | SyscallFunction::Cos => {
+                let angle_register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let angle = computer.cpu().get_register(angle_register).unwrap(); // same as above
+                let scale_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let scale = computer.cpu().get_register(scale_register).unwrap(); // same as above
+                
+                let radians = (angle as i16 as f64).to_radians();
+                let ratio = match function {
+                    SyscallFunction::Sin => radians.sin(),
+                    _ => radians.cos(),
+                };
+                // saturates to the signed range as the scale is unsigned
+                let value = (ratio * scale as f64).round() as i16;
+                computer.cpu_mut().set_register(angle_register, value as CpuArchitecture).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::Atoi => {
+                let pointer_register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let pointer = computer.cpu().get_register(pointer_register).unwrap(); // same as above
+                let length_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let length = computer.cpu().get_register(length_register).unwrap(); // same as above
+                
+                let number = computer.ram().borrow_buffer_checked(pointer, length, parse_number)?;
+                let (value, status) = match number {
+                    Some(value) => (value, 0),
+                    None => (0, CpuArchitecture::MAX),
+                };
+                computer.cpu_mut().set_register(pointer_register, value).unwrap(); // same as above
+                computer.cpu_mut().set_register(length_register, status).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::SortBytes => {
+                let (pointer, element_size, length) = read_elements_registers(computer)?;
+                if element_size == 0 {
+                    return Ok(());
+                }
+                
+                let sorted = computer.ram().borrow_buffer_checked(pointer, length, | buffer | {
+                    let mut elements: Vec<_> = buffer.chunks_exact(element_size as usize).collect();
+                    elements.sort_by(| a, b | { compare_elements(a, b) });
+                    elements.concat()
+                })?;
+                computer.ram().write_buffer_at_checked(pointer, &sorted)?;
+                Ok(())
+            },
+            SyscallFunction::BinarySearch => {
+                let (pointer, element_size, length) = read_elements_registers(computer)?;
+                let key_register = Register::new(4, size_of::<CpuArchitecture>() as u8);
+                let key_pointer = computer.cpu().get_register(key_register).unwrap(); // same as above
+                
+                let key = computer.ram().borrow_buffer_checked(key_pointer, element_size, | buffer | { buffer.to_vec() })?;
+                let index = if element_size == 0 {
+                    None
+                } else {
+                    computer.ram().borrow_buffer_checked(pointer, length, | buffer | {
+                        buffer.chunks_exact(element_size as usize).collect::<Vec<_>>()
+                            .binary_search_by(| element | { compare_elements(element, &key) }).ok()
+                    })?
+                };
+                
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                computer.cpu_mut().set_register(register, index.map_or(CpuArchitecture::MAX, | index | { index as CpuArchitecture })).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::Hash => {
+                let pointer_register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let pointer = computer.cpu().get_register(pointer_register).unwrap(); // same as above
+                let length_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let length = computer.cpu().get_register(length_register).unwrap(); // same as above
+                let algorithm_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
+                let algorithm = computer.cpu().get_register(algorithm_register).unwrap(); // same as above
+                
+                let hash = match algorithm {
+                    0 => Some(computer.ram().borrow_buffer_checked(pointer, length, crc32)?),
+                    1 => Some(computer.ram().borrow_buffer_checked(pointer, length, fnv1a)?),
+                    _ => None,
+                };
+                let (hash, status) = match hash {
+                    Some(hash) => (hash, 0),
+                    None => (0, CpuArchitecture::MAX),
+                };
+                computer.cpu_mut().set_register(pointer_register, hash as CpuArchitecture).unwrap(); // same as above
+                computer.cpu_mut().set_register(length_register, (hash >> CpuArchitecture::BITS) as CpuArchitecture).unwrap(); // same as above
+                computer.cpu_mut().set_register(algorithm_register, status).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::Compress | SyscallFunction::Decompress => {
+                let source_register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let source = computer.cpu().get_register(source_register).unwrap(); // same as above
+                let source_length_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let source_length = computer.cpu().get_register(source_length_register).unwrap(); // same as above
+                let destination_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
+                let destination = computer.cpu().get_register(destination_register).unwrap(); // same as above
+                let capacity_register = Register::new(4, size_of::<CpuArchitecture>() as u8);
+                let capacity = computer.cpu().get_register(capacity_register).unwrap(); // same as above
+                
+                let result = computer.ram().borrow_buffer_checked(source, source_length, | buffer | {
+                    match function {
+                        SyscallFunction::Compress => Some(compress_to_vec(buffer, COMPRESSION_LEVEL)),
+                        _ => decompress_to_vec_with_limit(buffer, capacity as usize).ok(),
+                    }
+                })?;
+                let length = match result.filter(| bytes | { bytes.len() <= capacity as usize }) {
+                    Some(bytes) => {
+                        computer.ram().write_buffer_at_checked(destination, &bytes)?;
+                        bytes.len() as CpuArchitecture
+                    },
+                    None => CpuArchitecture::MAX,
+                };
+                computer.cpu_mut().set_register(source_register, length).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::Exec => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let pointer = computer.cpu().get_register(register).unwrap(); // same as above
+                let length_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let length = computer.cpu().get_register(length_register).unwrap(); // same as above
+                let isolate_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
+                let isolate = computer.cpu().get_register(isolate_register).unwrap() != 0; // same as above
+                
+                let path = computer.ram().borrow_buffer_checked(pointer, length, | buffer | {
+                    String::from_utf8_lossy(buffer).into_owned()
+                })?;
+                
+                let exit_code = execute_sub_program(computer, &path, isolate)?;
+                computer.cpu_mut().set_register(register, exit_code).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::ShmCreate => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let size = computer.cpu().get_register(register).unwrap(); // same as above
+                
+                let key = create_shared_memory(size)?;
+                computer.cpu_mut().set_register(register, key).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::ShmAttach => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let key = computer.cpu().get_register(register).unwrap(); // same as above
+                
+                let option = get_shared_memory(key);
+                let memory = match option {
+                    Some(memory) => memory,
+                    None => return Err(InstructionError::with_message(InstructionErrorKind::SharedMemoryNotFound, format!("key: {}", key))),
+                };
+                
+                let bank = computer.ram_mut().attach_bank(memory)?;
+                computer.cpu_mut().set_register(register, bank as CpuArchitecture).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::MsgSend => {
+                let queue_register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let queue = computer.cpu().get_register(queue_register).unwrap(); // same as above
+                let pointer_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let pointer = computer.cpu().get_register(pointer_register).unwrap(); // same as above
+                let length_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
+                let length = computer.cpu().get_register(length_register).unwrap(); // same as above
+                
+                let message = computer.ram().borrow_buffer_checked(pointer, length, | buffer | { buffer.to_vec() })?;
+                send_message(queue, message);
+                Ok(())
+            },
+            SyscallFunction::MsgReceive => {
+                let queue_register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let queue = computer.cpu().get_register(queue_register).unwrap(); // same as above
+                let pointer_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let pointer = computer.cpu().get_register(pointer_register).unwrap(); // same as above
+                let length_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
+                let max_length = computer.cpu().get_register(length_register).unwrap(); // same as above
+                
+                // the full length of the message is returned, the message is truncated if the buffer is too small
+                let length = match receive_message(queue) {
+                    Some(message) => {
+                        let copy_length = message.len().min(max_length as usize);
+                        computer.ram().write_buffer_at_checked(pointer, &message[..copy_length])?;
+                        message.len() as CpuArchitecture
+                    },
+                    None => CpuArchitecture::MAX,
+                };
+                computer.cpu_mut().set_register(queue_register, length).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::FileOpen => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let pointer = computer.cpu().get_register(register).unwrap(); // same as above
+                let length_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let length = computer.cpu().get_register(length_register).unwrap(); // same as above
+                let create_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
+                let create = computer.cpu().get_register(create_register).unwrap() != 0; // same as above
+                
+                let name = computer.ram().borrow_buffer_checked(pointer, length, | buffer | {
+                    String::from_utf8_lossy(buffer).into_owned()
+                })?;
+                
+                // the files of the filesystem image are always allowed, the files of the host are not
+                if computer.files_mut().uses_host() && !SANDBOX.get().is_allowed(Permission::Fs) {
+                    return Err(InstructionError::with_message(InstructionErrorKind::PermissionDenied, format!("syscall: {}, allow it using {} or use --fs", function_number, Permission::Fs.flag())));
+                }
+                
+                let handle = computer.files_mut().open(&name, create).map_err(create_file_error)?;
+                computer.cpu_mut().set_register(register, handle).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::FileRead => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let handle = computer.cpu().get_register(register).unwrap(); // same as above
+                let pointer_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let pointer = computer.cpu().get_register(pointer_register).unwrap(); // same as above
+                let length_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
+                let length = computer.cpu().get_register(length_register).unwrap(); // same as above
+                
+                let mut buffer = vec![0u8; length as usize];
+                let read = computer.files_mut().read(handle, &mut buffer).map_err(create_file_error)?;
+                computer.ram().write_buffer_at_checked(pointer, &buffer[..read])?;
+                computer.cpu_mut().set_register(register, read as CpuArchitecture).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::FileWrite => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let handle = computer.cpu().get_register(register).unwrap(); // same as above
+                let pointer_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let pointer = computer.cpu().get_register(pointer_register).unwrap(); // same as above
+                let length_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
+                let length = computer.cpu().get_register(length_register).unwrap(); // same as above
+                
+                let buffer = computer.ram().borrow_buffer_checked(pointer, length, | buffer | { buffer.to_vec() })?;
+                let written = computer.files_mut().write(handle, &buffer).map_err(create_file_error)?;
+                computer.cpu_mut().set_register(register, written as CpuArchitecture).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::FileSeek => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let handle = computer.cpu().get_register(register).unwrap(); // same as above
+                let position_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+                let position = computer.cpu().get_register(position_register).unwrap(); // same as above
+                
+                let position = computer.files_mut().seek(handle, position as u32).map_err(create_file_error)?;
+                computer.cpu_mut().set_register(register, position as CpuArchitecture).unwrap(); // same as above
+                Ok(())
+            },
+            SyscallFunction::FileClose => {
+                let register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+                let handle = computer.cpu().get_register(register).unwrap(); // same as above
+                
+                computer.files_mut().close(handle).map_err(create_file_error)
+            }
+        },
+        None => Err(InstructionError::with_message(InstructionErrorKind::SyscallFunctionNotFound, format!("got: {}", function_number)))
+    }
+});
+
+// the doc comments are used as the syscall documentation, arguments are passed in x2 and onwards
+#[derive(FromPrimitive, ToPrimitive, EnumIter, EnumMessage, IntoStaticStr)]
+enum SyscallFunction {
+    /// allocates x2 bytes, x2 = pointer to the memory
+    Allocate = 0,
+    /// deallocates the memory at pointer x2, x2 = the amount of bytes deallocated or 0
+    Deallocate = 1,
+    /// prints the x3 bytes at pointer x2
+    Print = 2,
+    /// opens a window named by the x3 bytes at pointer x2 with width x4 and height x5, if x2 is 0 the window is named by the %title
+    /// of the program and x3 and x4 are the width and height
+    CreateWindow = 3,
+    /// takes the oldest queued window event, x2 = the event: 0 close, 1 cursor (x3 = x, x4 = y), 2 mouse (x3 = pressed,
+    /// x4 = button), 3 key (x3 = character, x4 = down), 4 timer, 65535 other or 5 when no event is queued, use hlt to
+    /// wait for the next event
+    GetWindowEvent = 4,
+    /// redraws the window
+    Redraw = 5,
+    /// runs the program at the path of x3 bytes at pointer x2, x4 != 0 gives it its own memory, x2 = exit code, requires --allow-fs,
+    /// when the memory is shared the allocations of the program are freed once it exits
+    Exec = 6,
+    /// creates x2 bytes of shared memory, x2 = key
+    ShmCreate = 7,
+    /// attaches the shared memory with key x2 as a memory bank, x2 = bank
+    ShmAttach = 8,
+    /// sends the x4 bytes at pointer x3 to queue x2
+    MsgSend = 9,
+    /// receives a message from queue x2 into pointer x3 of at most x4 bytes, x2 = message length or 65535 if the queue is empty
+    MsgReceive = 10,
+    /// opens the file named by the x3 bytes at pointer x2, x4 != 0 creates or truncates it, x2 = handle,
+    /// the file is in the --fs image or on the host when no image is given, which requires --allow-fs
+    FileOpen = 11,
+    /// reads at most x4 bytes from handle x2 into pointer x3, x2 = bytes read
+    FileRead = 12,
+    /// writes the x4 bytes at pointer x3 to handle x2, x2 = bytes written
+    FileWrite = 13,
+    /// sets the position of handle x2 to x3, x2 = new position
+    FileSeek = 14,
+    /// closes handle x2
+    FileClose = 15,
+    /// shows or hides the fps, instructions per frame and heap usage on top of the window after the next redraw
+    ToggleHud = 16,
+    /// uses the x3 handler addresses at pointer x2 as the vector table, a pointer of 0 removes it,
+    /// handlers 0 to 7 are called on a divide by zero, a segmentation fault, the timer, an invalid instruction, the alarm
+    /// of the clock device, an arithmetic overflow, the watchdog and a finished dma transfer, the others by int
+    SetInterruptTable = 17,
+    /// calls the timer interrupt handler every x2 milliseconds, 0 stops the timer
+    SetTimer = 18,
+    /// writes x2 as digits of base x3 (2 to 36) to pointer x4, x2 = the amount of digits written or 65535 if the base is invalid
+    Itoa = 19,
+    /// reads the decimal number, optionally starting with -, of the x3 bytes at pointer x2, x2 = the number and x3 = 0 or 65535 if it isn't a number
+    Atoi = 20,
+    /// sorts the x3 elements of x4 bytes at pointer x2 from small to large, the elements are compared as little endian unsigned numbers
+    SortBytes = 21,
+    /// searches the x3 sorted elements of x4 bytes at pointer x2 for the element at pointer x5, x2 = its index or 65535 if it isn't found
+    BinarySearch = 22,
+    /// hashes the x3 bytes at pointer x2 using crc32 if x4 is 0 or 32 bit fnv-1a if x4 is 1,
+    /// x2 = the lower and x3 = the upper 16 bits of the hash and x4 = 0 or 65535 if the algorithm doesn't exist
+    Hash = 23,
+    /// deflates the x3 bytes at pointer x2 into pointer x4 of at most x5 bytes, x2 = the compressed length or 65535 if it doesn't fit
+    Compress = 24,
+    /// inflates the x3 deflated bytes at pointer x2 into pointer x4 of at most x5 bytes,
+    /// x2 = the decompressed length or 65535 if it doesn't fit or isn't deflated data
+    Decompress = 25,
+    /// x2 = the sine of the signed angle in degrees in x2 times the scale in x3 rounded to a signed number, eg: a radius
+    Sin = 26,
+    /// x2 = the cosine of the signed angle in degrees in x2 times the scale in x3 rounded to a signed number, eg: a radius
+    Cos = 27,
+    /// makes add, sub, adc, sbb and mul raise the ArithmeticOverflow fault instead of wrapping when x2 is 1 for unsigned or
+    /// 2 for signed results, 0 turns it off, x2 = the previous mode or 65535 if the mode doesn't exist
+    SetOverflowTrap = 28,
+    /// prints the x3 bytes at pointer x2 as text, without adding a newline
+    PrintString = 29,
+    /// prints x2 as digits of base x3 (2 to 36), eg: 10 for decimal or 16 for hexadecimal, x4 != 0 prints it as a signed number,
+    /// x2 = 65535 if the base is invalid
+    PrintInt = 30,
+    /// x2 = the lower and x3 = the upper 16 bits of the milliseconds since the program started
+    GetTimeMs = 31,
+    /// x2 = the lower and x3 = the upper 16 bits of the seconds since the 1st of january 1970 in UTC
+    GetUnixTime = 32,
+    /// copies up to x5 bytes of the host environment variable named by the x3 bytes at pointer x2 to pointer x4,
+    /// x2 = the length of the value or 65535 if the variable doesn't exist, requires --allow-env or --allow-env-var
+    GetEnv = 33,
+    /// resizes the memory at pointer x2 to x3 bytes, keeping its contents, x2 = the pointer to the memory which has moved
+    /// if there was no free memory after it
+    Realloc = 34,
+    /// x2 = the size of the memory, x3 = the amount of bytes allocated and x4 = the largest amount of bytes that can be
+    /// allocated at once without growing the memory
+    MemInfo = 35,
+    /// connects to the address of the x3 bytes at pointer x2 using tcp, eg: "127.0.0.1:8080", x2 = handle,
+    /// requires --allow-net
+    Connect = 36,
+    /// listens for tcp connections on port x2 of localhost, x2 = handle, requires --allow-net
+    Listen = 37,
+    /// waits for a connection on the listening handle x2, x2 = handle of the connection, requires --allow-net
+    Accept = 38,
+    /// sends the x4 bytes at pointer x3 to the connection of handle x2, x2 = bytes sent, requires --allow-net
+    Send = 39,
+    /// waits for at most x4 bytes from the connection of handle x2 into pointer x3, x2 = bytes received or 0 when the
+    /// connection has been closed, requires --allow-net
+    Recv = 40,
+    /// closes the socket of handle x2, requires --allow-net
+    Close = 41,
+    /// plays a tone on channel x2, 0 = square and 1 = noise, with frequency x3 in hertz, volume x4 from 0 to 255 and
+    /// duration x5 in milliseconds, replacing the tone that was playing on the channel, a volume of 0 stops it
+    Tone = 42,
+    /// queues the x3 unsigned 8 bit pcm samples at pointer x2, played at 22050 samples per second after the samples
+    /// that are still queued
+    PlaySamples = 43,
+    /// draws a line from x2, x3 to x4, x5 into the canvas of the window with the color x6 = red | green << 8 and
+    /// x7 = blue | alpha << 8, the coordinates are signed and the parts outside of the canvas are clipped, use Redraw to show it
+    DrawLine = 44,
+    /// draws the outline of a rectangle with its top left corner at x2, x3, width x4 and height x5 with the color in x6 and x7
+    /// like DrawLine
+    DrawRect = 45,
+    /// fills a rectangle with its top left corner at x2, x3, width x4 and height x5 with the color in x6 and x7 like DrawLine
+    FillRect = 46,
+    /// draws the outline of a circle around x2, x3 with radius x4 with the color x5 = red | green << 8 and
+    /// x6 = blue | alpha << 8, like DrawLine
+    DrawCircle = 47,
+}
+
+/// the name, number and documentation of every syscall
+pub fn syscall_documentation() -> impl Iterator<Item = (&'static str, CpuArchitecture, &'static str)> {
+    SyscallFunction::iter().map(| function | {
+        let documentation = function.get_documentation().unwrap_or_default();
+        let name: &'static str = (&function).into();
+        (name, function as CpuArchitecture, documentation)
+    })
+}
+
+impl SyscallFunction {
+    /// the host access that the syscall requires
+    fn permission(&self) -> Option<Permission> {
+        match self {
+            SyscallFunction::Exec => Some(Permission::Fs),
+            SyscallFunction::Connect | SyscallFunction::Listen | SyscallFunction::Accept |
+            SyscallFunction::Send | SyscallFunction::Recv | SyscallFunction::Close => Some(Permission::Net),
+            _ => None,
+        }
+    }
+}
+
+/// the digits of the value in the base, None if the base isn't between 2 and 36
+fn format_number(mut value: CpuArchitecture, base: CpuArchitecture) -> Option<String> {
+    if !(2..=36).contains(&base) {
+        return None;
+    }
+    
+    let mut digits = Vec::new();
+    loop {
+        digits.push(char::from_digit((value % base) as u32, base as u32)?);
+        value /= base;
+        if value == 0 {
+            break;
+        }
+    }
+    Some(digits.iter().rev().collect())
+}
+
+/// parses the bytes as a decimal number, negative numbers are returned as their two's complement
+fn parse_number(buffer: &[u8]) -> Option<CpuArchitecture> {
+    let str = std::str::from_utf8(buffer).ok()?;
+    match str.strip_prefix('-') {
+        Some(_) => SignedCpuArchitecture::from_str(str).ok().map(| value | { value as CpuArchitecture }),
+        None => CpuArchitecture::from_str(str).ok(),
+    }
+}
+
+/// reads the pointer, element size and the length in bytes of the elements of SortBytes and BinarySearch
+fn read_elements_registers(computer: &Computer) -> Result<(CpuArchitecture, CpuArchitecture, CpuArchitecture)> {
+    let pointer_register = Register::new(1, size_of::<CpuArchitecture>() as u8);
+    let pointer = computer.cpu().get_register(pointer_register).unwrap(); // cpu is expected to have 4 registers
+    let count_register = Register::new(2, size_of::<CpuArchitecture>() as u8);
+    let count = computer.cpu().get_register(count_register).unwrap(); // same as above
+    let size_register = Register::new(3, size_of::<CpuArchitecture>() as u8);
+    let element_size = computer.cpu().get_register(size_register).unwrap(); // same as above
+    
+    match count.checked_mul(element_size) {
+        Some(length) => Ok((pointer, element_size, length)),
+        None => Err(InstructionError::with_message(InstructionErrorKind::RamError(RamError::new(RamErrorKind::IndexOutOfBounds)), format!("{} elements of {} bytes", count, element_size))),
+    }
+}
+
+/// compares the elements as little endian unsigned numbers of the same size
+fn compare_elements(a: &[u8], b: &[u8]) -> Ordering {
+    a.iter().rev().cmp(b.iter().rev())
+}
+
+/// the highest compression level as the buffers are small
+const COMPRESSION_LEVEL: u8 = 9;
+
+/// the crc32 used by zip and png
+fn crc32(buffer: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+    for byte in buffer {
+        crc ^= *byte as u32;
+        for _ in 0..u8::BITS {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+    !crc
+}
+
+fn fnv1a(buffer: &[u8]) -> u32 {
+    buffer.iter().fold(0x811C9DC5, | hash: u32, byte | { (hash ^ *byte as u32).wrapping_mul(0x01000193) })
+}
+
+fn create_file_error(err: std::io::Error) -> InstructionError {
+    InstructionError::with_message(InstructionErrorKind::FileError, err.to_string())
+}
+
+fn create_network_error(err: std::io::Error) -> InstructionError {
+    InstructionError::with_message(InstructionErrorKind::NetworkError, err.to_string())
+}
+
+/// keeps the deallocated allocation allocated so the sanitizer can find uses of it, the oldest allocations in quarantine
+/// are deallocated when the quarantine is full, returns the amount of bytes deallocated
+fn quarantine_allocation(computer: &mut Computer, pointer: CpuArchitecture) -> Option<CpuArchitecture> {
+    let range = computer.ram().allocated_ranges().into_iter().find(| range | { range.start == pointer })?;
+    let size = computer.ram().size();
+    let sanitizer = computer.cpu_mut().sanitizer_mut()?;
+    if sanitizer.is_quarantined(pointer) {
+        sanitizer.on_double_deallocation(pointer);
+        return None;
+    }
+    
+    let released = sanitizer.quarantine(range.clone(), size);
+    for pointer in released {
+        computer.ram_mut().dealloc(pointer);
+    }
+    Some(range.end - range.start)
+}
+
+const MAX_EXEC_DEPTH: u8 = 16;
+
+thread_local! {
+    static EXEC_DEPTH: Cell<u8> = const { Cell::new(0) };
+}
+
+/// runs the program at the path on a new cpu, the ram is either shared with the computer or a new ram of the same size
+fn execute_sub_program(computer: &mut Computer, path: &str, isolate: bool) -> Result<CpuArchitecture> {
+    let depth = EXEC_DEPTH.get();
+    if depth >= MAX_EXEC_DEPTH {
+        return Err(InstructionError::with_message(InstructionErrorKind::ExecError, format!("programs cannot be nested more than {} times", MAX_EXEC_DEPTH)));
+    }
+    
+    let result = load_program(Path::new(path));
+    let program = match result {
+        Ok(program) => program,
+        Err(err) => return Err(InstructionError::with_message(InstructionErrorKind::ExecError, format!("could not load program: {}, filename: {}", err, path))),
+    };
+    
+    let ram = if isolate {
+        let mut ram = Ram::with_maximum(computer.ram().size(), computer.ram().maximum_size())?;
+        ram.set_alloc_strategy(computer.ram().alloc_strategy());
+        ram
+    } else {
+        computer.ram().share()
+    };
+    let mut sub_computer = Computer::new(Cpu::new(), ram);
+    let mappings = computer.ram().mappings();
+    
+    EXEC_DEPTH.set(depth + 1);
+    let result = sub_computer.execute_program(program);
+    EXEC_DEPTH.set(depth);
+    
+    // the shared memory would otherwise keep the program, stack and allocations of the exited program
+    if !isolate {
+        for mapping in computer.ram().mappings() {
+            if !mappings.contains(&mapping) {
+                computer.ram_mut().dealloc(mapping.range.start);
+            }
+        }
+    }
+    
+    match result {
+        Ok(exit_code) => Ok(exit_code),
+        Err(err) => Err(InstructionError::with_message(InstructionErrorKind::ExecError, format!("filename: {}, error: {}", path, err))),
+    }
+}
+
+operand_instruction!(Push, | push:Push, computer: &mut Computer | -> Result<()> {
+    let value = push.source.read_from_computer(computer)?;
+    
+    let buffer = IntoBytes::into(&value);
+    computer.cpu_mut().push_buffer(&buffer[..push.source.size() as usize])?;
+    
+    Ok(())
+}, source);
+
+operand_instruction!(Pop, | pop:Pop, computer: &mut Computer | -> Result<()> {
+    let mut buffer = [0u8;size_of::<CpuArchitecture>()];
+    computer.cpu_mut().pop_buffer(&mut buffer[..pop.destination.size() as usize])?;
+    
+    let value:CpuArchitecture = FromBytes::from(buffer);
+    pop.destination.write_to_computer(computer, value)?;
+    
+    Ok(())
+}, destination);
+
+
+operand_instruction!(Jmp, | jmp:Jmp, computer:&mut Computer | -> Result<()> {
+    let cmp_flag = computer.cpu_mut().get_cmp_flag();
+    if cmp_flag {
+        let address = jmp.address.read_from_computer(computer)?;
+        computer.cpu_mut().set_program_counter(address);
+    }
+    Ok(())
+}, address);
+
+impl Jmp {
+    pub fn const_function_binary_size() -> CpuArchitecture {
+        Literal::binary_size()
+    }
+}
+
+impl From<Operand> for Jmp {
+    fn from(value: Operand) -> Self {
+        Self { address: value }
+    }
+}
+
+operand_instruction!(Rjmp, | rjmp:Rjmp, computer:&mut Computer | -> Result<()> {
+    let cmp_flag = computer.cpu_mut().get_cmp_flag();
+    if cmp_flag {
+        let address = rjmp.target(computer)?;
+        computer.cpu_mut().set_program_counter(address);
+    }
+    Ok(())
+}, offset);
+
+impl Rjmp {
+    pub fn const_function_binary_size() -> CpuArchitecture {
+        Literal::binary_size()
+    }
+
+    /// the address of the label, the program counter has to point to the next instruction
+    pub fn target(self, computer: &mut Computer) -> Result<CpuArchitecture> {
+        let offset = self.offset.read_from_computer(computer)?;
+        Ok(computer.cpu().get_program_counter().wrapping_add(offset))
+    }
+}
+
+operand_instruction!(Switch, | switch:Switch, computer:&mut Computer | -> Result<()> {
+    let index = switch.index.read_from_computer(computer)?;
+    let count = switch.count.read_from_computer(computer)?;
+    if index >= count {
+        return Ok(());
+    }
+    
+    // every case of the table has the same size as the address is always a literal
+    let table = switch.address.read_from_computer(computer)?;
+    let case_address = table.wrapping_add(index.wrapping_mul(Case::const_function_binary_size() + INSTRUCTION_SIZE));
+    let instruction = computer.cpu_mut().read_instruction_at(case_address).ok().map(| (instruction, _) | instruction);
+    match instruction.as_ref().and_then(Case::is).map(| case | { case.address }) {
+        Some(Operand::Literal(address)) => {
+            computer.cpu_mut().set_program_counter(address.literal());
+            Ok(())
+        },
+        _ => Err(InstructionError::with_message(InstructionErrorKind::InvalidSwitchTable, format!("table: 0x{:X}, index: {}", table, index))),
+    }
+}, index, address, count);
+
+operand_instruction!(Case, | _:Case, _:&mut Computer | -> Result<()> {
+    Ok(())
+}, address);
+
+impl Case {
+    pub fn const_function_binary_size() -> CpuArchitecture {
+        Literal::binary_size()
+    }
+}
+
+impl From<Operand> for Case {
+    fn from(value: Operand) -> Self {
+        Self { address: value }
+    }
+}
+
+macro_rules! cmp_instruction {
+    ($name:ident, $comparison:expr) => {
+        operand_instruction!($name, | compare: $name, computer: &mut Computer | -> Result<()> {
+            let cmp = compare.compare(computer)?;
+            computer.cpu_mut().set_cmp_flag(cmp);
+            
+            Ok(())
+        }, a, b);
+        
+        impl $name {
+            /// compares a and b and sets the flags as if b was subtracted from a
+            pub fn compare(self, computer: &mut Computer) -> Result<bool> {
+                let value1 = self.a.read_from_computer(computer)?;
+                let value2 = self.b.read_from_computer(computer)?;
+                arithmetic_with_flags(computer, value1, value2, false, self.a.size(), true);
+                
+                Ok(($comparison)(value1, value2))
+            }
+        }
+    };
+}
+
+cmp_instruction!(Cmpe, | a, b | { a == b });
+cmp_instruction!(Cmpne, | a, b | { a != b });
+cmp_instruction!(Cmple, | a, b | { a <= b });
+cmp_instruction!(Cmpl, | a, b | { a < b });
+cmp_instruction!(Cmpge, | a, b | { a >= b });
+cmp_instruction!(Cmpg, | a, b | { a > b });
+
+/// the jump is taken if the condition is true for the flags, the flags are kept
+macro_rules! flag_jump_instruction {
+    ($name:ident, $flag:ident, $value:literal) => {
+        flag_jump_instruction!($name, | flags: Flags | { flags.get(Flag::$flag) == $value });
+    };
+    ($name:ident, $condition:expr) => {
+        operand_instruction!($name, | jump: $name, computer: &mut Computer | -> Result<()> {
+            if ($condition)(computer.cpu().get_flags()) {
+                let address = jump.address.read_from_computer(computer)?;
+                computer.cpu_mut().set_program_counter(address);
+            }
+            Ok(())
+        }, address);
+        
+        impl $name {
+            pub fn const_function_binary_size() -> CpuArchitecture {
+                Literal::binary_size()
+            }
+        }
+        
+        impl From<Operand> for $name {
+            fn from(value: Operand) -> Self {
+                Self { address: value }
+            }
+        }
+    };
+}
+
+flag_jump_instruction!(Jz, Zero, true);
+flag_jump_instruction!(Jnz, Zero, false);
+flag_jump_instruction!(Jc, Carry, true);
+flag_jump_instruction!(Jnc, Carry, false);
+flag_jump_instruction!(Js, Sign, true);
+flag_jump_instruction!(Jns, Sign, false);
+flag_jump_instruction!(Jo, Overflow, true);
+flag_jump_instruction!(Jno, Overflow, false);
+flag_jump_instruction!(Jl, | flags: Flags | { flags.get(Flag::Sign) != flags.get(Flag::Overflow) });
+flag_jump_instruction!(Jle, | flags: Flags | { flags.get(Flag::Zero) || flags.get(Flag::Sign) != flags.get(Flag::Overflow) });
+flag_jump_instruction!(Jg, | flags: Flags | { !flags.get(Flag::Zero) && flags.get(Flag::Sign) == flags.get(Flag::Overflow) });
+flag_jump_instruction!(Jge, | flags: Flags | { flags.get(Flag::Sign) == flags.get(Flag::Overflow) });
+flag_jump_instruction!(Ja, | flags: Flags | { !flags.get(Flag::Carry) && !flags.get(Flag::Zero) });
+flag_jump_instruction!(Jbe, | flags: Flags | { flags.get(Flag::Carry) || flags.get(Flag::Zero) });
+
+macro_rules! control_flow_instructions {
+    ($($name:ident),*) => {
+        impl InstructionSet {
+            /// the numbers of the instructions that jump to or call an address
+            pub const CONTROL_FLOW_NUMBERS: &'static [CpuArchitecture] = &[$($name::NUMBER),*];
+            /// the names of the instructions that jump to or call an address
+            pub const CONTROL_FLOW_NAMES: &'static [&'static str] = &[$(stringify!($name)),*];
+            
+            /// the address operand of a call or jump instruction
+            pub fn control_flow_address(self) -> Option<Operand> {
+                match self {
+                    $(InstructionSet::$name(instruction) => Some(instruction.address()),)*
+                    _ => None,
+                }
+            }
+            
+            /// replaces the address operand of a call or jump instruction
+            pub fn with_control_flow_address(self, address: Operand) -> Option<InstructionSet> {
+                match self {
+                    $(InstructionSet::$name(mut instruction) => { instruction.address = address; Some(instruction.into()) },)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+control_flow_instructions!(Call, Tailcall, Addr, Switch, Case, Jmp, Jz, Jnz, Jc, Jnc, Js, Jns, Jo, Jno, Jl, Jle, Jg, Jge, Ja, Jbe);
+
+operand_instruction!(Set, | set:Set, computer: &mut Computer | {
+    let flag = computer.cpu_mut().get_cmp_flag();
+    set.destination.write_to_computer(computer, flag as CpuArchitecture)
+}, destination);
+
+operand_instruction!(Brk, | brk: Brk, computer: &mut Computer | -> Result<()> {
+    if DEBUG.get() {
+        let id = brk.id.read_from_computer(computer)?;
+        println!("breakpoint {} reached", id);
+        Break::new().execute(computer)
+    } else {
+        Ok(())
+    }
+}, id);
+
+empty_instruction!(Break, | computer: &mut Computer | -> Result<()> {
+    if DEBUG.get() {
+        let result = computer.breakpoint();
+        if let Err(err) = result {
+            Err(InstructionError::with_message(InstructionErrorKind::Other, err.to_string()))
+        } else {
+            Ok(())
+        }
+    } else {
         Ok(())
     }
-});
\ No newline at end of file
+});
+operand_instruction!(Bank, | bank:Bank, computer: &mut Computer | {
+    let value = bank.bank.read_from_computer(computer)?;
+    if value as usize >= computer.ram().bank_count() {
+        return Err(InstructionError::with_message(InstructionErrorKind::RamError(RamError::new(RamErrorKind::BankDoesNotExist)), format!("bank: {}", value)));
+    }
+    
+    computer.cpu_mut().set_bank(value as u8);
+    Ok(())
+}, bank);
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use crate::computer::{Computer, REGISTER_COUNT};
+    use crate::cpu::{Cpu, CpuArchitecture, Flag, Flags};
+    use crate::memory::Ram;
+    use crate::program::Program;
+
+    fn run(source: &str) -> (CpuArchitecture, Flags) {
+        let mut computer = Computer::new(Cpu::<REGISTER_COUNT>::new(), Ram::new(4096).unwrap());
+        let exit_code = computer.execute_program(Program::from_str(source).unwrap()).unwrap();
+        (exit_code, computer.cpu().get_flags())
+    }
+
+    #[test]
+    fn dec_sets_the_zero_flag() {
+        let (exit_code, flags) = run("main:
+            mov x2, 5
+            mov x3, 0
+            .loop
+            add x3, 2
+            dec x2
+            jnz loop
+            mov l1, l4
+            exit");
+        assert_eq!(exit_code, 10);
+        assert!(flags.get(Flag::Zero));
+    }
+
+    #[test]
+    fn inc_sets_the_sign_and_overflow_flags_and_keeps_carry() {
+        let (_, flags) = run("main:
+            mov x2, 0xFFFF
+            add x2, 1
+            mov l3, 0x7F
+            inc l3
+            mov l1, 0
+            exit");
+        assert!(flags.get(Flag::Carry));
+        assert!(flags.get(Flag::Sign));
+        assert!(flags.get(Flag::Overflow));
+        assert!(!flags.get(Flag::Zero));
+    }
+
+    #[test]
+    fn iret_restores_the_flags() {
+        let (exit_code, flags) = run("main:
+            mov x1, 0
+            mov x2, 12
+            syscall
+            mov x10, x2
+            addr x9, handler
+            mov word[x10 + 10], x9
+            mov x1, 17
+            mov x2, x10
+            mov x3, 6
+            syscall
+            mov x3, 0
+            add x3, 0
+            int 5
+            mov l1, l4
+            exit
+            handler:
+            mov x4, 1
+            add x4, 1
+            mov x3, 7
+            iret");
+        assert_eq!(exit_code, 7);
+        assert!(flags.get(Flag::Zero));
+        assert!(!flags.get(Flag::Interrupt));
+    }
+
+    #[test]
+    fn iret_restores_x2_after_a_fault() {
+        let (exit_code, _) = run("main:
+            mov x1, 0
+            mov x2, 2
+            syscall
+            mov x10, x2
+            addr x9, on_divide
+            mov word[x10], x9
+            mov x1, 17
+            mov x2, x10
+            mov x3, 1
+            syscall
+            mov x2, 40
+            mov x6, 0
+            mov x7, 10
+            div x7, x6
+            add x2, x3
+            mov l1, l3
+            exit
+            on_divide:
+            mov x3, word[sp - 4]
+            sub x3, 38
+            iret");
+        assert_eq!(exit_code, 42);
+    }
+
+    #[test]
+    fn shared_exec_frees_the_memory_of_the_program() {
+        let path = std::env::temp_dir().join(format!("emulator-exec-{}.asm", std::process::id()));
+        std::fs::write(&path, "main:
+            mov x1, 0
+            mov x2, 64
+            syscall
+            mov l1, 3
+            exit").unwrap();
+
+        let mut computer = Computer::new(Cpu::<REGISTER_COUNT>::new(), Ram::new(4096).unwrap());
+        // SAFETY: the memory is freed when the ram is dropped
+        let pointer = unsafe { computer.ram_mut().alloc_unsafe(16).unwrap() };
+        let mappings = computer.ram().mappings();
+        let exit_code = super::execute_sub_program(&mut computer, path.to_str().unwrap(), false);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(exit_code.unwrap(), 3);
+        assert_eq!(computer.ram().mappings(), mappings);
+        assert!(computer.ram().mappings().iter().any(| mapping | { mapping.range.start == pointer }));
+    }
+
+    #[test]
+    fn timer_doesnt_nest_handlers() {
+        let (exit_code, _) = run("main:
+            mov x1, 0
+            mov x2, 6
+            syscall
+            mov x10, x2
+            addr x9, on_timer
+            mov word[x10 + 4], x9
+            mov x1, 17
+            mov x2, x10
+            mov x3, 3
+            syscall
+            mov x6, 0
+            mov x1, 18
+            mov x2, 1
+            syscall
+            .wait
+                cmpl x6, 3
+                jmp wait
+            mov x1, 18
+            mov x2, 0
+            syscall
+            mov l1, l7
+            exit
+            on_timer:
+            mov x7, 0x2000
+            .slow
+                dec x7
+                jnz slow
+            inc x6
+            iret");
+        assert_eq!(exit_code, 3);
+    }
+}
@@ -0,0 +1,121 @@
+use std::time::{Duration, Instant};
+use crate::cpu::{CpuArchitecture, CpuError, CpuErrorKind};
+use crate::error::ErrorMessage;
+use crate::instructions::{InstructionError, InstructionErrorKind};
+use crate::memory::{Ram, RamError, RamErrorKind};
+
+/// the interrupts raised by the emulator itself, the number is the index of the handler within the vector table,
+/// the numbers after these can be used by int
+#[derive(Clone, Copy, Debug)]
+pub enum Interrupt {
+    DivideByZero = 0,
+    SegmentationFault = 1,
+    Timer = 2,
+    InvalidInstruction = 3,
+    /// the alarm of the clock device has gone off
+    Alarm = 4,
+    /// the result of add, sub or mul didn't fit while the overflow trap is enabled
+    ArithmeticOverflow = 5,
+    /// the watchdog device wasn't petted in time
+    Watchdog = 6,
+    /// a transfer of the dma controller has finished
+    Dma = 7,
+}
+
+/// the address within the message of an error, eg: the memory address of a segmentation fault
+fn error_address(message: Option<&ErrorMessage>) -> Option<CpuArchitecture> {
+    match message {
+        Some(ErrorMessage::Address(address)) => Some(*address as CpuArchitecture),
+        _ => None,
+    }
+}
+
+impl Interrupt {
+    /// the interrupt of a fault of an instruction at the address with the address that caused it,
+    /// None if the error isn't a fault that can be handled
+    pub fn from_fault(err: &InstructionError, instruction_address: CpuArchitecture) -> Option<(Self, CpuArchitecture)> {
+        let ram_error = match err.kind() {
+            InstructionErrorKind::DivideByZero => return Some((Interrupt::DivideByZero, instruction_address)),
+            InstructionErrorKind::ArithmeticOverflow => return Some((Interrupt::ArithmeticOverflow, instruction_address)),
+            InstructionErrorKind::RamError(err) => err,
+            InstructionErrorKind::CpuError(err) => return Self::from_cpu_fault(err, instruction_address),
+            _ => return None,
+        };
+        Self::from_ram_fault(ram_error, instruction_address)
+    }
+
+    /// the interrupt of a fault while fetching or executing the instruction at the address with the address that caused it
+    pub fn from_cpu_fault(err: &CpuError, instruction_address: CpuArchitecture) -> Option<(Self, CpuArchitecture)> {
+        match err.kind() {
+            CpuErrorKind::InvalidInstruction => Some((Interrupt::InvalidInstruction, instruction_address)),
+            CpuErrorKind::RamError(err) => Self::from_ram_fault(err, instruction_address),
+            _ => None,
+        }
+    }
+
+    fn from_ram_fault(err: &RamError, instruction_address: CpuArchitecture) -> Option<(Self, CpuArchitecture)> {
+        match err.kind() {
+            RamErrorKind::SegmentationFault | RamErrorKind::IndexOutOfBounds | RamErrorKind::Access(_) => {
+                Some((Interrupt::SegmentationFault, error_address(err.message()).unwrap_or(instruction_address)))
+            },
+            _ => None,
+        }
+    }
+}
+
+/// the vector table is an array in ram with the address of the handler of every interrupt, 0 if it has no handler
+#[derive(Clone)]
+pub struct Interrupts {
+    table: CpuArchitecture,
+    count: CpuArchitecture,
+    timer_interval: Duration,
+    next_timer: Option<Instant>,
+}
+
+impl Interrupts {
+    pub fn new() -> Self {
+        Self {
+            table: 0,
+            count: 0,
+            timer_interval: Duration::ZERO,
+            next_timer: None,
+        }
+    }
+
+    /// uses the count addresses at pointer table as the vector table, a table of 0 removes all handlers
+    pub fn set_table(&mut self, table: CpuArchitecture, count: CpuArchitecture) {
+        self.table = table;
+        self.count = if table == 0 { 0 } else { count };
+    }
+
+    /// raises the timer interrupt every interval, an interval of 0 stops the timer
+    pub fn set_timer(&mut self, interval: Duration) {
+        self.timer_interval = interval;
+        self.next_timer = (!interval.is_zero()).then(| | { Instant::now() + interval });
+    }
+
+    /// the address of the handler of the interrupt, None if it isn't within the table or the table cannot be read
+    pub fn handler(&self, ram: &Ram, interrupt: CpuArchitecture) -> Option<CpuArchitecture> {
+        if interrupt >= self.count {
+            return None;
+        }
+
+        let address = self.table.wrapping_add(interrupt.wrapping_mul(size_of::<CpuArchitecture>() as CpuArchitecture));
+        let handler = ram.read_at_checked::<CpuArchitecture>(address).ok()?;
+        (handler != 0).then_some(handler)
+    }
+
+    /// true once per interval, the interval is counted from the moment it is checked so slow handlers don't pile up
+    pub fn timer_due(&mut self) -> bool {
+        let Some(next_timer) = self.next_timer else {
+            return false;
+        };
+
+        let now = Instant::now();
+        if now < next_timer {
+            return false;
+        }
+        self.next_timer = Some(now + self.timer_interval);
+        true
+    }
+}
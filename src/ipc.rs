@@ -0,0 +1,42 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+use crate::cpu::CpuArchitecture;
+use crate::memory::RamError;
+use crate::memory_buffer::MemoryBuffer;
+
+// programs started using exec run on the same thread so thread locals are shared between them
+thread_local! {
+    static SHARED_MEMORY: RefCell<Vec<Rc<RefCell<MemoryBuffer>>>> = const { RefCell::new(Vec::new()) };
+    static MESSAGE_QUEUES: RefCell<HashMap<CpuArchitecture, VecDeque<Vec<u8>>>> = RefCell::new(HashMap::new());
+}
+
+/// creates zeroed shared memory of size bytes and returns its key, keys start at 1
+pub fn create_shared_memory(size: CpuArchitecture) -> Result<CpuArchitecture, RamError> {
+    let memory = MemoryBuffer::with_maximum(size as usize, size as usize)?;
+    SHARED_MEMORY.with_borrow_mut(| shared_memory | {
+        shared_memory.push(Rc::new(RefCell::new(memory)));
+        Ok(shared_memory.len() as CpuArchitecture)
+    })
+}
+
+pub fn get_shared_memory(key: CpuArchitecture) -> Option<Rc<RefCell<MemoryBuffer>>> {
+    SHARED_MEMORY.with_borrow(| shared_memory | {
+        (key as usize).checked_sub(1)
+            .and_then(| index | { shared_memory.get(index) })
+            .cloned()
+    })
+}
+
+pub fn send_message(queue: CpuArchitecture, message: Vec<u8>) {
+    MESSAGE_QUEUES.with_borrow_mut(| queues | {
+        queues.entry(queue).or_default().push_back(message);
+    })
+}
+
+/// removes and returns the oldest message within the queue
+pub fn receive_message(queue: CpuArchitecture) -> Option<Vec<u8>> {
+    MESSAGE_QUEUES.with_borrow_mut(| queues | {
+        queues.get_mut(&queue).and_then(| messages | { messages.pop_front() })
+    })
+}
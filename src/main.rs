@@ -4,8 +4,16 @@ extern crate core;
 
 use clap::Parser;
 use clap_derive::{Parser, Subcommand};
-use crate::compile::{build, run};
+use crate::compile::{build, run, RunOptions};
 use crate::cpu::CpuArchitecture;
+use crate::docs::{docs, DocsFormat};
+use crate::examples::examples;
+use crate::explain::explain;
+use crate::check::check;
+use crate::sandbox::SandboxPolicy;
+use crate::serial::SerialMode;
+use crate::audio::AudioOutput;
+use crate::memory::AllocStrategy;
 
 mod instructions;
 mod cpu;
@@ -13,7 +21,7 @@ mod computer;
 mod memory;
 mod program;
 mod error;
-mod array;
+mod memory_buffer;
 mod operand;
 mod read_ext;
 mod instruction_iter;
@@ -24,20 +32,144 @@ mod compile;
 mod pattern_ignore_case;
 mod dependency;
 mod window;
+mod canvas;
 mod break_point;
+mod hot_reload;
+mod ipc;
+mod fs_image;
+mod file_table;
+mod sandbox;
+mod decode_cache;
+mod stack_guard;
+mod sanitizer;
+mod call_stack;
+mod docs;
+mod explain;
+mod memviz;
+mod hud;
+mod debugger_overlay;
+mod perf_counters;
+mod profiler;
+mod call_graph;
+mod check;
+mod socket_table;
+mod frame_capture;
+mod audio;
+mod input_script;
+mod examples;
+mod interrupt;
+mod device_bus;
+mod serial;
+mod rtc;
+mod rng;
+mod watchdog;
+mod nvram;
+mod dma;
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)] // the command is only parsed once
 enum Commands {
     /// run a assembly or binary file
+    ///
+    /// while a window is open F5 pauses and resumes the program, F6 runs a single frame, F7 toggles turbo, F8 shows the debugger
+    /// and F9 rewinds the program by at least 3 seconds
     Run {
         /// the path to an assembly or binary file that will be run
         path: String,
         /// the amount of memory that the emulator will have
         #[arg(short, long, default_value_t = 1024)]
         memory_amount: CpuArchitecture,
+        /// lets the memory grow on demand from memory_amount up to this amount [optional]
+        #[arg(long)]
+        memory_max: Option<CpuArchitecture>,
+        /// how the free memory for an allocation is chosen, buddy allocates power of 2 blocks
+        #[arg(long, value_enum, default_value_t = AllocStrategy::FirstFit)]
+        alloc_strategy: AllocStrategy,
+        /// randomize where the program, the stack and the allocations start to expose hardcoded addresses,
+        /// the layout depends on the seed of rand
+        #[arg(long)]
+        aslr: bool,
+        /// the amount of extra 64K memory banks that can be selected using the bank instruction
+        #[arg(long, default_value_t = 0)]
+        banks: u8,
         /// indicate that the emulator should run in debug mode
         #[arg(short, long)]
         debug:bool,
+        /// watch the assembly file and hot reload changed functions while a window is open
+        #[arg(short, long)]
+        watch: bool,
+        /// cache decoded instructions and fuse common instruction pairs, cannot be used by self modifying programs
+        #[arg(long)]
+        fuse: bool,
+        /// a filesystem image that the file syscalls operate in, it is created if it doesn't exist [optional]
+        #[arg(long)]
+        fs: Option<String>,
+        /// a file of up to 4096 bytes that is loaded as the bank after the --banks banks and written back when the program exits [optional]
+        #[arg(long)]
+        nvram: Option<String>,
+        /// memory map dependency files instead of reading them from disk
+        #[arg(long)]
+        mmap_dependencies: bool,
+        /// verify the return address when returning from every function to detect stack corruption
+        #[arg(long)]
+        strict: bool,
+        /// report reads of uninitialized registers, misaligned pointers and uses of deallocated memory with the line where
+        /// they happened after the program exits, also verifies the return addresses like --strict, ignores --fuse
+        #[arg(long)]
+        sanitize: bool,
+        /// show the memory in a separate window colored by recent reads (green) and writes (red), cannot be used by programs that open a window
+        #[arg(long)]
+        memviz: bool,
+        /// show the fps, instructions per frame and heap usage on top of the window, can be toggled using the ToggleHud syscall
+        #[arg(long)]
+        hud: bool,
+        /// show the executed instructions, the time spent in syscalls, the redraws and the peak heap usage after the program exits
+        #[arg(long)]
+        time: bool,
+        /// count the instructions executed by every function and show the self and total counts per function after the program exits
+        #[arg(long)]
+        profile: bool,
+        /// write the calls between the functions observed while the program ran to this graphviz dot file [optional]
+        #[arg(long)]
+        emit_callgraph: Option<String>,
+        /// run the window like a console, executing this many instructions per frame at 60 fps and then presenting the frame [optional]
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        cycles_per_frame: Option<u32>,
+        /// save the presented frames of the window as bmp images in this directory [optional]
+        #[arg(long)]
+        capture_frames: Option<String>,
+        /// only save every nth presented frame when capturing frames
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        capture_every: u32,
+        /// play the sound of the program on the host with play or write it to the wav file at the path, the sound syscalls do
+        /// nothing without it [optional]
+        #[arg(long)]
+        audio: Option<AudioOutput>,
+        /// play back the window events in this file, eg. a line "t=500 key a down" presses a 500 ms after the window opened [optional]
+        #[arg(long)]
+        input_script: Option<String>,
+        /// where the serial console on the in and out ports is connected to, stdio or tcp:port which waits for a connection on localhost
+        #[arg(long, default_value = "stdio")]
+        serial: SerialMode,
+        /// the seed of the numbers given by rand, a run with the same seed gives the same numbers, based on the time if not given [optional]
+        #[arg(long)]
+        seed: Option<u64>,
+        /// allow the program to access the host filesystem
+        #[arg(long)]
+        allow_fs: bool,
+        /// allow the program to access the network
+        #[arg(long)]
+        allow_net: bool,
+        /// allow the program to read environment variables
+        #[arg(long)]
+        allow_env: bool,
+        /// allow the program to read this environment variable, can be given multiple times
+        #[arg(long, value_name = "NAME")]
+        allow_env_var: Vec<String>,
+        /// the arguments after -- are passed to the program, x1 = the amount of arguments and x2 = a pointer to the pointers
+        /// of the null terminated arguments
+        #[arg(last = true)]
+        arguments: Vec<String>,
     },
     /// build an assembly into a binary file
     Build { 
@@ -45,7 +177,48 @@ enum Commands {
         path: String,
         /// the path where the compiled file will be saved [optional]
         #[arg(short = 'o')]
-        out: Option<String>
+        out: Option<String>,
+        /// memory map dependency files instead of reading them from disk
+        #[arg(long)]
+        mmap_dependencies: bool,
+        /// the word size in bits of the cpu the binary is build for, it is stored in the binary so it isn't run on a cpu
+        /// with a different word size, only the native word size is supported
+        #[arg(long, default_value_t = CpuArchitecture::BITS)]
+        arch: u32,
+    },
+    /// compile an assembly or binary file without running it to find errors
+    Check {
+        /// the path to an assembly or binary file that will be checked
+        path: String,
+        /// warn about functions that return with a different stack depth than they were called with and about registers
+        /// that are used after calling a function that overwrites them
+        #[arg(long)]
+        analyze: bool,
+    },
+    /// generate a reference of all instructions and syscalls
+    Docs {
+        /// the format of the reference
+        #[arg(long, value_enum, default_value_t = DocsFormat::Markdown)]
+        format: DocsFormat,
+        /// the path where the reference will be saved, printed if not given [optional]
+        #[arg(short = 'o')]
+        out: Option<String>,
+    },
+    /// explain the encoding and semantics of an instruction, or an error when prefixed by E: eg. E:SegmentationFault
+    Explain {
+        /// the instruction or error to explain
+        input: String,
+    },
+    /// list the example programs, or show or run one of them
+    Examples {
+        /// the name of the example, all examples are listed if not given [optional]
+        name: Option<String>,
+        /// run the example instead of showing its source
+        #[arg(short, long)]
+        run: bool,
+        /// indicate that the emulator should run in debug mode
+        #[arg(short, long)]
+        debug: bool,
     },
 }
 
@@ -60,7 +233,40 @@ fn main() {
     let arguments = Args::parse();
     
     match arguments.command {
-        Commands::Build { path, out } => build(path, out),
-        Commands::Run { path, memory_amount, debug } => run(path, memory_amount, debug),
+        Commands::Build { path, out, mmap_dependencies, arch } => build(path, out, mmap_dependencies, arch),
+        Commands::Check { path, analyze } => check(path, analyze),
+        Commands::Docs { format, out } => docs(format, out),
+        Commands::Explain { input } => explain(input),
+        Commands::Examples { name, run, debug } => examples(name, run, debug),
+        Commands::Run { path, memory_amount, memory_max, alloc_strategy, aslr, banks, debug, watch, fuse, fs, nvram, mmap_dependencies, strict, sanitize, memviz, hud, time, profile, emit_callgraph, cycles_per_frame, capture_frames, capture_every, audio, input_script, serial, seed, allow_fs, allow_net, allow_env, allow_env_var, arguments } => run(path, RunOptions {
+            memory_amount,
+            memory_max,
+            alloc_strategy,
+            aslr,
+            banks,
+            debug,
+            watch,
+            fuse,
+            fs,
+            nvram,
+            mmap_dependencies,
+            strict,
+            sanitize,
+            memviz,
+            hud,
+            time,
+            profile,
+            emit_callgraph,
+            cycles_per_frame,
+            capture_frames,
+            capture_every,
+            audio,
+            input_script,
+            serial,
+            seed,
+            sandbox: SandboxPolicy::new(allow_fs, allow_net, allow_env),
+            allowed_env_vars: allow_env_var,
+            arguments,
+        }),
     }
 }
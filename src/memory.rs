@@ -1,37 +1,142 @@
 use core::ops::Range;
 use std::cell::RefCell;
+use std::fmt::{Display, Formatter, Write as _};
+use std::rc::Rc;
 use std::io::{Error, ErrorKind, Read, Write};
 use std::ops::{Deref, DerefMut};
-use crate::array::Array;
+use clap_derive::ValueEnum;
 use crate::cpu::{CpuArchitecture, FromBytes, IntoBytes};
-use crate::{array, error_creator};
+use crate::error_creator;
+use crate::error::ErrorMessage;
+use crate::memory_buffer::{MemoryBuffer, MemoryBufferError};
+use crate::memviz::record_access;
+use crate::rng::Rng;
 
-type Ranges = Rc<RefCell<Vec<Range<CpuArchitecture>>>>;
+type Mappings = Rc<RefCell<Vec<Mapping>>>;
 pub struct Ram {
-    memory: Rc<RefCell<Array<u8>>>,
-    allocated_ranges: Ranges
+    memory: Rc<RefCell<MemoryBuffer>>,
+    mappings: Mappings,
+    banks: Vec<Rc<RefCell<MemoryBuffer>>>,
+    alloc_strategy: AllocStrategy,
+    /// randomizes where allocations start when --aslr is used
+    aslr: Option<Rc<RefCell<Rng>>>,
 }
 
+/// how the free memory for an allocation is chosen
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum AllocStrategy {
+    /// the first gap that is large enough
+    #[default]
+    FirstFit,
+    /// the smallest gap that is large enough
+    BestFit,
+    /// a power of 2 block aligned to its size, split from the smallest free block
+    Buddy,
+}
+
+/// what a mapping of the memory can be used for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Permissions(u8);
+
+impl Permissions {
+    pub const READ: Self = Self(1);
+    pub const WRITE: Self = Self(2);
+    pub const EXECUTE: Self = Self(4);
+    /// the permissions of memory allocated by the program, eg: the stack and the window
+    pub const READ_WRITE: Self = Self(Self::READ.0 | Self::WRITE.0);
+    /// the permissions of the instructions of the program, which cannot be modified
+    pub const READ_EXECUTE: Self = Self(Self::READ.0 | Self::EXECUTE.0);
+    
+    pub fn allows(self, access: Access) -> bool {
+        let required = match access {
+            Access::Read => Self::READ,
+            Access::Write => Self::WRITE,
+            Access::Execute => Self::EXECUTE,
+        };
+        self.0 & required.0 != 0
+    }
+}
+
+impl Display for Permissions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (access, char) in [(Access::Read, 'r'), (Access::Write, 'w'), (Access::Execute, 'x')] {
+            f.write_char(if self.allows(access) { char } else { '-' })?;
+        }
+        Ok(())
+    }
+}
+
+/// the way memory is accessed, which has to be allowed by the permissions of its mapping
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Access {
+    Read,
+    Write,
+    Execute,
+}
+
+impl Display for Access {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Access::Read => f.write_str("Memory was read that isn't mapped as readable"),
+            Access::Write => f.write_str("Memory was written to that isn't mapped as writable"),
+            Access::Execute => f.write_str("Memory was executed that isn't mapped as executable"),
+        }
+    }
+}
+
+/// an allocated range of the memory with what it can be used for
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mapping {
+    pub range: Range<CpuArchitecture>,
+    pub permissions: Permissions,
+}
+
+/// the smallest block the buddy strategy allocates
+const MIN_BUDDY_BLOCK: usize = 8;
+/// the largest amount of free bytes aslr puts before an allocation, the offsets are even to keep words aligned
+const MAX_ASLR_OFFSET: CpuArchitecture = 64;
+
+/// the size of every bank besides bank 0, which is the allocated memory
+pub const BANK_SIZE: usize = CpuArchitecture::MAX as usize + 1;
+
 error_creator!(
     RamError,
     RamErrorKind,
+    /// an address or length that reaches past the end of the memory
     IndexOutOfBounds => "The given index is out of bounds for the memory",
+    /// the memory amount is too small for the program, its stack and its allocations, increase it using --memory-amount or --memory-max
     OutOfMemory => "Not enough memory to allocate data",
-    SegmentationFault => "Memory was read or written to that isn't allocated"
+    /// a pointer that was never allocated, is already deallocated or points past the end of an allocation,
+    /// commonly caused by using an uninitialized register as pointer or an off by one error in a loop
+    SegmentationFault => "Memory was read or written to that isn't allocated",
+    /// the bank instruction selected a bank that hasn't been added using --banks or attached as shared memory
+    BankDoesNotExist => "The selected memory bank does not exist",
+    /// more than 255 banks have been added or attached
+    TooManyBanks => "No more memory banks can be added",
+    /// the memory is allocated but its mapping doesn't allow the access, eg: writing to the instructions of the program
+    Access(Access) => "Memory was accessed in a way that its mapping doesn't permit",
+    MemoryBufferError(MemoryBufferError) => ""
 );
 
+/// the contents and allocations of the memory and the banks, used to rewind the program
+pub struct RamState {
+    memory: Vec<u8>,
+    mappings: Vec<Mapping>,
+    banks: Vec<Vec<u8>>,
+}
+
 pub struct AllocatedRam {
-    buffer: Rc<RefCell<Array<u8>>>,
+    buffer: Rc<RefCell<MemoryBuffer>>,
     start: CpuArchitecture,
     end: CpuArchitecture,
-    ranges: Ranges
+    mappings: Mappings
 }
 
 impl Drop for AllocatedRam {
     fn drop(&mut self) {
-        let mut borrow = self.ranges.borrow_mut();
+        let mut borrow = self.mappings.borrow_mut();
         for index in 0..borrow.len() {
-            let range = &borrow[index];
+            let range = &borrow[index].range;
             if range == &self.range() {
                 borrow.swap_remove(index);
                 return;
@@ -46,7 +151,7 @@ impl Default for AllocatedRam {
             buffer: Rc::default(),
             start: 0,
             end: 0,
-            ranges: Rc::new(RefCell::new(vec![])),
+            mappings: Rc::new(RefCell::new(vec![])),
         }
     }
 }
@@ -74,6 +179,7 @@ fn read_buffer_at(buffer:&[u8], index:CpuArchitecture, into_buffer:&mut [u8], ra
         Err(RamError::new(RamErrorKind::IndexOutOfBounds))
     } else {
         let range = index as usize..index as usize + into_buffer.len();
+        record_access(range.clone(), false);
         into_buffer.copy_from_slice(&buffer[range]);
 
         Ok(())
@@ -92,6 +198,7 @@ fn write_buffer_at(buffer:&mut [u8], index: CpuArchitecture, from_buffer:&[u8],
         Err(RamError::new(RamErrorKind::IndexOutOfBounds))
     } else {
         let range = index as usize..index as usize + from_buffer.len();
+        record_access(range.clone(), true);
         buffer[range].copy_from_slice(from_buffer);
 
         Ok(())
@@ -99,16 +206,47 @@ fn write_buffer_at(buffer:&mut [u8], index: CpuArchitecture, from_buffer:&[u8],
 }
 
 fn create_segment_fault_error(index: CpuArchitecture) -> RamError {
-    RamError::with_message(RamErrorKind::SegmentationFault, format!("(0x{:X})", index))
+    RamError::with_message(RamErrorKind::SegmentationFault, ErrorMessage::Address(index as u64))
+}
+
+fn create_access_error(access: Access, index: CpuArchitecture) -> RamError {
+    RamError::with_message(RamErrorKind::Access(access), ErrorMessage::Address(index as u64))
+}
+
+/// the mapping that contains the length bytes at the index
+fn find_mapping(mappings: &[Mapping], index: CpuArchitecture, length: usize) -> Option<&Mapping> {
+    mappings.iter().find(| mapping | {
+        let range = &mapping.range;
+        // an index before the start wraps around to an offset past the end
+        index.wrapping_sub(range.start) as usize + length <= (range.end - range.start) as usize
+    })
 }
 
 impl AllocatedRam {
-    pub(crate) fn new(buffer: Rc<RefCell<Array<u8>>>, start: CpuArchitecture, end: CpuArchitecture, ranges: Ranges) -> Self {
+    pub(crate) fn new(buffer: Rc<RefCell<MemoryBuffer>>, start: CpuArchitecture, end: CpuArchitecture, mappings: Mappings) -> Self {
         Self {
             buffer,
             start,
             end,
-            ranges
+            mappings
+        }
+    }
+    
+    /// checks if the index within the memory is in an executable mapping, an index past the end can be in another mapping
+    pub fn check_execute(&self, index: CpuArchitecture) -> Result<()> {
+        let address = self.start.wrapping_add(index);
+        match find_mapping(&self.mappings.borrow(), address, 1) {
+            Some(mapping) if mapping.permissions.allows(Access::Execute) => Ok(()),
+            _ => Err(create_access_error(Access::Execute, address)),
+        }
+    }
+    
+    /// changes what the memory can be used for by the program, the emulator itself can always read and write it
+    pub fn set_permissions(&self, permissions: Permissions) {
+        let range = self.range();
+        let mut borrow = self.mappings.borrow_mut();
+        if let Some(mapping) = borrow.iter_mut().find(| mapping | { mapping.range == range }) {
+            mapping.permissions = permissions;
         }
     }
     
@@ -177,70 +315,386 @@ impl AllocatedRam {
 }
 
 impl Ram {
-    pub fn new(amount:CpuArchitecture) -> Self {
-        Self {
-            memory: Rc::new(RefCell::new(array![0u8;amount as usize])),
-            allocated_ranges: Rc::new(RefCell::new(Vec::new())),
+    pub fn new(amount:CpuArchitecture) -> Result<Self> {
+        Self::with_maximum(amount, amount)
+    }
+    
+    /// creates ram with amount bytes committed, which lazily grows up to maximum bytes when allocating
+    pub fn with_maximum(amount:CpuArchitecture, maximum:CpuArchitecture) -> Result<Self> {
+        let buffer = MemoryBuffer::with_maximum(amount as usize, maximum as usize)?;
+        Ok(Self {
+            memory: Rc::new(RefCell::new(buffer)),
+            mappings: Rc::new(RefCell::new(Vec::new())),
+            banks: Vec::new(),
+            alloc_strategy: AllocStrategy::FirstFit,
+            aslr: None,
+        })
+    }
+    
+    /// adds count banks of BANK_SIZE bytes, these banks are not allocated and can be used freely by the program
+    pub fn add_banks(&mut self, count: u8) -> Result<()> {
+        for _ in 0..count {
+            let bank = MemoryBuffer::with_maximum(BANK_SIZE, BANK_SIZE)?;
+            self.attach_bank(Rc::new(RefCell::new(bank)))?;
+        }
+        Ok(())
+    }
+    
+    /// adds memory as a new bank and returns the number of the bank
+    pub fn attach_bank(&mut self, memory: Rc<RefCell<MemoryBuffer>>) -> Result<u8> {
+        let bank = self.bank_count();
+        if bank > u8::MAX as usize {
+            return Err(RamError::new(RamErrorKind::TooManyBanks));
         }
+        self.banks.push(memory);
+        Ok(bank as u8)
+    }
+    
+    /// the amount of banks including bank 0
+    pub fn bank_count(&self) -> usize {
+        self.banks.len() + 1
+    }
+    
+    fn get_bank(&self, bank: u8) -> Result<&Rc<RefCell<MemoryBuffer>>> {
+        match (bank as usize).checked_sub(1).and_then(| index | { self.banks.get(index) }) {
+            Some(val) => Ok(val),
+            None => Err(RamError::with_message(RamErrorKind::BankDoesNotExist, format!("bank: {}", bank))),
+        }
+    }
+    
+    /// reads from the bank at the index, bank 0 is the allocated memory and is checked
+    pub fn read_bank_buffer_at(&self, bank: u8, index: CpuArchitecture, buffer: &mut [u8]) -> Result<()> {
+        if bank == 0 {
+            return self.read_buffer_at_checked(index, buffer);
+        }
+        
+        let memory = self.get_bank(bank)?.borrow();
+        let range = index as usize..index as usize + buffer.len();
+        if range.end > memory.len() {
+            return Err(RamError::new(RamErrorKind::IndexOutOfBounds));
+        }
+        buffer.copy_from_slice(&memory[range]);
+        Ok(())
+    }
+    
+    /// writes to the bank at the index, bank 0 is the allocated memory and is checked
+    pub fn write_bank_buffer_at(&mut self, bank: u8, index: CpuArchitecture, buffer: &[u8]) -> Result<()> {
+        if bank == 0 {
+            return self.write_buffer_at_checked(index, buffer);
+        }
+        
+        let mut memory = self.get_bank(bank)?.borrow_mut();
+        let range = index as usize..index as usize + buffer.len();
+        if range.end > memory.len() {
+            return Err(RamError::new(RamErrorKind::IndexOutOfBounds));
+        }
+        memory[range].copy_from_slice(buffer);
+        Ok(())
+    }
+    
+    /// copies length bytes from source to destination within the bank in one copy, the source and destination can overlap,
+    /// bank 0 is the allocated memory and is checked
+    pub fn copy_bank_within(&mut self, bank: u8, destination: CpuArchitecture, source: CpuArchitecture, length: CpuArchitecture) -> Result<()> {
+        if length == 0 {
+            return Ok(());
+        }
+        let source_range = source as usize..source as usize + length as usize;
+        let destination_range = destination as usize..destination as usize + length as usize;
+        
+        let mut memory = if bank == 0 {
+            self.check_access(source, length as usize, Access::Read)?;
+            self.check_access(destination, length as usize, Access::Write)?;
+            record_access(source_range.clone(), false);
+            record_access(destination_range, true);
+            self.memory.borrow_mut()
+        } else {
+            let memory = self.get_bank(bank)?.borrow_mut();
+            if source_range.end.max(destination_range.end) > memory.len() {
+                return Err(RamError::new(RamErrorKind::IndexOutOfBounds));
+            }
+            memory
+        };
+        memory.copy_within(source_range, destination as usize);
+        Ok(())
+    }
+    
+    /// sets length bytes at the index within the bank to the value, bank 0 is the allocated memory and is checked
+    pub fn fill_bank(&mut self, bank: u8, index: CpuArchitecture, value: u8, length: CpuArchitecture) -> Result<()> {
+        if length == 0 {
+            return Ok(());
+        }
+        let range = index as usize..index as usize + length as usize;
+        
+        let mut memory = if bank == 0 {
+            self.check_access(index, length as usize, Access::Write)?;
+            record_access(range.clone(), true);
+            self.memory.borrow_mut()
+        } else {
+            let memory = self.get_bank(bank)?.borrow_mut();
+            if range.end > memory.len() {
+                return Err(RamError::new(RamErrorKind::IndexOutOfBounds));
+            }
+            memory
+        };
+        memory[range].fill(value);
+        Ok(())
     }
     
     pub fn size(&self) -> CpuArchitecture {
         self.memory.borrow().len() as CpuArchitecture
     }
     
+    pub fn maximum_size(&self) -> CpuArchitecture {
+        self.memory.borrow().maximum() as CpuArchitecture
+    }
+    
+    /// creates ram that shares the memory, allocations and banks with this ram
+    pub fn share(&self) -> Self {
+        Self {
+            memory: self.memory.clone(),
+            mappings: self.mappings.clone(),
+            banks: self.banks.clone(),
+            alloc_strategy: self.alloc_strategy,
+            aslr: self.aslr.clone(),
+        }
+    }
+    
+    /// copies the contents and allocations of the memory and the banks
+    pub fn state(&self) -> RamState {
+        RamState {
+            memory: self.memory.borrow().to_vec(),
+            mappings: self.mappings(),
+            banks: self.banks.iter().map(| bank | { bank.borrow().to_vec() }).collect(),
+        }
+    }
+    
+    /// puts back the copied contents and allocations, memory that has grown since is kept but isn't allocated,
+    /// the allocations made by the emulator, eg: the program and the window, have to exist in both
+    pub fn restore_state(&mut self, state: &RamState) {
+        self.memory.borrow_mut()[..state.memory.len()].copy_from_slice(&state.memory);
+        *self.mappings.borrow_mut() = state.mappings.clone();
+        for (bank, contents) in self.banks.iter().zip(&state.banks) {
+            bank.borrow_mut()[..contents.len()].copy_from_slice(contents);
+        }
+    }
+    
+    pub fn alloc_strategy(&self) -> AllocStrategy {
+        self.alloc_strategy
+    }
+    
+    pub fn set_alloc_strategy(&mut self, alloc_strategy: AllocStrategy) {
+        self.alloc_strategy = alloc_strategy;
+    }
+    
+    /// places every allocation at a random offset within the free memory chosen by the strategy, or a random free block
+    /// when using the buddy strategy
+    pub fn set_aslr(&mut self, rng: Rng) {
+        self.aslr = Some(Rc::new(RefCell::new(rng)));
+    }
+    
+    /// a random number below maximum if aslr is used
+    fn aslr_random(&self, maximum: usize) -> Option<usize> {
+        self.aslr.as_ref().map(| rng | { (rng.borrow_mut().next() % maximum as u64) as usize })
+    }
+    
+    /// the ranges of the memory that are allocated
+    pub fn allocated_ranges(&self) -> Vec<Range<CpuArchitecture>> {
+        self.mappings.borrow().iter().map(| mapping | { mapping.range.clone() }).collect()
+    }
+    
+    /// the allocated ranges with their permissions
+    pub fn mappings(&self) -> Vec<Mapping> {
+        self.mappings.borrow().clone()
+    }
+    
     pub fn size_left(&self) -> CpuArchitecture {
         self.memory.borrow().len() as CpuArchitecture - self.allocated_memory()
     }
     
-    fn allocated_memory(&self) -> CpuArchitecture {
+    /// the largest amount of bytes that can be allocated without growing the memory
+    pub fn largest_free_block(&self) -> CpuArchitecture {
+        let mut ranges = self.allocated_ranges();
+        ranges.sort_by_key(| range | { range.start });
+        
+        let mut largest = 0;
+        let mut index = 1;
+        for range in ranges {
+            largest = largest.max(range.start.saturating_sub(index));
+            index = index.max(range.end);
+        }
+        largest.max(self.size().saturating_sub(index))
+    }
+    
+    pub fn allocated_memory(&self) -> CpuArchitecture {
         let mut total_allocated_length = 0;
         
-        for range in self.allocated_ranges.borrow().iter() {
-            total_allocated_length += range.end - range.start
+        for mapping in self.mappings.borrow().iter() {
+            total_allocated_length += mapping.range.end - mapping.range.start
         }
         
         total_allocated_length
     }
     
     fn get_free_index(&mut self, length: CpuArchitecture) -> Option<CpuArchitecture> {
-        self.allocated_ranges.borrow_mut().sort_by(| a, b | {
-            a.start.cmp(&b.start)
+        self.mappings.borrow_mut().sort_by(| a, b | {
+            a.range.start.cmp(&b.range.start)
         });
         
+        let offset = match self.alloc_strategy {
+            AllocStrategy::Buddy => 0,
+            _ => self.aslr_random(MAX_ASLR_OFFSET as usize / 2 + 1).unwrap_or(0) as CpuArchitecture * 2,
+        };
+        // the allocation isn't offset when there is no room for it
+        if offset != 0 {
+            if let Some(index) = length.checked_add(offset).and_then(| padded_length | { self.get_strategy_index(padded_length) }) {
+                return Some(index + offset);
+            }
+        }
+        
+        self.get_strategy_index(length)
+    }
+    
+    fn get_strategy_index(&mut self, length: CpuArchitecture) -> Option<CpuArchitecture> {
+        match self.alloc_strategy {
+            AllocStrategy::FirstFit => self.get_first_fit_index(length),
+            AllocStrategy::BestFit => self.get_best_fit_index(length),
+            AllocStrategy::Buddy => self.get_buddy_index(length),
+        }
+    }
+    
+    fn get_first_fit_index(&mut self, length: CpuArchitecture) -> Option<CpuArchitecture> {
         let mut index = 1;
-        for range in self.allocated_ranges.borrow().iter() {
-            if range.start - index >= length {
+        for mapping in self.mappings.borrow().iter() {
+            if mapping.range.start - index >= length {
                 return Some(index)
             }
-            index = range.end
+            index = mapping.range.end
         }
         
         if self.memory.borrow().len() as CpuArchitecture - index >= length {
+            return Some(index);
+        }
+        
+        self.grow_for(index, length)
+    }
+    
+    fn get_best_fit_index(&mut self, length: CpuArchitecture) -> Option<CpuArchitecture> {
+        let mut best: Option<(CpuArchitecture, CpuArchitecture)> = None;
+        let mut index = 1;
+        let memory_length = self.memory.borrow().len() as CpuArchitecture;
+        let gap_ends = self.mappings.borrow().iter()
+            .map(| mapping | { (mapping.range.start, mapping.range.end) })
+            .chain([(memory_length, memory_length)])
+            .collect::<Vec<_>>();
+        for (start, end) in gap_ends {
+            let gap = start - index;
+            if gap >= length && best.is_none_or(| (_, best_gap) | { gap < best_gap }) {
+                best = Some((index, gap));
+            }
+            index = end;
+        }
+        
+        match best {
+            Some((index, _)) => Some(index),
+            None => self.grow_for(index, length),
+        }
+    }
+    
+    /// the block of the buddy strategy used by an allocation of length bytes
+    fn buddy_block_size(length: CpuArchitecture) -> usize {
+        (length as usize).next_power_of_two().max(MIN_BUDDY_BLOCK)
+    }
+    
+    /// the blocks start at 1 as the first byte cannot be allocated
+    fn is_buddy_block_free(&self, start: usize, size: usize) -> bool {
+        self.mappings.borrow().iter().all(| mapping | {
+            let range_start = mapping.range.start as usize;
+            let range_end = range_start + Self::buddy_block_size(mapping.range.end - mapping.range.start);
+            range_end <= start || range_start >= start + size
+        })
+    }
+    
+    fn get_buddy_index(&mut self, length: CpuArchitecture) -> Option<CpuArchitecture> {
+        let block_size = Self::buddy_block_size(length);
+        let memory_length = self.memory.borrow().len();
+        let maximum = self.memory.borrow().maximum();
+        
+        // a buddy allocator splits the smallest free block, which is the largest free block containing the chosen block
+        let mut best: Option<(usize, usize)> = None;
+        let mut free_blocks = Vec::new();
+        let mut start = 1;
+        while start + block_size <= memory_length {
+            if self.aslr.is_some() {
+                if self.is_buddy_block_free(start, block_size) {
+                    free_blocks.push(start);
+                }
+            } else if self.is_buddy_block_free(start, block_size) {
+                let mut free_size = block_size;
+                loop {
+                    let parent_size = free_size * 2;
+                    let parent_start = (start - 1) / parent_size * parent_size + 1;
+                    if parent_start + parent_size > memory_length || !self.is_buddy_block_free(parent_start, parent_size) {
+                        break;
+                    }
+                    free_size = parent_size;
+                }
+                if best.is_none_or(| (_, best_size) | { free_size < best_size }) {
+                    best = Some((start, free_size));
+                }
+                if free_size == block_size {
+                    break;
+                }
+            }
+            start += block_size;
+        }
+        
+        if let Some(index) = self.aslr_random(free_blocks.len().max(1)) {
+            if let Some(start) = free_blocks.get(index) {
+                return Some(*start as CpuArchitecture);
+            }
+        }
+        if let Some((start, _)) = best {
+            return Some(start as CpuArchitecture);
+        }
+        
+        // the first free block past the committed memory
+        let mut start = 1;
+        while start + block_size <= maximum {
+            if start + block_size > memory_length && self.is_buddy_block_free(start, block_size) {
+                return self.grow_for(start as CpuArchitecture, block_size as CpuArchitecture);
+            }
+            start += block_size;
+        }
+        None
+    }
+    
+    /// commits more memory for length bytes at index if the ram is allowed to grow
+    fn grow_for(&mut self, index: CpuArchitecture, length: CpuArchitecture) -> Option<CpuArchitecture> {
+        let mut memory = self.memory.borrow_mut();
+        let required_size = index as usize + length as usize;
+        let new_size = required_size.max(memory.len() * 2).min(memory.maximum());
+        if required_size <= new_size && memory.grow(new_size).is_ok() {
             Some(index)
         } else {
             None
         }
     }
     
-    fn is_index_allocated(&self, index:CpuArchitecture, length:usize) -> bool {
-        for range in self.allocated_ranges.borrow().iter() {
-            if index.wrapping_sub(range.start) <= (range.end - range.start).wrapping_sub(length as CpuArchitecture) {
-                return true;
-            }
+    /// checks if the length bytes at the index are allocated and their mapping allows the access
+    fn check_access(&self, index:CpuArchitecture, length:usize, access: Access) -> Result<()> {
+        match find_mapping(&self.mappings.borrow(), index, length) {
+            Some(mapping) if !mapping.permissions.allows(access) => Err(create_access_error(access, index)),
+            Some(_) => Ok(()),
+            None => Err(create_segment_fault_error(index)),
         }
-        
-        false
     }
 
     /// reads the generic type T to memory at the **byte** index and checks if its allocated
     pub fn read_at_checked<T : Sized + FromBytes>(&self, index:CpuArchitecture) -> Result<T>
         where [(); size_of::<T>()]:
     {
-        if !self.is_index_allocated(index, size_of::<T>()) {
-            Err(create_segment_fault_error(index))
-        } else {
-            self.read_at_unchecked(index)
-        }
+        self.check_access(index, size_of::<T>(), Access::Read)?;
+        self.read_at_unchecked(index)
     }
     
     pub fn read_at_unchecked<T : Sized + FromBytes>(&self, index: CpuArchitecture) -> Result<T>
@@ -254,20 +708,14 @@ impl Ram {
     pub fn write_at_checked<T : Sized + IntoBytes>(&mut self, index: CpuArchitecture, value:&T) -> Result<()>
         where [(); size_of::<T>()]:
     {
-        if !self.is_index_allocated(index, size_of::<T>()) {
-            Err(create_segment_fault_error(index))
-        } else {
-            let len = self.memory.borrow().len() as CpuArchitecture;
-            write_at(self.memory.borrow_mut().deref_mut(), index, value, 0..len)
-        }
+        self.check_access(index, size_of::<T>(), Access::Write)?;
+        let len = self.memory.borrow().len() as CpuArchitecture;
+        write_at(self.memory.borrow_mut().deref_mut(), index, value, 0..len)
     }
 
     pub fn read_buffer_at_checked(&self, index:CpuArchitecture, buffer:&mut [u8]) -> Result<()> {
-        if !self.is_index_allocated(index, buffer.len()) {
-            Err(create_segment_fault_error(index))
-        } else {
-            self.read_buffer_at_unchecked(index, buffer)
-        }
+        self.check_access(index, buffer.len(), Access::Read)?;
+        self.read_buffer_at_unchecked(index, buffer)
     }
 
     pub fn read_buffer_at_unchecked(&self, index:CpuArchitecture, buffer:&mut [u8]) -> Result<()> {
@@ -276,12 +724,9 @@ impl Ram {
     }
 
     pub fn write_buffer_at_checked(&self, index:CpuArchitecture, buffer:&[u8]) -> Result<()> {
-        if !self.is_index_allocated(index, buffer.len()) {
-            Err(create_segment_fault_error(index))
-        } else {
-            let len = self.memory.borrow().len() as CpuArchitecture;
-            write_buffer_at(self.memory.borrow_mut().deref_mut(), index, buffer, 0..len)
-        }
+        self.check_access(index, buffer.len(), Access::Write)?;
+        let len = self.memory.borrow().len() as CpuArchitecture;
+        write_buffer_at(self.memory.borrow_mut().deref_mut(), index, buffer, 0..len)
     }
     
     /// allocates length amount of bytes
@@ -293,7 +738,7 @@ impl Ram {
             self.memory.clone(),
             free_index,
             free_index + length,
-            self.allocated_ranges.clone(),
+            self.mappings.clone(),
         ))
     }
     
@@ -307,16 +752,58 @@ impl Ram {
             None => return Err(RamError::new(RamErrorKind::OutOfMemory)),
         };
 
-        let allocated_range = free_index..free_index + length;
-        self.allocated_ranges.borrow_mut().push(allocated_range);
+        self.mappings.borrow_mut().push(Mapping {
+            range: free_index..free_index + length,
+            permissions: Permissions::READ_WRITE,
+        });
         
         Ok(free_index)
     }
     
+    /// resizes the allocation at the pointer and returns where it is now, it grows in place when the memory after it is free,
+    /// otherwise the contents are moved to a new allocation with the same permissions
+    pub fn realloc(&mut self, pointer: CpuArchitecture, length: CpuArchitecture) -> Result<CpuArchitecture> {
+        self.mappings.borrow_mut().sort_by(| a, b | {
+            a.range.start.cmp(&b.range.start)
+        });
+        
+        let mappings = self.mappings.borrow();
+        let Some(index) = mappings.iter().position(| mapping | { mapping.range.start == pointer }) else {
+            return Err(create_segment_fault_error(pointer));
+        };
+        let old_range = mappings[index].range.clone();
+        let permissions = mappings[index].permissions;
+        let next_start = mappings.get(index + 1).map_or(self.memory.borrow().len(), | mapping | { mapping.range.start as usize });
+        drop(mappings);
+        
+        // a buddy allocation can only grow within its block
+        let in_place = match self.alloc_strategy {
+            AllocStrategy::Buddy => Self::buddy_block_size(length) == Self::buddy_block_size(old_range.end - old_range.start),
+            _ => pointer as usize + length as usize <= next_start,
+        };
+        if in_place {
+            self.mappings.borrow_mut()[index].range.end = pointer + length;
+            return Ok(pointer);
+        }
+        
+        // SAFETY: the new allocation replaces the old one, which is deallocated below
+        let new_pointer = unsafe { self.alloc_unsafe(length)? };
+        let copy_length = length.min(old_range.end - old_range.start) as usize;
+        self.memory.borrow_mut().copy_within(pointer as usize..pointer as usize + copy_length, new_pointer as usize);
+        record_access(new_pointer as usize..new_pointer as usize + copy_length, true);
+        
+        self.dealloc(pointer);
+        let mut mappings = self.mappings.borrow_mut();
+        if let Some(mapping) = mappings.iter_mut().find(| mapping | { mapping.range.start == new_pointer }) {
+            mapping.permissions = permissions;
+        }
+        Ok(new_pointer)
+    }
+    
     pub fn dealloc(&mut self, pointer: CpuArchitecture) -> Option<CpuArchitecture> {
-        let mut borrow = self.allocated_ranges.borrow_mut();
+        let mut borrow = self.mappings.borrow_mut();
         for index in 0..borrow.len() {
-            let range = borrow[index].clone();
+            let range = borrow[index].range.clone();
             if range.start == pointer {
                 borrow.swap_remove(index);
                 return Some(range.end - range.start);
@@ -329,16 +816,23 @@ impl Ram {
     pub fn borrow_buffer_checked<F, U>(&self, index: CpuArchitecture, length: CpuArchitecture, callback: F) -> Result<U>
         where F : FnOnce(&[u8]) -> U
     {
-        if !self.is_index_allocated(index, length as usize) {
-            Err(create_segment_fault_error(index))
-        } else {
-            let borrow = self.memory.borrow();
-            Ok(callback(&borrow[index as usize..(index + length) as usize]))
-        }
+        self.check_access(index, length as usize, Access::Read)?;
+        let borrow = self.memory.borrow();
+        record_access(index as usize..(index + length) as usize, false);
+        Ok(callback(&borrow[index as usize..(index + length) as usize]))
+    }
+    
+    pub fn borrow_buffer_mut_checked<F, U>(&mut self, index: CpuArchitecture, length: CpuArchitecture, callback: F) -> Result<U>
+        where F : FnOnce(&mut [u8]) -> U
+    {
+        self.check_access(index, length as usize, Access::Write)?;
+        let mut borrow = self.memory.borrow_mut();
+        record_access(index as usize..(index + length) as usize, true);
+        Ok(callback(&mut borrow[index as usize..(index + length) as usize]))
     }
     
     pub fn deallocate_all(&mut self) {
-        self.allocated_ranges.borrow_mut().clear()
+        self.mappings.borrow_mut().clear()
     }
 }
 
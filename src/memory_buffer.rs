@@ -0,0 +1,71 @@
+use std::ops::{Deref, DerefMut};
+use crate::error_creator;
+
+error_creator!(
+    MemoryBufferError,
+    MemoryBufferErrorKind,
+    AllocationFailed => "The host was unable to allocate memory for the emulator",
+    MaximumExceeded => "The memory cannot grow beyond its maximum size"
+);
+
+/// zero initialized guest memory which can optionally grow up to a maximum size,
+/// only the committed part of the memory is accessible through the slice
+#[derive(Default)]
+pub struct MemoryBuffer {
+    memory: Vec<u8>,
+    maximum: usize,
+}
+
+impl MemoryBuffer {
+    /// creates a buffer with size bytes committed which can grow up to maximum bytes
+    pub fn with_maximum(size: usize, maximum: usize) -> Result<Self> {
+        let mut buffer = Self {
+            memory: Vec::new(),
+            maximum: maximum.max(size),
+        };
+        buffer.grow(size)?;
+        Ok(buffer)
+    }
+
+    /// the amount of bytes that are currently committed
+    pub fn len(&self) -> usize {
+        self.memory.len()
+    }
+
+    pub fn maximum(&self) -> usize {
+        self.maximum
+    }
+
+    /// commits the memory up to new_size bytes, does nothing if the memory is already large enough
+    pub fn grow(&mut self, new_size: usize) -> Result<()> {
+        if new_size <= self.memory.len() {
+            return Ok(());
+        }
+        if new_size > self.maximum {
+            return Err(MemoryBufferError::with_message(MemoryBufferErrorKind::MaximumExceeded,
+                                                       format!("requested: {}, maximum: {}", new_size, self.maximum)));
+        }
+
+        let result = self.memory.try_reserve_exact(new_size - self.memory.len());
+        if let Err(err) = result {
+            return Err(MemoryBufferError::with_message(MemoryBufferErrorKind::AllocationFailed, err.to_string()));
+        }
+        self.memory.resize(new_size, 0);
+
+        Ok(())
+    }
+}
+
+impl Deref for MemoryBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.memory.as_slice()
+    }
+}
+
+impl DerefMut for MemoryBuffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.memory.as_mut_slice()
+    }
+}
@@ -0,0 +1,215 @@
+use std::cell::{Cell, RefCell};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+use glium::backend::glutin::SimpleWindowBuilder;
+use glium::glutin::surface::WindowSurface;
+use glium::{Display, IndexBuffer, Program as GlProgram, Surface, VertexBuffer};
+use glium::uniforms::EmptyUniforms;
+use glium::winit::application::ApplicationHandler;
+use glium::winit::error::EventLoopError;
+use glium::winit::event::WindowEvent;
+use glium::winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoopBuilder};
+use glium::winit::window::WindowId;
+use crate::computer::{Computer, ComputerError, ComputerErrorKind};
+use crate::cpu::CpuArchitecture;
+use crate::display_handler::Vertex;
+use crate::instructions::{InstructionError, InstructionErrorKind};
+use crate::program::Program;
+use crate::window::{index_buffer_from_size, FRAGMENT_SHADER_SRC, VERTEX_SHADER_SRC};
+
+/// the heat removed from every byte each frame, an access fades out after 255 / HEAT_DECAY frames
+const HEAT_DECAY: u8 = 4;
+/// the time spent executing instructions between frames
+const FRAME_TIME: Duration = Duration::from_millis(16);
+/// the amount of instructions executed between checking the frame time
+const INSTRUCTIONS_PER_CHECK: usize = 1024;
+/// the brightness of allocated bytes that haven't been accessed recently
+const ALLOCATED_COLOR: u8 = 0x30;
+
+/// how recently every byte of the memory has been read and written, 255 is accessed this frame
+struct MemoryHeat {
+    reads: Vec<u8>,
+    writes: Vec<u8>,
+}
+
+thread_local! {
+    static RECORDING: Cell<bool> = const { Cell::new(false) };
+    static MEMORY_HEAT: RefCell<MemoryHeat> = const { RefCell::new(MemoryHeat { reads: Vec::new(), writes: Vec::new() }) };
+}
+
+/// marks the bytes in the range of the memory as read or written, only recorded when running with --memviz
+pub fn record_access(range: Range<usize>, write: bool) {
+    if !RECORDING.get() {
+        return;
+    }
+
+    MEMORY_HEAT.with_borrow_mut(| heat | {
+        let bytes = if write { &mut heat.writes } else { &mut heat.reads };
+        if let Some(bytes) = bytes.get_mut(range) {
+            bytes.fill(u8::MAX);
+        }
+    });
+}
+
+/// the width and height of the grid of cells, one cell for every byte of the memory
+fn grid_size(memory_size: usize) -> (usize, usize) {
+    let width = (memory_size as f64).sqrt().ceil().max(1f64) as usize;
+    (width, memory_size.div_ceil(width).max(1))
+}
+
+/// shows the memory of the computer in a window while running the program
+pub struct MemoryVisualizer<'a> {
+    computer: &'a mut Computer,
+    result: Option<Result<CpuArchitecture, ComputerError>>,
+
+    display: Display<WindowSurface>,
+    program: GlProgram,
+    index_buffer: IndexBuffer<u32>,
+    size: (usize, usize),
+}
+
+impl<'a> MemoryVisualizer<'a> {
+    /// runs the program until it exits and returns the exit code, the window stays open until it is closed
+    pub fn run(computer: &mut Computer, program: Program) -> Result<CpuArchitecture, ComputerError> {
+        let result = EventLoopBuilder::default().build();
+        let event_loop = match result {
+            Ok(val) => val,
+            Err(err) => return Err(create_window_error(err)),
+        };
+        let (window, display) = SimpleWindowBuilder::new().with_inner_size(1024, 1024).build(&event_loop);
+        window.set_title("memory");
+
+        let memory_size = computer.ram().maximum_size() as usize;
+        let size = grid_size(memory_size);
+        MEMORY_HEAT.set(MemoryHeat {
+            reads: vec![0; memory_size],
+            writes: vec![0; memory_size],
+        });
+        RECORDING.set(true);
+
+        let index_buffer = index_buffer_from_size(&display, size);
+        let gl_program = GlProgram::from_source(&display, VERTEX_SHADER_SRC, FRAGMENT_SHADER_SRC, None).unwrap();
+
+        let result = computer.load_program(program);
+        let mut visualizer = MemoryVisualizer {
+            computer,
+            result: result.err().map(Err),
+            display,
+            program: gl_program,
+            index_buffer,
+            size,
+        };
+        let result = event_loop.run_app(&mut visualizer);
+        RECORDING.set(false);
+
+        if let Err(err) = result {
+            return Err(create_window_error(err));
+        }
+        match visualizer.result {
+            Some(result) => result,
+            None => Err(ComputerError::with_message(ComputerErrorKind::Other, "the memory window was closed before the program exited")),
+        }
+    }
+
+    /// executes instructions for a single frame or until the program exits
+    fn execute_frame(&mut self) {
+        let instant = Instant::now();
+        while instant.elapsed() < FRAME_TIME {
+            for _ in 0..INSTRUCTIONS_PER_CHECK {
+                let result = self.computer.step();
+                match result {
+                    Ok(false) => {},
+                    Ok(true) => { self.result = Some(Ok(self.computer.cpu().exit_code())); return; },
+                    Err(err) => { self.result = Some(Err(err)); return; },
+                }
+            }
+        }
+    }
+
+    fn vertex_buffer(&self) -> VertexBuffer<Vertex> {
+        let width_per_square = 2f32 / self.size.0 as f32;
+        let height_per_square = 2f32 / self.size.1 as f32;
+
+        let mut allocated = vec![false; self.size.0 * self.size.1];
+        for range in self.computer.ram().allocated_ranges() {
+            allocated[range.start as usize..range.end as usize].fill(true);
+        }
+
+        let vertices = MEMORY_HEAT.with_borrow(| heat | {
+            let mut vertices = vec![Vertex::default(); self.size.0 * self.size.1 * 4];
+            for (index, is_allocated) in allocated.iter().enumerate() {
+                let base = if *is_allocated { ALLOCATED_COLOR } else { 0 };
+                let read = heat.reads.get(index).copied().unwrap_or_default();
+                let write = heat.writes.get(index).copied().unwrap_or_default();
+                let color = [write.max(base), read.max(base), base, u8::MAX];
+
+                let x = -1f32 + (index % self.size.0) as f32 * width_per_square;
+                let y = 1f32 - (index / self.size.0) as f32 * height_per_square;
+                let vertex_index = index * 4;
+                vertices[vertex_index] = Vertex::new([x, y], color);
+                vertices[vertex_index + 1] = Vertex::new([x + width_per_square, y], color);
+                vertices[vertex_index + 2] = Vertex::new([x, y - height_per_square], color);
+                vertices[vertex_index + 3] = Vertex::new([x + width_per_square, y - height_per_square], color);
+            }
+            vertices
+        });
+
+        VertexBuffer::new(&self.display, &vertices).unwrap()
+    }
+
+    fn redraw(&self) {
+        let vertex_buffer = self.vertex_buffer();
+
+        let mut frame = self.display.draw();
+        frame.clear_color(0.0, 0.0, 0.0, 1.0);
+        frame.draw(&vertex_buffer, &self.index_buffer, &self.program,
+                   &EmptyUniforms, &Default::default()).unwrap();
+        frame.finish().unwrap();
+    }
+
+    fn decay() {
+        MEMORY_HEAT.with_borrow_mut(| heat | {
+            for value in heat.reads.iter_mut().chain(heat.writes.iter_mut()) {
+                *value = value.saturating_sub(HEAT_DECAY);
+            }
+        });
+    }
+}
+
+impl<'a> ApplicationHandler for MemoryVisualizer<'a> {
+    fn resumed(&mut self, _: &ActiveEventLoop) {
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::RedrawRequested => self.redraw(),
+            _ => {},
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if self.result.is_some() {
+            // the last frame stays visible until the window is closed
+            event_loop.set_control_flow(ControlFlow::Wait);
+            return;
+        }
+
+        event_loop.set_control_flow(ControlFlow::Poll);
+        self.execute_frame();
+        self.redraw();
+        Self::decay();
+
+        if self.result.is_some() {
+            println!("the program has exited, close the memory window to continue");
+        }
+    }
+}
+
+fn create_window_error(err: EventLoopError) -> ComputerError {
+    let err = match err {
+        EventLoopError::RecreationAttempt => InstructionError::new(InstructionErrorKind::WindowAlreadyCreated),
+        _ => InstructionError::with_message(InstructionErrorKind::Other, err.to_string()),
+    };
+    ComputerError::new(ComputerErrorKind::InstructionError(err))
+}
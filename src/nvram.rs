@@ -0,0 +1,53 @@
+use std::cell::RefCell;
+use std::io::{Error, ErrorKind, Result};
+use std::path::PathBuf;
+use std::rc::Rc;
+use crate::memory_buffer::MemoryBuffer;
+
+/// the size of the persistent memory in bytes
+pub const NVRAM_SIZE: usize = 4096;
+
+/// battery backed memory used as a bank, it is loaded from the file when opened and written back when the computer is
+/// dropped so the contents are kept even when the program stops with an error
+pub struct Nvram {
+    path: PathBuf,
+    memory: Rc<RefCell<MemoryBuffer>>,
+}
+
+impl Nvram {
+    /// loads the contents of the file into the memory, the memory is zeroed if the file doesn't exist
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let mut memory = MemoryBuffer::with_maximum(NVRAM_SIZE, NVRAM_SIZE)
+            .map_err(| err | { Error::new(ErrorKind::OutOfMemory, err.to_string()) })?;
+
+        if path.exists() {
+            let data = std::fs::read(&path)?;
+            if data.len() > NVRAM_SIZE {
+                return Err(Error::new(ErrorKind::InvalidData, format!("the file is larger than {} bytes", NVRAM_SIZE)));
+            }
+            memory[..data.len()].copy_from_slice(&data);
+        }
+
+        Ok(Self {
+            path,
+            memory: Rc::new(RefCell::new(memory)),
+        })
+    }
+
+    pub fn memory(&self) -> Rc<RefCell<MemoryBuffer>> {
+        self.memory.clone()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        std::fs::write(&self.path, &self.memory.borrow()[..])
+    }
+}
+
+impl Drop for Nvram {
+    fn drop(&mut self) {
+        if let Err(err) = self.save() {
+            println!("could not save the nvram: {}, filename: {}", err, self.path.display());
+        }
+    }
+}
@@ -1,10 +1,11 @@
 use std::fmt::{Display, Formatter, Write};
-use crate::cpu::CpuArchitecture;
+use crate::cpu::{CpuArchitecture, SignedCpuArchitecture, FLOAT_REGISTER_COUNT};
 use std::result::Result;
 use std::str::FromStr;
 use std::io::{Read as IORead, Write as IOWrite};
-use crate::computer::Computer;
+use crate::computer::{Computer, REGISTER_COUNT};
 use crate::instructions::{InstructionError, InstructionErrorKind};
+use crate::memory::{RamError, RamErrorKind};
 use crate::read_ext::ReadLine;
 use crate::write_ext::WriteExt;
 
@@ -51,6 +52,45 @@ pub struct LiteralPointer {
     literal: Literal,
 }
 
+/// one of the f32 registers of the float instructions, eg: f1
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub struct FloatRegister {
+    index: u8,
+}
+
+/// two full size registers used as one value of twice the size by the long instructions, eg: x1:x2 where x1 holds the
+/// upper half
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub struct RegisterPair {
+    high: Register,
+    low: Register,
+}
+
+/// points to the address within the lower register of the pair in the bank within the upper register, independent of
+/// the selected bank, eg: word[x1:x2]
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub struct BankedPointer {
+    pointer: Pointer,
+    pair: RegisterPair,
+}
+
+/// points to the address within the register plus the displacement, eg: word[x1 + 4] or word[bp - 2]
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub struct DisplacementPointer {
+    pointer: Pointer,
+    register: Register,
+    displacement: Literal,
+}
+
+/// points to the address within the register plus the index register times the scale, eg: word[x1 + x2 * 2]
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub struct IndexPointer {
+    pointer: Pointer,
+    register: Register,
+    index: Register,
+    scale: u8,
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
 pub enum Operand {
     #[default]
@@ -59,20 +99,50 @@ pub enum Operand {
     Literal(Literal),
     RegisterPointer(RegisterPointer),
     LiteralPointer(LiteralPointer),
+    DisplacementPointer(DisplacementPointer),
+    IndexPointer(IndexPointer),
+    FloatRegister(FloatRegister),
+    RegisterPair(RegisterPair),
+    BankedPointer(BankedPointer),
 }
 
 const POINTER_PART: u8 = u8::MAX - size_of::<CpuArchitecture>().ilog2() as u8;
 const LITERAL_PART: u8 = POINTER_PART - 1;
-const REGISTER_CHARACTERS:[char;4] = ['l', 'x', 'e', 'r'];
-const POINTER_STRINGS:[&str;4] = ["byte", "word", "dword", "qword"];
+pub const REGISTER_CHARACTERS:[char;4] = ['l', 'x', 'e', 'r'];
+pub const POINTER_STRINGS:[&str;4] = ["byte", "word", "dword", "qword"];
 pub const STACK_POINTER_REGISTER: u8 = LITERAL_PART - 1;
-const STACK_POINTER_NAME: &str = "sp";
+pub const STACK_POINTER_NAME: &str = "sp";
+pub const BASE_POINTER_REGISTER: u8 = STACK_POINTER_REGISTER - 1;
+pub const BASE_POINTER_NAME: &str = "bp";
+/// follows a pointer that is encoded as a register and a displacement
+const DISPLACEMENT_PART: u8 = BASE_POINTER_REGISTER - 1;
+/// follows a pointer that is encoded as a register, an index register and a scale
+const INDEX_PART: u8 = DISPLACEMENT_PART - 1;
+/// followed by the index of a float register
+const FLOAT_REGISTER_PART: u8 = INDEX_PART - 1;
+pub const FLOAT_REGISTER_CHARACTER: char = 'f';
+/// followed by the upper and lower register of a register pair
+const REGISTER_PAIR_PART: u8 = FLOAT_REGISTER_PART - 1;
+pub const REGISTER_PAIR_SEPARATOR: char = ':';
+pub const INDEX_SCALES: [u8; 4] = [1, 2, 4, 8];
 
 impl Operand {
     pub fn from_stream(stream: &mut impl IORead) -> std::io::Result<Operand> {
         let lower = stream.read_type::<u8>()?;
         
         fn get_literal_or_register(lower: u8, stream: &mut impl IORead) -> std::io::Result<Operand> {
+            if lower == FLOAT_REGISTER_PART {
+                let index = stream.read_type::<u8>()?;
+                if !FloatRegister::is_valid_index(index) {
+                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("float register {} doesn't exist", index as u16 + 1)));
+                }
+                return Ok(Operand::FloatRegister(FloatRegister::new(index)));
+            }
+            if lower == REGISTER_PAIR_PART {
+                let high = Register { register: stream.read_type::<u8>()? };
+                let low = Register { register: stream.read_type::<u8>()? };
+                return Ok(Operand::RegisterPair(RegisterPair::new(high, low)));
+            }
             if lower == LITERAL_PART {
                 let literal = stream.read_type::<CpuArchitecture>()?;
                 Ok(Operand::Literal(
@@ -91,9 +161,26 @@ impl Operand {
         
         if lower >= POINTER_PART {
             let upper = stream.read_type::<u8>()?;
+            let pointer = Pointer { value: lower };
+            
+            if upper == DISPLACEMENT_PART {
+                let register = Register { register: stream.read_type::<u8>()? };
+                let displacement = Literal::new(stream.read_type::<CpuArchitecture>()?);
+                return Ok(Operand::DisplacementPointer(DisplacementPointer::new(register, displacement, pointer)));
+            }
+            if upper == REGISTER_PAIR_PART {
+                let high = Register { register: stream.read_type::<u8>()? };
+                let low = Register { register: stream.read_type::<u8>()? };
+                return Ok(Operand::BankedPointer(BankedPointer::new(RegisterPair::new(high, low), pointer)));
+            }
+            if upper == INDEX_PART {
+                let register = Register { register: stream.read_type::<u8>()? };
+                let index = Register { register: stream.read_type::<u8>()? };
+                let scale = stream.read_type::<u8>()?;
+                return Ok(Operand::IndexPointer(IndexPointer::new(register, index, scale, pointer)));
+            }
             
             let operand = get_literal_or_register(upper, stream)?;
-            let pointer = Pointer { value: lower };
             Ok(match operand {
                 Operand::Register(reg) => Operand::RegisterPointer(RegisterPointer::new(reg, pointer)),
                 Operand::Literal(lit) => Operand::LiteralPointer(LiteralPointer::new(lit, pointer)),
@@ -104,6 +191,34 @@ impl Operand {
         }
     }
     
+    /// the binary size of the encoded operand at the start of the buffer without decoding it
+    pub fn encoded_size(buffer: &[u8]) -> Option<CpuArchitecture> {
+        fn literal_or_register_size(lower: u8) -> CpuArchitecture {
+            if lower == FLOAT_REGISTER_PART {
+                FloatRegister::binary_size()
+            } else if lower == REGISTER_PAIR_PART {
+                RegisterPair::binary_size()
+            } else if lower == LITERAL_PART {
+                Literal::binary_size()
+            } else {
+                Register::binary_size()
+            }
+        }
+        
+        let lower = *buffer.first()?;
+        if lower >= POINTER_PART {
+            let upper = *buffer.get(1)?;
+            match upper {
+                DISPLACEMENT_PART => Some(DisplacementPointer::binary_size()),
+                INDEX_PART => Some(IndexPointer::binary_size()),
+                REGISTER_PAIR_PART => Some(BankedPointer::binary_size()),
+                _ => Some(Pointer::binary_size() + literal_or_register_size(upper)),
+            }
+        } else {
+            Some(literal_or_register_size(lower))
+        }
+    }
+    
     pub fn write_to_stream(self, stream: &mut impl IOWrite) -> std::io::Result<CpuArchitecture> {
         match self {
             Operand::Register(register) => register.write_to_stream(stream),
@@ -118,6 +233,32 @@ impl Operand {
                 Ok(register_pointer.register.write_to_stream(stream)? +
                     size_of_val(&register_pointer.pointer.value) as CpuArchitecture)
             }
+            Operand::DisplacementPointer(displacement_pointer) => {
+                stream.write_type(&displacement_pointer.pointer.value)?;
+                stream.write_type(&DISPLACEMENT_PART)?;
+                displacement_pointer.register.write_to_stream(stream)?;
+                stream.write_type(&displacement_pointer.displacement.literal())?;
+                Ok(DisplacementPointer::binary_size())
+            },
+            Operand::IndexPointer(index_pointer) => {
+                stream.write_type(&index_pointer.pointer.value)?;
+                stream.write_type(&INDEX_PART)?;
+                index_pointer.register.write_to_stream(stream)?;
+                index_pointer.index.write_to_stream(stream)?;
+                stream.write_type(&index_pointer.scale)?;
+                Ok(IndexPointer::binary_size())
+            },
+            Operand::FloatRegister(float_register) => {
+                stream.write_type(&FLOAT_REGISTER_PART)?;
+                stream.write_type(&float_register.index)?;
+                Ok(FloatRegister::binary_size())
+            },
+            Operand::RegisterPair(register_pair) => register_pair.write_to_stream(stream),
+            Operand::BankedPointer(banked_pointer) => {
+                stream.write_type(&banked_pointer.pointer.value)?;
+                banked_pointer.pair.write_to_stream(stream)?;
+                Ok(BankedPointer::binary_size())
+            },
             Operand::Nop => Ok(0),
         }
     }
@@ -128,6 +269,11 @@ impl Operand {
             Operand::Literal(_) => Literal::binary_size(),
             Operand::LiteralPointer(_) => Literal::binary_size() + Pointer::binary_size(),
             Operand::RegisterPointer(_) => Register::binary_size() + Pointer::binary_size(),
+            Operand::DisplacementPointer(_) => DisplacementPointer::binary_size(),
+            Operand::IndexPointer(_) => IndexPointer::binary_size(),
+            Operand::FloatRegister(_) => FloatRegister::binary_size(),
+            Operand::RegisterPair(_) => RegisterPair::binary_size(),
+            Operand::BankedPointer(_) => BankedPointer::binary_size(),
             Operand::Nop => 0,
         }
     }
@@ -138,33 +284,179 @@ impl Operand {
             Operand::Literal(_) => size_of::<CpuArchitecture>() as CpuArchitecture,
             Operand::LiteralPointer(_) => size_of::<CpuArchitecture>() as CpuArchitecture,
             Operand::RegisterPointer(pointer) => pointer.pointer.pointed_to_size(),
+            Operand::DisplacementPointer(pointer) => pointer.pointer.pointed_to_size(),
+            Operand::IndexPointer(pointer) => pointer.pointer.pointed_to_size(),
+            Operand::FloatRegister(_) => size_of::<f32>() as CpuArchitecture,
+            Operand::RegisterPair(_) => (size_of::<CpuArchitecture>() * 2) as CpuArchitecture,
+            Operand::BankedPointer(pointer) => pointer.pointer.pointed_to_size(),
             Operand::Nop => 0,
         }
     }
     
+    /// describes the kind of the operand
+    pub fn description(self) -> String {
+        match self {
+            Operand::Register(register) if register.is_stack_pointer() => "the stack pointer register".to_string(),
+            Operand::Register(register) if register.is_base_pointer() => "the base pointer register".to_string(),
+            Operand::Register(register) => format!("{} byte register", register.register_size()),
+            Operand::Literal(literal) => format!("literal {}", literal.literal()),
+            Operand::RegisterPointer(pointer) => 
+                format!("{} byte value at the address within register {}", pointer.pointed_to_size(), pointer.register()),
+            Operand::LiteralPointer(pointer) => 
+                format!("{} byte value at address 0x{:X}", pointer.pointed_to_size(), pointer.address()),
+            Operand::DisplacementPointer(pointer) => 
+                format!("{} byte value at the address within register {} plus {}", pointer.pointer.pointed_to_size(), pointer.register, pointer.displacement.literal() as SignedCpuArchitecture),
+            Operand::IndexPointer(pointer) => 
+                format!("{} byte value at the address within register {} plus register {} times {}", pointer.pointer.pointed_to_size(), pointer.register, pointer.index, pointer.scale),
+            Operand::FloatRegister(_) => "float register".to_string(),
+            Operand::RegisterPair(register_pair) => 
+                format!("{} byte value of the registers {} (upper) and {} (lower)", size_of::<CpuArchitecture>() * 2, register_pair.high, register_pair.low),
+            Operand::BankedPointer(pointer) => 
+                format!("{} byte value at the address within register {} in the bank within register {}", pointer.pointer.pointed_to_size(), pointer.pair.low, pointer.pair.high),
+            Operand::Nop => "no operand".to_string(),
+        }
+    }
+    
+    /// returns the register if it doesn't exist on a cpu with register_count registers, called before every
+    /// instruction so it doesn't allocate
+    pub fn validate_registers(self, register_count: u8) -> Result<(), Register> {
+        match self.register_slots().into_iter().flatten().find(| register | { !register.exists(register_count) }) {
+            Some(register) => Err(register),
+            None => Ok(()),
+        }
+    }
+    
+    /// the registers used by the operand, the registers of a pointer hold the address that is pointed to
+    pub fn registers(self) -> impl Iterator<Item = Register> {
+        self.register_slots().into_iter().flatten()
+    }
+    
+    /// the up to two registers used by the operand
+    fn register_slots(self) -> [Option<Register>; 2] {
+        match self {
+            Operand::Register(register) => [Some(register), None],
+            Operand::RegisterPointer(register_pointer) => [Some(register_pointer.register), None],
+            Operand::DisplacementPointer(displacement_pointer) => [Some(displacement_pointer.register), None],
+            Operand::IndexPointer(index_pointer) => [Some(index_pointer.register), Some(index_pointer.index)],
+            Operand::RegisterPair(register_pair) => [Some(register_pair.high), Some(register_pair.low)],
+            Operand::BankedPointer(banked_pointer) => [Some(banked_pointer.pair.high), Some(banked_pointer.pair.low)],
+            _ => [None, None],
+        }
+    }
+    
+    /// the address and the size of the memory the pointer points to, None for the other operands and banked pointers
+    pub fn pointed_to(self, computer: &Computer) -> Option<(CpuArchitecture, CpuArchitecture)> {
+        match self {
+            Operand::RegisterPointer(pointer) => Some((pointer.address(computer).ok()?, pointer.pointed_to_size())),
+            Operand::LiteralPointer(pointer) => Some((pointer.address(), pointer.pointed_to_size())),
+            Operand::DisplacementPointer(pointer) => Some((pointer.address(computer).ok()?, pointer.pointer.pointed_to_size())),
+            Operand::IndexPointer(pointer) => Some((pointer.address(computer).ok()?, pointer.pointer.pointed_to_size())),
+            _ => None,
+        }
+    }
+    
+    /// reads the value of the operand, registers have to be validated using validate_registers
     pub fn read_from_computer(self, computer: &Computer) -> Result<CpuArchitecture, InstructionError> {
         match self {
             Operand::Register(register) => 
-                computer.cpu().get_register(register).or_else(| err | { Err(err.into()) }),
+                Ok(computer.cpu().get_register_unchecked(register)),
             Operand::RegisterPointer(register_pointer) => 
                 register_pointer.get_pointed_to_value(computer),
             Operand::LiteralPointer(literal_pointer) => 
                 literal_pointer.get_pointed_to_value(computer),
+            Operand::DisplacementPointer(displacement_pointer) => 
+                displacement_pointer.get_pointed_to_value(computer),
+            Operand::IndexPointer(index_pointer) => 
+                index_pointer.get_pointed_to_value(computer),
+            Operand::BankedPointer(banked_pointer) => 
+                banked_pointer.get_pointed_to_value(computer),
             Operand::Literal(literal) => 
                 Ok(literal.literal()),
+            Operand::FloatRegister(_) => 
+                Err(InstructionError::new(InstructionErrorKind::FloatRegisterNotAllowed)),
+            Operand::RegisterPair(_) => 
+                Err(InstructionError::new(InstructionErrorKind::RegisterPairNotAllowed)),
             Operand::Nop => 
                 Err(InstructionError::new(InstructionErrorKind::OperandNop)),
         }
     }
+    
+    /// reads the value of a register pair or the value of any other operand as unsigned number of twice the size
+    pub fn read_long_from_computer(self, computer: &Computer) -> Result<u32, InstructionError> {
+        match self {
+            Operand::RegisterPair(register_pair) => {
+                let high = computer.cpu().get_register_unchecked(register_pair.high) as u32;
+                let low = computer.cpu().get_register_unchecked(register_pair.low) as u32;
+                Ok(high << CpuArchitecture::BITS | low)
+            },
+            _ => Ok(self.read_from_computer(computer)? as u32),
+        }
+    }
+    
+    /// writes the value to the operand, only register pairs can be written to
+    pub fn write_long_to_computer(self, computer: &mut Computer, value: u32) -> Result<(), InstructionError> {
+        match self {
+            Operand::RegisterPair(register_pair) => {
+                computer.cpu_mut().set_register_unchecked(register_pair.high, (value >> CpuArchitecture::BITS) as CpuArchitecture);
+                computer.cpu_mut().set_register_unchecked(register_pair.low, value as CpuArchitecture);
+                Ok(())
+            },
+            _ => Err(InstructionError::new(InstructionErrorKind::DestinationInvalid)),
+        }
+    }
+    
+    /// reads the value of a float register or converts the signed value of any other operand
+    pub fn read_float_from_computer(self, computer: &Computer) -> Result<f32, InstructionError> {
+        match self {
+            Operand::FloatRegister(float_register) => Ok(computer.cpu().get_float_register(float_register)),
+            _ => Ok(self.read_signed_from_computer(computer)? as f32),
+        }
+    }
+    
+    /// writes the value to the operand, only float registers can be written to
+    pub fn write_float_to_computer(self, computer: &mut Computer, value: f32) -> Result<(), InstructionError> {
+        match self {
+            Operand::FloatRegister(float_register) => {
+                computer.cpu_mut().set_float_register(float_register, value);
+                Ok(())
+            },
+            _ => Err(InstructionError::new(InstructionErrorKind::DestinationInvalid)),
+        }
+    }
+
+    /// reads the value of the operand as a two's complement number of the size of the operand
+    pub fn read_signed_from_computer(self, computer: &Computer) -> Result<SignedCpuArchitecture, InstructionError> {
+        let value = self.read_from_computer(computer)?;
+        let unused_bits = (size_of::<CpuArchitecture>() as u32 - self.size() as u32) * 8;
+        
+        Ok(((value << unused_bits) as SignedCpuArchitecture) >> unused_bits)
+    }
+
+    /// if the operand can be used as destination
+    pub fn is_writable(self) -> bool {
+        matches!(self, Operand::Register(_) | Operand::RegisterPointer(_) | Operand::LiteralPointer(_) |
+            Operand::DisplacementPointer(_) | Operand::IndexPointer(_) | Operand::BankedPointer(_))
+    }
 
+    /// writes the value to the operand, registers have to be validated using validate_registers
     pub fn write_to_computer(self, computer: &mut Computer, value: CpuArchitecture) -> Result<(), InstructionError> {
         match self {
-            Operand::Register(register) => 
-                computer.cpu_mut().set_register(register, value).or_else(| err | Err(err.into())),
+            Operand::Register(register) => {
+                computer.cpu_mut().set_register_unchecked(register, value);
+                Ok(())
+            },
             Operand::RegisterPointer(register_pointer) => 
                 register_pointer.set_pointed_to_value(computer, value),
             Operand::LiteralPointer(literal_pointer) => 
                 literal_pointer.set_pointed_to_value(computer, value),
+            Operand::DisplacementPointer(displacement_pointer) => 
+                displacement_pointer.set_pointed_to_value(computer, value),
+            Operand::IndexPointer(index_pointer) => 
+                index_pointer.set_pointed_to_value(computer, value),
+            Operand::BankedPointer(banked_pointer) => 
+                banked_pointer.set_pointed_to_value(computer, value),
+            Operand::FloatRegister(_) => Err(InstructionError::new(InstructionErrorKind::FloatRegisterNotAllowed)),
+            Operand::RegisterPair(_) => Err(InstructionError::new(InstructionErrorKind::RegisterPairNotAllowed)),
             _ => Err(InstructionError::new(InstructionErrorKind::DestinationInvalid)),
         }
     }
@@ -180,19 +472,40 @@ impl FromStr for Operand {
         }
         
         fn get_register_or_literal(s: &str) -> Result<Operand, InstructionError> {
-            if s == STACK_POINTER_NAME {
+            if s.eq_ignore_ascii_case(STACK_POINTER_NAME) {
                 return Ok(Operand::Register(Register::stack_pointer()));
             }
+            if s.eq_ignore_ascii_case(BASE_POINTER_NAME) {
+                return Ok(Operand::Register(Register::base_pointer()));
+            }
             
-            let first_char = s.as_bytes()[0];
+            let Some(&first_char) = s.as_bytes().first() else {
+                return Err(InstructionError::new(InstructionErrorKind::InvalidOperandString));
+            };
+            if (first_char as char).eq_ignore_ascii_case(&FLOAT_REGISTER_CHARACTER) {
+                return match u8::from_str(&s[1..]) {
+                    Ok(val) if val >= 1 && FloatRegister::is_valid_index(val - 1) => Ok(Operand::FloatRegister(FloatRegister::new(val - 1))),
+                    _ => Err(InstructionError::new(InstructionErrorKind::InvalidOperandString)),
+                };
+            }
             if let Some(index) = REGISTER_CHARACTERS.iter().position(| val | {
                 val.to_lowercase().eq((first_char as char).to_lowercase())
             }) {
                 let size = (2 as CpuArchitecture).pow(index as u32);
+                if size as usize > size_of::<CpuArchitecture>() {
+                    return Err(InstructionError::with_message(InstructionErrorKind::InvalidOperandString, format!("register {} is larger than the cpu architecture", s)));
+                }
+                
                 let result = u8::from_str(&s[1..]);
                 return match result {
-                    Ok(val) => Ok(Operand::Register(Register::new(val - 1, size as u8))),
-                    Err(_) => Err(InstructionError::new(InstructionErrorKind::InvalidOperandString)),
+                    Ok(val) if val >= 1 && Register::is_valid_index(val - 1) => {
+                        let register = Register::new(val - 1, size as u8);
+                        if !register.exists(REGISTER_COUNT as u8) {
+                            return Err(InstructionError::with_message(InstructionErrorKind::InvalidOperandString, format!("register {} does not exist, the last registers are only used as sp and bp", s)));
+                        }
+                        Ok(Operand::Register(register))
+                    },
+                    _ => Err(InstructionError::new(InstructionErrorKind::InvalidOperandString)),
                 }
             }
 
@@ -209,10 +522,62 @@ impl FromStr for Operand {
             if let Ok(val) = CpuArchitecture::from_str_radix(stripped, base) {
                 return Ok(Operand::Literal(Literal::new(val)));
             }
+            
+            // negative literals are stored as two's complement
+            if let Some(negative) = s.strip_prefix('-').filter(| negative | { !negative.is_empty() }) {
+                if let Operand::Literal(literal) = get_register_or_literal(negative)? {
+                    if literal.literal() <= SignedCpuArchitecture::MIN.unsigned_abs() {
+                        return Ok(Operand::Literal(Literal::new(literal.literal().wrapping_neg())));
+                    }
+                }
+            }
 
             Err(InstructionError::new(InstructionErrorKind::InvalidOperandString))
         }
         
+        /// parses `register + literal`, `register - literal` or `register + register * scale`, None if there is no offset
+        fn parse_offset_pointer(s: &str, pointer: Pointer) -> Result<Option<Operand>, InstructionError> {
+            // the first character is skipped as it is the sign of a negative literal
+            let Some(position) = s.char_indices().skip(1).find(| (_, char) | { *char == '+' || *char == '-' }).map(| (index, _) | { index }) else {
+                return Ok(None);
+            };
+            let is_negative = s.as_bytes()[position] == b'-';
+            let Operand::Register(register) = get_register_or_literal(s[..position].trim())? else {
+                return Err(InstructionError::with_message(InstructionErrorKind::InvalidOperandString, "the base of a pointer with an offset has to be a register"));
+            };
+            
+            let offset = s[position + 1..].trim();
+            let (offset, scale) = match offset.split_once('*') {
+                Some((offset, scale)) => match u8::from_str(scale.trim()) {
+                    Ok(scale) if INDEX_SCALES.contains(&scale) => (offset.trim(), Some(scale)),
+                    _ => return Err(InstructionError::with_message(InstructionErrorKind::InvalidOperandString, format!("the scale has to be one of {:?}", INDEX_SCALES))),
+                },
+                None => (offset, None),
+            };
+            
+            match (get_register_or_literal(offset)?, scale) {
+                (Operand::Literal(literal), None) => {
+                    let displacement = if is_negative { literal.literal().wrapping_neg() } else { literal.literal() };
+                    Ok(Some(Operand::DisplacementPointer(DisplacementPointer::new(register, Literal::new(displacement), pointer))))
+                },
+                (Operand::Register(index), scale) if !is_negative => 
+                    Ok(Some(Operand::IndexPointer(IndexPointer::new(register, index, scale.unwrap_or(1), pointer)))),
+                _ => Err(InstructionError::with_message(InstructionErrorKind::InvalidOperandString, "an index register can only be added and a literal cannot be scaled")),
+            }
+        }
+        
+        /// parses `register:register`, None if there is no separator
+        fn parse_register_pair(s: &str) -> Result<Option<RegisterPair>, InstructionError> {
+            let Some((high, low)) = s.split_once(REGISTER_PAIR_SEPARATOR) else {
+                return Ok(None);
+            };
+            match (get_register_or_literal(high.trim())?, get_register_or_literal(low.trim())?) {
+                (Operand::Register(high), Operand::Register(low)) if high.register_size() as usize == size_of::<CpuArchitecture>() &&
+                    low.register_size() as usize == size_of::<CpuArchitecture>() => Ok(Some(RegisterPair::new(high, low))),
+                _ => Err(InstructionError::with_message(InstructionErrorKind::InvalidOperandString, "a register pair consists of two full size registers")),
+            }
+        }
+        
         let option =  trimmed_str.find('[');
         match option { 
             Some(index) => {
@@ -226,18 +591,27 @@ impl FromStr for Operand {
                         Err(InstructionError::new(InstructionErrorKind::InvalidOperandString))
                     } else {
                         let inner_value = &trimmed_str[index + 1..trimmed_str.len() - 1];
+                        if let Some(pair) = parse_register_pair(inner_value)? {
+                            return Ok(Operand::BankedPointer(BankedPointer::new(pair, pointer)));
+                        }
+                        if let Some(operand) = parse_offset_pointer(inner_value.trim(), pointer)? {
+                            return Ok(operand);
+                        }
                         let operand = get_register_or_literal(inner_value)?;
                         match operand {
                             Operand::Literal(literal) => Ok(Operand::LiteralPointer(LiteralPointer::new(literal, pointer))),
                             Operand::Register(register) => Ok(Operand::RegisterPointer(RegisterPointer::new(register, pointer))),
-                            _ => unreachable!("the get_register_or_literal function should only return a literal or register"),
+                            _ => Err(InstructionError::with_message(InstructionErrorKind::InvalidOperandString, "the address of a pointer has to be a register or literal")),
                         }
                     }
                 } else {
                     get_register_or_literal(trimmed_str)
                 }
             },
-            None => get_register_or_literal(trimmed_str),
+            None => match parse_register_pair(trimmed_str)? {
+                Some(pair) => Ok(Operand::RegisterPair(pair)),
+                None => get_register_or_literal(trimmed_str),
+            },
         }
         
     }
@@ -262,6 +636,42 @@ impl Display for Operand {
                 literal_pointer.literal.literal().fmt(f)?;
                 f.write_char(']')
             },
+            Operand::DisplacementPointer(displacement_pointer) => {
+                let index = displacement_pointer.pointer.pointed_to_size().ilog2();
+                f.write_str(POINTER_STRINGS[index as usize])?;
+                f.write_char('[')?;
+                displacement_pointer.register.fmt(f)?;
+                let displacement = displacement_pointer.displacement.literal() as SignedCpuArchitecture;
+                if displacement < 0 {
+                    f.write_str(" - ")?;
+                } else {
+                    f.write_str(" + ")?;
+                }
+                displacement.unsigned_abs().fmt(f)?;
+                f.write_char(']')
+            },
+            Operand::IndexPointer(index_pointer) => {
+                let index = index_pointer.pointer.pointed_to_size().ilog2();
+                f.write_str(POINTER_STRINGS[index as usize])?;
+                f.write_char('[')?;
+                index_pointer.register.fmt(f)?;
+                f.write_str(" + ")?;
+                index_pointer.index.fmt(f)?;
+                if index_pointer.scale != 1 {
+                    f.write_str(" * ")?;
+                    index_pointer.scale.fmt(f)?;
+                }
+                f.write_char(']')
+            },
+            Operand::FloatRegister(float_register) => float_register.fmt(f),
+            Operand::RegisterPair(register_pair) => register_pair.fmt(f),
+            Operand::BankedPointer(banked_pointer) => {
+                let index = banked_pointer.pointer.pointed_to_size().ilog2();
+                f.write_str(POINTER_STRINGS[index as usize])?;
+                f.write_char('[')?;
+                banked_pointer.pair.fmt(f)?;
+                f.write_char(']')
+            },
             Operand::Nop => f.write_str("Nop"),
         }
     }
@@ -279,19 +689,38 @@ impl Register {
         }
     }
     
+    /// whether the registers of the index can be encoded without overlapping the special operand values
+    fn is_valid_index(index: u8) -> bool {
+        (index as u16 + 1) * (size_of::<CpuArchitecture>() as u16) < REGISTER_PAIR_PART as u16
+    }
+    
     pub fn stack_pointer() -> Self {
         Self {
             register: STACK_POINTER_REGISTER,
         }
     }
     
+    pub fn base_pointer() -> Self {
+        Self {
+            register: BASE_POINTER_REGISTER,
+        }
+    }
+    
     const fn parts_per_register() -> u8 {
         size_of::<CpuArchitecture>().ilog2() as u8 + 1
     }
     
+    /// whether the register exists on a cpu with cpu_size registers, the last two registers are sp and bp which can
+    /// only be used by their names
+    pub fn exists(self, cpu_size: u8) -> bool {
+        self.is_stack_pointer() || self.is_base_pointer() || self.register_number(cpu_size) < cpu_size - 2
+    }
+    
     pub fn register_number(self, cpu_size: u8) -> u8 {
         if self.is_stack_pointer() {
             cpu_size - 1
+        } else if self.is_base_pointer() {
+            cpu_size - 2
         } else {
             let parts = Self::parts_per_register();
             self.register / parts
@@ -299,6 +728,9 @@ impl Register {
     }
     
     pub fn register_size(self) -> u8 {
+        if self.is_stack_pointer() || self.is_base_pointer() {
+            return size_of::<CpuArchitecture>() as u8;
+        }
         let parts = Self::parts_per_register();
         2u8.pow((parts - self.register % parts - 1) as u32)
     }
@@ -316,17 +748,24 @@ impl Register {
     pub fn is_stack_pointer(self) -> bool {
         self.register == STACK_POINTER_REGISTER
     }
+    
+    pub fn is_base_pointer(self) -> bool {
+        self.register == BASE_POINTER_REGISTER
+    }
 }
 
 impl Display for Register {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         if self.is_stack_pointer() {
             f.write_str(STACK_POINTER_NAME)
+        } else if self.is_base_pointer() {
+            f.write_str(BASE_POINTER_NAME)
         } else {
             let size = self.register_size();
             let char = REGISTER_CHARACTERS[size.ilog2() as usize];
 
-            let index = self.register_number(u8::MAX) + 1;
+            // inverse of Register::new as register_number gives the index of the cpu register instead
+            let index = (self.register - (size.ilog2() as u8 + 1)) / size_of::<CpuArchitecture>() as u8 + 1;
 
             f.write_char(char)?;
             index.fmt(f)
@@ -334,6 +773,98 @@ impl Display for Register {
     }
 }
 
+impl FloatRegister {
+    pub fn new(index: u8) -> Self {
+        if !Self::is_valid_index(index) {
+            panic!("the cpu only has {} float registers", FLOAT_REGISTER_COUNT);
+        }
+        Self {
+            index
+        }
+    }
+    
+    fn is_valid_index(index: u8) -> bool {
+        (index as usize) < FLOAT_REGISTER_COUNT
+    }
+    
+    pub fn index(self) -> u8 {
+        self.index
+    }
+    
+    /// the float register marker and the index
+    pub const fn binary_size() -> CpuArchitecture {
+        (size_of::<u8>() * 2) as CpuArchitecture
+    }
+}
+
+impl Display for RegisterPair {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.high.fmt(f)?;
+        f.write_char(REGISTER_PAIR_SEPARATOR)?;
+        self.low.fmt(f)
+    }
+}
+
+impl Display for FloatRegister {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_char(FLOAT_REGISTER_CHARACTER)?;
+        (self.index + 1).fmt(f)
+    }
+}
+
+impl RegisterPair {
+    pub fn new(high: Register, low: Register) -> Self {
+        Self {
+            high,
+            low,
+        }
+    }
+    
+    pub fn write_to_stream(self, stream: &mut impl IOWrite) -> std::io::Result<CpuArchitecture> {
+        stream.write_type(&REGISTER_PAIR_PART)?;
+        self.high.write_to_stream(stream)?;
+        self.low.write_to_stream(stream)?;
+        Ok(Self::binary_size())
+    }
+    
+    /// the register pair marker and both registers
+    pub const fn binary_size() -> CpuArchitecture {
+        size_of::<u8>() as CpuArchitecture + Register::binary_size() * 2
+    }
+}
+
+impl BankedPointer {
+    pub fn new(pair: RegisterPair, pointer: Pointer) -> Self {
+        Self {
+            pointer,
+            pair,
+        }
+    }
+    
+    /// the pointer and the register pair
+    pub const fn binary_size() -> CpuArchitecture {
+        Pointer::binary_size() + RegisterPair::binary_size()
+    }
+    
+    /// the bank within the upper register, an error if it is larger than any bank can be
+    fn bank(self, computer: &Computer) -> Result<u8, InstructionError> {
+        let bank = computer.cpu().get_register_unchecked(self.pair.high);
+        u8::try_from(bank).map_err(| _ | {
+            InstructionError::with_message(InstructionErrorKind::RamError(RamError::new(RamErrorKind::BankDoesNotExist)), format!("bank: {}", bank))
+        })
+    }
+    
+    pub fn get_pointed_to_value(self, computer: &Computer) -> Result<CpuArchitecture, InstructionError> {
+        let address = computer.cpu().get_register_unchecked(self.pair.low);
+        self.pointer.get_pointed_to_bank_value(self.bank(computer)?, address, computer)
+    }
+    
+    pub fn set_pointed_to_value(self, computer: &mut Computer, value: CpuArchitecture) -> Result<(), InstructionError> {
+        let address = computer.cpu().get_register_unchecked(self.pair.low);
+        self.pointer.set_pointed_to_bank_value(self.bank(computer)?, address, computer, value)
+    }
+}
+
 impl Literal {
     pub fn new(literal: CpuArchitecture) -> Self {
         Self {
@@ -374,18 +905,26 @@ impl Pointer {
     }
     
     pub fn get_pointed_to_value(self, index: CpuArchitecture, computer: &Computer) -> Result<CpuArchitecture, InstructionError> {
+        self.get_pointed_to_bank_value(computer.cpu().get_bank(), index, computer)
+    }
+    
+    pub fn get_pointed_to_bank_value(self, bank: u8, index: CpuArchitecture, computer: &Computer) -> Result<CpuArchitecture, InstructionError> {
         let mut buffer = [0u8;size_of::<CpuArchitecture>()];
         let sized_buffer = &mut buffer[..self.pointed_to_size() as usize];
 
-        computer.ram().read_buffer_at_checked(index, sized_buffer)?;
+        computer.ram().read_bank_buffer_at(bank, index, sized_buffer)?;
         Ok(CpuArchitecture::from_ne_bytes(buffer))
     }
     
     pub fn set_pointed_to_value(self, index: CpuArchitecture, computer: &mut Computer, value: CpuArchitecture) -> Result<(), InstructionError> {
+        self.set_pointed_to_bank_value(computer.cpu().get_bank(), index, computer, value)
+    }
+    
+    pub fn set_pointed_to_bank_value(self, bank: u8, index: CpuArchitecture, computer: &mut Computer, value: CpuArchitecture) -> Result<(), InstructionError> {
         let bytes = value.to_ne_bytes();
         let sized_bytes = &bytes[..self.pointed_to_size() as usize];
 
-        computer.ram_mut().write_buffer_at_checked(index, sized_bytes)?;
+        computer.ram_mut().write_bank_buffer_at(bank, index, sized_bytes)?;
         Ok(())
     }
 }
@@ -436,10 +975,62 @@ impl RegisterPointer {
 
 impl PointerType for RegisterPointer {
     fn address(self, computer: &Computer) -> Result<CpuArchitecture, InstructionError> {
-        computer.cpu().get_register(self.register()).or_else(| err | Err(err.into()))
+        Ok(computer.cpu().get_register_unchecked(self.register()))
     }
 
     fn pointer(self) -> Pointer {
         self.pointer
     }
-}
\ No newline at end of file
+}
+impl DisplacementPointer {
+    pub fn new(register: Register, displacement: Literal, pointer: Pointer) -> Self {
+        Self {
+            pointer,
+            register,
+            displacement,
+        }
+    }
+    
+    /// the pointer, the displacement marker, the register and the displacement without a literal marker
+    pub const fn binary_size() -> CpuArchitecture {
+        Pointer::binary_size() + size_of::<u8>() as CpuArchitecture + Register::binary_size() + size_of::<CpuArchitecture>() as CpuArchitecture
+    }
+}
+
+impl PointerType for DisplacementPointer {
+    fn address(self, computer: &Computer) -> Result<CpuArchitecture, InstructionError> {
+        Ok(computer.cpu().get_register_unchecked(self.register).wrapping_add(self.displacement.literal()))
+    }
+
+    fn pointer(self) -> Pointer {
+        self.pointer
+    }
+}
+
+impl IndexPointer {
+    pub fn new(register: Register, index: Register, scale: u8, pointer: Pointer) -> Self {
+        Self {
+            pointer,
+            register,
+            index,
+            scale,
+        }
+    }
+    
+    /// the pointer, the index marker, both registers and the scale
+    pub const fn binary_size() -> CpuArchitecture {
+        Pointer::binary_size() + size_of::<u8>() as CpuArchitecture + Register::binary_size() * 2 + size_of::<u8>() as CpuArchitecture
+    }
+}
+
+impl PointerType for IndexPointer {
+    fn address(self, computer: &Computer) -> Result<CpuArchitecture, InstructionError> {
+        let base = computer.cpu().get_register_unchecked(self.register);
+        let index = computer.cpu().get_register_unchecked(self.index);
+        Ok(base.wrapping_add(index.wrapping_mul(self.scale as CpuArchitecture)))
+    }
+
+    fn pointer(self) -> Pointer {
+        self.pointer
+    }
+}
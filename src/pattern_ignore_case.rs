@@ -37,7 +37,7 @@ unsafe impl<'a, 'b> Searcher<'b> for IgnoreCaseSearcher<'a, 'b> {
 
     fn next(&mut self) -> SearchStep {
         let end = self.position + self.value.len();
-        if end >= self.haystack.len() {
+        if end > self.haystack.len() {
             SearchStep::Done
         } else {
             let total_found = self.haystack.as_bytes()[self.position..].iter()
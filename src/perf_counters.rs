@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+use crate::cpu::CpuArchitecture;
+
+/// the statistics of a run, only gathered when running with --time
+#[derive(Default)]
+pub struct PerfCounters {
+    instructions: u64,
+    syscalls: u64,
+    cpu_time: Duration,
+    syscall_time: Duration,
+    redraws: u64,
+    peak_heap: CpuArchitecture,
+    /// the time spent by the instructions executed within the instructions being timed,
+    /// eg: the instructions run by the window inside the CreateWindow syscall
+    nested_time: Vec<Duration>,
+}
+
+impl PerfCounters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// starts timing an instruction, ended by end_instruction
+    pub fn begin_instruction(&mut self) -> Instant {
+        self.nested_time.push(Duration::ZERO);
+        Instant::now()
+    }
+
+    /// the time of the instructions executed within it isn't counted twice
+    pub fn end_instruction(&mut self, start: Instant, instructions: u64, syscall: bool) {
+        let elapsed = start.elapsed();
+        let nested_time = self.nested_time.pop().unwrap_or_default();
+        if let Some(parent_nested_time) = self.nested_time.last_mut() {
+            *parent_nested_time += elapsed;
+        }
+
+        let own_time = elapsed.saturating_sub(nested_time);
+        self.instructions += instructions;
+        if syscall {
+            self.syscalls += 1;
+            self.syscall_time += own_time;
+        } else {
+            self.cpu_time += own_time;
+        }
+    }
+
+    pub fn on_redraw(&mut self) {
+        self.redraws += 1;
+    }
+
+    /// the heap can only grow by a syscall or when the program is loaded, so it is only measured then
+    pub fn measure_heap(&mut self, heap: CpuArchitecture) {
+        self.peak_heap = self.peak_heap.max(heap);
+    }
+
+    /// the lines shown after the exit code
+    pub fn report(&self, memory_size: CpuArchitecture) -> String {
+        format!("instructions: {} ({} user, {} syscalls)\ncpu time: {} ms, syscall time: {} ms\nredraws: {}\npeak heap: {}/{} bytes",
+                self.instructions, self.instructions - self.syscalls, self.syscalls,
+                self.cpu_time.as_nanos() as f64 / 1e6, self.syscall_time.as_nanos() as f64 / 1e6,
+                self.redraws, self.peak_heap, memory_size)
+    }
+}
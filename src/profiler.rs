@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use crate::call_stack::ENTRY_ADDRESS;
+use crate::cpu::CpuArchitecture;
+
+#[derive(Default, Clone, Copy)]
+struct FunctionCount {
+    /// the instructions executed by the function itself
+    own: u64,
+    /// the instructions executed while the function was on the call stack, including the functions it called
+    total: u64,
+}
+
+/// counts the executed instructions of every function using the call stack, only used when running with --profile
+pub struct Profiler {
+    functions: HashMap<CpuArchitecture, String>,
+    counts: HashMap<CpuArchitecture, FunctionCount>,
+    instructions: u64,
+    /// the functions on the call stack of the current sample, a recursive function is only counted once
+    active: Vec<CpuArchitecture>,
+}
+
+impl Profiler {
+    pub fn new(functions: Vec<(String, CpuArchitecture)>) -> Self {
+        Self {
+            functions: functions.into_iter().map(| (name, address) | { (address, name) }).collect(),
+            counts: HashMap::new(),
+            instructions: 0,
+            active: Vec::new(),
+        }
+    }
+
+    /// counts the instructions for the function that is executing and every function that called it
+    pub fn sample(&mut self, functions: impl Iterator<Item = CpuArchitecture>, instructions: u64) {
+        self.active.clear();
+        self.active.push(ENTRY_ADDRESS);
+        self.active.extend(functions);
+
+        let current = *self.active.last().unwrap(); // the entry is always active
+        self.counts.entry(current).or_default().own += instructions;
+        self.active.sort_unstable();
+        self.active.dedup();
+        for function in &self.active {
+            self.counts.entry(*function).or_default().total += instructions;
+        }
+        self.instructions += instructions;
+    }
+
+    fn function_name(&self, address: CpuArchitecture) -> String {
+        match self.functions.get(&address) {
+            Some(name) => format!("{} (0x{:X})", name, address),
+            None => format!("0x{:X}", address),
+        }
+    }
+
+    /// the table of the functions sorted by the instructions executed by the function itself
+    pub fn report(&self) -> String {
+        let mut counts: Vec<_> = self.counts.iter().collect();
+        counts.sort_by(| (a_address, a), (b_address, b) | {
+            b.own.cmp(&a.own).then(b.total.cmp(&a.total)).then(a_address.cmp(b_address))
+        });
+
+        let names: Vec<_> = counts.iter().map(| (address, _) | { self.function_name(**address) }).collect();
+        let name_width = names.iter().map(String::len).max().unwrap_or(0).max("function".len());
+        let percentage = | count: u64 | { count as f64 * 100.0 / self.instructions.max(1) as f64 };
+
+        let mut lines = vec![format!("{:<name_width$} {:>12} {:>7} {:>12} {:>7}", "function", "self", "self %", "total", "total %")];
+        for (name, (_, count)) in names.iter().zip(counts) {
+            lines.push(format!("{:<name_width$} {:>12} {:>6.2}% {:>12} {:>6.2}%",
+                               name, count.own, percentage(count.own), count.total, percentage(count.total)));
+        }
+        lines.join("\n")
+    }
+}
@@ -1,17 +1,17 @@
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter, Write, UpperHex};
 use std::io::{Read, Error, Write as IOWrite, ErrorKind, SeekFrom, Seek};
 use std::str::FromStr;
 use itertools::Itertools;
 use crate::compile::DEBUG;
 use crate::cpu::{CpuArchitecture, IntoBytes};
-use crate::instructions::{InstructionSet, Instruction, InstructionError, Call, Jmp, Is, Break};
-use crate::memory::{AllocatedRam, Ram, RamError};
+use crate::instructions::{InstructionSet, Instruction, InstructionError, InstructionErrorKind, Call, Tailcall, Addr, Switch, Case, Jmp, Rjmp, Rcall, Is, Break, Brk, Push, Jz, Jnz, Jc, Jnc, Js, Jns, Jo, Jno, Jl, Jle, Jg, Jge, Ja, Jbe, resolve_alias};
+use crate::memory::{AllocatedRam, Permissions, Ram, RamError};
 use crate::error_creator;
 use crate::instruction_iter::Instructions;
-use crate::operand::{Literal, Operand};
-use crate::read_ext::ReadLine;
+use crate::operand::{Literal, Operand, STACK_POINTER_NAME};
+use crate::read_ext::{LineReader, ReadLine};
 use crate::write_ext::WriteExt;
 use crate::cpu::read_instruction;
 use crate::dependency::Dependency;
@@ -20,11 +20,22 @@ error_creator!(
     ProgramError,
     ProgramErrorKind,
     ProgramTooLarge => "The program that is currently is too large to be allocated to memory",
+    /// two functions or two labels with the same name
     FunctionAlreadyExits => "A function/label with the same name already exists",
+    /// a call or jmp to a misspelled function or label
     InvalidProgram => "program is invalid due to calls/jmp to functions/labels that don't exist",
     CannotReadDependency => "An error occurred while reading a dependency",
+    /// the function doesn't exist within the dependency or the dependency has been build from an older source
     DependencyFunctionDoesntExist => "A function within a dependency cannot be found",
+    /// the dependency has been built by an older emulator whose instructions behave differently, it has to be built again
+    DependencyOutdated => "A dependency has been built for an older version of the emulator",
     DependencyHasInvalidInstruction => "A dependency has a invalid instruction",
+    /// the dependency has been built for a cpu with a different word size than the emulator
+    DependencyWrongArchitecture => "A dependency has been built for a different cpu architecture",
+    /// the binary file is truncated, corrupted or not build by the emulator
+    InvalidBinary => "The binary file is malformed",
+    /// a directive other than %title, %author or %version, or a value longer than 253 bytes
+    InvalidDirective => "The directive is invalid",
     RamError(RamError) => "",
     InstructionError(InstructionError) => ""
 );
@@ -39,24 +50,96 @@ macro_rules! create_control_flows {
         $identifier:expr,
         $index:expr
     ) => {
-        let name = stringify!($control_flow_name);
-        if $trimmed_line.len() >= name.len() && $trimmed_line[..name.len()].eq_ignore_ascii_case(name) {
-            let control_flow_name = $trimmed_line[name.len()..].trim();
-            if CpuArchitecture::from_str(control_flow_name).is_err() {
-                Program::add_temporary_control_flow_instruction::<$control_flow_name>(&mut $instructions, &mut $temp_control_flows, &mut $control_flows, control_flow_name);
-                return Ok($index + $control_flow_name::const_function_binary_size() + INSTRUCTION_SIZE);
-            }
+        if let Some(control_flow_name) = Program::control_flow_name($trimmed_line, stringify!($control_flow_name)) {
+            Program::add_temporary_control_flow_instruction::<$control_flow_name>(&mut $instructions, &mut $temp_control_flows, &mut $control_flows, control_flow_name);
+            return Ok($index + $control_flow_name::const_function_binary_size() + INSTRUCTION_SIZE);
         }
         
         if let Some(stripped) = $identifier {
-            Program::on_control_flow_found::<$control_flow_name>(&mut $instructions, &mut $control_flows, &mut $temp_control_flows, stripped, $index)?;
+            Program::on_control_flow_found(&mut $instructions, &mut $control_flows, &mut $temp_control_flows, stripped, $index)?;
 
             return Ok($index);
         }
     };
 }
 
+/// the flag jumps use the labels of jmp, labels are only defined using jmp
+macro_rules! create_flag_jumps {
+    (
+        $program:expr,
+        $trimmed_line:expr,
+        $index:expr,
+        $($flag_jump:ident),*
+    ) => {
+        $(
+            create_control_flows!($program.instructions, $program.temporary_jmp_instructions, $program.labels, $trimmed_line, $flag_jump, None::<&str>, $index);
+        )*
+    };
+}
+
 pub const DEPENDENCY_EXTENSION:&str = ".dat";
+/// the version of the binaries that are built, binaries built before the version was stored are version 0
+pub const BINARY_VERSION: u8 = 1;
+/// from this version reading the compare flag doesn't reset it anymore, clf resets it instead
+pub const KEEP_CMP_FLAG_VERSION: u8 = 1;
+/// the version is stored as a function table entry without instructions named this byte followed by the version,
+/// so older emulators read it as an empty function
+const VERSION_ENTRY_PREFIX: u8 = 0;
+/// the metadata is stored as function table entries without instructions named this byte, the index of the directive and the value
+const METADATA_ENTRY_PREFIX: u8 = 1;
+/// the word size of the cpu the binary is built for is stored as a function table entry without instructions named this
+/// byte followed by the bits, binaries without it are built for 16 bit cpus
+const ARCH_ENTRY_PREFIX: u8 = 2;
+/// the directives that describe the program, eg: `%title Snake`
+const METADATA_DIRECTIVES: [&str; 3] = ["title", "author", "version"];
+/// the longest value of a directive, the entry name also holds the prefix and the index of the directive
+const MAX_METADATA_LENGTH: usize = u8::MAX as usize - 2;
+/// the pseudo instruction that pushes the arguments of a call, see parse_call_arguments
+const CALL_ARGUMENTS_NAME: &str = "callargs";
+/// smaller sources are parsed on a single thread, measured on generated programs (see the parsing_timings test) a line
+/// takes about 0.36µs on a single thread, with threads the instruction parsing takes about 0.26µs per line divided over
+/// the threads but the resolving pass still takes 0.22µs and starting the threads about 35µs, so 4 threads save about
+/// 0.3ms (20%) at 4096 lines while the saving of smaller sources is within the noise
+const PARALLEL_LINE_COUNT: usize = 4096;
+/// with fewer threads parsing in parallel is slower than on a single thread as the resolving pass redoes a part of the work
+const MIN_PARALLEL_THREAD_COUNT: usize = 4;
+
+/// the description of the program given by the %title, %author and %version directives
+#[derive(Clone, Default, Debug)]
+pub struct Metadata {
+    values: [Option<String>; METADATA_DIRECTIVES.len()],
+}
+
+impl Metadata {
+    pub fn title(&self) -> Option<&str> {
+        self.values[0].as_deref()
+    }
+
+    /// the title, version and author shown when the program is run, None if the program has no metadata
+    pub fn description(&self) -> Option<String> {
+        if self.values.iter().all(Option::is_none) {
+            return None;
+        }
+
+        let [title, author, version] = &self.values;
+        let mut description = title.clone().unwrap_or_else(| | { "untitled program".to_string() });
+        if let Some(version) = version {
+            description.push_str(&format!(" version {}", version));
+        }
+        if let Some(author) = author {
+            description.push_str(&format!(" by {}", author));
+        }
+        Some(description)
+    }
+
+    /// the names of the function table entries that store the metadata
+    fn entry_names(&self) -> Vec<Vec<u8>> {
+        self.values.iter().enumerate().filter_map(| (index, value) | {
+            let value = value.as_ref()?;
+            Some([METADATA_ENTRY_PREFIX, index as u8].into_iter().chain(value.bytes()).collect())
+        }).collect()
+    }
+}
 
 pub struct Program {
     instructions: Instructions,
@@ -64,6 +147,12 @@ pub struct Program {
     temporary_call_instructions: HashMap<String, Vec<usize>>,
     labels: HashMap<String, CpuArchitecture>,
     temporary_jmp_instructions: HashMap<String, Vec<usize>>,
+    /// the sizes of the arguments pushed by callargs by the return address of the call, used for the backtrace
+    call_arguments: HashMap<CpuArchitecture, Vec<CpuArchitecture>>,
+    /// the positions of the jmp/call instructions that become rjmp/rcall once their label/function is found
+    relative_control_flows: Vec<usize>,
+    version: u8,
+    metadata: Metadata,
 }
 
 pub const INSTRUCTION_SIZE: CpuArchitecture = get_instruction_size(InstructionSet::max_instruction_number());
@@ -82,6 +171,10 @@ impl Program {
             temporary_call_instructions: HashMap::with_capacity(4),
             labels: HashMap::with_capacity(4),
             temporary_jmp_instructions: HashMap::with_capacity(4),
+            call_arguments: HashMap::new(),
+            relative_control_flows: Vec::new(),
+            version: BINARY_VERSION,
+            metadata: Metadata::default(),
         }
     }
 
@@ -92,6 +185,10 @@ impl Program {
             temporary_call_instructions: HashMap::with_capacity(4),
             labels: HashMap::with_capacity(4),
             temporary_jmp_instructions: HashMap::with_capacity(4),
+            call_arguments: HashMap::new(),
+            relative_control_flows: Vec::new(),
+            version: BINARY_VERSION,
+            metadata: Metadata::default(),
         }
     }
 
@@ -99,9 +196,64 @@ impl Program {
         self.instructions.push(instruction);
     }
 
+    /// the version of the binary the program was read from, programs parsed from source are the current version
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// the name of the function table entry that stores the version
+    pub fn version_entry_name(version: u8) -> String {
+        String::from_utf8_lossy(&[VERSION_ENTRY_PREFIX, version]).into_owned()
+    }
+
+    /// the name of the function table entry that stores the word size of the cpu
+    pub fn arch_entry_name(bits: u32) -> String {
+        String::from_utf8_lossy(&[ARCH_ENTRY_PREFIX, bits as u8]).into_owned()
+    }
+
+    /// the word size in bits of the cpu the binary with the function table entries is built for
+    pub fn binary_arch<'a>(entry_names: impl IntoIterator<Item = &'a [u8]>) -> u32 {
+        entry_names.into_iter().find_map(| name | {
+            match name {
+                [ARCH_ENTRY_PREFIX, bits] => Some(*bits as u32),
+                _ => None,
+            }
+        }).unwrap_or(u16::BITS)
+    }
+
+    pub fn instructions(&self) -> &Instructions {
+        &self.instructions
+    }
+
+    /// the positions of the calls to functions of other programs, their address is only known once the program is allocated
+    pub fn dependency_calls(&self) -> impl Iterator<Item = usize> + '_ {
+        self.temporary_call_instructions.values().flatten().copied()
+    }
+
+    pub fn call_arguments(&self) -> &HashMap<CpuArchitecture, Vec<CpuArchitecture>> {
+        &self.call_arguments
+    }
+
+    /// the functions within the program sorted by their address, functions at the same address are sorted by name
+    pub fn sorted_functions(&self) -> Vec<(String, CpuArchitecture)> {
+        let mut functions: Vec<_> = self.functions.iter()
+            .map(| (name, address) | { (name.clone(), *address) })
+            .collect();
+        functions.sort_by(| a, b | {
+            a.1.cmp(&b.1).then_with(| | { a.0.cmp(&b.0) })
+        });
+        functions
+    }
+
+    /// the dependencies are sorted by name so they are always placed in the same order
     fn get_dependencies(temp_call_ins: &HashMap<String, Vec<usize>>) -> Result<Vec<Dependency>> {
-        Dependency::get_dependencies(temp_call_ins.iter()
-            .map(| (name, _) | { name.as_str() }))
+        Dependency::get_dependencies(temp_call_ins.keys()
+            .map(| name | { name.as_str() })
+            .sorted())
     }
 
     fn binary_size(&self, dependencies: &[Dependency]) -> Result<(CpuArchitecture, CpuArchitecture)> {
@@ -136,6 +288,7 @@ impl Program {
         if !self.temporary_jmp_instructions.is_empty() {
             let instructions = self.temporary_jmp_instructions.iter()
                 .map(| v | { v.0 } )
+                .sorted()
                 .join(", ");
             return Err(ProgramError::with_message(ProgramErrorKind::InvalidProgram, format!("jmps: [{}]", instructions)));
         }
@@ -145,6 +298,7 @@ impl Program {
         let mut allocated_ram = ram.alloc(binary_size)?;
 
         Self::allocate_iter(dependencies, &mut self.instructions, &mut allocated_ram, instruction_size, &mut self.temporary_call_instructions)?;
+        allocated_ram.set_permissions(Permissions::READ_EXECUTE);
 
         Ok(allocated_ram)
     }
@@ -158,7 +312,7 @@ impl Program {
     ) -> Result<()> {
         let mut dependency_position = instruction_size;
         for dependency in dependencies.iter() {
-            let option = Self::try_set_temp_instruction_instruction::<Call>(dependency.function_name().as_str(), dependency_position, tmp_call_instr, instructions);
+            let option = Self::try_set_temp_instruction_instruction(dependency.function_name().as_str(), dependency_position, tmp_call_instr, instructions);
             if option.is_none() {
                 unreachable!("this should not be possible as it shouldn't have been found as a dependency");
             }
@@ -190,10 +344,7 @@ impl Program {
             return Ok(0);
         }
 
-        let mut functions:Vec<_> = self.functions.into_iter().collect();
-        functions.sort_by(| a, b | {
-            a.1.cmp(&b.1)
-        });
+        let functions = self.sorted_functions();
 
         let starting_function_position = functions[0].1;
         let mut function_names_size = 0;
@@ -201,10 +352,24 @@ impl Program {
             function_names_size += function_name.len();
         }
 
-        let total_identification_size = (function_names_size + (size_of::<CpuArchitecture>() + size_of::<u8>()) * functions.len() + size_of::<u32>()) as u32;
+        let version_name = Self::version_entry_name(BINARY_VERSION);
+        let mut empty_entries = self.metadata.entry_names();
+        empty_entries.insert(0, version_name.into_bytes());
+        empty_entries.insert(1, Self::arch_entry_name(CpuArchitecture::BITS).into_bytes());
+        let empty_entries_size: usize = empty_entries.iter().map(Vec::len).sum();
+        let total_identification_size = (function_names_size + empty_entries_size + (size_of::<CpuArchitecture>() + size_of::<u8>()) * (functions.len() + empty_entries.len()) + size_of::<u32>()) as u32;
         stream.write_type(&total_identification_size)?;
         let mut bytes_written = size_of_val(&total_identification_size);
 
+        // the version, the arch and the metadata don't have instructions
+        for name in empty_entries {
+            stream.write_type(&(name.len() as u8))?;
+            bytes_written += size_of::<u8>();
+            bytes_written += stream.write(&name)?;
+            stream.write_type(&(0 as CpuArchitecture))?;
+            bytes_written += size_of::<CpuArchitecture>();
+        }
+
         for index in 0..(functions.len() - 1) {
             let (function_name, function_position) = &functions[index];
 
@@ -247,7 +412,11 @@ impl Program {
         stream.write_type(&length)?;
         bytes_written += size_of_val(&length);
 
-        let mut ram = Ram::new(binary_size + 1); // +1 as first byte cannot be allocated
+        let result = Ram::new(binary_size + 1); // +1 as first byte cannot be allocated
+        let mut ram = match result {
+            Ok(val) => val,
+            Err(err) => return Err(Error::new(ErrorKind::Other, err.to_string())),
+        };
         let mut alloc = ram.alloc(binary_size).unwrap(); // should never give an error here
 
         let a = self.instructions.iter().take_while(| (_, position) | {
@@ -268,12 +437,24 @@ impl Program {
         temp_instructions: &mut HashMap<String, Vec<usize>>,
         control_flows: &mut HashMap<String, CpuArchitecture>,
         function_name:&str
+    ) {
+        Self::add_temporary_control_flow(instructions, temp_instructions, control_flows, function_name, I::from(Operand::Literal(Literal::new(0))).into());
+    }
+
+    /// adds the control flow instruction with the address of the function/label, the address is set once the
+    /// function/label is found when it hasn't been found yet
+    fn add_temporary_control_flow(
+        instructions:&mut Instructions,
+        temp_instructions: &mut HashMap<String, Vec<usize>>,
+        control_flows: &mut HashMap<String, CpuArchitecture>,
+        function_name:&str,
+        instruction: InstructionSet
     ) {
         if let Some(address) = control_flows.get(function_name) {
-            instructions.push(I::from(Operand::Literal(Literal::new(*address))).into());
+            instructions.push(instruction.with_control_flow_address(Operand::Literal(Literal::new(*address))).unwrap()); // is a control flow instruction
         } else {
             let position = instructions.len();
-            instructions.push(I::from(Operand::Literal(Literal::new(0))).into());
+            instructions.push(instruction);
             let temp_locations = match temp_instructions.entry(function_name.to_string()) {
                 Entry::Occupied(o) => o.into_mut(),
                 Entry::Vacant(v) => v.insert(Vec::new()),
@@ -282,7 +463,7 @@ impl Program {
         }
     }
 
-    fn try_set_temp_instruction_instruction<I : Is<Other = InstructionSet> + Into<InstructionSet> + From<Operand>>(
+    fn try_set_temp_instruction_instruction(
         control_flow_name: &str,
         control_flow_index: CpuArchitecture,
         temp_instructions: &mut HashMap<String, Vec<usize>>,
@@ -293,11 +474,11 @@ impl Program {
             Some(vec) => {
                 for position in vec {
                     let instruction = instructions[position];
-                    if I::is(&instruction).is_none() {
-                        unreachable!("instruction here must be a control flow instruction, got: {}", instruction.as_ref());
-                    }
-
-                    instructions[position] = I::from(Operand::Literal(Literal::new(control_flow_index))).into();
+                    let option = instruction.with_control_flow_address(Operand::Literal(Literal::new(control_flow_index)));
+                    instructions[position] = match option {
+                        Some(instruction) => instruction,
+                        None => unreachable!("instruction here must be a control flow instruction, got: {}", instruction.as_ref()),
+                    };
                 }
 
                 Some(())
@@ -306,17 +487,37 @@ impl Program {
         }
     }
 
-    fn on_control_flow_found<I : Into<InstructionSet> + From<Operand> + Is<Other = InstructionSet>>(instructions: &mut Instructions, control_flows: &mut HashMap<String, CpuArchitecture>, temp_instructions: &mut HashMap<String, Vec<usize>>, control_flow_name: &str, control_flow_index: CpuArchitecture) -> Result<()> {
+    fn on_control_flow_found(instructions: &mut Instructions, control_flows: &mut HashMap<String, CpuArchitecture>, temp_instructions: &mut HashMap<String, Vec<usize>>, control_flow_name: &str, control_flow_index: CpuArchitecture) -> Result<()> {
         let function_string = control_flow_name.to_string();
         let inserted = control_flows.insert(function_string, control_flow_index);
         if inserted.is_some() {
             return Err(ProgramError::with_message(ProgramErrorKind::FunctionAlreadyExits, format!("function/label name: {}", control_flow_name)));
         }
 
-        Self::try_set_temp_instruction_instruction::<I>(control_flow_name, control_flow_index, temp_instructions, instructions);
+        Self::try_set_temp_instruction_instruction(control_flow_name, control_flow_index, temp_instructions, instructions);
         Ok(())
     }
 
+    /// returns the function/label name if the line is a call/jmp to a function/label instead of an address,
+    /// the name has to be followed by whitespace so eg: jl doesn't match jle
+    fn control_flow_name<'a>(trimmed_line: &'a str, name: &str) -> Option<&'a str> {
+        if trimmed_line.len() > name.len() && trimmed_line.is_char_boundary(name.len()) && trimmed_line[..name.len()].eq_ignore_ascii_case(name) &&
+            trimmed_line[name.len()..].starts_with(char::is_whitespace) {
+            let control_flow_name = trimmed_line[name.len()..].trim();
+            if CpuArchitecture::from_str(control_flow_name).is_err() {
+                return Some(control_flow_name);
+            }
+        }
+        None
+    }
+
+    fn is_instruction_line(trimmed_line: &str) -> bool {
+        !trimmed_line.is_empty() &&
+            !trimmed_line.starts_with('.') &&
+            !trimmed_line.ends_with(':') &&
+            InstructionSet::CONTROL_FLOW_NAMES.iter().all(| name | { Self::control_flow_name(trimmed_line, name).is_none() })
+    }
+
     fn remove_comments(line: &str) -> &str {
         if let Some(index) = line.find(';') {
             &line[..index]
@@ -325,23 +526,197 @@ impl Program {
         }
     }
 
-    fn parse_line(&mut self, line: &str, index: CpuArchitecture, line_number: u32) -> Result<CpuArchitecture> {
-        let trimmed_line = Self::remove_comments(line).trim();
+    /// expands `callargs function, argument1, argument2, ...` into pushing the arguments from the last to the first,
+    /// calling the function and removing the arguments from the stack again, the first argument is directly below the
+    /// return address
+    fn parse_call_arguments(&mut self, arguments: &str, mut index: CpuArchitecture) -> std::result::Result<CpuArchitecture, InstructionError> {
+        let mut split = arguments.split(',').map(| argument | { argument.trim() });
+        let function = split.next().unwrap_or_default(); // split always returns at least one value
+        let arguments: Vec<_> = split.collect();
+
+        let mut sizes = Vec::with_capacity(arguments.len());
+        for argument in arguments.iter().rev() {
+            let operand = Operand::from_str(argument)?;
+            let push: InstructionSet = Push::new(operand).into();
+            index += push.binary_size() + INSTRUCTION_SIZE;
+            sizes.push(operand.size());
+            self.add(push);
+        }
+
+        if let Ok(address) = CpuArchitecture::from_str(function) {
+            self.add(Call::from(Operand::Literal(Literal::new(address))).into());
+        } else {
+            Self::add_temporary_control_flow_instruction::<Call>(&mut self.instructions, &mut self.temporary_call_instructions, &mut self.functions, function);
+        }
+        index += Call::const_function_binary_size() + INSTRUCTION_SIZE;
+
+        let total_size: CpuArchitecture = sizes.iter().sum();
+        sizes.reverse();
+        self.call_arguments.insert(index, sizes);
+        if total_size != 0 {
+            let sub = InstructionSet::from_str(&format!("sub {}, {}", STACK_POINTER_NAME, total_size))?;
+            index += sub.binary_size() + INSTRUCTION_SIZE;
+            self.add(sub);
+        }
+
+        Ok(index)
+    }
+
+    /// parses `switch index, table, count` where the table is a label with the cases or an operand with its address
+    fn parse_switch(&mut self, operands: &str, index: CpuArchitecture) -> std::result::Result<CpuArchitecture, InstructionError> {
+        let operands: Vec<_> = operands.split(',').map(| operand | { operand.trim() }).collect();
+        if operands.len() != Switch::OPERAND_COUNT as usize {
+            return Err(InstructionError::with_message(InstructionErrorKind::InvalidOperandCount, format!("got {} operands, expected {}", operands.len(), Switch::OPERAND_COUNT)));
+        }
+
+        let switch_index = Operand::from_str(operands[0])?;
+        let count = Operand::from_str(operands[2])?;
+        match Operand::from_str(operands[1]) {
+            Ok(table) => self.add(Switch::new(switch_index, table, count).into()),
+            Err(_) => {
+                let switch = Switch::new(switch_index, Operand::Literal(Literal::new(0)), count);
+                Self::add_temporary_control_flow(&mut self.instructions, &mut self.temporary_jmp_instructions, &mut self.labels, operands[1], switch.into());
+            },
+        }
+
+        Ok(index + self.instructions.last().unwrap().binary_size() + INSTRUCTION_SIZE) // the switch has just been added
+    }
+
+    /// parses `addr destination, function` where the function is a name or an operand with its address
+    fn parse_address(&mut self, operands: &str, index: CpuArchitecture) -> std::result::Result<CpuArchitecture, InstructionError> {
+        let operands: Vec<_> = operands.split(',').map(| operand | { operand.trim() }).collect();
+        if operands.len() != Addr::OPERAND_COUNT as usize {
+            return Err(InstructionError::with_message(InstructionErrorKind::InvalidOperandCount, format!("got {} operands, expected {}", operands.len(), Addr::OPERAND_COUNT)));
+        }
+
+        let destination = Operand::from_str(operands[0])?;
+        match Operand::from_str(operands[1]) {
+            Ok(address) => self.add(Addr::new(destination, address).into()),
+            Err(_) => {
+                let addr = Addr::new(destination, Operand::Literal(Literal::new(0)));
+                Self::add_temporary_control_flow(&mut self.instructions, &mut self.temporary_call_instructions, &mut self.functions, operands[1], addr.into());
+            },
+        }
+
+        Ok(index + self.instructions.last().unwrap().binary_size() + INSTRUCTION_SIZE) // the addr has just been added
+    }
+
+    /// parses `jmp label`, `rjmp label` and `rcall function` into a jmp/call to the address, which is made relative
+    /// by resolve_relative_control_flows once all labels and functions are known
+    fn parse_relative_control_flow(&mut self, trimmed_line: &str, index: CpuArchitecture) -> Option<CpuArchitecture> {
+        let label = Self::control_flow_name(trimmed_line, stringify!(Jmp))
+            .or_else(| | { Self::control_flow_name(trimmed_line, stringify!(Rjmp)) });
+        if let Some(label) = label {
+            self.relative_control_flows.push(self.instructions.len());
+            Self::add_temporary_control_flow_instruction::<Jmp>(&mut self.instructions, &mut self.temporary_jmp_instructions, &mut self.labels, label);
+            return Some(index + Rjmp::const_function_binary_size() + INSTRUCTION_SIZE);
+        }
+
+        if let Some(function) = Self::control_flow_name(trimmed_line, stringify!(Rcall)) {
+            self.relative_control_flows.push(self.instructions.len());
+            Self::add_temporary_control_flow_instruction::<Call>(&mut self.instructions, &mut self.temporary_call_instructions, &mut self.functions, function);
+            return Some(index + Rcall::const_function_binary_size() + INSTRUCTION_SIZE);
+        }
+        None
+    }
+
+    /// replaces the jmp/call instructions of parse_relative_control_flow with an rjmp/rcall to the same address,
+    /// jmps to missing labels are kept so allocating reports them
+    fn resolve_relative_control_flows(&mut self) -> Result<()> {
+        let relative_control_flows = std::mem::take(&mut self.relative_control_flows);
+        let relative_calls = self.temporary_call_instructions.iter()
+            .filter(| (_, positions) | { positions.iter().any(| position | { relative_control_flows.contains(position) }) })
+            .map(| (name, _) | { name })
+            .sorted()
+            .join(", ");
+        if !relative_calls.is_empty() {
+            return Err(ProgramError::with_message(ProgramErrorKind::InvalidProgram, format!("relative calls to functions of other programs: [{}]", relative_calls)));
+        }
+
+        let missing_labels: HashSet<_> = self.temporary_jmp_instructions.values().flatten().copied().collect();
+        let next_positions: Vec<_> = self.instructions.iter()
+            .map(| (instruction, position) | { position + instruction.binary_size() + INSTRUCTION_SIZE })
+            .collect();
+        for position in relative_control_flows {
+            if missing_labels.contains(&position) {
+                continue;
+            }
+
+            let instruction = self.instructions[position];
+            let Some(Operand::Literal(address)) = instruction.control_flow_address() else {
+                unreachable!("instruction here must be a control flow instruction to an address, got: {}", instruction.as_ref());
+            };
+            let offset = Operand::Literal(Literal::new(address.literal().wrapping_sub(next_positions[position])));
+            self.instructions[position] = match instruction {
+                InstructionSet::Jmp(_) => Rjmp::new(offset).into(),
+                _ => Rcall::new(offset).into(),
+            };
+        }
+        Ok(())
+    }
+
+    /// sets the metadata of a directive line without the %, eg: `title Snake`, returns the error message if it is invalid
+    fn parse_directive(&mut self, directive: &str) -> std::result::Result<(), String> {
+        let (name, value) = directive.split_once(char::is_whitespace).unwrap_or((directive, ""));
+        let value = value.trim();
+        let value = value.strip_prefix('"').and_then(| value | { value.strip_suffix('"') }).unwrap_or(value);
+
+        let Some(index) = METADATA_DIRECTIVES.iter().position(| directive | { directive.eq_ignore_ascii_case(name) }) else {
+            return Err(format!("directive: %{}, expected one of: %{}", name, METADATA_DIRECTIVES.join(", %")));
+        };
+        if value.len() > MAX_METADATA_LENGTH {
+            return Err(format!("directive: %{}, the value is {} bytes, the maximum is {}", name, value.len(), MAX_METADATA_LENGTH));
+        }
+        self.metadata.values[index] = Some(value.to_string());
+        Ok(())
+    }
+
+    /// parses the line, parsed is the instruction of the line if it has already been parsed
+    fn parse_line(&mut self, line: &str, parsed: Option<InstructionSet>, index: CpuArchitecture, line_number: u32) -> Result<CpuArchitecture> {
+        let trimmed_line = resolve_alias(Self::remove_comments(line).trim());
+        let trimmed_line = trimmed_line.as_ref();
         if trimmed_line.is_empty() {
             return Ok(index);
         }
 
+        if let Some(directive) = trimmed_line.strip_prefix('%') {
+            return self.parse_directive(directive)
+                .map_err(| message | { ProgramError::with_message(ProgramErrorKind::InvalidDirective, format!("line number: {}, {}", line_number, message)) })
+                .map(| _ | index);
+        }
+        if let Some(arguments) = Self::control_flow_name(trimmed_line, CALL_ARGUMENTS_NAME) {
+            return self.parse_call_arguments(arguments, index)
+                .map_err(| err | { ProgramError::with_message(ProgramErrorKind::InstructionError(err), format!("line number: {}, line: {}", line_number, line)) });
+        }
+        if let Some(operands) = Self::control_flow_name(trimmed_line, stringify!(Addr)) {
+            return self.parse_address(operands, index)
+                .map_err(| err | { ProgramError::with_message(ProgramErrorKind::InstructionError(err), format!("line number: {}, line: {}", line_number, line)) });
+        }
+        if let Some(operands) = Self::control_flow_name(trimmed_line, stringify!(Switch)) {
+            return self.parse_switch(operands, index)
+                .map_err(| err | { ProgramError::with_message(ProgramErrorKind::InstructionError(err), format!("line number: {}, line: {}", line_number, line)) });
+        }
+        if let Some(index) = self.parse_relative_control_flow(trimmed_line, index) {
+            return Ok(index);
+        }
         create_control_flows!(self.instructions, self.temporary_jmp_instructions, self.labels, trimmed_line, Jmp, trimmed_line.strip_prefix('.'), index);
         create_control_flows!(self.instructions, self.temporary_call_instructions, self.functions, trimmed_line, Call, trimmed_line.strip_suffix(':'), index);
-
-        let result = InstructionSet::from_str(trimmed_line);
+        create_control_flows!(self.instructions, self.temporary_call_instructions, self.functions, trimmed_line, Tailcall, None::<&str>, index);
+        create_flag_jumps!(self, trimmed_line, index, Jz, Jnz, Jc, Jnc, Js, Jns, Jo, Jno, Jl, Jle, Jg, Jge, Ja, Jbe);
+        // the cases of a switch table also use the labels of jmp
+        create_control_flows!(self.instructions, self.temporary_jmp_instructions, self.labels, trimmed_line, Case, None::<&str>, index);
+
+        let result = match parsed {
+            Some(instruction) => Ok(instruction),
+            None => InstructionSet::from_str(trimmed_line),
+        };
         let instruction = match result {
             Ok(val) => val,
             Err(err) => return Err(ProgramError::with_message(ProgramErrorKind::InstructionError(err), format!("line number: {}, line: {}", line_number, line)))
         };
 
         let binary_size = if !DEBUG.get() &&
-            Break::is(&instruction).is_some() {
+            (Break::is(&instruction).is_some() || Brk::is(&instruction).is_some()) {
             0
         } else {
             self.add(instruction);
@@ -351,44 +726,136 @@ impl Program {
         Ok(index + binary_size)
     }
 
+    /// parses the instructions of the lines in a chunk per thread, None is returned for lines which aren't instructions
+    /// or contain errors as these are handled by parse_line, with a single thread every line is left to parse_line
+    fn parse_instructions(lines: &[String], thread_count: usize) -> Vec<Option<InstructionSet>> {
+        if thread_count <= 1 {
+            return vec![None; lines.len()];
+        }
+
+        let chunk_size = lines.len().div_ceil(thread_count);
+        std::thread::scope(| scope | {
+            let handles = lines.chunks(chunk_size).map(| chunk | {
+                scope.spawn(move || {
+                    chunk.iter().map(| line | {
+                        let trimmed_line = resolve_alias(Self::remove_comments(line).trim());
+                        if Self::is_instruction_line(&trimmed_line) {
+                            InstructionSet::from_str(&trimmed_line).ok()
+                        } else {
+                            None
+                        }
+                    }).collect::<Vec<_>>()
+                })
+            }).collect::<Vec<_>>();
+
+            handles.into_iter().flat_map(| handle | {
+                handle.join().expect("parsing thread panicked")
+            }).collect()
+        })
+    }
+
     pub fn from_stream(reader: &mut impl Read) -> std::io::Result<Self> {
+        let mut lines = Vec::new();
+        let mut line_reader = LineReader::new(reader);
+        while let Some(line) = line_reader.next_line()? {
+            lines.push(line.iter().map(| character | { *character as char }).collect::<String>());
+        }
+
+        let thread_count = std::thread::available_parallelism().map_or(1, | count | { count.get() });
+        let thread_count = if lines.len() < PARALLEL_LINE_COUNT || thread_count < MIN_PARALLEL_THREAD_COUNT {
+            1
+        } else {
+            thread_count
+        };
+        Self::from_lines(&lines, thread_count)
+    }
+
+    /// parses the instructions using thread_count threads and resolves the labels and functions afterwards
+    fn from_lines(lines: &[String], thread_count: usize) -> std::io::Result<Self> {
+        let parsed = Self::parse_instructions(lines, thread_count);
+
+        // labels and functions are resolved sequentially as they depend on the index of all previous lines
         let mut program = Self::new();
-        let mut str_buffer = String::with_capacity(128);
         let mut index = 0;
-        let mut line_number = 0;
+        for (line_number, (line, instruction)) in lines.iter().zip(parsed).enumerate() {
+            index = program.parse_line(line, instruction, index, line_number as u32 + 1)
+                .map_err(| err | { Error::other(err.to_string()) })?;
+        }
+        program.resolve_relative_control_flows()
+            .map_err(| err | { Error::other(err.to_string()) })?;
 
-        reader.read_lines(| line | {
-            line_number += 1;
-            for character in line {
-                str_buffer.push(*character as char);
-            }
+        Ok(program)
+    }
 
-            match program.parse_line(&str_buffer, index, line_number) {
-                Err(err) => return Err(err),
-                Ok(i) => index = i,
-            }
-            str_buffer.clear();
+    fn create_invalid_binary_error(message: String) -> Error {
+        let err = ProgramError::with_message(ProgramErrorKind::InvalidBinary, message);
+        Error::new(ErrorKind::InvalidData, err.to_string())
+    }
 
-            Ok(false)
-        })?;
+    /// validates the function table between the header and the instructions and returns the version and metadata of the binary,
+    /// the lengths of all functions have to add up to the length of the instructions and the binary has to be built for
+    /// the word size of the cpu
+    fn validate_binary_functions(reader: &mut impl Read, instruction_offset: u32, length: u64) -> std::io::Result<(u8, Metadata)> {
+        let mut index = size_of::<u32>() as u64;
+        let mut total_function_length = 0u64;
+        let mut version = 0;
+        let mut arch = u16::BITS;
+        let mut metadata = Metadata::default();
+        let mut name_buffer = [0u8;u8::MAX as usize];
+        while index < instruction_offset as u64 {
+            let name_length = reader.read_type::<u8>()? as u64;
+            if index + size_of::<u8>() as u64 + name_length + size_of::<CpuArchitecture>() as u64 > instruction_offset as u64 {
+                return Err(Self::create_invalid_binary_error(format!("field: function table, entry at offset {} exceeds the instruction offset {}", index, instruction_offset)));
+            }
+            reader.read_exact(&mut name_buffer[..name_length as usize])?;
+            match name_buffer[..name_length as usize] {
+                [VERSION_ENTRY_PREFIX, entry_version] => version = entry_version,
+                [ARCH_ENTRY_PREFIX, bits] => arch = bits as u32,
+                [METADATA_ENTRY_PREFIX, directive, ref value @ ..] if (directive as usize) < METADATA_DIRECTIVES.len() => {
+                    metadata.values[directive as usize] = Some(String::from_utf8_lossy(value).into_owned());
+                },
+                _ => {},
+            }
+            total_function_length += reader.read_type::<CpuArchitecture>()? as u64;
+            index += size_of::<u8>() as u64 + name_length + size_of::<CpuArchitecture>() as u64;
+        }
 
-        Ok(program)
+        if arch != CpuArchitecture::BITS {
+            return Err(Self::create_invalid_binary_error(format!("field: arch, the binary is built for a {} bit cpu, the emulator runs a {} bit cpu", arch, CpuArchitecture::BITS)));
+        }
+        if total_function_length != length {
+            return Err(Self::create_invalid_binary_error(format!("field: function lengths, total length {} doesn't match the instruction length {}", total_function_length, length)));
+        }
+        Ok((version, metadata))
     }
 
     pub fn from_binary(mut reader: &mut (impl Read+Seek)) -> std::io::Result<Self> {
+        let file_size = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+        if file_size < size_of::<u32>() as u64 {
+            return Err(Self::create_invalid_binary_error(format!("field: header, file size {} is smaller than the header", file_size)));
+        }
+
         let instruction_offset = reader.read_type::<u32>()?;
+        if (instruction_offset as u64) < size_of::<u32>() as u64 || instruction_offset as u64 > file_size {
+            return Err(Self::create_invalid_binary_error(format!("field: instruction offset, offset {} is outside of the file of size {}", instruction_offset, file_size)));
+        }
 
-        let mut total_bytes_read = 0;
-        let length = reader.seek(SeekFrom::End(0))? - instruction_offset as u64;
+        let length = file_size - instruction_offset as u64;
+        if length > CpuArchitecture::MAX as u64 {
+            return Err(Self::create_invalid_binary_error(format!("field: instruction offset, instruction length {} exceeds the maximum program size {}", length, CpuArchitecture::MAX)));
+        }
 
-        reader.seek(SeekFrom::Start(instruction_offset as u64))?;
+        let (version, metadata) = Self::validate_binary_functions(reader, instruction_offset, length)?;
 
-        let mut instructions = Instructions::with_capacity((length / 4) as usize);
+        let mut total_bytes_read = 0;
+        // every instruction is at least the instruction number
+        let mut instructions = Instructions::with_capacity((length / INSTRUCTION_SIZE as u64) as usize);
         while total_bytes_read < length {
             let result = read_instruction(&mut reader);
             let (instruction, bytes_read) = match result {
                 Ok(val) => val,
-                Err(err) => return Err(Error::new(ErrorKind::Other, err.to_string())),
+                Err(err) => return Err(Self::create_invalid_binary_error(format!("field: instructions, {} at offset {}", err, instruction_offset as u64 + total_bytes_read))),
             };
 
             total_bytes_read += bytes_read as u64;
@@ -397,6 +864,8 @@ impl Program {
 
         let mut program = Self::new();
         program.instructions = instructions;
+        program.version = version;
+        program.metadata = metadata;
         Ok(program)
     }
 
@@ -411,7 +880,7 @@ impl Program {
                 str_buffer.push(*character as char);
             }
 
-            match program.parse_line(&str_buffer, index, line_number) {
+            match program.parse_line(&str_buffer, None, index, line_number) {
                 Err(err) => return Err(err),
                 Ok(i) => index = i,
             }
@@ -439,13 +908,29 @@ impl FromStr for Program {
         let mut index = 0;
 
         for (line_number, line) in split.enumerate() {
-            index = program.parse_line(line, index, line_number as u32)?;
+            index = program.parse_line(line, None, index, line_number as u32)?;
         }
+        program.resolve_relative_control_flows()?;
 
         Ok(program)
     }
 }
 
+fn write_function_instruction_to_fmt(program: &Program, instruction: &InstructionSet, address: Operand, outer: &str, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let addr = match address {
+        Operand::Literal(l) => l.literal(),
+        _ => CpuArchitecture::MAX
+    };
+    if addr == 0 &&
+        !program.functions.iter().any(| func | {
+            *func.1 == 0
+        }) {
+        f.write_str(outer)
+    } else {
+        instruction.fmt(f)
+    }
+}
+
 fn write_instruction_to_fmt(program: &Program, instruction: &InstructionSet, addr: CpuArchitecture, f: &mut Formatter<'_>) -> std::fmt::Result {
     f.write_str("0x")?;
     UpperHex::fmt(&addr, f)?;
@@ -453,18 +938,10 @@ fn write_instruction_to_fmt(program: &Program, instruction: &InstructionSet, add
 
     match instruction {
         InstructionSet::Call(c) => {
-            let addr = match c.address() {
-                Operand::Literal(l) => l.literal(),
-                _ => CpuArchitecture::MAX
-            };
-            if addr == 0 &&
-                !program.functions.iter().any(| func | {
-                    *func.1 == 0
-                }) {
-                f.write_str( concat!(stringify!(Call), " outer::function"))
-            } else {
-                instruction.fmt(f)
-            }
+            write_function_instruction_to_fmt(program, instruction, c.address(), concat!(stringify!(Call), " outer::function"), f)
+        },
+        InstructionSet::Tailcall(c) => {
+            write_function_instruction_to_fmt(program, instruction, c.address(), concat!(stringify!(Tailcall), " outer::function"), f)
         },
         _ => instruction.fmt(f)
     }
@@ -489,4 +966,151 @@ impl Display for Program {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::str::FromStr;
+    use super::*;
+
+    fn build(source: &str) -> Vec<u8> {
+        let mut binary = Vec::new();
+        Program::from_str(source).unwrap().write_as_library(&mut binary).unwrap();
+        binary
+    }
+
+    #[test]
+    fn binary_records_its_arch() {
+        let mut binary = build("main:
+            mov l1, 0
+            exit");
+        assert!(Program::from_binary(&mut Cursor::new(&binary)).is_ok());
+
+        let entry = Program::arch_entry_name(CpuArchitecture::BITS).into_bytes();
+        let index = binary.windows(entry.len()).position(| window | { window == entry }).unwrap();
+        binary[index + 1] = 32;
+        let error = Program::from_binary(&mut Cursor::new(&binary)).err().unwrap();
+        assert!(error.to_string().contains("32 bit"), "{}", error);
+    }
+
+    #[test]
+    fn builds_are_reproducible() {
+        let directory = std::env::temp_dir().join(format!("emulator-reproducible-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        // zero is empty so it has the same address as one
+        let library_source = "%title math
+            zero:
+            one:
+            mov x2, 1
+            ret
+            two:
+            mov x2, 2
+            ret
+            three:
+            .start
+            mov x2, 3
+            ret";
+        for library in ["alpha", "beta", "gamma"] {
+            std::fs::write(directory.join(format!("{}.dat", library)), build(library_source)).unwrap();
+        }
+
+        let dependency = | library: &str, function: &str | { format!("{}::{}", directory.join(library).display(), function) };
+        let source = format!("%title reproducible
+            %author emulator
+            main:
+            call {}
+            call {}
+            call {}
+            call first
+            call second
+            exit
+            first:
+            call {}
+            ret
+            second:
+            call third
+            ret
+            third:
+            call {}
+            ret",
+            dependency("gamma", "three"), dependency("alpha", "one"), dependency("beta", "two"),
+            dependency("beta", "one"), dependency("gamma", "two"));
+
+        let first_build = build(&source);
+        // every program gets different hash map seeds so the builds would differ if the output depended on their order
+        for _ in 0..8 {
+            assert_eq!(build(library_source), build(library_source));
+            assert_eq!(build(&source), first_build);
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    /// a program with the functions, each of 12 lines with labels, comments, aliases, pointers and calls
+    fn generated_lines(function_count: usize) -> Vec<String> {
+        let mut lines = vec!["main:".to_string(), "    call f0".to_string(), "    exit".to_string()];
+        for index in 0..function_count {
+            let next = (index + 1) % function_count;
+            lines.extend([
+                format!("f{}: ; function {}", index, index),
+                format!("    mov x2, {}", index % 1000),
+                "    push x2".to_string(),
+                format!("    .loop{}", index),
+                "    add x2, 1".to_string(),
+                format!("    cmpl x2, {}", index % 1000 + 3),
+                format!("    jmp loop{}", index),
+                "    mov word[sp - 2], x2 ; overwrites the pushed value".to_string(),
+                "    pop x3".to_string(),
+                "    jne skip".to_string(),
+                format!("    call f{}", next),
+                "    ret".to_string(),
+            ]);
+        }
+        lines
+    }
+
+    fn binary(program: Program) -> Vec<u8> {
+        let mut binary = Vec::new();
+        program.write_as_library(&mut binary).unwrap();
+        binary
+    }
+
+    #[test]
+    fn parallel_parsing_gives_the_same_program() {
+        let mut lines = generated_lines(PARALLEL_LINE_COUNT / 12 + 1);
+        // the skip label is only defined once so the jne instructions are resolved in the second pass
+        lines.push("skip:".to_string());
+        lines.push("    ret".to_string());
+
+        let sequential = binary(Program::from_lines(&lines, 1).unwrap());
+        for thread_count in [2, 3, 8] {
+            assert_eq!(binary(Program::from_lines(&lines, thread_count).unwrap()), sequential, "threads: {}", thread_count);
+        }
+
+        // the errors are still reported by the sequential pass with the line they are on
+        let middle = lines.len() / 2;
+        lines[middle] = "    mov x2, x99".to_string();
+        let sequential = Program::from_lines(&lines, 1).err().unwrap().to_string();
+        let parallel = Program::from_lines(&lines, 4).err().unwrap().to_string();
+        assert_eq!(parallel, sequential);
+    }
+
+    /// prints how long parsing takes with a single and with multiple threads, used to choose PARALLEL_LINE_COUNT,
+    /// run using cargo test --release parsing_timings -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn parsing_timings() {
+        let thread_count = std::thread::available_parallelism().map_or(1, | count | { count.get() }).max(MIN_PARALLEL_THREAD_COUNT);
+        for line_count in [256, 1024, 2048, 4096, 16384, 65536] {
+            let mut lines = generated_lines(line_count / 12);
+            lines.push("skip:".to_string());
+            let time = | thread_count: usize | {
+                (0..5).map(| _ | {
+                    let start = std::time::Instant::now();
+                    Program::from_lines(&lines, thread_count).unwrap();
+                    start.elapsed()
+                }).min().unwrap()
+            };
+            println!("{} lines: 1 thread {:?}, {} threads {:?}", lines.len(), time(1), thread_count, time(thread_count));
+        }
+    }
+}
@@ -1,87 +1,105 @@
 use std::io::{Read, Result, Error, ErrorKind};
 use crate::cpu::FromBytes;
 
-pub trait ReadLine : Read {
-    fn read_lines<F, E : std::error::Error>(&mut self, mut callback:F) -> Result<usize>
-        where F : FnMut(&[u8]) -> std::result::Result<bool, E>
-    {
-        let mut vec = Vec::new();
-        let mut buffer = [0u8;1024];
+const LINE_BUFFER_SIZE: usize = 1024;
 
-        let mut total_read_bytes = 0;
+/// buffered line reader which splits on `\n`, `\r\n` and `\r`,
+/// the line endings themselves are never part of the returned line
+pub struct LineReader<'a, R : Read + ?Sized> {
+    reader: &'a mut R,
+    buffer: [u8; LINE_BUFFER_SIZE],
+    position: usize,
+    length: usize,
+    line: Vec<u8>,
+    skip_line_feed: bool,
+    total_read_bytes: usize,
+}
 
+impl<'a, R : Read + ?Sized> LineReader<'a, R> {
+    pub fn new(reader: &'a mut R) -> Self {
+        Self {
+            reader,
+            buffer: [0u8; LINE_BUFFER_SIZE],
+            position: 0,
+            length: 0,
+            line: Vec::with_capacity(128),
+            skip_line_feed: false,
+            total_read_bytes: 0,
+        }
+    }
+    
+    pub fn total_read_bytes(&self) -> usize {
+        self.total_read_bytes
+    }
+    
+    /// refills the buffer, returns false when the end of the stream has been reached
+    fn fill_buffer(&mut self) -> Result<bool> {
         loop {
-            let read_bytes = self.read(&mut buffer)?;
-            if read_bytes == 0 {
-                return Ok(total_read_bytes);
+            match self.reader.read(&mut self.buffer) {
+                Ok(read_bytes) => {
+                    self.position = 0;
+                    self.length = read_bytes;
+                    self.total_read_bytes += read_bytes;
+                    return Ok(read_bytes != 0);
+                },
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
             }
-
-            let current_buffer = &buffer[..read_bytes];
-
-            let option = current_buffer.iter().position(| b | { *b == b'\n' });
-            let mut position = match option {
-                Some(val) => val,
-                None => { vec.extend_from_slice(current_buffer); continue; }
-            };
-
-            if !vec.is_empty() {
-                vec.extend_from_slice(&current_buffer[..position]);
-                let result = callback(vec.as_slice());
-                match result {
-                    Ok(stop) => if stop { return Ok(read_bytes); }
-                    Err(err) => return Err(Error::new(ErrorKind::Other, err.to_string()))
-                }
-                vec.clear();
-            } else {
-                let result = callback(&current_buffer[..position]);
-                match result {
-                    Ok(stop) => if stop { return Ok(read_bytes); }
-                    Err(err) => return Err(Error::new(ErrorKind::Other, err.to_string()))
+        }
+    }
+    
+    /// returns the next line or None if the end of the stream has been reached
+    pub fn next_line(&mut self) -> Result<Option<&[u8]>> {
+        self.line.clear();
+        
+        loop {
+            if self.position == self.length && !self.fill_buffer()? {
+                return Ok(if self.line.is_empty() { None } else { Some(self.line.as_slice()) });
+            }
+            
+            // the \n of a \r\n can be in the next chunk so it has to be skipped here
+            if self.skip_line_feed {
+                self.skip_line_feed = false;
+                if self.buffer[self.position] == b'\n' {
+                    self.position += 1;
+                    continue;
                 }
             }
-
-            position += 1;
-            let mut previous_position = position;
-            while {
-                let option = if position < current_buffer.len() {
-                    current_buffer[position..].iter().position(| b | { *b == b'\n' })
-                } else {
-                    None
-                };
-                position = match option {
-                    Some(val) => val,
-                    None => { if read_bytes == buffer.len() { vec.extend_from_slice(&current_buffer[position..]); } 0 }
-                } + position;
-                option.is_some()
-            } {
-                let result = callback(&current_buffer[previous_position..position]);
-                match result {
-                    Ok(stop) => if stop { return Ok(read_bytes); }
-                    Err(err) => return Err(Error::new(ErrorKind::Other, err.to_string()))
+            
+            let current_buffer = &self.buffer[self.position..self.length];
+            let option = current_buffer.iter().position(| b | { *b == b'\n' || *b == b'\r' });
+            match option {
+                Some(index) => {
+                    self.line.extend_from_slice(&current_buffer[..index]);
+                    self.skip_line_feed = current_buffer[index] == b'\r';
+                    self.position += index + 1;
+                    return Ok(Some(self.line.as_slice()));
+                },
+                None => {
+                    self.line.extend_from_slice(current_buffer);
+                    self.position = self.length;
                 }
-
-                position += 1;
-                previous_position = position;
             }
+        }
+    }
+}
 
-            total_read_bytes += read_bytes;
-            if read_bytes != buffer.len() {
-                if !vec.is_empty() {
-                    let result = callback(vec.as_slice());
-                    match result {
-                        Ok(stop) => if stop { return Ok(read_bytes); }
-                        Err(err) => return Err(Error::new(ErrorKind::Other, err.to_string()))
-                    }
-                } else {
-                    let result = callback(&current_buffer[position..]);
-                    match result {
-                        Ok(stop) => if stop { return Ok(read_bytes); }
-                        Err(err) => return Err(Error::new(ErrorKind::Other, err.to_string()))
-                    }
-                }
-                return Ok(total_read_bytes);
+pub trait ReadLine : Read {
+    /// calls the callback for every line within the stream, the callback can return true to stop reading
+    fn read_lines<F, E : std::error::Error>(&mut self, mut callback:F) -> Result<usize>
+        where F : FnMut(&[u8]) -> std::result::Result<bool, E>
+    {
+        let mut line_reader = LineReader::new(self);
+        
+        while let Some(line) = line_reader.next_line()? {
+            let result = callback(line);
+            match result {
+                Ok(stop) => if stop { break; }
+                Err(err) => return Err(Error::new(ErrorKind::Other, err.to_string()))
             }
         }
+        
+        Ok(line_reader.total_read_bytes())
     }
 
     fn read_type<T : FromBytes>(&mut self) -> Result<T>
@@ -96,4 +114,58 @@ pub trait ReadLine : Read {
 
 impl<R : Read> ReadLine for R {
     
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(mut input: &[u8]) -> Vec<Vec<u8>> {
+        let mut line_reader = LineReader::new(&mut input);
+        let mut lines = Vec::new();
+        while let Some(line) = line_reader.next_line().unwrap() {
+            lines.push(line.to_vec());
+        }
+        lines
+    }
+
+    /// a line of length bytes followed by the line ending, so the line ending starts at that byte of the stream
+    fn line_ending_at(length: usize, line_ending: &[u8]) -> Vec<u8> {
+        let mut input = vec![b'a'; length];
+        input.extend_from_slice(line_ending);
+        input.extend_from_slice(b"b");
+        input
+    }
+
+    #[test]
+    fn carriage_return_line_feed_split_across_chunks() {
+        let input = line_ending_at(LINE_BUFFER_SIZE - 1, b"\r\n");
+        assert_eq!(lines(&input), vec![vec![b'a'; LINE_BUFFER_SIZE - 1], b"b".to_vec()]);
+    }
+
+    #[test]
+    fn carriage_return_at_the_end_of_a_chunk() {
+        let input = line_ending_at(LINE_BUFFER_SIZE - 1, b"\r");
+        assert_eq!(lines(&input), vec![vec![b'a'; LINE_BUFFER_SIZE - 1], b"b".to_vec()]);
+
+        let input = line_ending_at(LINE_BUFFER_SIZE - 1, b"\r\r");
+        assert_eq!(lines(&input), vec![vec![b'a'; LINE_BUFFER_SIZE - 1], Vec::new(), b"b".to_vec()]);
+    }
+
+    #[test]
+    fn carriage_return_before_carriage_return_line_feed() {
+        assert_eq!(lines(b"a\r\r\nb"), vec![b"a".to_vec(), Vec::new(), b"b".to_vec()]);
+    }
+
+    #[test]
+    fn final_line_without_line_ending() {
+        assert_eq!(lines(b"a\nb"), vec![b"a".to_vec(), b"b".to_vec()]);
+        assert_eq!(lines(b"a\n"), vec![b"a".to_vec()]);
+        assert_eq!(lines(b"a\r\n"), vec![b"a".to_vec()]);
+    }
+
+    #[test]
+    fn empty_input() {
+        assert!(lines(b"").is_empty());
+    }
+}
@@ -0,0 +1,82 @@
+use std::ops::Range;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use crate::cpu::CpuArchitecture;
+use crate::device_bus::{Device, DeviceSignal};
+use crate::interrupt::Interrupt;
+use crate::memory::Ram;
+
+/// the ports of the real time clock, reading gives the seconds, minutes and hours, the days since 1970, the day of the
+/// month, the month, the year and the day of the week (0 is sunday) in UTC, the last port is the alarm
+pub const RTC_PORTS: Range<CpuArchitecture> = 0x10..0x19;
+/// writing the seconds to this port raises the alarm interrupt once after that many seconds, 0 cancels the alarm,
+/// reading it gives the seconds left rounded up or 0 when no alarm is set
+pub const RTC_ALARM_PORT: CpuArchitecture = RTC_PORTS.end - 1;
+
+/// the year, month and day of the days since 1970
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    // the days are counted from the 1st of march in 0 so the leap day is at the end of the year
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+pub struct Rtc {
+    alarm: Option<Instant>,
+}
+
+impl Rtc {
+    pub fn new() -> Self {
+        Self {
+            alarm: None,
+        }
+    }
+}
+
+impl Device for Rtc {
+    fn read(&mut self, port: CpuArchitecture) -> CpuArchitecture {
+        if port == RTC_ALARM_PORT {
+            let left = self.alarm.map_or(Duration::ZERO, | alarm | { alarm.saturating_duration_since(Instant::now()) });
+            return (left.as_secs() + (left.subsec_nanos() != 0) as u64).min(CpuArchitecture::MAX as u64) as CpuArchitecture;
+        }
+
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(| duration | { duration.as_secs() })
+            .unwrap_or(0);
+        let days = seconds / 86400;
+        let (year, month, day) = civil_from_days(days as i64);
+
+        let value = match port - RTC_PORTS.start {
+            0 => seconds % 60,
+            1 => seconds / 60 % 60,
+            2 => seconds / 3600 % 24,
+            3 => days,
+            4 => day as u64,
+            5 => month as u64,
+            6 => year as u64,
+            _ => (days + 4) % 7, // the 1st of january 1970 was a thursday
+        };
+        value as CpuArchitecture
+    }
+
+    fn write(&mut self, port: CpuArchitecture, value: CpuArchitecture) {
+        if port == RTC_ALARM_PORT {
+            self.alarm = (value != 0).then(| | { Instant::now() + Duration::from_secs(value as u64) });
+        }
+    }
+
+    fn tick(&mut self, _ram: &mut Ram) -> Option<DeviceSignal> {
+        let alarm = self.alarm?;
+        if Instant::now() < alarm {
+            return None;
+        }
+        self.alarm = None;
+        Some(DeviceSignal::Interrupt(Interrupt::Alarm))
+    }
+}
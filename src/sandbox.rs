@@ -0,0 +1,60 @@
+use std::cell::{Cell, RefCell};
+
+thread_local! {
+    pub static SANDBOX: Cell<SandboxPolicy> = const { Cell::new(SandboxPolicy::deny_all()) };
+    /// the environment variables that can be read without --allow-env
+    pub static ALLOWED_ENV_VARS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// host access that a syscall can require, everything is denied unless allowed from the command line
+#[derive(Clone, Copy, Debug)]
+pub enum Permission {
+    Fs,
+    Net,
+    Env,
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SandboxPolicy {
+    allow_fs: bool,
+    allow_net: bool,
+    allow_env: bool,
+}
+
+impl SandboxPolicy {
+    pub const fn new(allow_fs: bool, allow_net: bool, allow_env: bool) -> Self {
+        Self {
+            allow_fs,
+            allow_net,
+            allow_env,
+        }
+    }
+    
+    pub const fn deny_all() -> Self {
+        Self::new(false, false, false)
+    }
+    
+    pub fn is_allowed(self, permission: Permission) -> bool {
+        match permission {
+            Permission::Fs => self.allow_fs,
+            Permission::Net => self.allow_net,
+            Permission::Env => self.allow_env,
+        }
+    }
+}
+
+/// if the environment variable can be read, either by --allow-env or by being in the allowed environment variables
+pub fn is_env_var_allowed(name: &str) -> bool {
+    SANDBOX.get().is_allowed(Permission::Env) || ALLOWED_ENV_VARS.with_borrow(| allowed | { allowed.iter().any(| allowed | { allowed == name }) })
+}
+
+impl Permission {
+    /// the command line flag that grants the permission
+    pub fn flag(self) -> &'static str {
+        match self {
+            Permission::Fs => "--allow-fs",
+            Permission::Net => "--allow-net",
+            Permission::Env => "--allow-env",
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::{HashMap, VecDeque};
+use std::ops::Range;
+use crate::check::{register_accesses, register_name};
+use crate::cpu::CpuArchitecture;
+use crate::instructions::{Instruction, InstructionSet};
+use crate::operand::{Operand, POINTER_STRINGS};
+
+/// the quarantined allocations are deallocated once they take up more than this part of the memory
+const QUARANTINE_FRACTION: CpuArchitecture = 8;
+
+/// undefined behavior found while the program ran, reported once per instruction and kind
+pub struct Violation {
+    pub address: CpuArchitecture,
+    /// the address of the next instruction, used to find the line of the instruction
+    pub next_address: CpuArchitecture,
+    pub message: String,
+    pub count: u64,
+}
+
+/// checks the instructions for reads of uninitialized registers, misaligned pointers and uses of deallocated memory,
+/// only used when running with --sanitize
+pub struct Sanitizer {
+    /// a bit for every register that has been written to, sp and bp are written when the program starts
+    initialized_registers: u32,
+    /// the deallocated allocations that are kept allocated so their memory isn't reused, the oldest first
+    quarantine: VecDeque<Range<CpuArchitecture>>,
+    /// the address and the address of the next instruction of the instruction that is executed
+    current: (CpuArchitecture, CpuArchitecture),
+    violations: Vec<Violation>,
+    /// the index of the violation by the address of the instruction and the message
+    indices: HashMap<(CpuArchitecture, String), usize>,
+}
+
+impl Sanitizer {
+    pub fn new() -> Self {
+        Self {
+            initialized_registers: 0,
+            quarantine: VecDeque::new(),
+            current: (0, 0),
+            violations: Vec::new(),
+            indices: HashMap::new(),
+        }
+    }
+
+    /// forgets which registers were written, except for the registers that are set when the program starts
+    pub fn reset_registers(&mut self, initialized: &[u8]) {
+        self.initialized_registers = initialized.iter().fold(0, | mask, register | { mask | 1 << register });
+    }
+
+    pub fn on_register_write(&mut self, register: u8) {
+        self.initialized_registers |= 1 << register;
+    }
+
+    /// checks the instruction before it is executed, pointers holds the address and the size of the memory every
+    /// pointer operand points to
+    pub fn check(&mut self, instruction: InstructionSet, pointers: &[(CpuArchitecture, CpuArchitecture)],
+                 address: CpuArchitecture, next_address: CpuArchitecture) {
+        self.current = (address, next_address);
+
+        if !is_zeroing(instruction) {
+            let (reads, _) = register_accesses(instruction);
+            for register in reads {
+                if self.initialized_registers & (1 << register) == 0 {
+                    self.report(format!("read of the uninitialized register {}", register_name(register)));
+                }
+            }
+        }
+
+        for (pointer, size) in pointers.iter().copied() {
+            if size > 1 && pointer % size != 0 {
+                let name = POINTER_STRINGS[size.ilog2() as usize];
+                self.report(format!("misaligned {} pointer, 0x{:X} is not a multiple of {}", name, pointer, size));
+            }
+            if let Some(range) = self.quarantine.iter().find(| range | { range.start < pointer.saturating_add(size) && pointer < range.end }) {
+                let message = format!("use of deallocated memory at 0x{:X} within the allocation at 0x{:X}", pointer, range.start);
+                self.report(message);
+            }
+        }
+    }
+
+    pub fn is_quarantined(&self, pointer: CpuArchitecture) -> bool {
+        self.quarantine.iter().any(| range | { range.start == pointer })
+    }
+
+    pub fn on_double_deallocation(&mut self, pointer: CpuArchitecture) {
+        self.report(format!("the allocation at 0x{:X} is deallocated twice", pointer));
+    }
+
+    /// keeps the deallocated range in quarantine and returns the pointers of the oldest quarantined allocations that
+    /// no longer fit within the quarantine of the memory size, these have to be deallocated
+    pub fn quarantine(&mut self, range: Range<CpuArchitecture>, memory_size: CpuArchitecture) -> Vec<CpuArchitecture> {
+        self.quarantine.push_back(range);
+
+        let maximum = memory_size / QUARANTINE_FRACTION;
+        let mut size: u32 = self.quarantine.iter().map(| range | { (range.end - range.start) as u32 }).sum();
+        let mut released = Vec::new();
+        while size > maximum as u32 {
+            let Some(range) = self.quarantine.pop_front() else { break; };
+            size -= (range.end - range.start) as u32;
+            released.push(range.start);
+        }
+        released
+    }
+
+    /// the violations in the order they were first found
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+
+    fn report(&mut self, message: String) {
+        let (address, next_address) = self.current;
+        match self.indices.get(&(address, message.clone())) {
+            Some(index) => self.violations[*index].count += 1,
+            None => {
+                self.indices.insert((address, message.clone()), self.violations.len());
+                self.violations.push(Violation {
+                    address,
+                    next_address,
+                    message,
+                    count: 1,
+                });
+            },
+        }
+    }
+}
+
+/// xor and sub of a register with itself only write it, eg: xor x1, x1
+fn is_zeroing(instruction: InstructionSet) -> bool {
+    let name: &str = instruction.as_ref();
+    let operands = instruction.operands();
+    matches!(name, "Xor" | "Sub") && matches!(operands.as_slice(), [Operand::Register(a), Operand::Register(b), ..] if a == b)
+}
@@ -0,0 +1,160 @@
+use std::io::{stdin, stdout, ErrorKind, Read, Result, Write};
+use std::net::{Ipv4Addr, TcpListener};
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
+use crate::cpu::CpuArchitecture;
+use crate::device_bus::Device;
+
+/// the ports of the uart, the first is the data port and the second the status port
+pub const SERIAL_PORTS: Range<CpuArchitecture> = 0x00..0x02;
+/// set in the status when a byte can be read from the data port without waiting
+pub const SERIAL_INPUT_READY: CpuArchitecture = 1;
+/// set in the status when the input has ended, reading the data port gives 65535
+pub const SERIAL_INPUT_ENDED: CpuArchitecture = 2;
+
+/// where the uart sends its output and gets its input from, given as stdio or tcp:port
+#[derive(Clone, Copy, Debug)]
+pub enum SerialMode {
+    Stdio,
+    /// listens on the port of localhost and waits for a single connection
+    Tcp(u16),
+}
+
+impl FromStr for SerialMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("stdio") {
+            return Ok(SerialMode::Stdio);
+        }
+
+        match s.split_once(':') {
+            Some((kind, port)) if kind.eq_ignore_ascii_case("tcp") => port.parse()
+                .map(SerialMode::Tcp)
+                .map_err(| _ | { format!("invalid port: {}", port) }),
+            _ => Err(format!("expected stdio or tcp:port, got: {}", s)),
+        }
+    }
+}
+
+/// a serial console, writing to the data port sends the lower byte and reading waits for the next byte, the input is
+/// only read once the program reads from the uart so stdin stays usable by the debugger otherwise
+pub struct Uart {
+    output: Box<dyn Write>,
+    input: Option<Box<dyn Read + Send>>,
+    received: Option<Receiver<u8>>,
+    pending: Option<u8>,
+    ended: bool,
+}
+
+impl Uart {
+    fn new(output: Box<dyn Write>, input: Box<dyn Read + Send>) -> Self {
+        Self {
+            output,
+            input: Some(input),
+            received: None,
+            pending: None,
+            ended: false,
+        }
+    }
+
+    pub fn stdio() -> Self {
+        Self::new(Box::new(stdout()), Box::new(stdin()))
+    }
+
+    /// opens the uart in the mode, a tcp uart waits until a connection has been made
+    pub fn open(mode: SerialMode) -> Result<Self> {
+        match mode {
+            SerialMode::Stdio => Ok(Self::stdio()),
+            SerialMode::Tcp(port) => {
+                let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+                println!("waiting for a serial connection on port {}", port);
+                let (stream, _) = listener.accept()?;
+                Ok(Self::new(Box::new(stream.try_clone()?), Box::new(stream)))
+            }
+        }
+    }
+
+    /// the bytes received by the reader thread, which is started the first time the input is used
+    fn received(&mut self) -> &Receiver<u8> {
+        if let Some(mut input) = self.input.take() {
+            let (sender, receiver) = channel();
+            thread::spawn(move || {
+                let mut byte = [0u8];
+                while let Ok(1) = input.read(&mut byte) {
+                    if sender.send(byte[0]).is_err() {
+                        break;
+                    }
+                }
+            });
+            self.received = Some(receiver);
+        }
+        self.received.as_ref().unwrap()
+    }
+
+    fn poll(&mut self) {
+        if self.pending.is_some() || self.ended {
+            return;
+        }
+
+        match self.received().try_recv() {
+            Ok(byte) => self.pending = Some(byte),
+            Err(TryRecvError::Disconnected) => self.ended = true,
+            Err(TryRecvError::Empty) => {},
+        }
+    }
+
+    fn status(&mut self) -> CpuArchitecture {
+        self.poll();
+        let mut status = 0;
+        if self.pending.is_some() {
+            status |= SERIAL_INPUT_READY;
+        }
+        if self.ended {
+            status |= SERIAL_INPUT_ENDED;
+        }
+        status
+    }
+
+    fn read_byte(&mut self) -> CpuArchitecture {
+        if let Some(byte) = self.pending.take() {
+            return byte as CpuArchitecture;
+        }
+        if self.ended {
+            return CpuArchitecture::MAX;
+        }
+
+        match self.received().recv() {
+            Ok(byte) => byte as CpuArchitecture,
+            Err(_) => {
+                self.ended = true;
+                CpuArchitecture::MAX
+            }
+        }
+    }
+}
+
+impl Device for Uart {
+    fn read(&mut self, port: CpuArchitecture) -> CpuArchitecture {
+        if port == SERIAL_PORTS.start {
+            self.read_byte()
+        } else {
+            self.status()
+        }
+    }
+
+    fn write(&mut self, port: CpuArchitecture, value: CpuArchitecture) {
+        if port != SERIAL_PORTS.start {
+            return;
+        }
+
+        let result = self.output.write_all(&[value as u8]).and_then(| _ | { self.output.flush() });
+        if let Err(err) = result {
+            if err.kind() != ErrorKind::BrokenPipe {
+                println!("could not write to the serial console: {}", err);
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use crate::cpu::CpuArchitecture;
+
+enum Socket {
+    Listener(TcpListener),
+    Stream(TcpStream),
+}
+
+/// the tcp sockets opened by the guest program, handles are indices into the table like the file handles
+pub struct SocketTable {
+    handles: Vec<Option<Socket>>,
+}
+
+impl SocketTable {
+    pub fn new() -> Self {
+        Self {
+            handles: Vec::new(),
+        }
+    }
+
+    fn handle(&mut self, handle: CpuArchitecture) -> Result<&mut Socket> {
+        match self.handles.get_mut(handle as usize) {
+            Some(Some(socket)) => Ok(socket),
+            _ => Err(Error::new(ErrorKind::NotFound, format!("socket handle {} is not open", handle))),
+        }
+    }
+
+    fn stream(&mut self, handle: CpuArchitecture) -> Result<&mut TcpStream> {
+        match self.handle(handle)? {
+            Socket::Stream(stream) => Ok(stream),
+            Socket::Listener(_) => Err(Error::new(ErrorKind::InvalidInput, format!("socket handle {} is listening and cannot send or receive", handle))),
+        }
+    }
+
+    fn insert(&mut self, socket: Socket) -> CpuArchitecture {
+        match self.handles.iter().position(| handle | { handle.is_none() }) {
+            Some(index) => {
+                self.handles[index] = Some(socket);
+                index as CpuArchitecture
+            },
+            None => {
+                self.handles.push(Some(socket));
+                (self.handles.len() - 1) as CpuArchitecture
+            }
+        }
+    }
+
+    /// connects to the address, eg: `example.com:80` or `127.0.0.1:8080`, and returns the handle of the connection
+    pub fn connect(&mut self, address: &str) -> Result<CpuArchitecture> {
+        let stream = TcpStream::connect(address)?;
+        Ok(self.insert(Socket::Stream(stream)))
+    }
+
+    /// listens on the port of localhost and returns the handle of the listener
+    pub fn listen(&mut self, port: u16) -> Result<CpuArchitecture> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        Ok(self.insert(Socket::Listener(listener)))
+    }
+
+    /// waits for a connection on the listener and returns the handle of the connection
+    pub fn accept(&mut self, handle: CpuArchitecture) -> Result<CpuArchitecture> {
+        let stream = match self.handle(handle)? {
+            Socket::Listener(listener) => listener.accept()?.0,
+            Socket::Stream(_) => return Err(Error::new(ErrorKind::InvalidInput, format!("socket handle {} is not listening", handle))),
+        };
+        Ok(self.insert(Socket::Stream(stream)))
+    }
+
+    pub fn send(&mut self, handle: CpuArchitecture, buffer: &[u8]) -> Result<usize> {
+        self.stream(handle)?.write(buffer)
+    }
+
+    /// waits for data, 0 bytes are received when the connection has been closed
+    pub fn receive(&mut self, handle: CpuArchitecture, buffer: &mut [u8]) -> Result<usize> {
+        self.stream(handle)?.read(buffer)
+    }
+
+    /// closes the socket, the socket is closed when it is dropped
+    pub fn close(&mut self, handle: CpuArchitecture) -> Result<()> {
+        self.handle(handle)?;
+        self.handles[handle as usize] = None;
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use crate::cpu::{CpuArchitecture, CpuError, CpuErrorKind};
+
+/// a call recorded by the stack guard
+#[derive(Clone, Copy)]
+struct GuardedCall {
+    function: CpuArchitecture,
+    return_address: CpuArchitecture,
+    /// the stack pointer the return address was pushed at
+    stack_pointer: CpuArchitecture,
+}
+
+/// keeps a shadow stack of the return addresses of the calls and verifies the return address popped when returning,
+/// nothing is stored on the stack of the program so it behaves the same with and without the guard,
+/// only used when running with --strict or --sanitize
+#[derive(Clone)]
+pub struct StackGuard {
+    functions: HashMap<CpuArchitecture, String>,
+    calls: Vec<GuardedCall>,
+}
+
+impl StackGuard {
+    pub fn new(functions: Vec<(String, CpuArchitecture)>) -> Self {
+        Self {
+            functions: functions.into_iter().map(| (name, address) | { (address, name) }).collect(),
+            calls: Vec::new(),
+        }
+    }
+
+    /// forgets the calls when the program is restarted
+    pub fn reset(&mut self) {
+        self.calls.clear();
+    }
+
+    /// records the call to the function whose return address is pushed at the stack pointer
+    pub fn on_call(&mut self, function: CpuArchitecture, return_address: CpuArchitecture, stack_pointer: CpuArchitecture) {
+        self.calls.push(GuardedCall {
+            function,
+            return_address,
+            stack_pointer,
+        });
+    }
+
+    /// checks the return address popped when returning from the current function
+    pub fn on_ret(&mut self, return_address: CpuArchitecture) -> Result<(), CpuError> {
+        let Some(call) = self.calls.pop() else {
+            return Ok(());
+        };
+        if return_address == call.return_address {
+            return Ok(());
+        }
+
+        let function = match self.functions.get(&call.function) {
+            Some(name) => format!("{} (0x{:X})", name, call.function),
+            None => format!("0x{:X}", call.function),
+        };
+        Err(CpuError::with_message(CpuErrorKind::StackCorrupted, format!("function: {}, expected the return address 0x{:X} pushed at 0x{:X}, got 0x{:X}",
+            function, call.return_address, call.stack_pointer, return_address)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use super::*;
+    use crate::compile::STRICT;
+    use crate::computer::{Computer, REGISTER_COUNT};
+    use crate::cpu::Cpu;
+    use crate::memory::Ram;
+    use crate::program::Program;
+
+    fn run(source: &str, strict: bool) -> Result<CpuArchitecture, String> {
+        STRICT.set(strict);
+        let mut computer = Computer::new(Cpu::<REGISTER_COUNT>::new(), Ram::new(4096).unwrap());
+        let result = computer.execute_program(Program::from_str(source).unwrap());
+        STRICT.set(false);
+        result.map_err(| err | { err.to_string() })
+    }
+
+    #[test]
+    fn guard_doesnt_change_the_stack_layout() {
+        let source = "main:
+            callargs double, 7
+            mov l1, l4
+            exit
+            double:
+            mov x3, word[sp - 4]
+            add x3, x3
+            ret";
+        assert_eq!(run(source, false), Ok(14));
+        assert_eq!(run(source, true), Ok(14));
+    }
+
+    #[test]
+    fn overwritten_return_address_is_detected() {
+        let source = "main:
+            call function
+            mov l1, 0
+            exit
+            function:
+            mov word[sp - 2], 0
+            ret";
+        let error = run(source, true).unwrap_err();
+        assert!(error.contains("return address"), "{}", error);
+    }
+}
@@ -0,0 +1,74 @@
+use std::ops::Range;
+use crate::cpu::CpuArchitecture;
+use crate::device_bus::{Device, DeviceSignal};
+use crate::interrupt::Interrupt;
+use crate::memory::Ram;
+
+/// the ports of the watchdog, the first sets the timeout, the second pets it and the third sets what happens when it expires
+pub const WATCHDOG_PORTS: Range<CpuArchitecture> = 0x20..0x23;
+/// writing starts the watchdog with a timeout of the value times 1000 executed instructions, 0 stops it,
+/// reading gives the thousands of instructions left
+pub const WATCHDOG_TIMEOUT_PORT: CpuArchitecture = WATCHDOG_PORTS.start;
+/// writing any value restarts the countdown
+pub const WATCHDOG_PET_PORT: CpuArchitecture = WATCHDOG_PORTS.start + 1;
+/// writing 0 raises the watchdog interrupt when it expires and 1 restarts the program
+pub const WATCHDOG_ACTION_PORT: CpuArchitecture = WATCHDOG_PORTS.start + 2;
+
+/// the timeout is counted in executed instructions instead of time so a hung program is detected the same way every run
+pub struct Watchdog {
+    timeout: u64,
+    left: u64,
+    reset: bool,
+}
+
+impl Watchdog {
+    pub fn new() -> Self {
+        Self {
+            timeout: 0,
+            left: 0,
+            reset: false,
+        }
+    }
+}
+
+impl Device for Watchdog {
+    fn read(&mut self, port: CpuArchitecture) -> CpuArchitecture {
+        match port {
+            WATCHDOG_TIMEOUT_PORT => self.left.div_ceil(1000) as CpuArchitecture,
+            WATCHDOG_ACTION_PORT => self.reset as CpuArchitecture,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, port: CpuArchitecture, value: CpuArchitecture) {
+        match port {
+            WATCHDOG_TIMEOUT_PORT => {
+                self.timeout = value as u64 * 1000;
+                self.left = self.timeout;
+            },
+            WATCHDOG_PET_PORT => self.left = self.timeout,
+            WATCHDOG_ACTION_PORT => self.reset = value == 1,
+            _ => {},
+        }
+    }
+
+    fn tick(&mut self, _ram: &mut Ram) -> Option<DeviceSignal> {
+        if self.timeout == 0 {
+            return None;
+        }
+
+        self.left -= 1;
+        if self.left != 0 {
+            return None;
+        }
+
+        if self.reset {
+            // like a hardware reset the watchdog is stopped until the program starts it again
+            self.timeout = 0;
+            Some(DeviceSignal::Reset)
+        } else {
+            self.left = self.timeout;
+            Some(DeviceSignal::Interrupt(Interrupt::Watchdog))
+        }
+    }
+}
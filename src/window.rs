@@ -1,13 +1,16 @@
+use std::collections::VecDeque;
 use glium::backend::glutin::SimpleWindowBuilder;
 use glium::glutin::surface::WindowSurface;
 use glium::{IndexBuffer, Program, Surface, VertexBuffer};
 use glium::index::PrimitiveType;
 use glium::winit::error::EventLoopError;
 use glium::winit::event_loop::EventLoopBuilder;
+use crate::canvas::Canvas;
 use crate::computer::Computer;
+use crate::compile::CYCLES_PER_FRAME;
 use crate::cpu::CpuArchitecture;
 use crate::display_handler::{AppHandler, Vertex};
-use crate::instructions::{InstructionError, InstructionErrorKind, AWAITING_EVENT};
+use crate::instructions::{InstructionError, InstructionErrorKind, AWAITING_EVENT, CANVAS, WAKE_TIME, WINDOW_EVENTS, WINDOW_OPEN};
 use crate::memory::{AllocatedRam, RamError};
 use crate::operand::Register;
 
@@ -73,7 +76,7 @@ pub(crate) fn vertex_buffer_from_memory(display: &glium::Display<WindowSurface>,
     Ok(vertex_buffer)
 }
 
-fn index_buffer_from_size(display: &glium::Display<WindowSurface>, size: (usize, usize)) -> IndexBuffer<u32> {
+pub(crate) fn index_buffer_from_size(display: &glium::Display<WindowSurface>, size: (usize, usize)) -> IndexBuffer<u32> {
     let total_size = size.0 * size.1;
     let mut index_buffer = vec![0; total_size * 6];
 
@@ -95,6 +98,17 @@ pub struct Window { }
 
 impl Window {
     pub fn run(canvas_size: (usize, usize), window_name: Option<&str>, computer: &mut Computer, alloc_base: Register) -> Result<(), InstructionError> {
+        WINDOW_OPEN.set(true);
+        let result = Self::run_window(canvas_size, window_name, computer, alloc_base);
+        WINDOW_OPEN.set(false);
+        WAKE_TIME.set(None);
+        AWAITING_EVENT.set(false);
+        WINDOW_EVENTS.with_borrow_mut(VecDeque::clear);
+        CANVAS.set(None);
+        result
+    }
+
+    fn run_window(canvas_size: (usize, usize), window_name: Option<&str>, computer: &mut Computer, alloc_base: Register) -> Result<(), InstructionError> {
         let result = EventLoopBuilder::default().build();
         let event_loop = match result {
             Ok(val) => val,
@@ -127,8 +141,10 @@ impl Window {
         frame.finish().unwrap();
 
         computer.cpu_mut().set_register(alloc_base, alloc.range().start)?; // same as above
+        CANVAS.set(Some(Canvas::new(alloc.range().start, canvas_size)));
 
-        while !AWAITING_EVENT.get() {
+        // with a cycle budget the program is run by the frames instead
+        while !AWAITING_EVENT.get() && CYCLES_PER_FRAME.get() == 0 {
             let result = computer.execute_next_instruction();
             match result {
                 Ok(val) => if val { break; },